@@ -34,6 +34,10 @@ export const tauriMockScript = /* js */ `
       has_album_art: true,
       bitrate: opts.bitrate || 900,
       scanned_at: 1700000000,
+      audio_hash: null,
+      rating: opts.rating ?? null,
+      album_id: null,
+      lyrics: null,
     };
   }
 
@@ -64,17 +68,21 @@ export const tauriMockScript = /* js */ `
     makeTrack(21, "Daft Punk", "Random Access Memories",  "Lose Yourself to Dance",  3, { year: 2013, genre: "Electronic", duration: 353 }),
   ];
 
-  // Group tracks into artist > album tree
+  // Group tracks into artist > album tree, numbering albums and artists
+  // like the albums/artists tables
   function buildTree(trackList) {
     const artistMap = {};
+    let albumCount = 0;
     for (const t of trackList) {
       const aKey = t.album_artist || t.artist || "Unknown Artist";
       if (!artistMap[aKey]) artistMap[aKey] = {};
       const albKey = t.album || "Unknown Album";
-      if (!artistMap[aKey][albKey]) artistMap[aKey][albKey] = { name: albKey, year: t.year, tracks: [] };
+      if (!artistMap[aKey][albKey]) artistMap[aKey][albKey] = { id: ++albumCount, name: albKey, year: t.year, tracks: [] };
+      t.album_id = artistMap[aKey][albKey].id;
       artistMap[aKey][albKey].tracks.push(t);
     }
-    const artists = Object.keys(artistMap).sort().map(name => ({
+    const artists = Object.keys(artistMap).sort().map((name, i) => ({
+      id: i + 1,
       name,
       albums: Object.values(artistMap[name]),
     }));
@@ -111,12 +119,12 @@ export const tauriMockScript = /* js */ `
     {
       id: "prof-1", name: "Laptop Backup", source_path: "/Users/demo/Music",
       target_path: "/Volumes/Backup/Music", sync_mode: "one_way",
-      exclude_patterns: ["*.tmp"], created_at: 1700000000, last_synced_at: 1700500000,
+      exclude_patterns: ["*.tmp"], conflict_policies: [], keep_both_naming: "timestamp", hash_kind: "file", created_at: 1700000000, last_synced_at: 1700500000,
     },
     {
       id: "prof-2", name: "NAS Sync", source_path: "/Users/demo/Music",
       target_path: "/Volumes/NAS/Music", sync_mode: "two_way",
-      exclude_patterns: [], created_at: 1700000000, last_synced_at: null,
+      exclude_patterns: [], conflict_policies: [], keep_both_naming: "timestamp", hash_kind: "file", created_at: 1700000000, last_synced_at: null,
     },
   ];
 
@@ -129,7 +137,7 @@ export const tauriMockScript = /* js */ `
         created_at: 1700000000, last_synced_at: 1700400000,
       },
       connected: true,
-      selected_artists: ["Pink Floyd", "Radiohead"],
+      selected_artists: [2, 3],
       selected_albums: [],
     },
     {
@@ -152,19 +160,19 @@ export const tauriMockScript = /* js */ `
   ];
 
   const artistSummaries = [
-    { name: "Daft Punk",  album_count: 2, track_count: 6,  total_size: 6 * 15_000_000 },
-    { name: "Pink Floyd", album_count: 2, track_count: 8,  total_size: 8 * 35_000_000 },
-    { name: "Radiohead",  album_count: 2, track_count: 7,  total_size: 7 * 35_000_000 },
+    { id: 1, name: "Daft Punk",  sort_name: "daft punk",  album_count: 2, track_count: 6,  total_size: 6 * 15_000_000 },
+    { id: 2, name: "Pink Floyd", sort_name: "pink floyd", album_count: 2, track_count: 8,  total_size: 8 * 35_000_000 },
+    { id: 3, name: "Radiohead",  sort_name: "radiohead",  album_count: 2, track_count: 7,  total_size: 7 * 35_000_000 },
   ];
 
   const albumSummaries = [
-    { artist_name: "Daft Punk",  album_name: "Discovery",              track_count: 3, total_size: 22_700_000, year: 2001 },
-    { artist_name: "Daft Punk",  album_name: "Random Access Memories", track_count: 3, total_size: 105_000_000, year: 2013 },
-    { artist_name: "Pink Floyd", album_name: "The Dark Side of the Moon", track_count: 5, total_size: 175_000_000, year: 1973 },
-    { artist_name: "Pink Floyd", album_name: "Wish You Were Here",     track_count: 3, total_size: 105_000_000, year: 1975 },
-    { artist_name: "Radiohead",  album_name: "In Rainbows",            track_count: 3, total_size: 105_000_000, year: 2007 },
-    { artist_name: "Radiohead",  album_name: "OK Computer",            track_count: 4, total_size: 140_000_000, year: 1997 },
-  ];
+    { id: 1, artist_id: 1, artist_name: "Daft Punk",  album_name: "Discovery",              sort_name: "discovery",              track_count: 3, total_size: 22_700_000, year: 2001, duration_secs: 720 },
+    { id: 2, artist_id: 1, artist_name: "Daft Punk",  album_name: "Random Access Memories", sort_name: "random access memories", track_count: 3, total_size: 105_000_000, year: 2013, duration_secs: 900 },
+    { id: 3, artist_id: 2, artist_name: "Pink Floyd", album_name: "The Dark Side of the Moon", sort_name: "dark side of the moon", track_count: 5, total_size: 175_000_000, year: 1973, duration_secs: 1500 },
+    { id: 4, artist_id: 2, artist_name: "Pink Floyd", album_name: "Wish You Were Here",     sort_name: "wish you were here",     track_count: 3, total_size: 105_000_000, year: 1975, duration_secs: 1300 },
+    { id: 5, artist_id: 3, artist_name: "Radiohead",  album_name: "In Rainbows",            sort_name: "in rainbows",            track_count: 3, total_size: 105_000_000, year: 2007, duration_secs: 800 },
+    { id: 6, artist_id: 3, artist_name: "Radiohead",  album_name: "OK Computer",            sort_name: "ok computer",            track_count: 4, total_size: 140_000_000, year: 1997, duration_secs: 1000 },
+  ].map((a) => ({ year_min: a.year, cover_track_id: null, rating: null, ...a }));
 
   // ── IPC handler ────────────────────────────────────────────────────────
 
@@ -189,13 +197,25 @@ export const tauriMockScript = /* js */ `
     set_setting: () => null,
     search_library: () => [],
     get_track_artwork: () => null,
+    set_track_rating: (args) => ({
+      ...tracks.find((t) => t.id === args.trackId),
+      rating: args.rating || null,
+    }),
+    set_album_rating: () => null,
+    get_track_lyrics: () => null,
+    set_track_lyrics: () => null,
     toggle_favorite: () => true,
     is_favorite: () => false,
     list_favorites: () => [],
     list_all_favorites: () => [],
     get_favorite_tracks: () => [],
     import_tracks: () => 0,
-    record_play: () => null,
+    record_play: () => 1,
+    finish_play: () => null,
+    record_skip: () => null,
+    export_listenbrainz: () => 0,
+    export_scrobbler_log: () => 0,
+    import_scrobbler_log: () => ({ imported: 0, duplicates: 0, unmatched: 0 }),
     get_recently_added: () => tracks.slice(0, 5),
     get_recently_played: () => tracks.slice(0, 5),
   };
@@ -0,0 +1,859 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::{params, Connection, DatabaseName};
+
+use crate::db::{album_repo, device_repo, favorite_repo, profile_repo, schema, settings_repo};
+use crate::error::AppError;
+use crate::models::backup::{
+    ArchivedAlbumRating, ArchivedDevice, ArchivedFavorite, ArchivedPlay, ArchivedPlaylist,
+    ArchivedTrackRating, BackupFile, ImportSummary, TrackRef, UserDataArchive,
+    ARCHIVE_FORMAT_VERSION,
+};
+use crate::models::device::AlbumSelection;
+use crate::models::playlist::Playlist;
+
+/// Settings key: hours between automatic backups; `0` turns them off.
+pub const AUTO_BACKUP_INTERVAL_SETTING: &str = "auto_backup_interval_hours";
+/// Settings key: how many automatic backups to keep.
+pub const AUTO_BACKUP_KEEP_SETTING: &str = "auto_backup_keep";
+
+const DEFAULT_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_KEEP: usize = 7;
+
+const BACKUP_PREFIX: &str = "orchestra-";
+const BACKUP_EXTENSION: &str = ".db";
+/// Millisecond timestamps, so names sort by age and never collide.
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S%3f";
+
+// --- Export / import ---
+
+/// Collects the user's playlists, favorites, play history, sync profiles,
+/// devices and settings.
+pub fn export_user_data(conn: &Connection) -> Result<UserDataArchive, AppError> {
+    let refs = track_refs(conn)?;
+    let track_ref = |id: i64| refs.get(&id).cloned();
+
+    let mut playlists = Vec::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, created_at, updated_at FROM playlists ORDER BY created_at, id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Playlist {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tracks_stmt = conn.prepare(
+            "SELECT track_id FROM playlist_tracks WHERE playlist_id = ?1 ORDER BY position",
+        )?;
+        for playlist in rows {
+            let tracks = tracks_stmt
+                .query_map(params![playlist.id], |row| row.get::<_, i64>(0))?
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter_map(track_ref)
+                .collect();
+            playlists.push(ArchivedPlaylist { playlist, tracks });
+        }
+    }
+
+    let favorites = {
+        let mut stmt = conn.prepare(
+            "SELECT f.entity_type, f.track_id,
+                    COALESCE(ar.name, album_artist.name || char(0) || al.name), f.created_at
+             FROM favorites f
+             LEFT JOIN artists ar ON ar.id = f.artist_id
+             LEFT JOIN albums al ON al.id = f.album_id
+             LEFT JOIN artists album_artist ON album_artist.id = al.artist_id
+             ORDER BY f.created_at, f.id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .filter_map(|(entity_type, track_id, name, created_at)| {
+                let (entity_id, track) = match track_id {
+                    Some(id) => (id.to_string(), Some(track_ref(id)?)),
+                    None => (name?, None),
+                };
+                Some(ArchivedFavorite {
+                    entity_type,
+                    entity_id,
+                    track,
+                    created_at,
+                })
+            })
+            .collect()
+    };
+
+    let play_history = {
+        let mut stmt = conn.prepare(
+            "SELECT track_id, played_at, listened_secs, completed, skipped
+             FROM play_history ORDER BY played_at, id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .filter_map(|(id, played_at, listened_secs, completed, skipped)| {
+                Some(ArchivedPlay {
+                    track: track_ref(id)?,
+                    played_at,
+                    listened_secs,
+                    completed,
+                    skipped,
+                })
+            })
+            .collect()
+    };
+
+    let track_ratings = {
+        let mut stmt =
+            conn.prepare("SELECT id, rating FROM tracks WHERE rating IS NOT NULL ORDER BY id")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u8>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .filter_map(|(id, rating)| {
+                Some(ArchivedTrackRating {
+                    track: track_ref(id)?,
+                    rating,
+                })
+            })
+            .collect()
+    };
+
+    let album_ratings = {
+        let mut stmt = conn.prepare(
+            "SELECT ar.name, al.name, r.rating, r.rated_at
+             FROM album_ratings r
+             JOIN albums al ON al.id = r.album_id
+             JOIN artists ar ON ar.id = al.artist_id
+             ORDER BY ar.name, al.name",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(ArchivedAlbumRating {
+                    artist_name: row.get(0)?,
+                    album_name: row.get(1)?,
+                    rating: row.get(2)?,
+                    rated_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut artist_stmt = conn.prepare(
+        "SELECT ar.name FROM device_artist_selections s
+         JOIN artists ar ON ar.id = s.artist_id
+         WHERE s.device_id = ?1 ORDER BY ar.name",
+    )?;
+    let mut album_stmt = conn.prepare(
+        "SELECT ar.name, al.name FROM device_album_selections s
+         JOIN albums al ON al.id = s.album_id
+         JOIN artists ar ON ar.id = al.artist_id
+         WHERE s.device_id = ?1 ORDER BY ar.name, al.name",
+    )?;
+    let mut devices = Vec::new();
+    for device in device_repo::list_devices(conn)? {
+        let selected_artists = artist_stmt
+            .query_map(params![device.id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let selected_albums = album_stmt
+            .query_map(params![device.id], |row| {
+                Ok(AlbumSelection {
+                    artist_name: row.get(0)?,
+                    album_name: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        devices.push(ArchivedDevice {
+            device,
+            selected_artists,
+            selected_albums,
+        });
+    }
+
+    Ok(UserDataArchive {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        exported_at: Utc::now().timestamp(),
+        playlists,
+        favorites,
+        play_history,
+        track_ratings,
+        album_ratings,
+        sync_profiles: profile_repo::list_profiles(conn)?,
+        devices,
+        settings: settings_repo::get_all_settings(conn)?.into_iter().collect(),
+    })
+}
+
+/// The album row for an artist and album name, if the library has one.
+fn find_album(conn: &Connection, artist: &str, album: &str) -> Result<Option<i64>, AppError> {
+    match album_repo::find_artist(conn, artist)? {
+        Some(artist_id) => album_repo::find_album(conn, artist_id, album),
+        None => Ok(None),
+    }
+}
+
+/// Adds an archive's contents to the database in one transaction, linking
+/// track references to the library's tracks. Scan the library first: tracks
+/// that cannot be found are dropped and counted in `unmatched_tracks`.
+pub fn import_user_data(
+    conn: &Connection,
+    archive: &UserDataArchive,
+) -> Result<ImportSummary, AppError> {
+    if archive.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(AppError::General(format!(
+            "Backup archive format {} is newer than this build supports ({ARCHIVE_FORMAT_VERSION})",
+            archive.format_version
+        )));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let index = TrackIndex::load(&tx)?;
+    let mut summary = ImportSummary::default();
+    let mut resolve = |track: &TrackRef| {
+        let id = index.resolve(track);
+        if id.is_none() {
+            summary.unmatched_tracks += 1;
+        }
+        id
+    };
+
+    let mut playlist_tracks = 0;
+    let mut playlists = 0;
+    for archived in &archive.playlists {
+        let playlist = &archived.playlist;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO playlists (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                playlist.id,
+                playlist.name,
+                playlist.created_at,
+                playlist.updated_at
+            ],
+        )?;
+        if inserted == 0 {
+            continue;
+        }
+        playlists += 1;
+        let mut position = 0;
+        for track in &archived.tracks {
+            let Some(track_id) = resolve(track) else {
+                continue;
+            };
+            position += tx.execute(
+                "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?1, ?2, ?3)",
+                params![playlist.id, track_id, position],
+            )? as i64;
+        }
+        playlist_tracks += position as usize;
+    }
+
+    let mut favorites = 0;
+    for favorite in &archive.favorites {
+        let entity_id = match (&favorite.track, favorite.entity_type.as_str()) {
+            (Some(track), _) => resolve(track),
+            (None, "artist") => album_repo::find_artist(&tx, &favorite.entity_id)?,
+            (None, "album") => match favorite.entity_id.split_once('\0') {
+                Some((artist, album)) => find_album(&tx, artist, album)?,
+                None => None,
+            },
+            _ => None,
+        };
+        let Some(entity_id) = entity_id else {
+            continue;
+        };
+        let column = favorite_repo::id_column(&favorite.entity_type)?;
+        favorites += tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO favorites (entity_type, {column}, created_at) VALUES (?1, ?2, ?3)"
+            ),
+            params![favorite.entity_type, entity_id, favorite.created_at],
+        )?;
+    }
+
+    let mut plays = 0;
+    for play in &archive.play_history {
+        let Some(track_id) = resolve(&play.track) else {
+            continue;
+        };
+        // Importing the same archive twice does not double the history
+        plays += tx.execute(
+            "INSERT INTO play_history (track_id, played_at, listened_secs, completed, skipped)
+             SELECT ?1, ?2, ?3, ?4, ?5 WHERE NOT EXISTS
+               (SELECT 1 FROM play_history WHERE track_id = ?1 AND played_at = ?2)",
+            params![
+                track_id,
+                play.played_at,
+                play.listened_secs,
+                play.completed,
+                play.skipped
+            ],
+        )?;
+    }
+
+    let mut ratings = 0;
+    for archived in &archive.track_ratings {
+        let Some(track_id) = resolve(&archived.track) else {
+            continue;
+        };
+        ratings += tx.execute(
+            "UPDATE tracks SET rating = ?2 WHERE id = ?1 AND rating IS NULL",
+            params![track_id, archived.rating],
+        )?;
+    }
+    for archived in &archive.album_ratings {
+        let Some(album_id) = find_album(&tx, &archived.artist_name, &archived.album_name)? else {
+            continue;
+        };
+        ratings += tx.execute(
+            "INSERT OR IGNORE INTO album_ratings (album_id, rating, rated_at) VALUES (?1, ?2, ?3)",
+            params![album_id, archived.rating, archived.rated_at],
+        )?;
+    }
+
+    let mut sync_profiles = 0;
+    for profile in &archive.sync_profiles {
+        if matches!(
+            profile_repo::get_profile(&tx, &profile.id),
+            Err(AppError::ProfileNotFound(_))
+        ) {
+            profile_repo::create_profile(&tx, profile)?;
+            sync_profiles += 1;
+        }
+    }
+
+    let mut devices = 0;
+    for archived in &archive.devices {
+        let device = &archived.device;
+        if device_repo::get_device_by_uuid(&tx, &device.volume_uuid)?.is_some() {
+            continue;
+        }
+        device_repo::save_device(&tx, device)?;
+        let mut artist_ids = Vec::new();
+        for name in &archived.selected_artists {
+            artist_ids.extend(album_repo::find_artist(&tx, name)?);
+        }
+        let mut album_ids = Vec::new();
+        for album in &archived.selected_albums {
+            album_ids.extend(find_album(&tx, &album.artist_name, &album.album_name)?);
+        }
+        device_repo::set_selected_artists(&tx, &device.id, &artist_ids)?;
+        device_repo::set_selected_albums(&tx, &device.id, &album_ids)?;
+        devices += 1;
+    }
+
+    for (key, value) in &archive.settings {
+        settings_repo::set_setting(&tx, key, value)?;
+    }
+
+    tx.commit()?;
+    Ok(ImportSummary {
+        playlists,
+        playlist_tracks,
+        favorites,
+        plays,
+        ratings,
+        sync_profiles,
+        devices,
+        settings: archive.settings.len(),
+        ..summary
+    })
+}
+
+/// Writes `export_user_data` to `path` as pretty-printed JSON.
+pub fn export_to_file(conn: &Connection, path: &Path) -> Result<UserDataArchive, AppError> {
+    let archive = export_user_data(conn)?;
+    let json =
+        serde_json::to_string_pretty(&archive).map_err(|e| AppError::General(e.to_string()))?;
+    std::fs::write(path, json)?;
+    Ok(archive)
+}
+
+pub fn import_from_file(conn: &Connection, path: &Path) -> Result<ImportSummary, AppError> {
+    let json = std::fs::read_to_string(path)?;
+    let archive: UserDataArchive = serde_json::from_str(&json).map_err(|e| {
+        AppError::General(format!("{} is not a backup archive: {e}", path.display()))
+    })?;
+    import_user_data(conn, &archive)
+}
+
+/// Archive references for every track, by row id.
+fn track_refs(conn: &Connection) -> Result<HashMap<i64, TrackRef>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, relative_path, hash, audio_hash FROM tracks")?;
+    let refs = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                TrackRef {
+                    relative_path: row.get(1)?,
+                    hash: row.get(2)?,
+                    audio_hash: row.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(refs)
+}
+
+/// Looks up library tracks by relative path, file hash and audio hash.
+struct TrackIndex {
+    by_path: HashMap<String, i64>,
+    by_hash: HashMap<String, i64>,
+    by_audio_hash: HashMap<String, i64>,
+}
+
+impl TrackIndex {
+    fn load(conn: &Connection) -> Result<Self, AppError> {
+        let mut index = TrackIndex {
+            by_path: HashMap::new(),
+            by_hash: HashMap::new(),
+            by_audio_hash: HashMap::new(),
+        };
+        for (id, track) in track_refs(conn)? {
+            index.by_path.entry(track.relative_path).or_insert(id);
+            if let Some(hash) = track.hash {
+                index.by_hash.entry(hash).or_insert(id);
+            }
+            if let Some(hash) = track.audio_hash {
+                index.by_audio_hash.entry(hash).or_insert(id);
+            }
+        }
+        Ok(index)
+    }
+
+    fn resolve(&self, track: &TrackRef) -> Option<i64> {
+        let by_hash = |map: &HashMap<String, i64>, hash: &Option<String>| {
+            hash.as_ref().and_then(|h| map.get(h)).copied()
+        };
+        self.by_path
+            .get(&track.relative_path)
+            .copied()
+            .or_else(|| by_hash(&self.by_hash, &track.hash))
+            .or_else(|| by_hash(&self.by_audio_hash, &track.audio_hash))
+    }
+}
+
+// --- Database backups ---
+
+/// Copies the whole database into `dir` with SQLite's online backup, which
+/// is safe while other connections are writing, then deletes all but the
+/// newest `keep` backups.
+pub fn create_backup(conn: &Connection, dir: &Path, keep: usize) -> Result<BackupFile, AppError> {
+    std::fs::create_dir_all(dir)?;
+    let now = Utc::now();
+    let path = dir.join(format!(
+        "{BACKUP_PREFIX}{}{BACKUP_EXTENSION}",
+        now.format(BACKUP_TIME_FORMAT)
+    ));
+    conn.backup(DatabaseName::Main, &path, None)?;
+
+    for old in list_backups(dir)?.iter().skip(keep.max(1)) {
+        std::fs::remove_file(&old.path)?;
+    }
+
+    Ok(BackupFile {
+        size: std::fs::metadata(&path)?.len(),
+        path: path.to_string_lossy().to_string(),
+        created_at: now.timestamp(),
+    })
+}
+
+/// Backups in `dir`, newest first. Other files are ignored.
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupFile>, AppError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(created_at) = name
+            .strip_prefix(BACKUP_PREFIX)
+            .and_then(|n| n.strip_suffix(BACKUP_EXTENSION))
+            .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, BACKUP_TIME_FORMAT).ok())
+        else {
+            continue;
+        };
+        backups.push(BackupFile {
+            path: entry.path().to_string_lossy().to_string(),
+            created_at: created_at.and_utc().timestamp(),
+            size: entry.metadata()?.len(),
+        });
+    }
+    // The names sort by time, including milliseconds the timestamp drops
+    backups.sort_by(|a, b| b.path.cmp(&a.path));
+    Ok(backups)
+}
+
+/// Replaces the database behind `conn` with the backup at `path` and
+/// upgrades it if it came from an older build.
+pub fn restore_backup(conn: &mut Connection, path: &Path) -> Result<(), AppError> {
+    if !path.is_file() {
+        return Err(AppError::PathNotAccessible(path.display().to_string()));
+    }
+    schema::check_not_newer(&Connection::open(path)?)?;
+    conn.restore(
+        DatabaseName::Main,
+        path,
+        None::<fn(rusqlite::backup::Progress)>,
+    )?;
+    schema::run_migrations(conn)
+}
+
+/// Makes a backup into `dir` if the newest one is older than the configured
+/// interval (24 hours unless set otherwise). Returns the new backup, or
+/// `None` when none was due or automatic backups are off.
+pub fn run_scheduled_backup(conn: &Connection, dir: &Path) -> Result<Option<BackupFile>, AppError> {
+    let setting = |key: &str| -> Result<Option<u64>, AppError> {
+        Ok(settings_repo::get_setting(conn, key)?.and_then(|v| v.trim().parse().ok()))
+    };
+    let interval_hours = setting(AUTO_BACKUP_INTERVAL_SETTING)?.unwrap_or(DEFAULT_INTERVAL_HOURS);
+    if interval_hours == 0 {
+        return Ok(None);
+    }
+    let keep = setting(AUTO_BACKUP_KEEP_SETTING)?.map_or(DEFAULT_KEEP, |k| k as usize);
+
+    let interval = Duration::from_secs(interval_hours * 3600);
+    let newest = list_backups(dir)?.first().map(|b| b.created_at);
+    let due = newest
+        .is_none_or(|created_at| Utc::now().timestamp() - created_at >= interval.as_secs() as i64);
+    if !due {
+        return Ok(None);
+    }
+    create_backup(conn, dir, keep).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{library_repo, playlist_repo, rating_repo, recent_repo};
+    use crate::models::device::Device;
+    use crate::models::sync_profile::{KeepBothNaming, SyncMode, SyncProfile};
+    use crate::models::track::{HashKind, Track};
+    use tempfile::TempDir;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn track(relative_path: &str, hash: &str, audio_hash: &str) -> Track {
+        Track {
+            id: None,
+            file_path: format!("/music/{relative_path}"),
+            relative_path: relative_path.to_string(),
+            library_root: "/music".to_string(),
+            title: None,
+            artist: None,
+            album_artist: None,
+            album: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: None,
+            format: "flac".to_string(),
+            file_size: 1,
+            modified_at: 1,
+            hash: Some(hash.to_string()),
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: Some(audio_hash.to_string()),
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    /// `track` filed under Joni Mitchell's Blue.
+    fn on_blue(mut track: Track) -> Track {
+        track.artist = Some("Joni".to_string());
+        track.album = Some("Blue".to_string());
+        track
+    }
+
+    fn joni_id(conn: &Connection) -> i64 {
+        album_repo::refresh(conn).unwrap();
+        album_repo::find_artist(conn, "Joni").unwrap().unwrap()
+    }
+
+    fn blue_id(conn: &Connection) -> i64 {
+        album_repo::find_album(conn, joni_id(conn), "Blue")
+            .unwrap()
+            .unwrap()
+    }
+
+    fn track_id(conn: &Connection, relative_path: &str) -> i64 {
+        conn.query_row(
+            "SELECT id FROM tracks WHERE relative_path = ?1",
+            [relative_path],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    /// A library of three tracks with a playlist, favorites, plays, ratings,
+    /// a sync profile, a device and a setting.
+    fn populated_db() -> Connection {
+        let conn = setup_db();
+        for t in [
+            on_blue(track("a.flac", "ha", "aa")),
+            track("b.flac", "hb", "ab"),
+            track("c.flac", "hc", "ac"),
+        ] {
+            library_repo::upsert_track(&conn, &t).unwrap();
+        }
+        let (a, b, c) = (
+            track_id(&conn, "a.flac"),
+            track_id(&conn, "b.flac"),
+            track_id(&conn, "c.flac"),
+        );
+
+        playlist_repo::create_playlist(
+            &conn,
+            &Playlist {
+                id: "p1".to_string(),
+                name: "Mix".to_string(),
+                created_at: 1,
+                updated_at: 2,
+            },
+        )
+        .unwrap();
+        playlist_repo::add_tracks(&conn, "p1", &[c, a, b]).unwrap();
+        favorite_repo::add_favorite(&conn, "track", &b.to_string()).unwrap();
+        favorite_repo::add_favorite(&conn, "album", &blue_id(&conn).to_string()).unwrap();
+        recent_repo::record_play(&conn, a).unwrap();
+        rating_repo::set_track_rating(&conn, c, Some(80)).unwrap();
+        rating_repo::set_album_rating(&conn, blue_id(&conn), Some(100)).unwrap();
+
+        profile_repo::create_profile(
+            &conn,
+            &SyncProfile {
+                id: "s1".to_string(),
+                name: "Phone".to_string(),
+                source_path: "/music".to_string(),
+                target_path: "/phone".to_string(),
+                sync_mode: SyncMode::OneWay,
+                exclude_patterns: vec!["*.tmp".to_string()],
+                conflict_policies: Vec::new(),
+                keep_both_naming: KeepBothNaming::Timestamp,
+                hash_kind: HashKind::File,
+                created_at: 1,
+                last_synced_at: None,
+            },
+        )
+        .unwrap();
+        device_repo::save_device(
+            &conn,
+            &Device {
+                id: "d1".to_string(),
+                name: "Player".to_string(),
+                volume_uuid: "uuid-1".to_string(),
+                volume_name: "PLAYER".to_string(),
+                mount_path: None,
+                capacity_bytes: Some(1000),
+                music_folder: "Music".to_string(),
+                created_at: 1,
+                last_synced_at: None,
+            },
+        )
+        .unwrap();
+        device_repo::set_selected_artists(&conn, "d1", &[joni_id(&conn)]).unwrap();
+        device_repo::set_selected_albums(&conn, "d1", &[blue_id(&conn)]).unwrap();
+        settings_repo::set_setting(&conn, "library_root", "/music").unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_export_import_relinks_tracks() {
+        let archive = export_user_data(&populated_db()).unwrap();
+        assert_eq!(archive.format_version, ARCHIVE_FORMAT_VERSION);
+        assert_eq!(archive.playlists[0].tracks.len(), 3);
+
+        // A fresh library: scanned in a different order, `b` moved to a new
+        // folder and `c` re-tagged (only its audio hash still matches)
+        let conn = setup_db();
+        for t in [
+            track("c.flac", "hc-retagged", "ac"),
+            track("moved/b.flac", "hb", "ab"),
+            on_blue(track("a.flac", "ha", "aa")),
+        ] {
+            library_repo::upsert_track(&conn, &t).unwrap();
+        }
+        album_repo::refresh(&conn).unwrap();
+        let summary = import_user_data(&conn, &archive).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                playlists: 1,
+                playlist_tracks: 3,
+                favorites: 2,
+                plays: 1,
+                ratings: 2,
+                sync_profiles: 1,
+                devices: 1,
+                settings: 1,
+                unmatched_tracks: 0,
+            }
+        );
+
+        let playlist: Vec<String> = playlist_repo::get_playlist_tracks(&conn, "p1")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.relative_path)
+            .collect();
+        assert_eq!(playlist, ["c.flac", "a.flac", "moved/b.flac"]);
+        let b = track_id(&conn, "moved/b.flac");
+        assert!(favorite_repo::is_favorite(&conn, "track", &b.to_string()).unwrap());
+        assert!(favorite_repo::is_favorite(&conn, "album", &blue_id(&conn).to_string()).unwrap());
+        assert_eq!(
+            recent_repo::get_recently_played(&conn, 10).unwrap()[0].relative_path,
+            "a.flac"
+        );
+        let c = track_id(&conn, "c.flac");
+        let rating: Option<u8> = conn
+            .query_row("SELECT rating FROM tracks WHERE id = ?1", [c], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rating, Some(80));
+        assert_eq!(
+            rating_repo::get_album_rating(&conn, blue_id(&conn)).unwrap(),
+            Some(100)
+        );
+        assert_eq!(
+            profile_repo::get_profile(&conn, "s1").unwrap().name,
+            "Phone"
+        );
+        assert_eq!(
+            device_repo::get_selected_artists(&conn, "d1").unwrap(),
+            [joni_id(&conn)]
+        );
+        assert_eq!(
+            device_repo::get_selected_albums(&conn, "d1").unwrap(),
+            [blue_id(&conn)]
+        );
+
+        // A second import adds nothing
+        let again = import_user_data(&conn, &archive).unwrap();
+        assert_eq!(
+            (
+                again.playlists,
+                again.favorites,
+                again.plays,
+                again.ratings,
+                again.devices
+            ),
+            (0, 0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_import_counts_unmatched_tracks() {
+        let archive = export_user_data(&populated_db()).unwrap();
+        let conn = setup_db();
+        library_repo::upsert_track(&conn, &track("a.flac", "ha", "aa")).unwrap();
+
+        let summary = import_user_data(&conn, &archive).unwrap();
+        assert_eq!(summary.playlist_tracks, 1);
+        // b and c in the playlist, b's favorite, c's rating
+        assert_eq!(summary.unmatched_tracks, 4);
+        assert_eq!(summary.plays, 1);
+    }
+
+    #[test]
+    fn test_import_rejects_newer_archive() {
+        let mut archive = export_user_data(&setup_db()).unwrap();
+        archive.format_version = ARCHIVE_FORMAT_VERSION + 1;
+        assert!(import_user_data(&setup_db(), &archive).is_err());
+    }
+
+    #[test]
+    fn test_archive_file_round_trip() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("orchestra-export.json");
+        export_to_file(&populated_db(), &path).unwrap();
+
+        let conn = setup_db();
+        let summary = import_from_file(&conn, &path).unwrap();
+        assert_eq!(summary.sync_profiles, 1);
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(import_from_file(&conn, &path).is_err());
+    }
+
+    #[test]
+    fn test_backups_rotate_and_restore() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("backups");
+        let conn = populated_db();
+
+        for _ in 0..3 {
+            create_backup(&conn, &dir, 2).unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        std::fs::write(dir.join("notes.txt"), "not a backup").unwrap();
+        let backups = list_backups(&dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups[0].path > backups[1].path);
+
+        let mut restored = setup_db();
+        restore_backup(&mut restored, Path::new(&backups[0].path)).unwrap();
+        assert_eq!(
+            playlist_repo::get_playlist_tracks(&restored, "p1")
+                .unwrap()
+                .len(),
+            3
+        );
+
+        assert!(restore_backup(&mut restored, &dir.join("missing.db")).is_err());
+    }
+
+    #[test]
+    fn test_scheduled_backup_honours_settings() {
+        let tmp = TempDir::new().unwrap();
+        let conn = setup_db();
+
+        settings_repo::set_setting(&conn, AUTO_BACKUP_INTERVAL_SETTING, "0").unwrap();
+        assert_eq!(run_scheduled_backup(&conn, tmp.path()).unwrap(), None);
+
+        settings_repo::set_setting(&conn, AUTO_BACKUP_INTERVAL_SETTING, "24").unwrap();
+        assert!(run_scheduled_backup(&conn, tmp.path()).unwrap().is_some());
+        // Not due again until the interval has passed
+        assert_eq!(run_scheduled_backup(&conn, tmp.path()).unwrap(), None);
+        assert_eq!(list_backups(tmp.path()).unwrap().len(), 1);
+    }
+}
@@ -1,12 +1,9 @@
 use lofty::picture::PictureType;
 use lofty::prelude::*;
 
-/// Extract embedded album art from `file_path` and write it to
-/// `$TMPDIR/{temp_filename}`. Returns a `file://` URI on success.
-///
-/// Using a fixed path per caller avoids accumulating orphaned temp files —
-/// the file is overwritten on each track change.
-pub fn extract_cover(file_path: &str, temp_filename: &str) -> Option<String> {
+/// Read the embedded album art of `file_path`, preferring the front cover.
+/// Returns the encoded image bytes (usually JPEG or PNG).
+pub fn read_cover(file_path: &str) -> Option<Vec<u8>> {
     let path = std::path::Path::new(file_path);
     let tagged_file = lofty::read_from_path(path).ok()?;
 
@@ -20,8 +17,19 @@ pub fn extract_cover(file_path: &str, temp_filename: &str) -> Option<String> {
         .find(|p| p.pic_type() == PictureType::CoverFront)
         .or_else(|| tag.pictures().first())?;
 
+    Some(picture.data().to_vec())
+}
+
+/// Extract embedded album art from `file_path` and write it to
+/// `$TMPDIR/{temp_filename}`. Returns a `file://` URI on success.
+///
+/// Using a fixed path per caller avoids accumulating orphaned temp files —
+/// the file is overwritten on each track change.
+pub fn extract_cover(file_path: &str, temp_filename: &str) -> Option<String> {
+    let data = read_cover(file_path)?;
+
     let temp_path = std::env::temp_dir().join(temp_filename);
-    std::fs::write(&temp_path, picture.data()).ok()?;
+    std::fs::write(&temp_path, data).ok()?;
 
     Some(format!("file://{}", temp_path.display()))
 }
@@ -35,6 +43,11 @@ mod tests {
         assert!(extract_cover("/nonexistent/path/to/file.mp3", "test-art.jpg").is_none());
     }
 
+    #[test]
+    fn read_cover_nonexistent_file_returns_none() {
+        assert!(read_cover("/nonexistent/path/to/file.mp3").is_none());
+    }
+
     #[test]
     fn extract_cover_empty_file_returns_none() {
         let tmp = tempfile::NamedTempFile::new().unwrap();
@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::AppError;
+
+/// The artist a track is filed under, as SQL over the `tracks` table.
+pub const ARTIST_KEY_SQL: &str = "COALESCE(album_artist, artist, 'Unknown Artist')";
+/// The album a track is filed under, as SQL over the `tracks` table.
+pub const ALBUM_KEY_SQL: &str = "COALESCE(album, 'Unknown Album')";
+
+/// Sorting key for an album or artist name: lowercased, without a leading
+/// "The", "A" or "An", so "The Beatles" sorts under B.
+pub fn sort_name(name: &str) -> String {
+    let lower = name.trim().to_lowercase();
+    ["the ", "a ", "an "]
+        .iter()
+        .find_map(|article| lower.strip_prefix(article))
+        .map(str::trim_start)
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(&lower)
+        .to_string()
+}
+
+/// Moves what refers to artist `old` over to `into`. Where `into` already
+/// has the same favorite or selection, `old`'s copy is dropped.
+fn merge_artist(conn: &Connection, old: i64, into: i64) -> Result<(), AppError> {
+    conn.execute_batch(&format!(
+        "UPDATE OR IGNORE favorites SET artist_id = {into} WHERE artist_id = {old};
+         DELETE FROM favorites WHERE artist_id = {old};
+         UPDATE OR IGNORE device_artist_selections SET artist_id = {into} WHERE artist_id = {old};
+         DELETE FROM device_artist_selections WHERE artist_id = {old};"
+    ))?;
+    Ok(())
+}
+
+/// `merge_artist` for albums, which may also carry a rating.
+fn merge_album(conn: &Connection, old: i64, into: i64) -> Result<(), AppError> {
+    conn.execute_batch(&format!(
+        "UPDATE OR IGNORE favorites SET album_id = {into} WHERE album_id = {old};
+         DELETE FROM favorites WHERE album_id = {old};
+         UPDATE OR IGNORE device_album_selections SET album_id = {into} WHERE album_id = {old};
+         DELETE FROM device_album_selections WHERE album_id = {old};
+         UPDATE OR IGNORE album_ratings SET album_id = {into} WHERE album_id = {old};
+         DELETE FROM album_ratings WHERE album_id = {old};"
+    ))?;
+    Ok(())
+}
+
+/// A track whose album row no longer matches its tags (or that has none).
+struct Moved {
+    track_id: i64,
+    album_id: Option<i64>,
+    artist_id: Option<i64>,
+    artist: String,
+    album: String,
+}
+
+fn moved_tracks(conn: &Connection) -> Result<Vec<Moved>, AppError> {
+    let sql = format!(
+        "SELECT t.id, al.id, al.artist_id, t.artist_key, t.album_key
+         FROM (SELECT id, album_id, {ARTIST_KEY_SQL} AS artist_key, {ALBUM_KEY_SQL} AS album_key
+               FROM tracks) t
+         LEFT JOIN albums al ON al.id = t.album_id
+         LEFT JOIN artists ar ON ar.id = al.artist_id
+         WHERE al.id IS NULL OR ar.name IS NOT t.artist_key OR al.name IS NOT t.album_key"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let moved = stmt
+        .query_map([], |row| {
+            Ok(Moved {
+                track_id: row.get(0)?,
+                album_id: row.get(1)?,
+                artist_id: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(moved)
+}
+
+/// Track counts per id, from a `SELECT id, COUNT(*)` query.
+fn counts(conn: &Connection, sql: &str) -> Result<HashMap<i64, usize>, AppError> {
+    let mut stmt = conn.prepare(sql)?;
+    let counts = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(counts)
+}
+
+/// The old ids all of whose tracks moved to one and the same new key: their
+/// tags were renamed, so the row should follow rather than be replaced.
+fn renamed<K: Clone + Eq + Hash>(
+    moves: impl IntoIterator<Item = (i64, K)>,
+    totals: &HashMap<i64, usize>,
+) -> Vec<(i64, K)> {
+    let mut groups: HashMap<i64, (Option<K>, usize)> = HashMap::new();
+    for (id, key) in moves {
+        let (target, count) = groups.entry(id).or_insert((Some(key.clone()), 0));
+        if target.as_ref() != Some(&key) {
+            *target = None;
+        }
+        *count += 1;
+    }
+    let mut renamed: Vec<(i64, K)> = groups
+        .into_iter()
+        .filter(|(id, (_, count))| totals.get(id) == Some(count))
+        .filter_map(|(id, (key, _))| key.map(|key| (id, key)))
+        .collect();
+    renamed.sort_by_key(|(id, _)| *id);
+    renamed
+}
+
+pub fn find_artist(conn: &Connection, name: &str) -> Result<Option<i64>, AppError> {
+    let id = conn
+        .query_row(
+            "SELECT id FROM artists WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id)
+}
+
+pub fn find_album(conn: &Connection, artist_id: i64, name: &str) -> Result<Option<i64>, AppError> {
+    let id = conn
+        .query_row(
+            "SELECT id FROM albums WHERE artist_id = ?1 AND name = ?2",
+            params![artist_id, name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id)
+}
+
+fn artist_id(conn: &Connection, name: &str) -> Result<i64, AppError> {
+    match find_artist(conn, name)? {
+        Some(id) => Ok(id),
+        None => {
+            conn.execute(
+                "INSERT INTO artists (name, sort_name) VALUES (?1, ?2)",
+                params![name, sort_name(name)],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+fn album_id(conn: &Connection, artist_id: i64, name: &str) -> Result<i64, AppError> {
+    match find_album(conn, artist_id, name)? {
+        Some(id) => Ok(id),
+        None => {
+            conn.execute(
+                "INSERT INTO albums (artist_id, name, sort_name) VALUES (?1, ?2, ?3)",
+                params![artist_id, name, sort_name(name)],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// Brings the `artists` and `albums` rows in line with the tracks' tags,
+/// and recomputes each album's year range, duration and cover track, inside
+/// a transaction unless the caller already opened one. Call it after tracks
+/// are added, re-tagged or deleted.
+///
+/// When every track of an album (or artist) now carries the same new name,
+/// the row is renamed in place, so its id and favorite survive the tag edit;
+/// if a row with the new name already exists, the favorite, device
+/// selections and rating move there. Albums and artists left without tracks
+/// are deleted unless a favorite, device selection or album rating still
+/// refers to them, so those survive files going missing for a while and
+/// apply again once tracks with the same names are back.
+pub fn refresh(conn: &Connection) -> Result<(), AppError> {
+    let tx = if conn.is_autocommit() {
+        Some(conn.unchecked_transaction()?)
+    } else {
+        None
+    };
+
+    let moved = moved_tracks(conn)?;
+    if !moved.is_empty() {
+        let artist_totals = counts(
+            conn,
+            "SELECT al.artist_id, COUNT(*) FROM tracks t
+             JOIN albums al ON al.id = t.album_id GROUP BY al.artist_id",
+        )?;
+        let artist_moves = moved
+            .iter()
+            .filter_map(|m| m.artist_id.map(|id| (id, m.artist.clone())));
+        for (old, name) in renamed(artist_moves, &artist_totals) {
+            match find_artist(conn, &name)? {
+                None => {
+                    conn.execute(
+                        "UPDATE artists SET name = ?2, sort_name = ?3 WHERE id = ?1",
+                        params![old, name, sort_name(&name)],
+                    )?;
+                }
+                Some(existing) if existing != old => merge_artist(conn, old, existing)?,
+                Some(_) => {}
+            }
+        }
+
+        let moved = moved_tracks(conn)?;
+        let album_totals = counts(
+            conn,
+            "SELECT album_id, COUNT(*) FROM tracks WHERE album_id IS NOT NULL GROUP BY album_id",
+        )?;
+        let album_moves = moved.iter().filter_map(|m| {
+            m.album_id
+                .map(|id| (id, (m.artist.clone(), m.album.clone())))
+        });
+        for (old, (artist, album)) in renamed(album_moves, &album_totals) {
+            let artist_id = artist_id(conn, &artist)?;
+            match find_album(conn, artist_id, &album)? {
+                None => {
+                    conn.execute(
+                        "UPDATE albums SET artist_id = ?2, name = ?3, sort_name = ?4 WHERE id = ?1",
+                        params![old, artist_id, album, sort_name(&album)],
+                    )?;
+                }
+                Some(existing) if existing != old => merge_album(conn, old, existing)?,
+                Some(_) => {}
+            }
+        }
+
+        let mut assign = conn.prepare("UPDATE tracks SET album_id = ?2 WHERE id = ?1")?;
+        for m in moved_tracks(conn)? {
+            let artist_id = artist_id(conn, &m.artist)?;
+            let album_id = album_id(conn, artist_id, &m.album)?;
+            assign.execute(params![m.track_id, album_id])?;
+        }
+    }
+
+    conn.execute_batch(
+        "DELETE FROM albums WHERE id NOT IN
+           (SELECT album_id FROM tracks WHERE album_id IS NOT NULL
+            UNION SELECT album_id FROM favorites WHERE album_id IS NOT NULL
+            UNION SELECT album_id FROM device_album_selections
+            UNION SELECT album_id FROM album_ratings);
+         DELETE FROM artists WHERE id NOT IN
+           (SELECT artist_id FROM albums
+            UNION SELECT artist_id FROM favorites WHERE artist_id IS NOT NULL
+            UNION SELECT artist_id FROM device_artist_selections);
+         UPDATE albums SET
+           year_min = (SELECT MIN(year) FROM tracks WHERE album_id = albums.id),
+           year_max = (SELECT MAX(year) FROM tracks WHERE album_id = albums.id),
+           duration_secs = (SELECT COALESCE(SUM(duration_secs), 0) FROM tracks
+                            WHERE album_id = albums.id),
+           cover_track_id = (SELECT id FROM tracks WHERE album_id = albums.id AND has_album_art
+                             ORDER BY disc_number, track_number, id LIMIT 1);",
+    )?;
+
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{favorite_repo, library_repo, schema};
+    use crate::models::track::Track;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn track(name: &str, artist: &str, album: &str) -> Track {
+        Track {
+            id: None,
+            file_path: format!("/music/{name}.flac"),
+            relative_path: format!("{name}.flac"),
+            library_root: "/music".to_string(),
+            title: Some(name.to_string()),
+            artist: Some(artist.to_string()),
+            album_artist: None,
+            album: Some(album.to_string()),
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: Some(200.0),
+            format: "flac".to_string(),
+            file_size: 1000,
+            modified_at: 1,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    fn album_of(conn: &Connection, name: &str) -> Option<i64> {
+        conn.query_row(
+            "SELECT album_id FROM tracks WHERE title = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refresh_assigns_albums() {
+        let conn = setup_db();
+        library_repo::upsert_tracks(
+            &conn,
+            [
+                track("a", "Artist", "One"),
+                track("b", "Artist", "One"),
+                track("c", "Artist", "Two"),
+            ],
+        )
+        .unwrap();
+        refresh(&conn).unwrap();
+
+        assert!(album_of(&conn, "a").is_some());
+        assert_eq!(album_of(&conn, "a"), album_of(&conn, "b"));
+        assert_ne!(album_of(&conn, "a"), album_of(&conn, "c"));
+        assert_eq!(count(&conn, "artists"), 1);
+        assert_eq!(count(&conn, "albums"), 2);
+    }
+
+    #[test]
+    fn test_sort_name() {
+        assert_eq!(sort_name("The Beatles"), "beatles");
+        assert_eq!(sort_name("A Tribe Called Quest"), "tribe called quest");
+        assert_eq!(sort_name("Another Green World"), "another green world");
+        assert_eq!(sort_name("The"), "the");
+    }
+
+    #[test]
+    fn test_refresh_computes_album_details() {
+        let conn = setup_db();
+        let mut first = track("a", "The Artist", "Album");
+        first.year = Some(1999);
+        let mut second = track("b", "The Artist", "Album");
+        second.year = Some(2004);
+        second.has_album_art = true;
+        library_repo::upsert_tracks(&conn, [first, second]).unwrap();
+        refresh(&conn).unwrap();
+
+        let details: (String, Option<i32>, Option<i32>, f64, Option<i64>) = conn
+            .query_row(
+                "SELECT ar.sort_name, al.year_min, al.year_max, al.duration_secs, al.cover_track_id
+                 FROM albums al JOIN artists ar ON ar.id = al.artist_id",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        let cover: i64 = conn
+            .query_row("SELECT id FROM tracks WHERE title = 'b'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(
+            details,
+            (
+                "artist".to_string(),
+                Some(1999),
+                Some(2004),
+                400.0,
+                Some(cover)
+            )
+        );
+    }
+
+    #[test]
+    fn test_renamed_album_keeps_id_and_favorite() {
+        let conn = setup_db();
+        library_repo::upsert_tracks(
+            &conn,
+            [track("a", "Artist", "Albm"), track("b", "Artist", "Albm")],
+        )
+        .unwrap();
+        refresh(&conn).unwrap();
+        let album = album_of(&conn, "a").unwrap();
+        favorite_repo::add_favorite(&conn, "album", &album.to_string()).unwrap();
+        let artist = find_artist(&conn, "Artist").unwrap().unwrap();
+        favorite_repo::add_favorite(&conn, "artist", &artist.to_string()).unwrap();
+
+        library_repo::upsert_tracks(
+            &conn,
+            [
+                track("a", "The Artist", "Album"),
+                track("b", "The Artist", "Album"),
+            ],
+        )
+        .unwrap();
+        refresh(&conn).unwrap();
+
+        assert_eq!(album_of(&conn, "a"), Some(album));
+        assert_eq!(find_artist(&conn, "The Artist").unwrap(), Some(artist));
+        assert!(favorite_repo::is_favorite(&conn, "album", &album.to_string()).unwrap());
+        assert!(favorite_repo::is_favorite(&conn, "artist", &artist.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_merged_album_carries_favorite() {
+        let conn = setup_db();
+        library_repo::upsert_tracks(
+            &conn,
+            [
+                track("a", "Artist", "Album"),
+                track("b", "Artist", "Album (Disc 2)"),
+            ],
+        )
+        .unwrap();
+        refresh(&conn).unwrap();
+        let disc2 = album_of(&conn, "b").unwrap();
+        favorite_repo::add_favorite(&conn, "album", &disc2.to_string()).unwrap();
+
+        library_repo::upsert_track(&conn, &track("b", "Artist", "Album")).unwrap();
+        refresh(&conn).unwrap();
+
+        let album = album_of(&conn, "a").unwrap();
+        assert_eq!(album_of(&conn, "b"), Some(album));
+        assert_eq!(count(&conn, "albums"), 1);
+        assert!(favorite_repo::is_favorite(&conn, "album", &album.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_split_album_and_orphans() {
+        let conn = setup_db();
+        library_repo::upsert_tracks(
+            &conn,
+            [track("a", "Artist", "Album"), track("b", "Artist", "Album")],
+        )
+        .unwrap();
+        refresh(&conn).unwrap();
+        let album = album_of(&conn, "a").unwrap();
+
+        // Only one track re-tagged: the album stays with the other
+        library_repo::upsert_track(&conn, &track("b", "Other", "Single")).unwrap();
+        refresh(&conn).unwrap();
+        assert_eq!(album_of(&conn, "a"), Some(album));
+        assert_ne!(album_of(&conn, "b"), Some(album));
+        favorite_repo::add_favorite(&conn, "album", &album.to_string()).unwrap();
+
+        conn.execute("DELETE FROM tracks WHERE title = 'a'", [])
+            .unwrap();
+        refresh(&conn).unwrap();
+        assert_eq!(count(&conn, "albums"), 2);
+        assert_eq!(count(&conn, "artists"), 2);
+        assert_eq!(count(&conn, "favorites"), 1);
+
+        // Without the favorite, nothing keeps the empty album and artist
+        favorite_repo::remove_favorite(&conn, "album", &album.to_string()).unwrap();
+        refresh(&conn).unwrap();
+        assert_eq!(count(&conn, "albums"), 1);
+        assert_eq!(count(&conn, "artists"), 1);
+    }
+
+    #[test]
+    fn test_album_data_survives_files_missing_for_a_rescan() {
+        let conn = setup_db();
+        let tracks = [track("a", "Artist", "Album"), track("b", "Artist", "Album")];
+        library_repo::upsert_tracks(&conn, &tracks).unwrap();
+        refresh(&conn).unwrap();
+        let album = album_of(&conn, "a").unwrap();
+        let artist = find_artist(&conn, "Artist").unwrap().unwrap();
+        favorite_repo::add_favorite(&conn, "album", &album.to_string()).unwrap();
+        favorite_repo::add_favorite(&conn, "artist", &artist.to_string()).unwrap();
+        crate::db::rating_repo::set_album_rating(&conn, album, Some(80)).unwrap();
+
+        // A scan while the folder is being reorganised finds nothing
+        let scan = library_repo::begin_seen_paths(&conn).unwrap();
+        library_repo::remove_unseen_tracks(&conn, scan, "/music").unwrap();
+        refresh(&conn).unwrap();
+        assert_eq!(count(&conn, "tracks"), 0);
+
+        // The next scan finds the files again
+        library_repo::upsert_tracks(&conn, &tracks).unwrap();
+        refresh(&conn).unwrap();
+        assert_eq!(album_of(&conn, "a"), Some(album));
+        assert_eq!(find_artist(&conn, "Artist").unwrap(), Some(artist));
+        assert!(favorite_repo::is_favorite(&conn, "album", &album.to_string()).unwrap());
+        assert!(favorite_repo::is_favorite(&conn, "artist", &artist.to_string()).unwrap());
+        assert_eq!(count(&conn, "album_ratings"), 1);
+    }
+}
@@ -2,7 +2,7 @@ use rusqlite::{params, Connection};
 use unicode_normalization::UnicodeNormalization;
 
 use crate::error::AppError;
-use crate::models::device::{AlbumSelection, Device};
+use crate::models::device::Device;
 
 /// Normalize a path key for cache lookups (must match device::sync::normalize_path)
 fn normalize_cache_key(p: &str) -> String {
@@ -194,12 +194,14 @@ pub fn save_file_cache(
 
 // --- Artist selections ---
 
-pub fn get_selected_artists(conn: &Connection, device_id: &str) -> Result<Vec<String>, AppError> {
+pub fn get_selected_artists(conn: &Connection, device_id: &str) -> Result<Vec<i64>, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT artist_name FROM device_artist_selections WHERE device_id = ?1 ORDER BY artist_name COLLATE NOCASE",
+        "SELECT s.artist_id FROM device_artist_selections s
+         JOIN artists ar ON ar.id = s.artist_id
+         WHERE s.device_id = ?1 ORDER BY ar.sort_name, ar.name",
     )?;
     let artists = stmt
-        .query_map(params![device_id], |row| row.get::<_, String>(0))?
+        .query_map(params![device_id], |row| row.get::<_, i64>(0))?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(artists)
 }
@@ -207,36 +209,32 @@ pub fn get_selected_artists(conn: &Connection, device_id: &str) -> Result<Vec<St
 pub fn set_selected_artists(
     conn: &Connection,
     device_id: &str,
-    artists: &[String],
+    artist_ids: &[i64],
 ) -> Result<(), AppError> {
     conn.execute(
         "DELETE FROM device_artist_selections WHERE device_id = ?1",
         params![device_id],
     )?;
-    let mut stmt = conn
-        .prepare("INSERT INTO device_artist_selections (device_id, artist_name) VALUES (?1, ?2)")?;
-    for artist in artists {
-        stmt.execute(params![device_id, artist])?;
+    let mut stmt = conn.prepare(
+        "INSERT OR IGNORE INTO device_artist_selections (device_id, artist_id) VALUES (?1, ?2)",
+    )?;
+    for artist_id in artist_ids {
+        stmt.execute(params![device_id, artist_id])?;
     }
     Ok(())
 }
 
 // --- Album selections ---
 
-pub fn get_selected_albums(
-    conn: &Connection,
-    device_id: &str,
-) -> Result<Vec<AlbumSelection>, AppError> {
+pub fn get_selected_albums(conn: &Connection, device_id: &str) -> Result<Vec<i64>, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT artist_name, album_name FROM device_album_selections WHERE device_id = ?1 ORDER BY artist_name COLLATE NOCASE, album_name COLLATE NOCASE",
+        "SELECT s.album_id FROM device_album_selections s
+         JOIN albums al ON al.id = s.album_id
+         JOIN artists ar ON ar.id = al.artist_id
+         WHERE s.device_id = ?1 ORDER BY ar.sort_name, ar.name, al.sort_name, al.name",
     )?;
     let albums = stmt
-        .query_map(params![device_id], |row| {
-            Ok(AlbumSelection {
-                artist_name: row.get(0)?,
-                album_name: row.get(1)?,
-            })
-        })?
+        .query_map(params![device_id], |row| row.get::<_, i64>(0))?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(albums)
 }
@@ -244,17 +242,17 @@ pub fn get_selected_albums(
 pub fn set_selected_albums(
     conn: &Connection,
     device_id: &str,
-    albums: &[AlbumSelection],
+    album_ids: &[i64],
 ) -> Result<(), AppError> {
     conn.execute(
         "DELETE FROM device_album_selections WHERE device_id = ?1",
         params![device_id],
     )?;
     let mut stmt = conn.prepare(
-        "INSERT INTO device_album_selections (device_id, artist_name, album_name) VALUES (?1, ?2, ?3)",
+        "INSERT OR IGNORE INTO device_album_selections (device_id, album_id) VALUES (?1, ?2)",
     )?;
-    for album in albums {
-        stmt.execute(params![device_id, album.artist_name, album.album_name])?;
+    for album_id in album_ids {
+        stmt.execute(params![device_id, album_id])?;
     }
     Ok(())
 }
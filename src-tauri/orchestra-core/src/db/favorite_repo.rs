@@ -5,11 +5,33 @@ use crate::error::AppError;
 use crate::models::favorite::Favorite;
 use crate::models::track::Track;
 
+/// The `favorites` column holding ids of `entity_type` ("track", "album"
+/// or "artist").
+pub fn id_column(entity_type: &str) -> Result<&'static str, AppError> {
+    match entity_type {
+        "track" => Ok("track_id"),
+        "album" => Ok("album_id"),
+        "artist" => Ok("artist_id"),
+        other => Err(AppError::General(format!("Unknown favorite type: {other}"))),
+    }
+}
+
+fn parse_id(entity_id: &str) -> Result<i64, AppError> {
+    entity_id
+        .parse()
+        .map_err(|_| AppError::General(format!("Invalid favorite id: {entity_id}")))
+}
+
+/// Marks a track, album or artist (by row id) as a favorite. Fails if the
+/// row does not exist.
 pub fn add_favorite(conn: &Connection, entity_type: &str, entity_id: &str) -> Result<(), AppError> {
+    let column = id_column(entity_type)?;
     let now = chrono::Utc::now().timestamp();
     conn.execute(
-        "INSERT OR IGNORE INTO favorites (entity_type, entity_id, created_at) VALUES (?1, ?2, ?3)",
-        params![entity_type, entity_id, now],
+        &format!(
+            "INSERT OR IGNORE INTO favorites (entity_type, {column}, created_at) VALUES (?1, ?2, ?3)"
+        ),
+        params![entity_type, parse_id(entity_id)?, now],
     )?;
     Ok(())
 }
@@ -19,9 +41,10 @@ pub fn remove_favorite(
     entity_type: &str,
     entity_id: &str,
 ) -> Result<(), AppError> {
+    let column = id_column(entity_type)?;
     conn.execute(
-        "DELETE FROM favorites WHERE entity_type = ?1 AND entity_id = ?2",
-        params![entity_type, entity_id],
+        &format!("DELETE FROM favorites WHERE {column} = ?1"),
+        params![parse_id(entity_id)?],
     )?;
     Ok(())
 }
@@ -31,55 +54,61 @@ pub fn is_favorite(
     entity_type: &str,
     entity_id: &str,
 ) -> Result<bool, AppError> {
+    let column = id_column(entity_type)?;
     let count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM favorites WHERE entity_type = ?1 AND entity_id = ?2",
-        params![entity_type, entity_id],
+        &format!("SELECT COUNT(*) FROM favorites WHERE {column} = ?1"),
+        params![parse_id(entity_id)?],
         |row| row.get(0),
     )?;
     Ok(count > 0)
 }
 
+const FAVORITE_COLUMNS: &str = "entity_type, COALESCE(track_id, album_id, artist_id), created_at";
+
+fn favorite_from_row(row: &rusqlite::Row) -> rusqlite::Result<Favorite> {
+    Ok(Favorite {
+        entity_type: row.get(0)?,
+        entity_id: row.get::<_, i64>(1)?.to_string(),
+        created_at: row.get(2)?,
+    })
+}
+
 pub fn list_favorites(conn: &Connection, entity_type: &str) -> Result<Vec<Favorite>, AppError> {
-    let mut stmt = conn.prepare(
-        "SELECT entity_type, entity_id, created_at FROM favorites WHERE entity_type = ?1 ORDER BY created_at DESC",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {FAVORITE_COLUMNS} FROM favorites WHERE entity_type = ?1 ORDER BY created_at DESC"
+    ))?;
     let favorites = stmt
-        .query_map(params![entity_type], |row| {
-            Ok(Favorite {
-                entity_type: row.get(0)?,
-                entity_id: row.get(1)?,
-                created_at: row.get(2)?,
-            })
-        })?
+        .query_map(params![entity_type], favorite_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(favorites)
 }
 
 pub fn list_all_favorites(conn: &Connection) -> Result<Vec<Favorite>, AppError> {
-    let mut stmt = conn.prepare(
-        "SELECT entity_type, entity_id, created_at FROM favorites ORDER BY entity_type, created_at DESC",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {FAVORITE_COLUMNS} FROM favorites ORDER BY entity_type, created_at DESC"
+    ))?;
     let favorites = stmt
-        .query_map([], |row| {
-            Ok(Favorite {
-                entity_type: row.get(0)?,
-                entity_id: row.get(1)?,
-                created_at: row.get(2)?,
-            })
-        })?
+        .query_map([], favorite_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(favorites)
 }
 
+/// Favorite tracks plus every track of a favorite album or artist, most
+/// recently favorited first, in album order within each favorite.
 pub fn get_favorite_tracks(conn: &Connection) -> Result<Vec<Track>, AppError> {
     let mut stmt = conn.prepare(
         "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist,
                 t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
-                t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at
-         FROM favorites f
-         JOIN tracks t ON t.id = CAST(f.entity_id AS INTEGER)
-         WHERE f.entity_type = 'track'
-         ORDER BY f.created_at DESC",
+                t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+                t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics
+         FROM tracks t
+         LEFT JOIN albums al ON al.id = t.album_id
+         JOIN favorites f
+           ON f.track_id = t.id OR f.album_id = t.album_id OR f.artist_id = al.artist_id
+         GROUP BY t.id
+         ORDER BY MAX(f.created_at) DESC,
+                  COALESCE(t.album_artist, t.artist) COLLATE NOCASE,
+                  t.album COLLATE NOCASE, t.disc_number, t.track_number",
     )?;
     let tracks = stmt
         .query_map([], track_from_row)?
@@ -101,11 +130,20 @@ pub fn toggle_favorite(
     }
 }
 
+/// Moves a track favorite from `from_id` to `to_id` (a no-op if `from_id`
+/// was not a favorite).
+pub fn reassign_track(conn: &Connection, from_id: i64, to_id: i64) -> Result<(), AppError> {
+    if is_favorite(conn, "track", &from_id.to_string())? {
+        remove_favorite(conn, "track", &from_id.to_string())?;
+        add_favorite(conn, "track", &to_id.to_string())?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::library_repo;
-    use crate::db::schema;
+    use crate::db::{album_repo, library_repo, schema};
     use crate::models::track::Track;
 
     fn setup_db() -> Connection {
@@ -114,6 +152,16 @@ mod tests {
         conn
     }
 
+    /// The artist and album ids of the test tracks, after refreshing them.
+    fn entities(conn: &Connection) -> (String, String) {
+        album_repo::refresh(conn).unwrap();
+        let artist = album_repo::find_artist(conn, "Artist").unwrap().unwrap();
+        let album = album_repo::find_album(conn, artist, "Album")
+            .unwrap()
+            .unwrap();
+        (artist.to_string(), album.to_string())
+    }
+
     fn insert_test_track(conn: &Connection, file_suffix: &str) -> i64 {
         let track = Track {
             id: None,
@@ -136,6 +184,14 @@ mod tests {
             has_album_art: false,
             bitrate: None,
             scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
         };
         library_repo::upsert_track(conn, &track).unwrap();
         conn.query_row(
@@ -175,8 +231,10 @@ mod tests {
     #[test]
     fn test_add_duplicate_favorite_is_idempotent() {
         let conn = setup_db();
-        add_favorite(&conn, "artist", "Pink Floyd").unwrap();
-        add_favorite(&conn, "artist", "Pink Floyd").unwrap();
+        insert_test_track(&conn, "t1");
+        let (artist, _) = entities(&conn);
+        add_favorite(&conn, "artist", &artist).unwrap();
+        add_favorite(&conn, "artist", &artist).unwrap();
         let favs = list_favorites(&conn, "artist").unwrap();
         assert_eq!(favs.len(), 1);
     }
@@ -185,12 +243,13 @@ mod tests {
     fn test_list_favorites_by_type() {
         let conn = setup_db();
         let track_id = insert_test_track(&conn, "t1");
-        add_favorite(&conn, "artist", "Pink Floyd").unwrap();
+        let (artist, _) = entities(&conn);
+        add_favorite(&conn, "artist", &artist).unwrap();
         add_favorite(&conn, "track", &track_id.to_string()).unwrap();
 
         let artists = list_favorites(&conn, "artist").unwrap();
         assert_eq!(artists.len(), 1);
-        assert_eq!(artists[0].entity_id, "Pink Floyd");
+        assert_eq!(artists[0].entity_id, artist);
 
         let tracks = list_favorites(&conn, "track").unwrap();
         assert_eq!(tracks.len(), 1);
@@ -199,14 +258,25 @@ mod tests {
     #[test]
     fn test_list_all_favorites() {
         let conn = setup_db();
-        add_favorite(&conn, "artist", "Pink Floyd").unwrap();
-        add_favorite(&conn, "album", "Pink Floyd\0The Wall").unwrap();
-        add_favorite(&conn, "track", "42").unwrap();
+        let track_id = insert_test_track(&conn, "t1");
+        let (artist, album) = entities(&conn);
+        add_favorite(&conn, "artist", &artist).unwrap();
+        add_favorite(&conn, "album", &album).unwrap();
+        add_favorite(&conn, "track", &track_id.to_string()).unwrap();
 
         let all = list_all_favorites(&conn).unwrap();
         assert_eq!(all.len(), 3);
     }
 
+    #[test]
+    fn test_favorites_require_existing_rows() {
+        let conn = setup_db();
+        assert!(add_favorite(&conn, "track", "42").is_err());
+        assert!(add_favorite(&conn, "artist", "Pink Floyd").is_err());
+        assert!(add_favorite(&conn, "playlist", "1").is_err());
+        assert!(list_all_favorites(&conn).unwrap().is_empty());
+    }
+
     #[test]
     fn test_get_favorite_tracks_returns_full_track_data() {
         let conn = setup_db();
@@ -220,7 +290,30 @@ mod tests {
     }
 
     #[test]
-    fn test_get_favorite_tracks_skips_deleted_tracks() {
+    fn test_get_favorite_tracks_expands_albums_and_artists() {
+        let conn = setup_db();
+        let t1 = insert_test_track(&conn, "t1");
+        let t2 = insert_test_track(&conn, "t2");
+        let (artist, album) = entities(&conn);
+        add_favorite(&conn, "track", &t1.to_string()).unwrap();
+        add_favorite(&conn, "album", &album).unwrap();
+        add_favorite(&conn, "artist", &artist).unwrap();
+
+        let ids: Vec<_> = get_favorite_tracks(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id.unwrap())
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&t1) && ids.contains(&t2));
+
+        remove_favorite(&conn, "track", &t1.to_string()).unwrap();
+        remove_favorite(&conn, "artist", &artist).unwrap();
+        assert_eq!(get_favorite_tracks(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_deleted_track_loses_its_favorite() {
         let conn = setup_db();
         let track_id = insert_test_track(&conn, "t1");
         add_favorite(&conn, "track", &track_id.to_string()).unwrap();
@@ -228,19 +321,37 @@ mod tests {
         conn.execute("DELETE FROM tracks WHERE id = ?1", params![track_id])
             .unwrap();
 
-        let tracks = get_favorite_tracks(&conn).unwrap();
-        assert!(tracks.is_empty());
+        assert!(get_favorite_tracks(&conn).unwrap().is_empty());
+        assert!(list_all_favorites(&conn).unwrap().is_empty());
     }
 
     #[test]
     fn test_toggle_favorite_add_then_remove() {
         let conn = setup_db();
-        let result1 = toggle_favorite(&conn, "artist", "Radiohead").unwrap();
+        insert_test_track(&conn, "t1");
+        let (artist, _) = entities(&conn);
+        let result1 = toggle_favorite(&conn, "artist", &artist).unwrap();
         assert!(result1); // added
 
-        let result2 = toggle_favorite(&conn, "artist", "Radiohead").unwrap();
+        let result2 = toggle_favorite(&conn, "artist", &artist).unwrap();
         assert!(!result2); // removed
 
-        assert!(!is_favorite(&conn, "artist", "Radiohead").unwrap());
+        assert!(!is_favorite(&conn, "artist", &artist).unwrap());
+    }
+
+    #[test]
+    fn test_reassign_track() {
+        let conn = setup_db();
+        let dup = insert_test_track(&conn, "dup");
+        let keeper = insert_test_track(&conn, "keeper");
+        add_favorite(&conn, "track", &dup.to_string()).unwrap();
+
+        reassign_track(&conn, dup, keeper).unwrap();
+        assert!(!is_favorite(&conn, "track", &dup.to_string()).unwrap());
+        assert!(is_favorite(&conn, "track", &keeper.to_string()).unwrap());
+
+        // Not a favorite: nothing to move
+        reassign_track(&conn, dup, keeper).unwrap();
+        assert_eq!(list_favorites(&conn, "track").unwrap().len(), 1);
     }
 }
@@ -1,14 +1,24 @@
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension, Statement};
+use std::borrow::Borrow;
 use std::collections::HashMap;
 
 use crate::error::AppError;
-use crate::models::device::{AlbumSelection, AlbumSummary, ArtistSummary};
+use crate::models::device::{AlbumSummary, ArtistSummary};
 use crate::models::duplicate::{DuplicateGroup, DuplicateMatchType};
+use crate::models::lyrics::LyricsKind;
 use crate::models::track::{
-    AlbumNode, ArtistNode, FormatStat, GenreStat, LibraryStats, LibraryTree, Track,
+    AlbumNode, ArtistNode, FormatStat, GenreStat, HashKind, LibraryStats, LibraryTree, Track,
+    UpsertSummary,
 };
+use crate::rating;
+use crate::scanner::fingerprint;
+use crate::scanner::loudness::Gain;
 
-/// Maps a row from a SELECT that returns all 20 Track columns (id first) to a Track struct.
+/// Tracks whose durations differ by more than this are never compared by
+/// fingerprint.
+const FINGERPRINT_DURATION_TOLERANCE_SECS: f64 = 5.0;
+
+/// Maps a row from a SELECT that returns all 27 Track columns (id first) to a Track struct.
 pub(crate) fn track_from_row(row: &rusqlite::Row) -> rusqlite::Result<Track> {
     Ok(Track {
         id: Some(row.get(0)?),
@@ -31,78 +41,211 @@ pub(crate) fn track_from_row(row: &rusqlite::Row) -> rusqlite::Result<Track> {
         has_album_art: row.get(17)?,
         bitrate: row.get(18)?,
         scanned_at: row.get(19)?,
+        audio_hash: row.get(20)?,
+        replaygain_track_gain: row.get(21)?,
+        replaygain_track_peak: row.get(22)?,
+        replaygain_album_gain: row.get(23)?,
+        replaygain_album_peak: row.get(24)?,
+        rating: row.get(25)?,
+        album_id: row.get(26)?,
+        lyrics: row
+            .get::<_, Option<String>>(27)?
+            .as_deref()
+            .and_then(LyricsKind::parse),
     })
 }
 
+/// `tracks.lyrics` for a scanned file without lyrics. NULL means the file
+/// has not been scanned since lyrics were tracked.
+const NO_LYRICS: &str = "none";
+
+fn lyrics_value(kind: Option<LyricsKind>) -> &'static str {
+    kind.map_or(NO_LYRICS, LyricsKind::as_str)
+}
+
+const UPSERT_TRACK_SQL: &str =
+    "INSERT INTO tracks (file_path, relative_path, library_root, title, artist, album_artist, album,
+     track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+     replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, lyrics)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26)
+     ON CONFLICT(file_path) DO UPDATE SET
+       relative_path=excluded.relative_path, library_root=excluded.library_root,
+       title=excluded.title, artist=excluded.artist, album_artist=excluded.album_artist,
+       album=excluded.album, track_number=excluded.track_number, disc_number=excluded.disc_number,
+       year=excluded.year, genre=excluded.genre, duration_secs=excluded.duration_secs,
+       format=excluded.format, file_size=excluded.file_size, modified_at=excluded.modified_at,
+       hash=excluded.hash, has_album_art=excluded.has_album_art, bitrate=excluded.bitrate,
+       scanned_at=excluded.scanned_at, audio_hash=excluded.audio_hash,
+       replaygain_track_gain=CASE WHEN excluded.replaygain_track_gain IS NULL AND tracks.modified_at = excluded.modified_at
+         THEN tracks.replaygain_track_gain ELSE excluded.replaygain_track_gain END,
+       replaygain_track_peak=CASE WHEN excluded.replaygain_track_gain IS NULL AND tracks.modified_at = excluded.modified_at
+         THEN tracks.replaygain_track_peak ELSE excluded.replaygain_track_peak END,
+       replaygain_album_gain=CASE WHEN excluded.replaygain_track_gain IS NULL AND tracks.modified_at = excluded.modified_at
+         THEN tracks.replaygain_album_gain ELSE excluded.replaygain_album_gain END,
+       replaygain_album_peak=CASE WHEN excluded.replaygain_track_gain IS NULL AND tracks.modified_at = excluded.modified_at
+         THEN tracks.replaygain_album_peak ELSE excluded.replaygain_album_peak END,
+       rating=COALESCE(excluded.rating, tracks.rating), lyrics=excluded.lyrics";
+
+fn execute_upsert(stmt: &mut Statement, track: &Track, now: i64) -> Result<(), AppError> {
+    stmt.execute(params![
+        track.file_path,
+        track.relative_path,
+        track.library_root,
+        track.title,
+        track.artist,
+        track.album_artist,
+        track.album,
+        track.track_number,
+        track.disc_number,
+        track.year,
+        track.genre,
+        track.duration_secs,
+        track.format,
+        track.file_size,
+        track.modified_at,
+        track.hash,
+        track.has_album_art,
+        track.bitrate,
+        now,
+        track.audio_hash,
+        track.replaygain_track_gain,
+        track.replaygain_track_peak,
+        track.replaygain_album_gain,
+        track.replaygain_album_peak,
+        track.rating,
+        lyrics_value(track.lyrics),
+    ])?;
+    Ok(())
+}
+
+/// Inserts or updates a track by path. ReplayGain values from analysis are
+/// kept when the file is unchanged and carries no ReplayGain tags itself; a
+/// rating set in the app is kept when the file's tags have none.
 pub fn upsert_track(conn: &Connection, track: &Track) -> Result<(), AppError> {
     let now = chrono::Utc::now().timestamp();
-    conn.execute(
-        "INSERT INTO tracks (file_path, relative_path, library_root, title, artist, album_artist, album,
-         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)
-         ON CONFLICT(file_path) DO UPDATE SET
-           relative_path=excluded.relative_path, library_root=excluded.library_root,
-           title=excluded.title, artist=excluded.artist, album_artist=excluded.album_artist,
-           album=excluded.album, track_number=excluded.track_number, disc_number=excluded.disc_number,
-           year=excluded.year, genre=excluded.genre, duration_secs=excluded.duration_secs,
-           format=excluded.format, file_size=excluded.file_size, modified_at=excluded.modified_at,
-           hash=excluded.hash, has_album_art=excluded.has_album_art, bitrate=excluded.bitrate,
-           scanned_at=excluded.scanned_at",
-        params![
-            track.file_path,
-            track.relative_path,
-            track.library_root,
-            track.title,
-            track.artist,
-            track.album_artist,
-            track.album,
-            track.track_number,
-            track.disc_number,
-            track.year,
-            track.genre,
-            track.duration_secs,
-            track.format,
-            track.file_size,
-            track.modified_at,
-            track.hash,
-            track.has_album_art,
-            track.bitrate,
-            now,
-        ],
-    )?;
-    Ok(())
+    execute_upsert(&mut *conn.prepare_cached(UPSERT_TRACK_SQL)?, track, now)
 }
 
-pub fn remove_tracks_not_in(
-    conn: &Connection,
-    library_root: &str,
-    existing_paths: &[String],
-) -> Result<usize, AppError> {
-    if existing_paths.is_empty() {
-        let deleted = conn.execute(
-            "DELETE FROM tracks WHERE library_root = ?1",
-            params![library_root],
+/// Whether upserting `track` over `existing` would leave the row as it is
+/// (apart from `scanned_at`).
+fn upsert_is_noop(existing: &Track, track: &Track) -> bool {
+    let mut merged = track.clone();
+    merged.id = existing.id;
+    merged.scanned_at = existing.scanned_at;
+    // Set by `album_repo::refresh`, never by the scanner
+    merged.album_id = existing.album_id;
+    if track.replaygain_track_gain.is_none() && track.modified_at == existing.modified_at {
+        merged.replaygain_track_gain = existing.replaygain_track_gain;
+        merged.replaygain_track_peak = existing.replaygain_track_peak;
+        merged.replaygain_album_gain = existing.replaygain_album_gain;
+        merged.replaygain_album_peak = existing.replaygain_album_peak;
+    }
+    merged.rating = track.rating.or(existing.rating);
+    merged == *existing
+}
+
+/// Upserts many tracks with one prepared statement, inside a single
+/// transaction unless the caller already opened one. Tracks identical to
+/// their stored row are not written, so their `scanned_at` is kept, unless
+/// the row has yet to record the file's lyrics.
+pub fn upsert_tracks<I>(conn: &Connection, tracks: I) -> Result<UpsertSummary, AppError>
+where
+    I: IntoIterator,
+    I::Item: Borrow<Track>,
+{
+    let tx = if conn.is_autocommit() {
+        Some(conn.unchecked_transaction()?)
+    } else {
+        None
+    };
+    let now = chrono::Utc::now().timestamp();
+    let mut summary = UpsertSummary::default();
+    {
+        let mut existing_stmt = conn.prepare(
+            "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
+                    track_number, disc_number, year, genre, duration_secs, format, file_size,
+                    modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+                    replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics,
+                    lyrics IS NULL
+             FROM tracks WHERE file_path = ?1",
         )?;
-        return Ok(deleted);
+        let mut upsert_stmt = conn.prepare(UPSERT_TRACK_SQL)?;
+        for track in tracks {
+            let track = track.borrow();
+            let existing = existing_stmt
+                .query_row(params![track.file_path], |row| {
+                    Ok((track_from_row(row)?, row.get::<_, bool>(28)?))
+                })
+                .optional()?;
+            match existing {
+                Some((existing, false)) if upsert_is_noop(&existing, track) => {
+                    summary.unchanged += 1
+                }
+                Some(_) => {
+                    execute_upsert(&mut upsert_stmt, track, now)?;
+                    summary.updated += 1;
+                }
+                None => {
+                    execute_upsert(&mut upsert_stmt, track, now)?;
+                    summary.inserted += 1;
+                }
+            }
+        }
     }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(summary)
+}
 
-    let placeholders: Vec<String> = (0..existing_paths.len())
-        .map(|i| format!("?{}", i + 2))
-        .collect();
-    let sql = format!(
-        "DELETE FROM tracks WHERE library_root = ?1 AND file_path NOT IN ({})",
-        placeholders.join(",")
-    );
+/// Starts recording the files found by a scan in a connection-local temp
+/// table, for `remove_unseen_tracks` to compare against, and returns the id
+/// the scan's paths are kept under. Temp tables belong to one connection, so
+/// the whole scan must use the same one (the writer); scans running at the
+/// same time each get their own id.
+pub fn begin_seen_paths(conn: &Connection) -> Result<i64, AppError> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS seen_paths (
+             scan_id INTEGER NOT NULL,
+             file_path TEXT NOT NULL,
+             PRIMARY KEY (scan_id, file_path)
+         );
+         CREATE TEMP TABLE IF NOT EXISTS seen_scans (id INTEGER PRIMARY KEY AUTOINCREMENT);",
+    )?;
+    conn.execute("INSERT INTO temp.seen_scans DEFAULT VALUES", [])?;
+    Ok(conn.last_insert_rowid())
+}
 
-    let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-    param_values.push(Box::new(library_root.to_string()));
-    for p in existing_paths {
-        param_values.push(Box::new(p.clone()));
+pub fn mark_paths_seen<I>(conn: &Connection, scan_id: i64, paths: I) -> Result<(), AppError>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO temp.seen_paths (scan_id, file_path) VALUES (?1, ?2)",
+    )?;
+    for path in paths {
+        stmt.execute(params![scan_id, path.as_ref()])?;
     }
-    let params_ref: Vec<&dyn rusqlite::types::ToSql> =
-        param_values.iter().map(|p| p.as_ref()).collect();
+    Ok(())
+}
 
-    let deleted = conn.execute(&sql, params_ref.as_slice())?;
+/// Deletes the tracks under `library_root` whose paths were not marked seen
+/// by scan `scan_id`, then drops that scan's seen list.
+pub fn remove_unseen_tracks(
+    conn: &Connection,
+    scan_id: i64,
+    library_root: &str,
+) -> Result<usize, AppError> {
+    let deleted = conn.execute(
+        "DELETE FROM tracks WHERE library_root = ?1
+           AND file_path NOT IN (SELECT file_path FROM temp.seen_paths WHERE scan_id = ?2)",
+        params![library_root, scan_id],
+    )?;
+    conn.execute(
+        "DELETE FROM temp.seen_paths WHERE scan_id = ?1",
+        params![scan_id],
+    )?;
     Ok(deleted)
 }
 
@@ -142,44 +285,60 @@ pub fn remove_tracks_by_directory(
     Ok(deleted)
 }
 
-/// Returns a map of file_path -> (file_size, modified_at) for all tracks in a library root.
+/// `(file_size, modified_at, has_lyrics)` of a scanned file. `has_lyrics` is
+/// `None` for files whose lyrics were never read.
+pub type ScanFingerprint = (u64, i64, Option<bool>);
+
+/// Returns a map of file_path -> fingerprint for all tracks in a library root.
 /// Used by incremental scan to skip unchanged files.
 pub fn get_track_fingerprints(
     conn: &Connection,
     library_root: &str,
-) -> Result<HashMap<String, (u64, i64)>, AppError> {
-    let mut stmt = conn
-        .prepare("SELECT file_path, file_size, modified_at FROM tracks WHERE library_root = ?1")?;
+) -> Result<HashMap<String, ScanFingerprint>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT file_path, file_size, modified_at, lyrics FROM tracks WHERE library_root = ?1",
+    )?;
     let rows = stmt.query_map(params![library_root], |row| {
         Ok((
             row.get::<_, String>(0)?,
             row.get::<_, u64>(1)?,
             row.get::<_, i64>(2)?,
+            row.get::<_, Option<String>>(3)?,
         ))
     })?;
     let mut map = HashMap::new();
     for row in rows {
-        let (path, size, mtime) = row?;
-        map.insert(path, (size, mtime));
+        let (path, size, mtime, lyrics) = row?;
+        map.insert(path, (size, mtime, lyrics.map(|l| l != NO_LYRICS)));
     }
     Ok(map)
 }
 
+/// The library grouped by artist and album, both in sort-name order.
 pub fn get_library_tree(conn: &Connection, library_root: &str) -> Result<LibraryTree, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
-         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at
-         FROM tracks WHERE library_root = ?1
-         ORDER BY COALESCE(album_artist, artist) COLLATE NOCASE,
-                  album COLLATE NOCASE,
-                  disc_number,
-                  track_number",
+        "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist, t.album_artist, t.album,
+         t.track_number, t.disc_number, t.year, t.genre, t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+         t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics
+         FROM tracks t
+         LEFT JOIN albums al ON al.id = t.album_id
+         LEFT JOIN artists ar ON ar.id = al.artist_id
+         WHERE t.library_root = ?1
+         ORDER BY COALESCE(ar.sort_name, LOWER(COALESCE(t.album_artist, t.artist))),
+                  COALESCE(al.sort_name, LOWER(t.album)),
+                  t.disc_number,
+                  t.track_number",
     )?;
 
     let tracks = stmt
         .query_map(params![library_root], track_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
+    let album_artists: HashMap<i64, i64> = conn
+        .prepare("SELECT id, artist_id FROM albums")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
     let total_tracks = tracks.len();
     let mut artists: Vec<ArtistNode> = Vec::new();
 
@@ -198,17 +357,22 @@ pub fn get_library_tree(conn: &Connection, library_root: &str) -> Result<Library
             a
         } else {
             artists.push(ArtistNode {
+                id: None,
                 name: artist_name.clone(),
                 albums: Vec::new(),
             });
             artists.last_mut().unwrap()
         };
+        if let Some(artist_id) = track.album_id.and_then(|id| album_artists.get(&id)) {
+            artist_node.id.get_or_insert(*artist_id);
+        }
 
         let album_node =
             if let Some(a) = artist_node.albums.iter_mut().find(|a| a.name == album_name) {
                 a
             } else {
                 artist_node.albums.push(AlbumNode {
+                    id: None,
                     name: album_name.clone(),
                     year: track.year,
                     tracks: Vec::new(),
@@ -216,6 +380,9 @@ pub fn get_library_tree(conn: &Connection, library_root: &str) -> Result<Library
                 artist_node.albums.last_mut().unwrap()
             };
 
+        if let Some(album_id) = track.album_id {
+            album_node.id.get_or_insert(album_id);
+        }
         album_node.tracks.push(track);
     }
 
@@ -226,148 +393,142 @@ pub fn get_library_tree(conn: &Connection, library_root: &str) -> Result<Library
     })
 }
 
+/// Artists with tracks under `library_root`, by sort name.
 pub fn list_artists(conn: &Connection, library_root: &str) -> Result<Vec<ArtistSummary>, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT COALESCE(album_artist, artist, 'Unknown Artist') as display_artist,
-                COUNT(DISTINCT album) as album_count,
-                COUNT(*) as track_count,
-                SUM(file_size) as total_size
-         FROM tracks
-         WHERE library_root = ?1
-         GROUP BY display_artist
-         ORDER BY display_artist COLLATE NOCASE",
+        "SELECT ar.id, ar.name, ar.sort_name,
+                COUNT(DISTINCT al.id), COUNT(*), SUM(t.file_size)
+         FROM artists ar
+         JOIN albums al ON al.artist_id = ar.id
+         JOIN tracks t ON t.album_id = al.id
+         WHERE t.library_root = ?1
+         GROUP BY ar.id
+         ORDER BY ar.sort_name, ar.name",
     )?;
     let artists = stmt
         .query_map(params![library_root], |row| {
             Ok(ArtistSummary {
-                name: row.get(0)?,
-                album_count: row.get::<_, i64>(1)? as usize,
-                track_count: row.get::<_, i64>(2)? as usize,
-                total_size: row.get::<_, i64>(3)? as u64,
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sort_name: row.get(2)?,
+                album_count: row.get::<_, i64>(3)? as usize,
+                track_count: row.get::<_, i64>(4)? as usize,
+                total_size: row.get::<_, i64>(5)? as u64,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(artists)
 }
 
+/// Finds tracks whose title, artist, album or album artist contains the
+/// query. Terms such as `rating>=4` (see `rating::RatingFilter`) filter by
+/// stars instead.
 pub fn search_tracks(conn: &Connection, query: &str) -> Result<Vec<Track>, AppError> {
-    let pattern = format!("%{}%", query);
-    let mut stmt = conn.prepare(
+    let (filters, text) = rating::split_query(query);
+    // Without text, a query of only filters matches every track's text
+    let pattern = (!text.is_empty() || filters.is_empty()).then(|| format!("%{}%", text));
+    let mut conditions = Vec::new();
+    if pattern.is_some() {
+        conditions.push(
+            "(title LIKE ?1 OR artist LIKE ?1 OR album LIKE ?1 OR album_artist LIKE ?1)"
+                .to_string(),
+        );
+    }
+    for filter in &filters {
+        conditions.push(format!(
+            "MIN((COALESCE(rating, 0) + 10) / 20, 5) {} {}",
+            filter.op.sql(),
+            filter.stars
+        ));
+    }
+    let sql = format!(
         "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
-         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at
+         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+         replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
          FROM tracks
-         WHERE title LIKE ?1 OR artist LIKE ?1 OR album LIKE ?1 OR album_artist LIKE ?1
+         WHERE {}
          ORDER BY artist COLLATE NOCASE, album COLLATE NOCASE, track_number
          LIMIT 200",
-    )?;
+        conditions.join(" AND ")
+    );
+    let mut stmt = conn.prepare(&sql)?;
 
     let tracks = stmt
-        .query_map(params![pattern], track_from_row)?
+        .query_map(rusqlite::params_from_iter(pattern), track_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(tracks)
 }
 
+/// Tracks under `library_root` by any of the artists or on any of the
+/// albums, in album order.
 pub fn get_tracks_for_device(
     conn: &Connection,
     library_root: &str,
-    artist_names: &[String],
-    albums: &[AlbumSelection],
+    artist_ids: &[i64],
+    album_ids: &[i64],
 ) -> Result<Vec<Track>, AppError> {
-    if artist_names.is_empty() && albums.is_empty() {
+    if artist_ids.is_empty() && album_ids.is_empty() {
         return Ok(vec![]);
     }
 
-    // Build a UNION query for artist tracks and album tracks
-    let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-    let mut query_parts = Vec::new();
-    let mut idx = 1;
-
-    let select_cols = "id, file_path, relative_path, library_root, title, artist, album_artist, album,
-         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at";
-
-    if !artist_names.is_empty() {
-        let lib_param = format!("?{}", idx);
-        param_values.push(Box::new(library_root.to_string()));
-        idx += 1;
-
-        let placeholders: Vec<String> = artist_names
-            .iter()
-            .map(|_| {
-                let p = format!("?{}", idx);
-                idx += 1;
-                p
-            })
-            .collect();
-        for name in artist_names {
-            param_values.push(Box::new(name.clone()));
-        }
-
-        query_parts.push(format!(
-            "SELECT {} FROM tracks WHERE library_root = {} AND COALESCE(album_artist, artist, 'Unknown Artist') IN ({})",
-            select_cols, lib_param, placeholders.join(",")
-        ));
-    }
-
-    if !albums.is_empty() {
-        let lib_param = format!("?{}", idx);
-        param_values.push(Box::new(library_root.to_string()));
-        idx += 1;
-
-        let mut conditions = Vec::new();
-        for album in albums {
-            conditions.push(format!(
-                "(COALESCE(album_artist, artist, 'Unknown Artist') = ?{} AND COALESCE(album, 'Unknown Album') = ?{})",
-                idx, idx + 1
-            ));
-            param_values.push(Box::new(album.artist_name.clone()));
-            param_values.push(Box::new(album.album_name.clone()));
-            idx += 2;
-        }
-
-        query_parts.push(format!(
-            "SELECT {} FROM tracks WHERE library_root = {} AND ({})",
-            select_cols,
-            lib_param,
-            conditions.join(" OR ")
-        ));
-    }
-
+    let placeholders = |ids: &[i64], first: usize| {
+        (first..first + ids.len())
+            .map(|i| format!("?{i}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
     let sql = format!(
-        "SELECT * FROM ({}) ORDER BY COALESCE(album_artist, artist) COLLATE NOCASE, album COLLATE NOCASE, disc_number, track_number",
-        query_parts.join(" UNION ")
+        "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist, t.album_artist, t.album,
+         t.track_number, t.disc_number, t.year, t.genre, t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+         t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics
+         FROM tracks t
+         JOIN albums al ON al.id = t.album_id
+         JOIN artists ar ON ar.id = al.artist_id
+         WHERE t.library_root = ?1 AND (al.artist_id IN ({}) OR al.id IN ({}))
+         ORDER BY ar.sort_name, ar.name, al.sort_name, al.name, t.disc_number, t.track_number",
+        placeholders(artist_ids, 2),
+        placeholders(album_ids, 2 + artist_ids.len()),
     );
-
-    let params_ref: Vec<&dyn rusqlite::types::ToSql> =
-        param_values.iter().map(|p| p.as_ref()).collect();
+    let mut values: Vec<rusqlite::types::Value> = vec![library_root.to_string().into()];
+    values.extend(artist_ids.iter().chain(album_ids).map(|id| (*id).into()));
 
     let mut stmt = conn.prepare(&sql)?;
     let tracks = stmt
-        .query_map(params_ref.as_slice(), track_from_row)?
+        .query_map(rusqlite::params_from_iter(values), track_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(tracks)
 }
 
+/// Albums with tracks under `library_root`, by artist and album sort name.
+/// Counts and sizes cover only the tracks under the root.
 pub fn list_albums(conn: &Connection, library_root: &str) -> Result<Vec<AlbumSummary>, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT COALESCE(album_artist, artist, 'Unknown Artist') as display_artist,
-                COALESCE(album, 'Unknown Album') as display_album,
-                COUNT(*) as track_count,
-                SUM(file_size) as total_size,
-                MAX(year) as year
-         FROM tracks
-         WHERE library_root = ?1
-         GROUP BY display_artist, display_album
-         ORDER BY display_artist COLLATE NOCASE, display_album COLLATE NOCASE",
+        "SELECT al.id, ar.id, ar.name, al.name, al.sort_name, COUNT(*), SUM(t.file_size),
+                al.year_max, al.year_min, al.duration_secs, al.cover_track_id, r.rating
+         FROM albums al
+         JOIN artists ar ON ar.id = al.artist_id
+         JOIN tracks t ON t.album_id = al.id
+         LEFT JOIN album_ratings r ON r.album_id = al.id
+         WHERE t.library_root = ?1
+         GROUP BY al.id
+         ORDER BY ar.sort_name, ar.name, al.sort_name, al.name",
     )?;
     let albums = stmt
         .query_map(params![library_root], |row| {
             Ok(AlbumSummary {
-                artist_name: row.get(0)?,
-                album_name: row.get(1)?,
-                track_count: row.get::<_, i64>(2)? as usize,
-                total_size: row.get::<_, i64>(3)? as u64,
-                year: row.get(4)?,
+                id: row.get(0)?,
+                artist_id: row.get(1)?,
+                artist_name: row.get(2)?,
+                album_name: row.get(3)?,
+                sort_name: row.get(4)?,
+                track_count: row.get::<_, i64>(5)? as usize,
+                total_size: row.get::<_, i64>(6)? as u64,
+                year: row.get(7)?,
+                year_min: row.get(8)?,
+                duration_secs: row.get(9)?,
+                cover_track_id: row.get(10)?,
+                rating: row.get(11)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -380,7 +541,8 @@ pub fn get_incomplete_tracks(
 ) -> Result<Vec<Track>, AppError> {
     let mut stmt = conn.prepare(
         "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
-         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at
+         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+         replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
          FROM tracks
          WHERE library_root = ?1
            AND (title IS NULL OR artist IS NULL OR album IS NULL OR has_album_art = 0)
@@ -413,15 +575,15 @@ pub fn get_library_stats(conn: &Connection, library_root: &str) -> Result<Librar
         )?;
 
     let total_artists: usize = conn.query_row(
-        "SELECT COUNT(DISTINCT COALESCE(album_artist, artist, 'Unknown Artist'))
-         FROM tracks WHERE library_root = ?1",
+        "SELECT COUNT(DISTINCT al.artist_id)
+         FROM tracks t JOIN albums al ON al.id = t.album_id
+         WHERE t.library_root = ?1",
         params![library_root],
         |row| row.get::<_, i64>(0).map(|v| v as usize),
     )?;
 
     let total_albums: usize = conn.query_row(
-        "SELECT COUNT(DISTINCT COALESCE(album, 'Unknown Album'))
-         FROM tracks WHERE library_root = ?1",
+        "SELECT COUNT(DISTINCT album_id) FROM tracks WHERE library_root = ?1",
         params![library_root],
         |row| row.get::<_, i64>(0).map(|v| v as usize),
     )?;
@@ -469,19 +631,34 @@ pub fn get_library_stats(conn: &Connection, library_root: &str) -> Result<Librar
     })
 }
 
+/// The `tracks` column holding the given kind of hash.
+fn hash_column(kind: &HashKind) -> &'static str {
+    match kind {
+        HashKind::File => "hash",
+        HashKind::Audio => "audio_hash",
+    }
+}
+
 pub fn find_hash_duplicates(
     conn: &Connection,
     library_root: &str,
+    kind: &HashKind,
 ) -> Result<Vec<DuplicateGroup>, AppError> {
+    let column = hash_column(kind);
+    let match_type = match kind {
+        HashKind::File => DuplicateMatchType::ContentHash,
+        HashKind::Audio => DuplicateMatchType::AudioHash,
+    };
+
     // Step 1: Find hashes that appear more than once
-    let mut hash_stmt = conn.prepare(
-        "SELECT hash, COUNT(*) as cnt
+    let mut hash_stmt = conn.prepare(&format!(
+        "SELECT {column}, COUNT(*) as cnt
          FROM tracks
-         WHERE library_root = ?1 AND hash IS NOT NULL
-         GROUP BY hash
+         WHERE library_root = ?1 AND {column} IS NOT NULL
+         GROUP BY {column}
          HAVING cnt > 1
-         ORDER BY cnt DESC",
-    )?;
+         ORDER BY cnt DESC"
+    ))?;
     let dup_hashes: Vec<String> = hash_stmt
         .query_map(params![library_root], |row| row.get::<_, String>(0))?
         .collect::<Result<Vec<_>, _>>()?;
@@ -489,30 +666,35 @@ pub fn find_hash_duplicates(
     // Step 2: For each hash, fetch the full tracks
     let mut groups = Vec::new();
     for hash in dup_hashes {
-        let mut track_stmt = conn.prepare(
+        let mut track_stmt = conn.prepare(&format!(
             "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
-             track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at
+             track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+         replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
              FROM tracks
-             WHERE library_root = ?1 AND hash = ?2
-             ORDER BY file_path",
-        )?;
+             WHERE library_root = ?1 AND {column} = ?2
+             ORDER BY file_path"
+        ))?;
         let tracks = track_stmt
             .query_map(params![library_root, hash], track_from_row)?
             .collect::<Result<Vec<_>, _>>()?;
 
         groups.push(DuplicateGroup {
-            match_type: DuplicateMatchType::ContentHash,
+            match_type: match_type.clone(),
             match_key: hash,
             tracks,
+            keeper_id: None,
         });
     }
 
     Ok(groups)
 }
 
+/// `kind` is the hash used by the content match, so groups it already covers
+/// are not reported twice.
 pub fn find_metadata_duplicates(
     conn: &Connection,
     library_root: &str,
+    kind: &HashKind,
 ) -> Result<Vec<DuplicateGroup>, AppError> {
     // Group by lowercase title + artist + duration rounded to nearest second
     let mut stmt = conn.prepare(
@@ -538,7 +720,8 @@ pub fn find_metadata_duplicates(
     for (title, artist, dur) in keys {
         let mut track_stmt = conn.prepare(
             "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
-             track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at
+             track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+         replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
              FROM tracks
              WHERE library_root = ?1
                AND LOWER(title) = ?2
@@ -551,9 +734,13 @@ pub fn find_metadata_duplicates(
             .collect::<Result<Vec<_>, _>>()?;
 
         // Skip groups that are entirely the same hash (already covered by content match)
+        let hash_of = |t: &Track| match kind {
+            HashKind::File => t.hash.clone(),
+            HashKind::Audio => t.audio_hash.clone(),
+        };
         let all_same_hash = tracks.len() > 1
-            && tracks[0].hash.is_some()
-            && tracks.iter().all(|t| t.hash == tracks[0].hash);
+            && hash_of(&tracks[0]).is_some()
+            && tracks.iter().all(|t| hash_of(t) == hash_of(&tracks[0]));
         if all_same_hash {
             continue;
         }
@@ -563,18 +750,186 @@ pub fn find_metadata_duplicates(
             match_type: DuplicateMatchType::MetadataSimilarity,
             match_key,
             tracks,
+            keeper_id: None,
         });
     }
 
     Ok(groups)
 }
 
+/// Groups tracks whose acoustic fingerprints are at least `threshold` similar
+/// (see `fingerprint::similarity`). Only tracks within
+/// `FINGERPRINT_DURATION_TOLERANCE_SECS` of each other that are paired up by
+/// `fingerprint::candidate_pairs` are compared. Groups
+/// that are entirely the same `kind` hash are left to the hash match.
+pub fn find_fingerprint_duplicates(
+    conn: &Connection,
+    library_root: &str,
+    threshold: f64,
+    kind: &HashKind,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist, t.album_artist, t.album,
+         t.track_number, t.disc_number, t.year, t.genre, t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+         t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics,
+         f.fingerprint
+         FROM tracks t
+         JOIN track_fingerprints f ON f.track_id = t.id
+         WHERE t.library_root = ?1 AND f.modified_at = t.modified_at
+           AND LENGTH(f.fingerprint) > 0 AND t.duration_secs IS NOT NULL",
+    )?;
+    let candidates: Vec<(Track, Vec<u32>)> = stmt
+        .query_map(params![library_root], |row| {
+            Ok((
+                track_from_row(row)?,
+                fingerprint::decode(&row.get::<_, Vec<u8>>(28)?),
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Union-find over candidates
+    let mut parent: Vec<usize> = (0..candidates.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let fingerprints: Vec<&[u32]> = candidates.iter().map(|(_, fp)| fp.as_slice()).collect();
+    for (i, j) in fingerprint::candidate_pairs(&fingerprints) {
+        let gap = candidates[i].0.duration_secs.unwrap_or_default()
+            - candidates[j].0.duration_secs.unwrap_or_default();
+        if gap.abs() > FINGERPRINT_DURATION_TOLERANCE_SECS {
+            continue;
+        }
+        if fingerprint::similarity(fingerprints[i], fingerprints[j]) >= threshold {
+            let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+            parent[a] = b;
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<Track>> = HashMap::new();
+    for (i, (track, _)) in candidates.into_iter().enumerate() {
+        members.entry(root(&mut parent, i)).or_default().push(track);
+    }
+
+    let hash_of = |t: &Track| match kind {
+        HashKind::File => t.hash.clone(),
+        HashKind::Audio => t.audio_hash.clone(),
+    };
+    let mut groups: Vec<DuplicateGroup> = members
+        .into_values()
+        .filter(|tracks| tracks.len() > 1)
+        .filter(|tracks| {
+            hash_of(&tracks[0]).is_none()
+                || tracks.iter().any(|t| hash_of(t) != hash_of(&tracks[0]))
+        })
+        .map(|mut tracks| {
+            tracks.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+            DuplicateGroup {
+                match_type: DuplicateMatchType::AcousticFingerprint,
+                match_key: tracks[0].relative_path.clone(),
+                tracks,
+                keeper_id: None,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.tracks
+            .len()
+            .cmp(&a.tracks.len())
+            .then(a.match_key.cmp(&b.match_key))
+    });
+
+    Ok(groups)
+}
+
+/// Tracks with no fingerprint yet, or whose file changed since it was taken.
+pub fn get_tracks_without_fingerprint(
+    conn: &Connection,
+    library_root: &str,
+) -> Result<Vec<(i64, String)>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.file_path FROM tracks t
+         LEFT JOIN track_fingerprints f ON f.track_id = t.id
+         WHERE t.library_root = ?1 AND (f.track_id IS NULL OR f.modified_at != t.modified_at)",
+    )?;
+    let rows = stmt
+        .query_map(params![library_root], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Stores a fingerprint against the track's current `modified_at`. An empty
+/// fingerprint records that the file could not be decoded, so it is not
+/// retried until it changes.
+pub fn save_fingerprint(
+    conn: &Connection,
+    track_id: i64,
+    fingerprint: &[u32],
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO track_fingerprints (track_id, fingerprint, modified_at)
+         SELECT id, ?2, modified_at FROM tracks WHERE id = ?1",
+        params![track_id, fingerprint::encode(fingerprint)],
+    )?;
+    Ok(())
+}
+
+/// Tracks with no ReplayGain from tags or analysis, together with the rest
+/// of their album so its album gain can be measured across all of it.
+/// Ordered so an album's tracks are adjacent.
+pub fn get_tracks_needing_replaygain(
+    conn: &Connection,
+    library_root: &str,
+) -> Result<Vec<Track>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
+         track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+         replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
+         FROM tracks t
+         WHERE library_root = ?1
+           AND (replaygain_track_gain IS NULL
+                OR (album IS NOT NULL AND EXISTS (
+                    SELECT 1 FROM tracks p
+                    WHERE p.library_root = t.library_root AND p.replaygain_track_gain IS NULL
+                      AND p.album = t.album
+                      AND COALESCE(p.album_artist, p.artist) IS COALESCE(t.album_artist, t.artist))))
+         ORDER BY COALESCE(album_artist, artist), album, disc_number, track_number, file_path",
+    )?;
+    let tracks = stmt
+        .query_map(params![library_root], track_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tracks)
+}
+
+pub fn save_replaygain(
+    conn: &Connection,
+    track_id: i64,
+    track: &Gain,
+    album: &Gain,
+) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE tracks SET replaygain_track_gain = ?2, replaygain_track_peak = ?3,
+         replaygain_album_gain = ?4, replaygain_album_peak = ?5
+         WHERE id = ?1",
+        params![track_id, track.gain, track.peak, album.gain, album.peak],
+    )?;
+    Ok(())
+}
+
 pub fn get_tracks_without_hash(
     conn: &Connection,
     library_root: &str,
+    kind: &HashKind,
 ) -> Result<Vec<(i64, String)>, AppError> {
-    let mut stmt =
-        conn.prepare("SELECT id, file_path FROM tracks WHERE library_root = ?1 AND hash IS NULL")?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, file_path FROM tracks WHERE library_root = ?1 AND {} IS NULL",
+        hash_column(kind)
+    ))?;
     let rows = stmt
         .query_map(params![library_root], |row| {
             Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
@@ -583,9 +938,14 @@ pub fn get_tracks_without_hash(
     Ok(rows)
 }
 
-pub fn update_track_hash(conn: &Connection, track_id: i64, hash: &str) -> Result<(), AppError> {
+pub fn update_track_hash(
+    conn: &Connection,
+    track_id: i64,
+    hash: &str,
+    kind: &HashKind,
+) -> Result<(), AppError> {
     conn.execute(
-        "UPDATE tracks SET hash = ?1 WHERE id = ?2",
+        &format!("UPDATE tracks SET {} = ?1 WHERE id = ?2", hash_column(kind)),
         params![hash, track_id],
     )?;
     Ok(())
@@ -610,6 +970,46 @@ pub fn delete_tracks_by_ids(conn: &Connection, ids: &[i64]) -> Result<usize, App
     Ok(deleted)
 }
 
+pub fn get_track_path(conn: &Connection, id: i64) -> Result<Option<String>, AppError> {
+    match conn.query_row(
+        "SELECT file_path FROM tracks WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    ) {
+        Ok(path) => Ok(Some(path)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::from(e)),
+    }
+}
+
+pub fn get_track(conn: &Connection, id: i64) -> Result<Option<Track>, AppError> {
+    let track = conn
+        .query_row(
+            "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
+             track_number, disc_number, year, genre, duration_secs, format, file_size, modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+             replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
+             FROM tracks WHERE id = ?1",
+            params![id],
+            track_from_row,
+        )
+        .optional()?;
+    Ok(track)
+}
+
+/// Records what lyrics a track was last found to have, for files whose
+/// lyrics or sidecar changed since they were scanned.
+pub fn set_track_lyrics(
+    conn: &Connection,
+    id: i64,
+    lyrics: Option<LyricsKind>,
+) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE tracks SET lyrics = ?1 WHERE id = ?2",
+        params![lyrics_value(lyrics), id],
+    )?;
+    Ok(())
+}
+
 #[cfg(test)]
 fn setup_db() -> Connection {
     use crate::db::schema;
@@ -619,6 +1019,7 @@ fn setup_db() -> Connection {
 }
 
 #[cfg(test)]
+#[allow(clippy::too_many_arguments)]
 fn make_track(
     artist: &str,
     album: &str,
@@ -650,6 +1051,270 @@ fn make_track(
         has_album_art: false,
         bitrate,
         scanned_at: 0,
+        audio_hash: None,
+        replaygain_track_gain: None,
+        replaygain_track_peak: None,
+        replaygain_album_gain: None,
+        replaygain_album_peak: None,
+        rating: None,
+        album_id: None,
+        lyrics: None,
+    }
+}
+
+#[cfg(test)]
+mod upsert_tests {
+    use super::*;
+
+    fn track(suffix: &str) -> Track {
+        make_track(
+            "Artist", "Album", "flac", "Rock", 1_000, 200.0, None, suffix,
+        )
+    }
+
+    fn track_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_upsert_tracks_counts() {
+        let conn = setup_db();
+        let tracks: Vec<Track> = ["a", "b", "c"].into_iter().map(track).collect();
+        let summary = upsert_tracks(&conn, &tracks).unwrap();
+        assert_eq!(
+            summary,
+            UpsertSummary {
+                inserted: 3,
+                updated: 0,
+                unchanged: 0
+            }
+        );
+
+        let mut changed = tracks.clone();
+        changed[1].title = Some("Renamed".to_string());
+        changed.push(track("d"));
+        let summary = upsert_tracks(&conn, changed).unwrap();
+        assert_eq!(
+            summary,
+            UpsertSummary {
+                inserted: 1,
+                updated: 1,
+                unchanged: 2
+            }
+        );
+        assert_eq!(track_count(&conn), 4);
+    }
+
+    #[test]
+    fn test_rescan_of_unchanged_track_with_album_is_unchanged() {
+        let conn = setup_db();
+        let t = track("a");
+        upsert_tracks(&conn, [&t]).unwrap();
+        crate::db::album_repo::refresh(&conn).unwrap();
+        let album_id: Option<i64> = conn
+            .query_row("SELECT album_id FROM tracks", [], |row| row.get(0))
+            .unwrap();
+        assert!(album_id.is_some());
+
+        // The scanner never knows the album id, which is not a change
+        assert_eq!(t.album_id, None);
+        assert_eq!(upsert_tracks(&conn, [&t]).unwrap().unchanged, 1);
+    }
+
+    #[test]
+    fn test_rescan_records_lyrics_of_tracks_scanned_before_them() {
+        let conn = setup_db();
+        let t = track("a");
+        upsert_tracks(&conn, [&t]).unwrap();
+        let lyrics_of = |conn: &Connection| -> Option<bool> {
+            get_track_fingerprints(conn, &t.library_root).unwrap()[&t.file_path].2
+        };
+        assert_eq!(lyrics_of(&conn), Some(false));
+
+        // As left by the migration that added the column
+        conn.execute("UPDATE tracks SET lyrics = NULL", []).unwrap();
+        assert_eq!(lyrics_of(&conn), None);
+        assert_eq!(upsert_tracks(&conn, [&t]).unwrap().updated, 1);
+        assert_eq!(lyrics_of(&conn), Some(false));
+        assert_eq!(upsert_tracks(&conn, [&t]).unwrap().unchanged, 1);
+
+        let id: i64 = conn
+            .query_row("SELECT id FROM tracks", [], |row| row.get(0))
+            .unwrap();
+        set_track_lyrics(&conn, id, Some(LyricsKind::Synced)).unwrap();
+        assert_eq!(lyrics_of(&conn), Some(true));
+        assert_eq!(
+            get_track(&conn, id).unwrap().unwrap().lyrics,
+            Some(LyricsKind::Synced)
+        );
+    }
+
+    #[test]
+    fn test_upsert_tracks_keeps_analysed_replaygain_unchanged() {
+        let conn = setup_db();
+        let t = track("a");
+        upsert_tracks(&conn, [&t]).unwrap();
+        let id: i64 = conn
+            .query_row("SELECT id FROM tracks", [], |row| row.get(0))
+            .unwrap();
+        let gain = Gain {
+            gain: -3.0,
+            peak: 0.9,
+        };
+        save_replaygain(&conn, id, &gain, &gain).unwrap();
+
+        // Re-reading the same untagged file is not a change
+        let summary = upsert_tracks(&conn, [&t]).unwrap();
+        assert_eq!(summary.unchanged, 1);
+        let stored: Option<f64> = conn
+            .query_row("SELECT replaygain_track_gain FROM tracks", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stored, Some(-3.0));
+    }
+
+    #[test]
+    fn test_upsert_keeps_app_rating_unless_tagged() {
+        let conn = setup_db();
+        let mut t = track("a");
+        upsert_tracks(&conn, [&t]).unwrap();
+        conn.execute("UPDATE tracks SET rating = 60", []).unwrap();
+        let stored = |conn: &Connection| -> Option<u8> {
+            conn.query_row("SELECT rating FROM tracks", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        // A file without a rating tag keeps the rating set in the app
+        assert_eq!(upsert_tracks(&conn, [&t]).unwrap().unchanged, 1);
+        assert_eq!(stored(&conn), Some(60));
+
+        t.rating = Some(100);
+        assert_eq!(upsert_tracks(&conn, [&t]).unwrap().updated, 1);
+        assert_eq!(stored(&conn), Some(100));
+    }
+
+    #[test]
+    fn test_search_rating_filters() {
+        let conn = setup_db();
+        let mut tracks: Vec<Track> = ["a", "b", "c"].into_iter().map(track).collect();
+        tracks[0].rating = Some(80);
+        tracks[1].rating = Some(40);
+        tracks[2].title = Some("Other".to_string());
+        upsert_tracks(&conn, &tracks).unwrap();
+        let found = |query: &str| -> Vec<String> {
+            search_tracks(&conn, query)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.relative_path)
+                .collect()
+        };
+
+        assert_eq!(found("rating>=4"), [tracks[0].relative_path.clone()]);
+        assert_eq!(found("other rating:0"), [tracks[2].relative_path.clone()]);
+        assert_eq!(found("Track rating<4").len(), 1);
+        assert_eq!(found("album").len(), 3);
+    }
+
+    #[test]
+    fn test_upsert_tracks_joins_open_transaction() {
+        let conn = setup_db();
+        let tx = conn.unchecked_transaction().unwrap();
+        upsert_tracks(&tx, [track("a"), track("b")]).unwrap();
+        tx.rollback().unwrap();
+        assert_eq!(track_count(&conn), 0);
+    }
+
+    #[test]
+    fn test_remove_unseen_tracks() {
+        let conn = setup_db();
+        upsert_tracks(&conn, ["a", "b", "c"].map(track)).unwrap();
+        let mut other_root = track("x");
+        other_root.library_root = "/other".to_string();
+        other_root.file_path = "/other/x.flac".to_string();
+        upsert_track(&conn, &other_root).unwrap();
+
+        let scan = begin_seen_paths(&conn).unwrap();
+        mark_paths_seen(&conn, scan, [&track("a").file_path, &track("c").file_path]).unwrap();
+        assert_eq!(remove_unseen_tracks(&conn, scan, "/music").unwrap(), 1);
+        assert_eq!(track_count(&conn), 3);
+
+        // The seen list starts over, so an empty scan clears the root
+        let scan = begin_seen_paths(&conn).unwrap();
+        assert_eq!(remove_unseen_tracks(&conn, scan, "/music").unwrap(), 2);
+        assert_eq!(track_count(&conn), 1);
+    }
+
+    #[test]
+    fn test_concurrent_scans_keep_their_own_seen_paths() {
+        let conn = setup_db();
+        upsert_tracks(&conn, ["a", "b"].map(track)).unwrap();
+
+        let first = begin_seen_paths(&conn).unwrap();
+        mark_paths_seen(&conn, first, [&track("a").file_path]).unwrap();
+        // A second scan starting and finishing midway leaves the first alone
+        let second = begin_seen_paths(&conn).unwrap();
+        assert_ne!(first, second);
+        mark_paths_seen(&conn, second, [&track("a").file_path]).unwrap();
+        mark_paths_seen(&conn, second, [&track("b").file_path]).unwrap();
+        assert_eq!(remove_unseen_tracks(&conn, second, "/music").unwrap(), 0);
+
+        mark_paths_seen(&conn, first, [&track("b").file_path]).unwrap();
+        assert_eq!(remove_unseen_tracks(&conn, first, "/music").unwrap(), 0);
+        assert_eq!(track_count(&conn), 2);
+    }
+
+    #[test]
+    fn test_albums_by_sort_name_and_device_tracks_by_id() {
+        let conn = setup_db();
+        let mut beatles = make_track(
+            "The Beatles",
+            "Abbey Road",
+            "flac",
+            "Rock",
+            1_000,
+            200.0,
+            None,
+            "a",
+        );
+        beatles.year = Some(1969);
+        upsert_tracks(
+            &conn,
+            [
+                beatles.clone(),
+                make_track("Abba", "Arrival", "flac", "Pop", 1_000, 100.0, None, "b"),
+                make_track("Can", "Tago Mago", "flac", "Rock", 1_000, 100.0, None, "c"),
+            ],
+        )
+        .unwrap();
+        crate::db::album_repo::refresh(&conn).unwrap();
+
+        let albums = list_albums(&conn, "/music").unwrap();
+        let names: Vec<&str> = albums.iter().map(|a| a.artist_name.as_str()).collect();
+        assert_eq!(names, ["Abba", "The Beatles", "Can"]);
+        let abbey_road = &albums[1];
+        assert_eq!(abbey_road.year, Some(1969));
+        assert!(abbey_road.cover_track_id.is_none());
+
+        let can = list_artists(&conn, "/music").unwrap()[2].id;
+        let selected = |conn: &Connection| -> Vec<String> {
+            get_tracks_for_device(conn, "/music", &[can], &[abbey_road.id])
+                .unwrap()
+                .into_iter()
+                .map(|t| t.relative_path)
+                .collect()
+        };
+        assert_eq!(selected(&conn).len(), 2);
+
+        // Re-tagging the album keeps its row, so the selection still holds
+        beatles.album = Some("Abbey Road (Remaster)".to_string());
+        upsert_track(&conn, &beatles).unwrap();
+        crate::db::album_repo::refresh(&conn).unwrap();
+        assert_eq!(selected(&conn).len(), 2);
+        assert!(get_tracks_for_device(&conn, "/music", &[], &[])
+            .unwrap()
+            .is_empty());
     }
 }
 
@@ -717,6 +1382,7 @@ mod stats_tests {
         )
         .unwrap();
 
+        crate::db::album_repo::refresh(&conn).unwrap();
         let stats = get_library_stats(&conn, "/music").unwrap();
         assert_eq!(stats.total_tracks, 3);
         assert_eq!(stats.total_artists, 2);
@@ -853,6 +1519,7 @@ mod stats_tests {
         })
         .unwrap();
 
+        crate::db::album_repo::refresh(&conn).unwrap();
         let stats = get_library_stats(&conn, "/music").unwrap();
         assert_eq!(stats.total_tracks, 1);
         assert_eq!(stats.total_artists, 1);
@@ -867,7 +1534,7 @@ mod duplicate_tests {
     #[test]
     fn test_find_hash_duplicates_empty_library() {
         let conn = setup_db();
-        let groups = find_hash_duplicates(&conn, "/music").unwrap();
+        let groups = find_hash_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert!(groups.is_empty());
     }
 
@@ -884,7 +1551,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t2).unwrap();
         upsert_track(&conn, &t3).unwrap();
 
-        let groups = find_hash_duplicates(&conn, "/music").unwrap();
+        let groups = find_hash_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert!(groups.is_empty());
     }
 
@@ -901,7 +1568,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t2).unwrap();
         upsert_track(&conn, &t3).unwrap();
 
-        let groups = find_hash_duplicates(&conn, "/music").unwrap();
+        let groups = find_hash_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].tracks.len(), 2);
         assert_eq!(groups[0].match_type, DuplicateMatchType::ContentHash);
@@ -915,14 +1582,14 @@ mod duplicate_tests {
         upsert_track(&conn, &t1).unwrap();
         upsert_track(&conn, &t2).unwrap();
 
-        let groups = find_hash_duplicates(&conn, "/music").unwrap();
+        let groups = find_hash_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert!(groups.is_empty());
     }
 
     #[test]
     fn test_find_metadata_duplicates_empty_library() {
         let conn = setup_db();
-        let groups = find_metadata_duplicates(&conn, "/music").unwrap();
+        let groups = find_metadata_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert!(groups.is_empty());
     }
 
@@ -942,7 +1609,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t1).unwrap();
         upsert_track(&conn, &t2).unwrap();
 
-        let groups = find_metadata_duplicates(&conn, "/music").unwrap();
+        let groups = find_metadata_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].tracks.len(), 2);
         assert_eq!(groups[0].match_type, DuplicateMatchType::MetadataSimilarity);
@@ -964,7 +1631,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t1).unwrap();
         upsert_track(&conn, &t2).unwrap();
 
-        let groups = find_metadata_duplicates(&conn, "/music").unwrap();
+        let groups = find_metadata_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert!(groups.is_empty());
     }
 
@@ -984,7 +1651,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t1).unwrap();
         upsert_track(&conn, &t2).unwrap();
 
-        let groups = find_metadata_duplicates(&conn, "/music").unwrap();
+        let groups = find_metadata_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert_eq!(groups.len(), 1);
     }
 
@@ -1004,7 +1671,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t1).unwrap();
         upsert_track(&conn, &t2).unwrap();
 
-        let groups = find_metadata_duplicates(&conn, "/music").unwrap();
+        let groups = find_metadata_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert_eq!(groups.len(), 1);
     }
 
@@ -1019,7 +1686,7 @@ mod duplicate_tests {
         upsert_track(&conn, &t2).unwrap();
         upsert_track(&conn, &t3).unwrap();
 
-        let unhashed = get_tracks_without_hash(&conn, "/music").unwrap();
+        let unhashed = get_tracks_without_hash(&conn, "/music", &HashKind::File).unwrap();
         assert_eq!(unhashed.len(), 2);
     }
 
@@ -1030,14 +1697,14 @@ mod duplicate_tests {
         upsert_track(&conn, &t1).unwrap();
 
         // Get the track id
-        let unhashed = get_tracks_without_hash(&conn, "/music").unwrap();
+        let unhashed = get_tracks_without_hash(&conn, "/music", &HashKind::File).unwrap();
         assert_eq!(unhashed.len(), 1);
         let (id, _) = &unhashed[0];
 
-        update_track_hash(&conn, *id, "new_hash").unwrap();
+        update_track_hash(&conn, *id, "new_hash", &HashKind::File).unwrap();
 
         // Verify hash is updated
-        let unhashed_after = get_tracks_without_hash(&conn, "/music").unwrap();
+        let unhashed_after = get_tracks_without_hash(&conn, "/music", &HashKind::File).unwrap();
         assert!(unhashed_after.is_empty());
 
         // Verify via direct query
@@ -1051,6 +1718,234 @@ mod duplicate_tests {
         assert_eq!(hash, Some("new_hash".to_string()));
     }
 
+    #[test]
+    fn test_find_audio_hash_duplicates_ignores_file_hash() {
+        let conn = setup_db();
+        let mut t1 = make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t1");
+        t1.hash = Some("file_a".to_string());
+        t1.audio_hash = Some("audio_same".to_string());
+        let mut t2 = make_track("A", "A1", "flac", "Rock", 50_000_100, 300.0, None, "t2");
+        t2.hash = Some("file_b".to_string());
+        t2.audio_hash = Some("audio_same".to_string());
+        upsert_track(&conn, &t1).unwrap();
+        upsert_track(&conn, &t2).unwrap();
+
+        assert!(find_hash_duplicates(&conn, "/music", &HashKind::File)
+            .unwrap()
+            .is_empty());
+
+        let groups = find_hash_duplicates(&conn, "/music", &HashKind::Audio).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].tracks.len(), 2);
+        assert_eq!(groups[0].match_type, DuplicateMatchType::AudioHash);
+        assert_eq!(groups[0].match_key, "audio_same");
+    }
+
+    fn track_id(conn: &Connection, file_path: &str) -> i64 {
+        conn.query_row(
+            "SELECT id FROM tracks WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_fingerprint_duplicates_across_formats() {
+        let conn = setup_db();
+        let song: Vec<u32> = (0..64u32).map(|i| i.wrapping_mul(2654435761)).collect();
+        let mut retagged = song.clone();
+        retagged[10] ^= 0xFF;
+        let other: Vec<u32> = song.iter().map(|v| !v).collect();
+
+        let tracks = [
+            (
+                make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t1"),
+                &song,
+            ),
+            (
+                make_track("A", "A1", "mp3", "Rock", 12_000_000, 300.4, Some(320), "t1"),
+                &retagged,
+            ),
+            (
+                make_track("B", "B1", "flac", "Rock", 50_000_000, 301.0, None, "t2"),
+                &other,
+            ),
+            // Same audio, but too far apart in duration to be compared
+            (
+                make_track("C", "C1", "flac", "Rock", 50_000_000, 320.0, None, "t3"),
+                &song,
+            ),
+        ];
+        for (track, fp) in &tracks {
+            upsert_track(&conn, track).unwrap();
+            save_fingerprint(&conn, track_id(&conn, &track.file_path), fp).unwrap();
+        }
+
+        let groups = find_fingerprint_duplicates(&conn, "/music", 0.9, &HashKind::File).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0].match_type,
+            DuplicateMatchType::AcousticFingerprint
+        );
+        let formats: Vec<&str> = groups[0].tracks.iter().map(|t| t.format.as_str()).collect();
+        assert_eq!(formats, vec!["flac", "mp3"]);
+    }
+
+    #[test]
+    fn test_find_fingerprint_duplicates_skips_identical_hash_groups() {
+        let conn = setup_db();
+        let fp: Vec<u32> = (0..64).collect();
+        for suffix in ["t1", "t2"] {
+            let mut track = make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, suffix);
+            track.hash = Some("same".to_string());
+            upsert_track(&conn, &track).unwrap();
+            save_fingerprint(&conn, track_id(&conn, &track.file_path), &fp).unwrap();
+        }
+
+        assert!(
+            find_fingerprint_duplicates(&conn, "/music", 0.9, &HashKind::File)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_get_tracks_without_fingerprint_refreshes_on_change() {
+        let conn = setup_db();
+        let mut track = make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t1");
+        upsert_track(&conn, &track).unwrap();
+        let id = track_id(&conn, &track.file_path);
+        assert_eq!(
+            get_tracks_without_fingerprint(&conn, "/music")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // An empty (undecodable) fingerprint still counts as done
+        save_fingerprint(&conn, id, &[]).unwrap();
+        assert!(get_tracks_without_fingerprint(&conn, "/music")
+            .unwrap()
+            .is_empty());
+
+        track.modified_at += 60;
+        upsert_track(&conn, &track).unwrap();
+        assert_eq!(
+            get_tracks_without_fingerprint(&conn, "/music").unwrap(),
+            vec![(id, track.file_path.clone())]
+        );
+    }
+
+    /// Track gain and album peak of a track.
+    fn stored_replaygain(conn: &Connection, id: i64) -> (Option<f64>, Option<f64>) {
+        conn.query_row(
+            "SELECT replaygain_track_gain, replaygain_album_peak FROM tracks WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_analysed_replaygain_survives_rescan() {
+        let conn = setup_db();
+        let mut track = make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t1");
+        upsert_track(&conn, &track).unwrap();
+        let id = track_id(&conn, &track.file_path);
+        assert_eq!(
+            get_tracks_needing_replaygain(&conn, "/music")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let gain = Gain {
+            gain: -4.5,
+            peak: 0.9,
+        };
+        save_replaygain(&conn, id, &gain, &gain).unwrap();
+        assert!(get_tracks_needing_replaygain(&conn, "/music")
+            .unwrap()
+            .is_empty());
+
+        // Rescanning the unchanged, untagged file keeps the analysed values
+        upsert_track(&conn, &track).unwrap();
+        let stored = stored_replaygain(&conn, id);
+        assert_eq!(stored, (Some(-4.5), Some(0.9)));
+
+        // Tags found on rescan win
+        track.replaygain_track_gain = Some(-2.0);
+        upsert_track(&conn, &track).unwrap();
+        let stored = stored_replaygain(&conn, id);
+        assert_eq!(stored, (Some(-2.0), None));
+
+        // A changed file without tags needs analysing again
+        track.replaygain_track_gain = None;
+        track.modified_at += 60;
+        upsert_track(&conn, &track).unwrap();
+        assert_eq!(
+            get_tracks_needing_replaygain(&conn, "/music")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_tracks_needing_replaygain_include_their_album() {
+        let conn = setup_db();
+        let gain = Gain {
+            gain: -4.5,
+            peak: 0.9,
+        };
+        let tracks = [
+            make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t1"),
+            make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t2"),
+            make_track("B", "B1", "flac", "Rock", 50_000_000, 300.0, None, "t3"),
+        ];
+        for track in &tracks {
+            upsert_track(&conn, track).unwrap();
+            let id = track_id(&conn, &track.file_path);
+            save_replaygain(&conn, id, &gain, &gain).unwrap();
+        }
+        assert!(get_tracks_needing_replaygain(&conn, "/music")
+            .unwrap()
+            .is_empty());
+
+        // A new track on A1 brings back the whole album, not B1
+        let added = make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t4");
+        upsert_track(&conn, &added).unwrap();
+        let pending: Vec<String> = get_tracks_needing_replaygain(&conn, "/music")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.file_path)
+            .collect();
+        assert_eq!(pending.len(), 3);
+        assert!(!pending.contains(&tracks[2].file_path));
+    }
+
+    #[test]
+    fn test_update_track_audio_hash_leaves_file_hash() {
+        let conn = setup_db();
+        let t1 = make_track("A", "A1", "flac", "Rock", 50_000_000, 300.0, None, "t1");
+        upsert_track(&conn, &t1).unwrap();
+
+        let unhashed = get_tracks_without_hash(&conn, "/music", &HashKind::Audio).unwrap();
+        let (id, _) = &unhashed[0];
+        update_track_hash(&conn, *id, "audio", &HashKind::Audio).unwrap();
+
+        assert!(get_tracks_without_hash(&conn, "/music", &HashKind::Audio)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            get_tracks_without_hash(&conn, "/music", &HashKind::File)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
     #[test]
     fn test_delete_tracks_by_ids() {
         let conn = setup_db();
@@ -1109,10 +2004,10 @@ mod duplicate_tests {
         upsert_track(&conn, &t2).unwrap();
 
         // Each library root has only 1 track with that hash, so no duplicates
-        let groups = find_hash_duplicates(&conn, "/music").unwrap();
+        let groups = find_hash_duplicates(&conn, "/music", &HashKind::File).unwrap();
         assert!(groups.is_empty());
 
-        let groups = find_hash_duplicates(&conn, "/other").unwrap();
+        let groups = find_hash_duplicates(&conn, "/other", &HashKind::File).unwrap();
         assert!(groups.is_empty());
     }
 }
@@ -1,9 +1,13 @@
+pub mod album_repo;
 pub mod device_repo;
 pub mod favorite_repo;
 pub mod library_repo;
 pub mod playlist_repo;
+pub mod pool;
 pub mod profile_repo;
+pub mod rating_repo;
 pub mod recent_repo;
 pub mod schema;
 pub mod settings_repo;
+pub mod stats_repo;
 pub mod sync_state_repo;
@@ -145,7 +145,8 @@ pub fn get_playlist_tracks(conn: &Connection, playlist_id: &str) -> Result<Vec<T
     let mut stmt = conn.prepare(
         "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist,
                 t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
-                t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at
+                t.duration_secs, t.format, t.file_size, t.modified_at, t.hash, t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+                t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics
          FROM playlist_tracks pt
          JOIN tracks t ON t.id = pt.track_id
          WHERE pt.playlist_id = ?1
@@ -166,6 +167,24 @@ pub fn get_playlist_with_tracks(
     Ok(PlaylistWithTracks { playlist, tracks })
 }
 
+/// Points every playlist entry for `from_id` at `to_id`. Playlists that
+/// already contain `to_id` just drop the `from_id` entry.
+pub fn reassign_track(conn: &Connection, from_id: i64, to_id: i64) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE OR IGNORE playlist_tracks SET track_id = ?2 WHERE track_id = ?1",
+        params![from_id, to_id],
+    )?;
+
+    let mut stmt = conn.prepare("SELECT playlist_id FROM playlist_tracks WHERE track_id = ?1")?;
+    let leftover: Vec<String> = stmt
+        .query_map(params![from_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for playlist_id in leftover {
+        remove_tracks(conn, &playlist_id, &[from_id])?;
+    }
+    Ok(())
+}
+
 fn reorder_remaining(conn: &Connection, playlist_id: &str) -> Result<(), AppError> {
     let mut stmt =
         conn.prepare("SELECT id FROM playlist_tracks WHERE playlist_id = ?1 ORDER BY position")?;
@@ -216,6 +235,14 @@ mod tests {
             has_album_art: false,
             bitrate: None,
             scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
         };
         library_repo::upsert_track(conn, &track).unwrap();
         conn.query_row(
@@ -389,4 +416,38 @@ mod tests {
         assert_eq!(fetched.name, "Renamed");
         assert_eq!(fetched.updated_at, 2000);
     }
+
+    #[test]
+    fn test_reassign_track() {
+        let conn = setup_db();
+        let dup = insert_test_track(&conn, "dup");
+        let keeper = insert_test_track(&conn, "keeper");
+        let other = insert_test_track(&conn, "other");
+        for id in ["p1", "p2"] {
+            create_playlist(
+                &conn,
+                &Playlist {
+                    id: id.into(),
+                    name: id.into(),
+                    created_at: 1000,
+                    updated_at: 1000,
+                },
+            )
+            .unwrap();
+        }
+        add_tracks(&conn, "p1", &[other, dup]).unwrap();
+        add_tracks(&conn, "p2", &[dup, keeper, other]).unwrap();
+
+        reassign_track(&conn, dup, keeper).unwrap();
+
+        let ids = |pl: &str| -> Vec<Option<i64>> {
+            get_playlist_tracks(&conn, pl)
+                .unwrap()
+                .iter()
+                .map(|t| t.id)
+                .collect()
+        };
+        assert_eq!(ids("p1"), vec![Some(other), Some(keeper)]);
+        assert_eq!(ids("p2"), vec![Some(keeper), Some(other)]);
+    }
 }
@@ -0,0 +1,266 @@
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::error::AppError;
+
+/// Read-only connections the app opens next to its writer.
+pub const DEFAULT_READERS: usize = 4;
+
+/// Rows written per transaction by long-running write loops, so other
+/// writers get the lock back between batches.
+pub const WRITE_BATCH_SIZE: usize = 200;
+
+/// How long a connection waits on a lock held by another process (the TUI,
+/// a backup) before giving up with `SQLITE_BUSY`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One writer connection plus a set of read-only connections to the same WAL
+/// database. In WAL mode readers see the last committed state and are never
+/// blocked by the writer, so a long scan no longer stalls the library view.
+///
+/// Repos take `&Connection` and work with either kind; callers choose with
+/// `read()` or `write()`.
+pub struct DbPool {
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+    reader_count: usize,
+    returned: Condvar,
+}
+
+impl DbPool {
+    /// Wraps `writer`, which must already be migrated, and opens `readers`
+    /// read-only connections to `db_path`.
+    pub fn new(writer: Connection, db_path: &Path, readers: usize) -> Result<Self, AppError> {
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX
+            | OpenFlags::SQLITE_OPEN_URI;
+        let readers = (0..readers)
+            .map(|_| {
+                let conn = Connection::open_with_flags(db_path, flags)?;
+                conn.busy_timeout(BUSY_TIMEOUT)?;
+                Ok(conn)
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(DbPool {
+            writer: Mutex::new(writer),
+            reader_count: readers.len(),
+            readers: Mutex::new(readers),
+            returned: Condvar::new(),
+        })
+    }
+
+    /// A pool without readers, where reads go through the writer. For
+    /// in-memory databases, which cannot be shared between connections.
+    pub fn single(conn: Connection) -> Self {
+        DbPool {
+            writer: Mutex::new(conn),
+            readers: Mutex::new(Vec::new()),
+            reader_count: 0,
+            returned: Condvar::new(),
+        }
+    }
+
+    /// The writer connection. Hold it only as long as needed: every other
+    /// write waits on it.
+    pub fn write(&self) -> Result<MutexGuard<'_, Connection>, AppError> {
+        self.writer
+            .lock()
+            .map_err(|e| AppError::General(e.to_string()))
+    }
+
+    /// A read-only connection, waiting for one to be returned if all are in
+    /// use.
+    pub fn read(&self) -> Result<ReadConnection<'_>, AppError> {
+        if self.reader_count == 0 {
+            return Ok(ReadConnection::Writer(self.write()?));
+        }
+        let mut idle = self
+            .readers
+            .lock()
+            .map_err(|e| AppError::General(e.to_string()))?;
+        loop {
+            if let Some(conn) = idle.pop() {
+                return Ok(ReadConnection::Reader {
+                    pool: self,
+                    conn: Some(conn),
+                });
+            }
+            idle = self
+                .returned
+                .wait(idle)
+                .map_err(|e| AppError::General(e.to_string()))?;
+        }
+    }
+
+    /// Hands `items` to `write` in slices of `batch_size`, each in its own
+    /// transaction, taking the writer lock per batch rather than for the
+    /// whole loop.
+    pub fn write_in_batches<T>(
+        &self,
+        items: &[T],
+        batch_size: usize,
+        mut write: impl FnMut(&Connection, &[T]) -> Result<(), AppError>,
+    ) -> Result<(), AppError> {
+        for batch in items.chunks(batch_size.max(1)) {
+            let conn = self.write()?;
+            let tx = conn.unchecked_transaction()?;
+            write(&tx, batch)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+}
+
+/// A connection borrowed from `DbPool::read`, handed back on drop.
+pub enum ReadConnection<'a> {
+    Reader {
+        pool: &'a DbPool,
+        conn: Option<Connection>,
+    },
+    Writer(MutexGuard<'a, Connection>),
+}
+
+impl Deref for ReadConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            ReadConnection::Reader { conn, .. } => conn.as_ref().expect("connection in use"),
+            ReadConnection::Writer(conn) => conn,
+        }
+    }
+}
+
+impl Drop for ReadConnection<'_> {
+    fn drop(&mut self) {
+        if let ReadConnection::Reader { pool, conn } = self {
+            if let (Some(conn), Ok(mut idle)) = (conn.take(), pool.readers.lock()) {
+                idle.push(conn);
+                pool.returned.notify_one();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{schema, settings_repo};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn open_pool(dir: &TempDir, readers: usize) -> DbPool {
+        let path = dir.path().join("orchestra.db");
+        let writer = Connection::open(&path).unwrap();
+        schema::run_migrations(&writer).unwrap();
+        DbPool::new(writer, &path, readers).unwrap()
+    }
+
+    #[test]
+    fn test_readers_see_committed_writes() {
+        let dir = TempDir::new().unwrap();
+        let pool = open_pool(&dir, 2);
+
+        settings_repo::set_setting(&pool.write().unwrap(), "k", "v").unwrap();
+        let value = settings_repo::get_setting(&pool.read().unwrap(), "k").unwrap();
+        assert_eq!(value.as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn test_readers_are_read_only() {
+        let dir = TempDir::new().unwrap();
+        let pool = open_pool(&dir, 1);
+        assert!(settings_repo::set_setting(&pool.read().unwrap(), "k", "v").is_err());
+    }
+
+    #[test]
+    fn test_reads_not_blocked_by_open_write_transaction() {
+        let dir = TempDir::new().unwrap();
+        let pool = open_pool(&dir, 1);
+        settings_repo::set_setting(&pool.write().unwrap(), "k", "old").unwrap();
+
+        let writer = pool.write().unwrap();
+        let tx = writer.unchecked_transaction().unwrap();
+        settings_repo::set_setting(&tx, "k", "new").unwrap();
+
+        // The uncommitted value is invisible, and the read does not wait
+        let value = settings_repo::get_setting(&pool.read().unwrap(), "k").unwrap();
+        assert_eq!(value.as_deref(), Some("old"));
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_read_waits_for_returned_connection() {
+        let dir = TempDir::new().unwrap();
+        let pool = Arc::new(open_pool(&dir, 1));
+
+        let held = pool.read().unwrap();
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            std::thread::spawn(move || {
+                let conn = pool.read().unwrap();
+                settings_repo::get_setting(&conn, "k").unwrap()
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        drop(held);
+        assert_eq!(waiter.join().unwrap(), None);
+    }
+
+    #[test]
+    fn test_single_reads_through_writer() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        let pool = DbPool::single(conn);
+
+        settings_repo::set_setting(&pool.write().unwrap(), "k", "v").unwrap();
+        let value = settings_repo::get_setting(&pool.read().unwrap(), "k").unwrap();
+        assert_eq!(value.as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn test_write_in_batches() {
+        let dir = TempDir::new().unwrap();
+        let pool = open_pool(&dir, 1);
+        let keys: Vec<String> = (0..5).map(|i| format!("k{i}")).collect();
+
+        pool.write_in_batches(&keys, 2, |conn, batch| {
+            batch
+                .iter()
+                .try_for_each(|key| settings_repo::set_setting(conn, key, "v"))
+        })
+        .unwrap();
+        let count: i64 = pool
+            .read()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 5);
+
+        // A failing batch rolls back on its own; earlier batches stay
+        let result = pool.write_in_batches(&keys, 2, |conn, batch| {
+            batch.iter().try_for_each(|key| {
+                if key == "k3" {
+                    return Err(AppError::General("boom".to_string()));
+                }
+                settings_repo::set_setting(conn, key, "w")
+            })
+        });
+        assert!(result.is_err());
+        let read = pool.read().unwrap();
+        assert_eq!(
+            settings_repo::get_setting(&read, "k1").unwrap().as_deref(),
+            Some("w")
+        );
+        assert_eq!(
+            settings_repo::get_setting(&read, "k2").unwrap().as_deref(),
+            Some("v")
+        );
+    }
+}
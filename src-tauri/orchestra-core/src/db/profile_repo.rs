@@ -1,19 +1,42 @@
 use rusqlite::{params, Connection};
 
 use crate::error::AppError;
-use crate::models::sync_profile::{SyncMode, SyncProfile};
+use crate::models::conflict::ConflictPolicyRule;
+use crate::models::sync_profile::{KeepBothNaming, SyncMode, SyncProfile};
+use crate::models::track::HashKind;
+
+/// Checks that every policy pattern is a valid glob, so a typo is reported
+/// when the profile is saved rather than the rule never matching.
+pub fn validate_policies(policies: &[ConflictPolicyRule]) -> Result<(), AppError> {
+    for pattern in policies.iter().filter_map(|rule| rule.pattern.as_deref()) {
+        glob::Pattern::new(pattern).map_err(|e| {
+            AppError::General(format!("Invalid conflict policy pattern '{pattern}': {e}"))
+        })?;
+    }
+    Ok(())
+}
 
 pub fn create_profile(conn: &Connection, profile: &SyncProfile) -> Result<(), AppError> {
     let exclude_json = serde_json::to_string(&profile.exclude_patterns)
         .map_err(|e| AppError::General(e.to_string()))?;
+    let policies_json = serde_json::to_string(&profile.conflict_policies)
+        .map_err(|e| AppError::General(e.to_string()))?;
     let mode_str = match profile.sync_mode {
         SyncMode::OneWay => "one_way",
         SyncMode::TwoWay => "two_way",
     };
+    let naming_str = match profile.keep_both_naming {
+        KeepBothNaming::Timestamp => "timestamp",
+        KeepBothNaming::Hostname => "hostname",
+    };
+    let hash_kind_str = match profile.hash_kind {
+        HashKind::File => "file",
+        HashKind::Audio => "audio",
+    };
 
     conn.execute(
-        "INSERT INTO sync_profiles (id, name, source_path, target_path, sync_mode, exclude_patterns, created_at, last_synced_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO sync_profiles (id, name, source_path, target_path, sync_mode, exclude_patterns, conflict_policies, keep_both_naming, hash_kind, created_at, last_synced_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         params![
             profile.id,
             profile.name,
@@ -21,6 +44,9 @@ pub fn create_profile(conn: &Connection, profile: &SyncProfile) -> Result<(), Ap
             profile.target_path,
             mode_str,
             exclude_json,
+            policies_json,
+            naming_str,
+            hash_kind_str,
             profile.created_at,
             profile.last_synced_at,
         ],
@@ -30,13 +56,16 @@ pub fn create_profile(conn: &Connection, profile: &SyncProfile) -> Result<(), Ap
 
 pub fn get_profile(conn: &Connection, id: &str) -> Result<SyncProfile, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT id, name, source_path, target_path, sync_mode, exclude_patterns, created_at, last_synced_at
+        "SELECT id, name, source_path, target_path, sync_mode, exclude_patterns, conflict_policies, keep_both_naming, hash_kind, created_at, last_synced_at
          FROM sync_profiles WHERE id = ?1",
     )?;
 
     stmt.query_row(params![id], |row| {
         let mode_str: String = row.get(4)?;
         let exclude_json: String = row.get(5)?;
+        let policies_json: String = row.get(6)?;
+        let naming_str: String = row.get(7)?;
+        let hash_kind_str: String = row.get(8)?;
         Ok(SyncProfile {
             id: row.get(0)?,
             name: row.get(1)?,
@@ -48,8 +77,19 @@ pub fn get_profile(conn: &Connection, id: &str) -> Result<SyncProfile, AppError>
                 SyncMode::OneWay
             },
             exclude_patterns: serde_json::from_str(&exclude_json).unwrap_or_default(),
-            created_at: row.get(6)?,
-            last_synced_at: row.get(7)?,
+            conflict_policies: serde_json::from_str(&policies_json).unwrap_or_default(),
+            keep_both_naming: if naming_str == "hostname" {
+                KeepBothNaming::Hostname
+            } else {
+                KeepBothNaming::Timestamp
+            },
+            hash_kind: if hash_kind_str == "audio" {
+                HashKind::Audio
+            } else {
+                HashKind::File
+            },
+            created_at: row.get(9)?,
+            last_synced_at: row.get(10)?,
         })
     })
     .map_err(|e| match e {
@@ -60,7 +100,7 @@ pub fn get_profile(conn: &Connection, id: &str) -> Result<SyncProfile, AppError>
 
 pub fn list_profiles(conn: &Connection) -> Result<Vec<SyncProfile>, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT id, name, source_path, target_path, sync_mode, exclude_patterns, created_at, last_synced_at
+        "SELECT id, name, source_path, target_path, sync_mode, exclude_patterns, conflict_policies, keep_both_naming, hash_kind, created_at, last_synced_at
          FROM sync_profiles ORDER BY created_at DESC",
     )?;
 
@@ -68,6 +108,9 @@ pub fn list_profiles(conn: &Connection) -> Result<Vec<SyncProfile>, AppError> {
         .query_map([], |row| {
             let mode_str: String = row.get(4)?;
             let exclude_json: String = row.get(5)?;
+            let policies_json: String = row.get(6)?;
+            let naming_str: String = row.get(7)?;
+            let hash_kind_str: String = row.get(8)?;
             Ok(SyncProfile {
                 id: row.get(0)?,
                 name: row.get(1)?,
@@ -79,8 +122,19 @@ pub fn list_profiles(conn: &Connection) -> Result<Vec<SyncProfile>, AppError> {
                     SyncMode::OneWay
                 },
                 exclude_patterns: serde_json::from_str(&exclude_json).unwrap_or_default(),
-                created_at: row.get(6)?,
-                last_synced_at: row.get(7)?,
+                conflict_policies: serde_json::from_str(&policies_json).unwrap_or_default(),
+                keep_both_naming: if naming_str == "hostname" {
+                    KeepBothNaming::Hostname
+                } else {
+                    KeepBothNaming::Timestamp
+                },
+                hash_kind: if hash_kind_str == "audio" {
+                    HashKind::Audio
+                } else {
+                    HashKind::File
+                },
+                created_at: row.get(9)?,
+                last_synced_at: row.get(10)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -91,14 +145,24 @@ pub fn list_profiles(conn: &Connection) -> Result<Vec<SyncProfile>, AppError> {
 pub fn update_profile(conn: &Connection, profile: &SyncProfile) -> Result<(), AppError> {
     let exclude_json = serde_json::to_string(&profile.exclude_patterns)
         .map_err(|e| AppError::General(e.to_string()))?;
+    let policies_json = serde_json::to_string(&profile.conflict_policies)
+        .map_err(|e| AppError::General(e.to_string()))?;
     let mode_str = match profile.sync_mode {
         SyncMode::OneWay => "one_way",
         SyncMode::TwoWay => "two_way",
     };
+    let naming_str = match profile.keep_both_naming {
+        KeepBothNaming::Timestamp => "timestamp",
+        KeepBothNaming::Hostname => "hostname",
+    };
+    let hash_kind_str = match profile.hash_kind {
+        HashKind::File => "file",
+        HashKind::Audio => "audio",
+    };
 
     let rows = conn.execute(
         "UPDATE sync_profiles SET name=?2, source_path=?3, target_path=?4, sync_mode=?5,
-         exclude_patterns=?6, last_synced_at=?7 WHERE id=?1",
+         exclude_patterns=?6, conflict_policies=?7, keep_both_naming=?8, hash_kind=?9, last_synced_at=?10 WHERE id=?1",
         params![
             profile.id,
             profile.name,
@@ -106,6 +170,9 @@ pub fn update_profile(conn: &Connection, profile: &SyncProfile) -> Result<(), Ap
             profile.target_path,
             mode_str,
             exclude_json,
+            policies_json,
+            naming_str,
+            hash_kind_str,
             profile.last_synced_at,
         ],
     )?;
@@ -136,7 +203,9 @@ pub fn update_last_synced(conn: &Connection, id: &str, timestamp: i64) -> Result
 mod tests {
     use super::*;
     use crate::db::schema;
-    use crate::models::sync_profile::{SyncMode, SyncProfile};
+    use crate::models::conflict::{ConflictPolicy, ConflictPolicyRule, ConflictType};
+    use crate::models::sync_profile::{KeepBothNaming, SyncMode, SyncProfile};
+    use crate::models::track::HashKind;
 
     fn setup_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
@@ -152,6 +221,9 @@ mod tests {
             target_path: "/target".to_string(),
             sync_mode: SyncMode::OneWay,
             exclude_patterns: vec![],
+            conflict_policies: vec![],
+            keep_both_naming: KeepBothNaming::Timestamp,
+            hash_kind: HashKind::File,
             created_at: 1700000000,
             last_synced_at: None,
         }
@@ -267,6 +339,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_profile_conflict_policies_roundtrip() {
+        let conn = setup_db();
+        let mut profile = make_profile("p1");
+        profile.conflict_policies = vec![
+            ConflictPolicyRule {
+                policy: ConflictPolicy::KeepBoth,
+                conflict_type: None,
+                pattern: Some("Live/**".to_string()),
+            },
+            ConflictPolicyRule {
+                policy: ConflictPolicy::NewestWins,
+                conflict_type: Some(ConflictType::BothModified),
+                pattern: None,
+            },
+        ];
+        create_profile(&conn, &profile).unwrap();
+
+        let fetched = get_profile(&conn, "p1").unwrap();
+        assert_eq!(fetched.conflict_policies, profile.conflict_policies);
+
+        profile.conflict_policies = vec![];
+        update_profile(&conn, &profile).unwrap();
+        let fetched = get_profile(&conn, "p1").unwrap();
+        assert!(fetched.conflict_policies.is_empty());
+    }
+
+    #[test]
+    fn test_validate_policies() {
+        let rule = |pattern: &str| ConflictPolicyRule {
+            policy: ConflictPolicy::SourceWins,
+            conflict_type: None,
+            pattern: Some(pattern.to_string()),
+        };
+        assert!(validate_policies(&[rule("Live/**"), rule("*.flac")]).is_ok());
+        assert!(validate_policies(&[rule("Live/**"), rule("[abc")]).is_err());
+    }
+
+    #[test]
+    fn test_profile_keep_both_naming_roundtrip() {
+        let conn = setup_db();
+        let mut profile = make_profile("p1");
+        create_profile(&conn, &profile).unwrap();
+        assert_eq!(
+            get_profile(&conn, "p1").unwrap().keep_both_naming,
+            KeepBothNaming::Timestamp
+        );
+
+        profile.keep_both_naming = KeepBothNaming::Hostname;
+        update_profile(&conn, &profile).unwrap();
+        assert_eq!(
+            get_profile(&conn, "p1").unwrap().keep_both_naming,
+            KeepBothNaming::Hostname
+        );
+    }
+
+    #[test]
+    fn test_profile_hash_kind_roundtrip() {
+        let conn = setup_db();
+        let mut profile = make_profile("p1");
+        create_profile(&conn, &profile).unwrap();
+        assert_eq!(get_profile(&conn, "p1").unwrap().hash_kind, HashKind::File);
+
+        profile.hash_kind = HashKind::Audio;
+        update_profile(&conn, &profile).unwrap();
+        assert_eq!(list_profiles(&conn).unwrap()[0].hash_kind, HashKind::Audio);
+    }
+
     #[test]
     fn test_get_nonexistent_profile_returns_error() {
         let conn = setup_db();
@@ -0,0 +1,117 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::AppError;
+use crate::rating::MAX_RATING;
+
+/// Checks a rating from the frontend. `Some(0)` means unrated.
+pub fn validate(rating: Option<u8>) -> Result<Option<u8>, AppError> {
+    match rating {
+        Some(r) if r > MAX_RATING => Err(AppError::General(format!(
+            "Rating {r} is out of range (0-{MAX_RATING})"
+        ))),
+        Some(0) => Ok(None),
+        r => Ok(r),
+    }
+}
+
+/// Sets or clears (`None`) a track's rating in the database only; see
+/// `writer::write_metadata` for the tags.
+pub fn set_track_rating(
+    conn: &Connection,
+    track_id: i64,
+    rating: Option<u8>,
+) -> Result<(), AppError> {
+    let rating = validate(rating)?;
+    let updated = conn.execute(
+        "UPDATE tracks SET rating = ?2 WHERE id = ?1",
+        params![track_id, rating],
+    )?;
+    if updated == 0 {
+        return Err(AppError::General(format!("Track {track_id} not found")));
+    }
+    Ok(())
+}
+
+pub fn set_album_rating(
+    conn: &Connection,
+    album_id: i64,
+    rating: Option<u8>,
+) -> Result<(), AppError> {
+    match validate(rating)? {
+        Some(rating) => {
+            let now = chrono::Utc::now().timestamp();
+            conn.execute(
+                "INSERT INTO album_ratings (album_id, rating, rated_at)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(album_id) DO UPDATE SET
+                   rating = excluded.rating, rated_at = excluded.rated_at",
+                params![album_id, rating, now],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM album_ratings WHERE album_id = ?1",
+                params![album_id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_album_rating(conn: &Connection, album_id: i64) -> Result<Option<u8>, AppError> {
+    let rating = conn
+        .query_row(
+            "SELECT rating FROM album_ratings WHERE album_id = ?1",
+            params![album_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(rating)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(Some(0)).unwrap(), None);
+        assert_eq!(validate(Some(80)).unwrap(), Some(80));
+        assert_eq!(validate(None).unwrap(), None);
+        assert!(validate(Some(101)).is_err());
+    }
+
+    #[test]
+    fn test_album_rating_set_update_clear() {
+        let conn = setup_db();
+        conn.execute_batch(
+            "INSERT INTO artists (id, name, sort_name) VALUES (1, 'Artist', 'artist');
+             INSERT INTO albums (id, artist_id, name, sort_name) VALUES (7, 1, 'Album', 'album');",
+        )
+        .unwrap();
+        assert_eq!(get_album_rating(&conn, 7).unwrap(), None);
+
+        set_album_rating(&conn, 7, Some(60)).unwrap();
+        set_album_rating(&conn, 7, Some(100)).unwrap();
+        assert_eq!(get_album_rating(&conn, 7).unwrap(), Some(100));
+
+        set_album_rating(&conn, 7, Some(0)).unwrap();
+        assert_eq!(get_album_rating(&conn, 7).unwrap(), None);
+
+        // Ratings hang off the album row
+        assert!(set_album_rating(&conn, 8, Some(60)).is_err());
+    }
+
+    #[test]
+    fn test_set_track_rating_unknown_track() {
+        let conn = setup_db();
+        assert!(set_track_rating(&conn, 42, Some(80)).is_err());
+    }
+}
@@ -4,13 +4,40 @@ use crate::db::library_repo::track_from_row;
 use crate::error::AppError;
 use crate::models::track::Track;
 
-/// Insert a play event. Called when a track starts playing.
-pub fn record_play(conn: &Connection, track_id: i64) -> Result<(), AppError> {
+/// Insert a play event. Called once enough of a track has been heard to
+/// count as played; `finish_play` fills in how it ended. Returns the play's id.
+pub fn record_play(conn: &Connection, track_id: i64) -> Result<i64, AppError> {
     let now = chrono::Utc::now().timestamp();
     conn.execute(
         "INSERT INTO play_history (track_id, played_at) VALUES (?1, ?2)",
         params![track_id, now],
     )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Records how long a play from `record_play` lasted and whether the track
+/// was heard to the end.
+pub fn finish_play(
+    conn: &Connection,
+    play_id: i64,
+    listened_secs: f64,
+    completed: bool,
+) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE play_history SET listened_secs = ?2, completed = ?3 WHERE id = ?1",
+        params![play_id, listened_secs, completed],
+    )?;
+    Ok(())
+}
+
+/// Records a track left before it counted as played.
+pub fn record_skip(conn: &Connection, track_id: i64, listened_secs: f64) -> Result<(), AppError> {
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "INSERT INTO play_history (track_id, played_at, listened_secs, skipped)
+         VALUES (?1, ?2, ?3, 1)",
+        params![track_id, now, listened_secs],
+    )?;
     Ok(())
 }
 
@@ -24,7 +51,8 @@ pub fn get_recently_added(
     let mut stmt = conn.prepare(
         "SELECT id, file_path, relative_path, library_root, title, artist, album_artist, album,
                 track_number, disc_number, year, genre, duration_secs, format, file_size,
-                modified_at, hash, has_album_art, bitrate, scanned_at
+                modified_at, hash, has_album_art, bitrate, scanned_at, audio_hash,
+                replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak, rating, album_id, lyrics
          FROM tracks
          WHERE scanned_at >= ?1
          ORDER BY scanned_at DESC
@@ -36,15 +64,18 @@ pub fn get_recently_added(
     Ok(tracks)
 }
 
-/// Most recently played tracks (distinct), most recent first. Capped at `limit`.
+/// Most recently played tracks (distinct, skips excluded), most recent
+/// first. Capped at `limit`.
 pub fn get_recently_played(conn: &Connection, limit: usize) -> Result<Vec<Track>, AppError> {
     let mut stmt = conn.prepare(
         "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist,
                 t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
                 t.duration_secs, t.format, t.file_size, t.modified_at, t.hash,
-                t.has_album_art, t.bitrate, t.scanned_at
+                t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+                t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics
          FROM play_history ph
          JOIN tracks t ON t.id = ph.track_id
+         WHERE ph.skipped = 0
          GROUP BY ph.track_id
          ORDER BY MAX(ph.played_at) DESC
          LIMIT ?1",
@@ -55,6 +86,15 @@ pub fn get_recently_played(conn: &Connection, limit: usize) -> Result<Vec<Track>
     Ok(tracks)
 }
 
+/// Moves the play history of `from_id` onto `to_id`.
+pub fn reassign_track(conn: &Connection, from_id: i64, to_id: i64) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE play_history SET track_id = ?2 WHERE track_id = ?1",
+        params![from_id, to_id],
+    )?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,6 +130,14 @@ mod tests {
             has_album_art: false,
             bitrate: None,
             scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
         };
         library_repo::upsert_track(conn, &track).unwrap();
         conn.query_row(
@@ -141,6 +189,31 @@ mod tests {
         assert_eq!(count, 2);
     }
 
+    #[test]
+    fn test_finish_play_and_record_skip() {
+        let conn = setup_db();
+        let played = insert_test_track(&conn, "t1");
+        let skipped = insert_test_track(&conn, "t2");
+
+        let play_id = record_play(&conn, played).unwrap();
+        finish_play(&conn, play_id, 195.5, true).unwrap();
+        record_skip(&conn, skipped, 4.0).unwrap();
+
+        let row: (f64, bool, bool) = conn
+            .query_row(
+                "SELECT listened_secs, completed, skipped FROM play_history WHERE id = ?1",
+                params![play_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(row, (195.5, true, false));
+
+        // Skips are not "recently played"
+        let recent = get_recently_played(&conn, 10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].id, Some(played));
+    }
+
     #[test]
     fn test_get_recently_added_within_window() {
         let conn = setup_db();
@@ -1,11 +1,127 @@
+use std::path::{Path, PathBuf};
+
 use rusqlite::Connection;
 
 use crate::error::AppError;
 
+/// One schema change, applied in its own transaction. Its version is its
+/// position in `MIGRATIONS` plus one and is stored in `PRAGMA user_version`.
+///
+/// Migrations are append-only: never edit one that has shipped. Columns can
+/// be renamed or dropped with `ALTER TABLE ... RENAME COLUMN` / `DROP
+/// COLUMN`; anything else (changing a type or constraint) means creating the
+/// new table, copying the rows over and renaming it into place.
+struct Migration {
+    description: &'static str,
+    up: fn(&Connection) -> Result<(), AppError>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "baseline schema",
+        up: baseline,
+    },
+    Migration {
+        description: "play statistics",
+        up: play_statistics,
+    },
+    Migration {
+        description: "ratings",
+        up: ratings,
+    },
+    Migration {
+        description: "album and artist entities",
+        up: album_artist_entities,
+    },
+    Migration {
+        description: "album and artist details",
+        up: album_artist_details,
+    },
+    Migration {
+        description: "lyrics",
+        up: lyrics,
+    },
+];
+
+/// The schema version this build creates and understands.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// The database's schema version, 0 for a new database or one created
+/// before versioning.
+pub fn schema_version(conn: &Connection) -> Result<i64, AppError> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Fails if the database was written by a newer Orchestra, whose schema this
+/// build may misread.
+pub fn check_not_newer(conn: &Connection) -> Result<(), AppError> {
+    let found = schema_version(conn)?;
+    if found > SCHEMA_VERSION {
+        return Err(AppError::SchemaTooNew {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    Ok(())
+}
+
+/// Brings the schema up to `SCHEMA_VERSION`. Each migration commits
+/// together with its version bump, so an interrupted upgrade resumes from
+/// the last completed step.
 pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
     conn.execute_batch("PRAGMA journal_mode=WAL;")?;
     conn.execute_batch("PRAGMA foreign_keys=ON;")?;
 
+    check_not_newer(conn)?;
+    let current = schema_version(conn)?;
+    for (version, migration) in (1..).zip(MIGRATIONS).skip(current as usize) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx).map_err(|e| {
+            AppError::General(format!(
+                "Migration {version} ({}) failed: {e}",
+                migration.description
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Like `run_migrations`, but first saves a copy of the database next to
+/// `db_path` (as `<name>.v<version>.bak`) when an existing database is about
+/// to be upgraded. Returns the backup's path if one was made.
+pub fn migrate_with_backup(conn: &Connection, db_path: &Path) -> Result<Option<PathBuf>, AppError> {
+    check_not_newer(conn)?;
+    let current = schema_version(conn)?;
+    let has_data: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let backup = if current < SCHEMA_VERSION && has_data {
+        let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".v{current}.bak"));
+        let backup = db_path.with_file_name(name);
+        if backup.exists() {
+            std::fs::remove_file(&backup)?;
+        }
+        // VACUUM INTO writes a consistent copy, including WAL contents
+        conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+        Some(backup)
+    } else {
+        None
+    };
+
+    run_migrations(conn)?;
+    Ok(backup)
+}
+
+/// Version 1: the schema as it stood before migrations were versioned.
+/// Databases from that time may be at any point of its history, so every
+/// step checks before it acts.
+fn baseline(conn: &Connection) -> Result<(), AppError> {
     conn.execute_batch(
         "
         CREATE TABLE IF NOT EXISTS tracks (
@@ -121,35 +237,43 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
         ",
     )?;
 
-    // Migration: add has_album_art column if it doesn't exist
-    let has_column: bool = conn
-        .prepare("SELECT COUNT(*) FROM pragma_table_info('tracks') WHERE name='has_album_art'")?
-        .query_row([], |row| row.get::<_, i64>(0))
-        .map(|count| count > 0)?;
-
-    if !has_column {
-        conn.execute_batch(
-            "ALTER TABLE tracks ADD COLUMN has_album_art INTEGER NOT NULL DEFAULT 0;",
-        )?;
-    }
-
-    let has_bitrate: bool = conn
-        .prepare("SELECT COUNT(*) FROM pragma_table_info('tracks') WHERE name='bitrate'")?
-        .query_row([], |row| row.get::<_, i64>(0))
-        .map(|count| count > 0)?;
-
-    if !has_bitrate {
-        conn.execute_batch("ALTER TABLE tracks ADD COLUMN bitrate INTEGER;")?;
+    add_column(
+        conn,
+        "tracks",
+        "has_album_art",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(conn, "tracks", "bitrate", "INTEGER")?;
+    add_column(conn, "tracks", "scanned_at", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "tracks", "audio_hash", "TEXT")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_tracks_audio_hash ON tracks(audio_hash);")?;
+    for column in [
+        "replaygain_track_gain",
+        "replaygain_track_peak",
+        "replaygain_album_gain",
+        "replaygain_album_peak",
+    ] {
+        add_column(conn, "tracks", column, "REAL")?;
     }
 
-    let has_scanned_at: bool = conn
-        .prepare("SELECT COUNT(*) FROM pragma_table_info('tracks') WHERE name='scanned_at'")?
-        .query_row([], |row| row.get::<_, i64>(0))
-        .map(|count| count > 0)?;
-
-    if !has_scanned_at {
-        conn.execute_batch("ALTER TABLE tracks ADD COLUMN scanned_at INTEGER NOT NULL DEFAULT 0;")?;
-    }
+    add_column(
+        conn,
+        "sync_profiles",
+        "conflict_policies",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    add_column(
+        conn,
+        "sync_profiles",
+        "keep_both_naming",
+        "TEXT NOT NULL DEFAULT 'timestamp'",
+    )?;
+    add_column(
+        conn,
+        "sync_profiles",
+        "hash_kind",
+        "TEXT NOT NULL DEFAULT 'file'",
+    )?;
 
     conn.execute_batch(
         "
@@ -169,8 +293,364 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
         );
         CREATE INDEX IF NOT EXISTS idx_play_history_track ON play_history(track_id);
         CREATE INDEX IF NOT EXISTS idx_play_history_played_at ON play_history(played_at DESC);
+
+        CREATE TABLE IF NOT EXISTS track_fingerprints (
+            track_id INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+            fingerprint BLOB NOT NULL,
+            modified_at INTEGER NOT NULL
+        );
         ",
     )?;
 
     Ok(())
 }
+
+/// Adds `column` to `table` unless a pre-versioning build already did.
+fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), AppError> {
+    let exists: bool = conn
+        .prepare("SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2")?
+        .query_row([table, column], |row| row.get::<_, i64>(0))
+        .map(|count| count > 0)?;
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition};"
+        ))?;
+    }
+    Ok(())
+}
+
+/// Version 2: how long each play lasted and how it ended. Plays recorded
+/// before this have no listened time and count as neither completed nor
+/// skipped.
+fn play_statistics(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE play_history ADD COLUMN listened_secs REAL;
+         ALTER TABLE play_history ADD COLUMN completed INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE play_history ADD COLUMN skipped INTEGER NOT NULL DEFAULT 0;",
+    )?;
+    Ok(())
+}
+
+/// Version 3: track ratings, read from tags or set in the app, and album
+/// ratings, which only live in the database. Albums are keyed like device
+/// album selections.
+fn ratings(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE tracks ADD COLUMN rating INTEGER;
+         CREATE TABLE album_ratings (
+             artist_name TEXT NOT NULL,
+             album_name TEXT NOT NULL,
+             rating INTEGER NOT NULL,
+             rated_at INTEGER NOT NULL,
+             PRIMARY KEY (artist_name, album_name)
+         );",
+    )?;
+    Ok(())
+}
+
+/// Version 4: albums and artists as rows with stable ids, which tracks and
+/// favorites point to. Favorites get one foreign key column per entity
+/// type, so deleting a track, album or artist deletes its favorite; album
+/// and artist favorites, which were stored by name, are matched to the new
+/// rows and dropped if nothing matches.
+fn album_artist_entities(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE artists (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             name TEXT NOT NULL UNIQUE
+         );
+         CREATE TABLE albums (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             artist_id INTEGER NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+             name TEXT NOT NULL,
+             UNIQUE(artist_id, name)
+         );
+         ALTER TABLE tracks ADD COLUMN album_id INTEGER REFERENCES albums(id) ON DELETE SET NULL;
+         CREATE INDEX idx_tracks_album_id ON tracks(album_id);
+
+         INSERT INTO artists (name)
+           SELECT DISTINCT COALESCE(album_artist, artist, 'Unknown Artist') FROM tracks;
+         INSERT INTO albums (artist_id, name)
+           SELECT DISTINCT ar.id, COALESCE(t.album, 'Unknown Album')
+           FROM tracks t
+           JOIN artists ar ON ar.name = COALESCE(t.album_artist, t.artist, 'Unknown Artist');
+         UPDATE tracks SET album_id = (
+           SELECT al.id FROM albums al JOIN artists ar ON ar.id = al.artist_id
+           WHERE ar.name = COALESCE(tracks.album_artist, tracks.artist, 'Unknown Artist')
+             AND al.name = COALESCE(tracks.album, 'Unknown Album'));
+
+         ALTER TABLE favorites RENAME TO favorites_old;
+         DROP INDEX idx_favorites_type;
+         CREATE TABLE favorites (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             entity_type TEXT NOT NULL CHECK (entity_type IN ('track', 'album', 'artist')),
+             track_id INTEGER UNIQUE REFERENCES tracks(id) ON DELETE CASCADE,
+             album_id INTEGER UNIQUE REFERENCES albums(id) ON DELETE CASCADE,
+             artist_id INTEGER UNIQUE REFERENCES artists(id) ON DELETE CASCADE,
+             created_at INTEGER NOT NULL,
+             CHECK ((track_id IS NOT NULL) + (album_id IS NOT NULL) + (artist_id IS NOT NULL) = 1)
+         );
+         CREATE INDEX idx_favorites_type ON favorites(entity_type);
+         INSERT INTO favorites (entity_type, track_id, created_at)
+           SELECT 'track', t.id, f.created_at FROM favorites_old f
+           JOIN tracks t ON t.id = CAST(f.entity_id AS INTEGER)
+           WHERE f.entity_type = 'track';
+         INSERT INTO favorites (entity_type, album_id, created_at)
+           SELECT 'album', al.id, f.created_at FROM favorites_old f
+           JOIN albums al JOIN artists ar ON ar.id = al.artist_id
+           WHERE f.entity_type = 'album' AND f.entity_id = ar.name || char(0) || al.name;
+         INSERT INTO favorites (entity_type, artist_id, created_at)
+           SELECT 'artist', ar.id, f.created_at FROM favorites_old f
+           JOIN artists ar ON ar.name = f.entity_id
+           WHERE f.entity_type = 'artist';
+         DROP TABLE favorites_old;",
+    )?;
+    Ok(())
+}
+
+/// Version 5: sort names, year ranges, durations and a cover track on the
+/// entity rows, and device selections and album ratings keyed by album and
+/// artist id instead of by name. Name-keyed rows with no matching album or
+/// artist are dropped.
+fn album_artist_details(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE artists ADD COLUMN sort_name TEXT NOT NULL DEFAULT '';
+         ALTER TABLE albums ADD COLUMN sort_name TEXT NOT NULL DEFAULT '';
+         ALTER TABLE albums ADD COLUMN year_min INTEGER;
+         ALTER TABLE albums ADD COLUMN year_max INTEGER;
+         ALTER TABLE albums ADD COLUMN duration_secs REAL NOT NULL DEFAULT 0;
+         ALTER TABLE albums ADD COLUMN cover_track_id INTEGER
+           REFERENCES tracks(id) ON DELETE SET NULL;
+
+         ALTER TABLE device_artist_selections RENAME TO device_artist_selections_old;
+         DROP INDEX idx_device_artist_device;
+         CREATE TABLE device_artist_selections (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             device_id TEXT NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+             artist_id INTEGER NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+             UNIQUE(device_id, artist_id)
+         );
+         CREATE INDEX idx_device_artist_device ON device_artist_selections(device_id);
+         INSERT INTO device_artist_selections (device_id, artist_id)
+           SELECT s.device_id, ar.id FROM device_artist_selections_old s
+           JOIN artists ar ON ar.name = s.artist_name;
+         DROP TABLE device_artist_selections_old;
+
+         ALTER TABLE device_album_selections RENAME TO device_album_selections_old;
+         DROP INDEX idx_device_album_device;
+         CREATE TABLE device_album_selections (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             device_id TEXT NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+             album_id INTEGER NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+             UNIQUE(device_id, album_id)
+         );
+         CREATE INDEX idx_device_album_device ON device_album_selections(device_id);
+         INSERT INTO device_album_selections (device_id, album_id)
+           SELECT s.device_id, al.id FROM device_album_selections_old s
+           JOIN artists ar ON ar.name = s.artist_name
+           JOIN albums al ON al.artist_id = ar.id AND al.name = s.album_name;
+         DROP TABLE device_album_selections_old;
+
+         ALTER TABLE album_ratings RENAME TO album_ratings_old;
+         CREATE TABLE album_ratings (
+             album_id INTEGER PRIMARY KEY REFERENCES albums(id) ON DELETE CASCADE,
+             rating INTEGER NOT NULL,
+             rated_at INTEGER NOT NULL
+         );
+         INSERT INTO album_ratings (album_id, rating, rated_at)
+           SELECT al.id, r.rating, r.rated_at FROM album_ratings_old r
+           JOIN artists ar ON ar.name = r.artist_name
+           JOIN albums al ON al.artist_id = ar.id AND al.name = r.album_name;
+         DROP TABLE album_ratings_old;
+
+         UPDATE albums SET
+           year_min = (SELECT MIN(year) FROM tracks WHERE album_id = albums.id),
+           year_max = (SELECT MAX(year) FROM tracks WHERE album_id = albums.id),
+           duration_secs = (SELECT COALESCE(SUM(duration_secs), 0) FROM tracks
+                            WHERE album_id = albums.id),
+           cover_track_id = (SELECT id FROM tracks WHERE album_id = albums.id AND has_album_art
+                             ORDER BY disc_number, track_number, id LIMIT 1);",
+    )?;
+
+    for table in ["artists", "albums"] {
+        let names: Vec<(i64, String)> = conn
+            .prepare(&format!("SELECT id, name FROM {table}"))?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        let mut update =
+            conn.prepare(&format!("UPDATE {table} SET sort_name = ?2 WHERE id = ?1"))?;
+        for (id, name) in names {
+            update.execute(rusqlite::params![id, v5_sort_name(&name)])?;
+        }
+    }
+    Ok(())
+}
+
+/// `album_repo::sort_name` as of version 5, kept here so the migration gives
+/// the same result however that function changes.
+fn v5_sort_name(name: &str) -> String {
+    let lower = name.trim().to_lowercase();
+    ["the ", "a ", "an "]
+        .iter()
+        .find_map(|article| lower.strip_prefix(article))
+        .map(str::trim_start)
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(&lower)
+        .to_string()
+}
+
+/// Version 6: whether each track has plain or synced lyrics, or `none`.
+/// NULL until the file is next scanned, which the scan treats as a change.
+fn lyrics(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch("ALTER TABLE tracks ADD COLUMN lyrics TEXT;")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A database as created by a build from before `bitrate`, `audio_hash`
+    /// and versioning existed.
+    fn legacy_db(conn: &Connection) {
+        conn.execute_batch(
+            "CREATE TABLE tracks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_path TEXT NOT NULL UNIQUE,
+                relative_path TEXT NOT NULL,
+                library_root TEXT NOT NULL,
+                title TEXT, artist TEXT, album_artist TEXT, album TEXT,
+                track_number INTEGER, disc_number INTEGER, year INTEGER, genre TEXT,
+                duration_secs REAL,
+                format TEXT NOT NULL,
+                file_size INTEGER NOT NULL,
+                modified_at INTEGER NOT NULL,
+                hash TEXT,
+                has_album_art INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO tracks (file_path, relative_path, library_root, format, file_size, modified_at)
+            VALUES ('/music/a.flac', 'a.flac', '/music', 'flac', 1, 1);",
+        )
+        .unwrap();
+    }
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn test_new_database_reaches_current_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        // Running again is a no-op
+        run_migrations(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_unversioned_database_is_upgraded() {
+        let conn = Connection::open_in_memory().unwrap();
+        legacy_db(&conn);
+        run_migrations(&conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&conn, "tracks", "bitrate"));
+        assert!(has_column(&conn, "tracks", "audio_hash"));
+        assert!(has_column(&conn, "sync_profiles", "hash_kind"));
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_name_keyed_favorites_move_to_entities() {
+        let conn = Connection::open_in_memory().unwrap();
+        for (version, migration) in (1..).zip(&MIGRATIONS[..3]) {
+            (migration.up)(&conn).unwrap();
+            conn.pragma_update(None, "user_version", version).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO tracks (file_path, relative_path, library_root, artist, album, format, file_size, modified_at)
+             VALUES ('/music/a.flac', 'a.flac', '/music', 'Artist', 'Album', 'flac', 1, 1);
+             INSERT INTO favorites (entity_type, entity_id, created_at) VALUES
+               ('track', '1', 1), ('album', 'Artist' || char(0) || 'Album', 2),
+               ('artist', 'Artist', 3), ('artist', 'Gone', 4), ('track', '99', 5);",
+        )
+        .unwrap();
+        run_migrations(&conn).unwrap();
+
+        let album_id: i64 = conn
+            .query_row("SELECT album_id FROM tracks", [], |row| row.get(0))
+            .unwrap();
+        let favorites: Vec<(String, i64)> = conn
+            .prepare(
+                "SELECT entity_type, COALESCE(track_id, album_id, artist_id)
+                 FROM favorites ORDER BY created_at",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            favorites,
+            vec![
+                ("track".to_string(), 1),
+                ("album".to_string(), album_id),
+                ("artist".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        let err = run_migrations(&conn).unwrap_err();
+        assert!(matches!(err, AppError::SchemaTooNew { found, supported }
+            if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION));
+        assert!(check_not_newer(&conn).is_err());
+    }
+
+    #[test]
+    fn test_backup_only_before_upgrading_existing_data() {
+        let tmp = TempDir::new().unwrap();
+
+        // A new database has nothing to back up
+        let fresh_path = tmp.path().join("fresh.db");
+        let conn = Connection::open(&fresh_path).unwrap();
+        assert_eq!(migrate_with_backup(&conn, &fresh_path).unwrap(), None);
+
+        let path = tmp.path().join("orchestra.db");
+        let conn = Connection::open(&path).unwrap();
+        legacy_db(&conn);
+        let backup = migrate_with_backup(&conn, &path).unwrap().unwrap();
+        assert_eq!(backup, tmp.path().join("orchestra.db.v0.bak"));
+
+        // The backup is the database as it was before the upgrade
+        let old = Connection::open(&backup).unwrap();
+        assert_eq!(schema_version(&old).unwrap(), 0);
+        assert!(!has_column(&old, "tracks", "bitrate"));
+
+        // Up to date: no new backup
+        assert_eq!(migrate_with_backup(&conn, &path).unwrap(), None);
+    }
+}
@@ -0,0 +1,478 @@
+use chrono::{Months, NaiveDate};
+use rusqlite::{params, Connection};
+
+use crate::db::library_repo::track_from_row;
+use crate::error::AppError;
+use crate::models::stats::{
+    ListeningStreaks, ListeningTime, PlayStat, StatsPeriod, TrackPlayStats,
+};
+use crate::models::track::Track;
+
+/// Seconds heard of a play. Plays recorded before listened time was tracked
+/// count as the whole track.
+const LISTENED_SECS: &str =
+    "COALESCE(ph.listened_secs, CASE WHEN ph.skipped THEN 0 ELSE t.duration_secs END, 0)";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Start of a window of the last `days` days; all time for `None`.
+fn window_start(days: Option<u32>) -> i64 {
+    days.map_or(i64::MIN, |days| {
+        chrono::Utc::now().timestamp() - days as i64 * 86_400
+    })
+}
+
+/// Most played tracks within the last `days` days. Skips are counted but do
+/// not rank a track.
+pub fn top_tracks(
+    conn: &Connection,
+    library_root: &str,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<TrackPlayStats>, AppError> {
+    let sql = format!(
+        "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist,
+                t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
+                t.duration_secs, t.format, t.file_size, t.modified_at, t.hash,
+                t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+                t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics,
+                SUM(ph.skipped = 0) AS plays, SUM(ph.skipped), SUM({LISTENED_SECS}) AS listened,
+                MAX(ph.played_at)
+         FROM play_history ph
+         JOIN tracks t ON t.id = ph.track_id
+         WHERE t.library_root = ?1 AND ph.played_at >= ?2
+         GROUP BY t.id
+         HAVING plays > 0
+         ORDER BY plays DESC, listened DESC
+         LIMIT ?3"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let stats = stmt
+        .query_map(
+            params![library_root, window_start(days), limit as i64],
+            |row| {
+                Ok(TrackPlayStats {
+                    track: track_from_row(row)?,
+                    plays: row.get(28)?,
+                    skips: row.get(29)?,
+                    listened_secs: row.get(30)?,
+                    last_played: row.get(31)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(stats)
+}
+
+/// Ranks the groups named by `name` (and `artist`, for albums) by plays.
+fn top_grouped(
+    conn: &Connection,
+    library_root: &str,
+    days: Option<u32>,
+    limit: usize,
+    name: &str,
+    artist: Option<&str>,
+) -> Result<Vec<PlayStat>, AppError> {
+    let artist = artist.unwrap_or("NULL");
+    let sql = format!(
+        "SELECT {name} AS name, {artist} AS artist,
+                SUM(ph.skipped = 0) AS plays, SUM({LISTENED_SECS}) AS listened
+         FROM play_history ph
+         JOIN tracks t ON t.id = ph.track_id
+         WHERE t.library_root = ?1 AND ph.played_at >= ?2
+         GROUP BY name, artist
+         HAVING plays > 0
+         ORDER BY plays DESC, listened DESC
+         LIMIT ?3"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let stats = stmt
+        .query_map(
+            params![library_root, window_start(days), limit as i64],
+            |row| {
+                Ok(PlayStat {
+                    name: row.get(0)?,
+                    artist: row.get(1)?,
+                    plays: row.get(2)?,
+                    listened_secs: row.get(3)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(stats)
+}
+
+pub fn top_artists(
+    conn: &Connection,
+    library_root: &str,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<PlayStat>, AppError> {
+    top_grouped(
+        conn,
+        library_root,
+        days,
+        limit,
+        "COALESCE(t.album_artist, t.artist, 'Unknown Artist')",
+        None,
+    )
+}
+
+pub fn top_albums(
+    conn: &Connection,
+    library_root: &str,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<PlayStat>, AppError> {
+    top_grouped(
+        conn,
+        library_root,
+        days,
+        limit,
+        "COALESCE(t.album, 'Unknown Album')",
+        Some("COALESCE(t.album_artist, t.artist, 'Unknown Artist')"),
+    )
+}
+
+pub fn top_genres(
+    conn: &Connection,
+    library_root: &str,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<PlayStat>, AppError> {
+    top_grouped(
+        conn,
+        library_root,
+        days,
+        limit,
+        "COALESCE(t.genre, 'Unknown')",
+        None,
+    )
+}
+
+/// Listening per day or week over the last `days` days, oldest first.
+/// Periods without plays are left out.
+pub fn listening_time(
+    conn: &Connection,
+    library_root: &str,
+    period: StatsPeriod,
+    days: Option<u32>,
+) -> Result<Vec<ListeningTime>, AppError> {
+    let start = match period {
+        StatsPeriod::Day => "date(ph.played_at, 'unixepoch', 'localtime')",
+        StatsPeriod::Week => "date(ph.played_at, 'unixepoch', 'localtime', 'weekday 0', '-6 days')",
+    };
+    let sql = format!(
+        "SELECT {start} AS period, SUM(ph.skipped = 0), SUM({LISTENED_SECS})
+         FROM play_history ph
+         JOIN tracks t ON t.id = ph.track_id
+         WHERE t.library_root = ?1 AND ph.played_at >= ?2
+         GROUP BY period
+         ORDER BY period"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let periods = stmt
+        .query_map(params![library_root, window_start(days)], |row| {
+            Ok(ListeningTime {
+                period: row.get(0)?,
+                plays: row.get(1)?,
+                listened_secs: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(periods)
+}
+
+/// Current and longest runs of days with at least one play, in local time.
+/// `today` decides whether the latest run is still going.
+pub fn listening_streaks(
+    conn: &Connection,
+    library_root: &str,
+    today: NaiveDate,
+) -> Result<ListeningStreaks, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT date(ph.played_at, 'unixepoch', 'localtime') AS day
+         FROM play_history ph
+         JOIN tracks t ON t.id = ph.track_id
+         WHERE t.library_root = ?1 AND ph.skipped = 0
+         ORDER BY day",
+    )?;
+    let days = stmt
+        .query_map(params![library_root], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut streaks = ListeningStreaks::default();
+    let mut run: Option<(NaiveDate, NaiveDate)> = None;
+    for day in days {
+        let Ok(day) = NaiveDate::parse_from_str(&day, DATE_FORMAT) else {
+            continue;
+        };
+        let (start, end) = match run {
+            Some((start, end)) if end.succ_opt() == Some(day) => (start, day),
+            _ => (day, day),
+        };
+        run = Some((start, end));
+        let length = (end - start).num_days() as u32 + 1;
+        if length > streaks.longest_days {
+            streaks.longest_days = length;
+            streaks.longest_start = Some(start.format(DATE_FORMAT).to_string());
+            streaks.longest_end = Some(end.format(DATE_FORMAT).to_string());
+        }
+    }
+    if let Some((start, end)) = run {
+        if end == today || end.succ_opt() == Some(today) {
+            streaks.current_days = (end - start).num_days() as u32 + 1;
+        }
+    }
+    Ok(streaks)
+}
+
+/// Tracks last played more than `months` months ago, longest ago first.
+/// With `include_never_played`, tracks that were never played come first.
+pub fn tracks_not_played_since(
+    conn: &Connection,
+    library_root: &str,
+    months: u32,
+    include_never_played: bool,
+    limit: usize,
+) -> Result<Vec<Track>, AppError> {
+    let now = chrono::Utc::now();
+    let cutoff = now
+        .checked_sub_months(Months::new(months))
+        .unwrap_or(now)
+        .timestamp();
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.file_path, t.relative_path, t.library_root, t.title, t.artist,
+                t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
+                t.duration_secs, t.format, t.file_size, t.modified_at, t.hash,
+                t.has_album_art, t.bitrate, t.scanned_at, t.audio_hash,
+                t.replaygain_track_gain, t.replaygain_track_peak, t.replaygain_album_gain, t.replaygain_album_peak, t.rating, t.album_id, t.lyrics
+         FROM tracks t
+         LEFT JOIN (SELECT track_id, MAX(played_at) AS last_played
+                    FROM play_history WHERE skipped = 0 GROUP BY track_id) p
+           ON p.track_id = t.id
+         WHERE t.library_root = ?1
+           AND (p.last_played < ?2 OR (?3 AND p.last_played IS NULL))
+         ORDER BY p.last_played, COALESCE(t.album_artist, t.artist) COLLATE NOCASE,
+                  t.album COLLATE NOCASE, t.disc_number, t.track_number
+         LIMIT ?4",
+    )?;
+    let tracks = stmt
+        .query_map(
+            params![library_root, cutoff, include_never_played, limit as i64],
+            track_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{library_repo, schema};
+    use chrono::{Local, TimeZone};
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn insert_track(conn: &Connection, name: &str, artist: &str, genre: &str) -> i64 {
+        let track = Track {
+            id: None,
+            file_path: format!("/music/{artist}/{name}.flac"),
+            relative_path: format!("{artist}/{name}.flac"),
+            library_root: "/music".to_string(),
+            title: Some(name.to_string()),
+            artist: Some(artist.to_string()),
+            album_artist: None,
+            album: Some(format!("{artist} Album")),
+            track_number: Some(1),
+            disc_number: Some(1),
+            year: Some(2024),
+            genre: Some(genre.to_string()),
+            duration_secs: Some(200.0),
+            format: "flac".to_string(),
+            file_size: 1_000,
+            modified_at: 1700000000,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        };
+        library_repo::upsert_track(conn, &track).unwrap();
+        conn.query_row(
+            "SELECT id FROM tracks WHERE file_path = ?1",
+            params![track.file_path],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn add_play(conn: &Connection, track_id: i64, played_at: i64, listened: f64, skipped: bool) {
+        conn.execute(
+            "INSERT INTO play_history (track_id, played_at, listened_secs, skipped)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![track_id, played_at, listened, skipped],
+        )
+        .unwrap();
+    }
+
+    fn noon(date: NaiveDate) -> i64 {
+        Local
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .timestamp()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_top_tracks_and_groups() {
+        let conn = setup_db();
+        let now = chrono::Utc::now().timestamp();
+        let a = insert_track(&conn, "a", "Alpha", "Rock");
+        let b = insert_track(&conn, "b", "Beta", "Jazz");
+        let c = insert_track(&conn, "c", "Beta", "Jazz");
+        add_play(&conn, a, now - 100, 200.0, false);
+        add_play(&conn, a, now - 50, 250.0, false);
+        add_play(&conn, a, now - 10, 5.0, true);
+        add_play(&conn, b, now - 100, 200.0, false);
+        add_play(&conn, c, now - 100, 200.0, false);
+        add_play(&conn, c, now - 40 * 86_400, 200.0, false);
+
+        let tracks = top_tracks(&conn, "/music", None, 10).unwrap();
+        assert_eq!(tracks[0].track.id, Some(a));
+        assert_eq!(
+            (tracks[0].plays, tracks[0].skips, tracks[0].listened_secs),
+            (2, 1, 455.0)
+        );
+        assert_eq!(tracks[0].last_played, now - 10);
+        assert_eq!(tracks[1].track.id, Some(c));
+
+        // The old play of c falls outside a 30-day window
+        let recent = top_tracks(&conn, "/music", Some(30), 10).unwrap();
+        assert_eq!(
+            recent.iter().find(|t| t.track.id == Some(c)).unwrap().plays,
+            1
+        );
+
+        let artists = top_artists(&conn, "/music", None, 10).unwrap();
+        assert_eq!(artists[0].name, "Beta");
+        assert_eq!(artists[0].plays, 3);
+        assert_eq!(artists[0].artist, None);
+
+        let albums = top_albums(&conn, "/music", Some(30), 1).unwrap();
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].artist.as_deref(), Some("Alpha"));
+
+        let genres = top_genres(&conn, "/music", None, 10).unwrap();
+        assert_eq!(genres[0].name, "Jazz");
+        assert_eq!(top_genres(&conn, "/other", None, 10).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_listening_time() {
+        let conn = setup_db();
+        let a = insert_track(&conn, "a", "Alpha", "Rock");
+        // Monday, Tuesday and the following Monday
+        add_play(&conn, a, noon(date("2024-03-04")), 100.0, false);
+        add_play(&conn, a, noon(date("2024-03-05")), 50.0, false);
+        add_play(&conn, a, noon(date("2024-03-05")) + 60, 5.0, true);
+        add_play(&conn, a, noon(date("2024-03-11")), 200.0, false);
+        // A play from before listened time was tracked counts in full
+        conn.execute(
+            "INSERT INTO play_history (track_id, played_at) VALUES (?1, ?2)",
+            params![a, noon(date("2024-03-11"))],
+        )
+        .unwrap();
+
+        let days = listening_time(&conn, "/music", StatsPeriod::Day, None).unwrap();
+        assert_eq!(days.len(), 3);
+        assert_eq!(
+            days[1],
+            ListeningTime {
+                period: "2024-03-05".to_string(),
+                plays: 1,
+                listened_secs: 55.0,
+            }
+        );
+
+        let weeks = listening_time(&conn, "/music", StatsPeriod::Week, None).unwrap();
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[0].period, "2024-03-04");
+        assert_eq!(weeks[0].plays, 2);
+        assert_eq!(weeks[1].period, "2024-03-11");
+        assert_eq!(weeks[1].listened_secs, 400.0);
+    }
+
+    #[test]
+    fn test_listening_streaks() {
+        let conn = setup_db();
+        let a = insert_track(&conn, "a", "Alpha", "Rock");
+        for day in [
+            "2024-03-01",
+            "2024-03-02",
+            "2024-03-03",
+            "2024-03-09",
+            "2024-03-10",
+        ] {
+            add_play(&conn, a, noon(date(day)), 100.0, false);
+        }
+        // Skips do not keep a streak going
+        add_play(&conn, a, noon(date("2024-03-04")), 5.0, true);
+
+        let streaks = listening_streaks(&conn, "/music", date("2024-03-11")).unwrap();
+        assert_eq!(streaks.longest_days, 3);
+        assert_eq!(streaks.longest_start.as_deref(), Some("2024-03-01"));
+        assert_eq!(streaks.longest_end.as_deref(), Some("2024-03-03"));
+        assert_eq!(streaks.current_days, 2);
+
+        let later = listening_streaks(&conn, "/music", date("2024-03-12")).unwrap();
+        assert_eq!(later.current_days, 0);
+        assert_eq!(
+            listening_streaks(&conn, "/other", date("2024-03-12")).unwrap(),
+            ListeningStreaks::default()
+        );
+    }
+
+    #[test]
+    fn test_tracks_not_played_since() {
+        let conn = setup_db();
+        let now = chrono::Utc::now().timestamp();
+        let recent = insert_track(&conn, "recent", "Alpha", "Rock");
+        let old = insert_track(&conn, "old", "Alpha", "Rock");
+        let never = insert_track(&conn, "never", "Alpha", "Rock");
+        let skipped = insert_track(&conn, "skipped", "Alpha", "Rock");
+        add_play(&conn, recent, now - 86_400, 200.0, false);
+        add_play(&conn, old, now - 200 * 86_400, 200.0, false);
+        add_play(&conn, old, now - 86_400, 3.0, true);
+        add_play(&conn, skipped, now - 86_400, 3.0, true);
+
+        let ids = |tracks: Vec<Track>| {
+            tracks
+                .into_iter()
+                .map(|t| t.id.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(tracks_not_played_since(&conn, "/music", 6, false, 10).unwrap()),
+            vec![old]
+        );
+        let with_never = ids(tracks_not_played_since(&conn, "/music", 6, true, 10).unwrap());
+        assert_eq!(with_never.len(), 3);
+        assert!(with_never.contains(&never) && with_never.contains(&skipped));
+        assert_eq!(with_never[2], old);
+    }
+}
@@ -82,7 +82,8 @@ pub fn save_baselines(
 mod tests {
     use super::*;
     use crate::db::{profile_repo, schema};
-    use crate::models::sync_profile::{SyncMode, SyncProfile};
+    use crate::models::sync_profile::{KeepBothNaming, SyncMode, SyncProfile};
+    use crate::models::track::HashKind;
 
     fn setup_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
@@ -98,6 +99,9 @@ mod tests {
             target_path: "/target".to_string(),
             sync_mode: SyncMode::OneWay,
             exclude_patterns: vec![],
+            conflict_policies: vec![],
+            keep_both_naming: KeepBothNaming::Timestamp,
+            hash_kind: HashKind::File,
             created_at: 1700000000,
             last_synced_at: None,
         };
@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::models::duplicate::{DuplicateGroup, KeepRule};
+use crate::models::track::Track;
+
+/// Settings key holding the user's keep rules as a JSON array.
+pub const KEEP_RULES_SETTING: &str = "duplicate_keep_rules";
+
+const LOSSLESS_FORMATS: &[&str] = &["flac", "wav", "alac", "aiff", "ape", "wv"];
+
+/// Rules used when the user has not configured any.
+pub fn default_keep_rules() -> Vec<KeepRule> {
+    vec![
+        KeepRule::PreferLossless,
+        KeepRule::HigherBitrate,
+        KeepRule::HasAlbumArt,
+        KeepRule::MoreCompleteTags,
+        KeepRule::HigherRating,
+        KeepRule::Newest,
+    ]
+}
+
+/// Parses the stored keep rules, falling back to the defaults when the
+/// setting is missing or unreadable.
+pub fn parse_keep_rules(setting: Option<&str>) -> Vec<KeepRule> {
+    setting
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_else(default_keep_rules)
+}
+
+/// Returns the id of the track to keep. When the rules cannot separate two
+/// tracks, the one listed first wins.
+pub fn pick_keeper(tracks: &[Track], rules: &[KeepRule]) -> Option<i64> {
+    let mut candidates = tracks.iter().filter(|t| t.id.is_some());
+    let first = candidates.next()?;
+    let best = candidates.fold(first, |best, t| {
+        if compare(t, best, rules) == Ordering::Greater {
+            t
+        } else {
+            best
+        }
+    });
+    best.id
+}
+
+/// Sets `keeper_id` on every group.
+pub fn mark_keepers(groups: &mut [DuplicateGroup], rules: &[KeepRule]) {
+    for group in groups {
+        group.keeper_id = pick_keeper(&group.tracks, rules);
+    }
+}
+
+/// `Greater` means `a` is the better track to keep.
+fn compare(a: &Track, b: &Track, rules: &[KeepRule]) -> Ordering {
+    rules
+        .iter()
+        .map(|rule| match rule {
+            KeepRule::PreferLossless => is_lossless(a).cmp(&is_lossless(b)),
+            KeepRule::HigherBitrate => a.bitrate.cmp(&b.bitrate),
+            KeepRule::HasAlbumArt => a.has_album_art.cmp(&b.has_album_art),
+            KeepRule::MoreCompleteTags => tag_completeness(a).cmp(&tag_completeness(b)),
+            KeepRule::HigherRating => a.rating.cmp(&b.rating),
+            KeepRule::PreferPathPrefix { prefix } => {
+                // Whole components only: `/music/flac` is not under `/music/flac-old`
+                let under = |t: &Track| Path::new(&t.file_path).starts_with(prefix);
+                under(a).cmp(&under(b))
+            }
+            KeepRule::Newest => a.modified_at.cmp(&b.modified_at),
+        })
+        .find(|ord| ord.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn is_lossless(track: &Track) -> bool {
+    LOSSLESS_FORMATS.contains(&track.format.to_lowercase().as_str())
+}
+
+fn tag_completeness(track: &Track) -> usize {
+    [
+        track.title.is_some(),
+        track.artist.is_some(),
+        track.album_artist.is_some(),
+        track.album.is_some(),
+        track.track_number.is_some(),
+        track.disc_number.is_some(),
+        track.year.is_some(),
+        track.genre.is_some(),
+    ]
+    .iter()
+    .filter(|present| **present)
+    .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i64, format: &str, bitrate: Option<u32>) -> Track {
+        Track {
+            id: Some(id),
+            file_path: format!("/music/a/{}.{}", id, format),
+            relative_path: format!("a/{}.{}", id, format),
+            library_root: "/music".to_string(),
+            title: Some("Song".to_string()),
+            artist: Some("Artist".to_string()),
+            album_artist: None,
+            album: Some("Album".to_string()),
+            track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: Some(200.0),
+            format: format.to_string(),
+            file_size: 1000,
+            modified_at: 1700000000,
+            hash: None,
+            has_album_art: false,
+            bitrate,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    #[test]
+    fn test_default_rules_prefer_lossless_over_bitrate() {
+        let tracks = vec![track(1, "mp3", Some(320)), track(2, "FLAC", Some(900))];
+        assert_eq!(pick_keeper(&tracks, &default_keep_rules()), Some(2));
+
+        let tracks = vec![track(1, "mp3", Some(320)), track(2, "flac", None)];
+        assert_eq!(pick_keeper(&tracks, &default_keep_rules()), Some(2));
+    }
+
+    #[test]
+    fn test_later_rules_break_ties() {
+        let mut a = track(1, "mp3", Some(320));
+        let mut b = track(2, "mp3", Some(320));
+        b.has_album_art = true;
+        assert_eq!(
+            pick_keeper(&[a.clone(), b.clone()], &default_keep_rules()),
+            Some(2)
+        );
+
+        a.has_album_art = true;
+        a.year = Some(2001);
+        assert_eq!(
+            pick_keeper(&[a.clone(), b.clone()], &default_keep_rules()),
+            Some(1)
+        );
+
+        b.year = Some(2001);
+        b.modified_at += 10;
+        assert_eq!(
+            pick_keeper(&[a.clone(), b.clone()], &default_keep_rules()),
+            Some(2)
+        );
+
+        a.rating = Some(80);
+        assert_eq!(pick_keeper(&[a, b], &default_keep_rules()), Some(1));
+    }
+
+    #[test]
+    fn test_path_prefix_rule_and_order_matter() {
+        let mut a = track(1, "flac", Some(900));
+        a.file_path = "/music/hq/song.flac".to_string();
+        let mut b = track(2, "mp3", Some(320));
+        b.file_path = "/music/phone/song.mp3".to_string();
+        let tracks = vec![a, b];
+
+        let rules = vec![
+            KeepRule::PreferPathPrefix {
+                prefix: "/music/phone".to_string(),
+            },
+            KeepRule::PreferLossless,
+        ];
+        assert_eq!(pick_keeper(&tracks, &rules), Some(2));
+        assert_eq!(pick_keeper(&tracks, &rules[1..]), Some(1));
+    }
+
+    #[test]
+    fn test_path_prefix_rule_matches_whole_components() {
+        let mut a = track(1, "mp3", Some(320));
+        a.file_path = "/music/flac-old/song.mp3".to_string();
+        let mut b = track(2, "mp3", Some(320));
+        b.file_path = "/music/flac/song.mp3".to_string();
+        let rules = vec![KeepRule::PreferPathPrefix {
+            prefix: "/music/flac".to_string(),
+        }];
+        assert_eq!(pick_keeper(&[a.clone(), b.clone()], &rules), Some(2));
+
+        // A trailing slash makes no difference
+        let rules = vec![KeepRule::PreferPathPrefix {
+            prefix: "/music/flac/".to_string(),
+        }];
+        assert_eq!(pick_keeper(&[a, b], &rules), Some(2));
+    }
+
+    #[test]
+    fn test_full_tie_keeps_first_and_skips_missing_ids() {
+        let mut unsaved = track(0, "flac", Some(1000));
+        unsaved.id = None;
+        let tracks = vec![unsaved, track(5, "mp3", None), track(6, "mp3", None)];
+        assert_eq!(pick_keeper(&tracks, &default_keep_rules()), Some(5));
+        assert_eq!(pick_keeper(&[], &default_keep_rules()), None);
+    }
+
+    #[test]
+    fn test_parse_keep_rules() {
+        let json = r#"[{"rule":"prefer_path_prefix","prefix":"/nas"},{"rule":"newest"}]"#;
+        assert_eq!(
+            parse_keep_rules(Some(json)),
+            vec![
+                KeepRule::PreferPathPrefix {
+                    prefix: "/nas".to_string()
+                },
+                KeepRule::Newest,
+            ]
+        );
+        assert_eq!(parse_keep_rules(None), default_keep_rules());
+        assert_eq!(parse_keep_rules(Some("not json")), default_keep_rules());
+    }
+}
@@ -14,6 +14,9 @@ pub enum AppError {
     #[error("Metadata error: {0}")]
     Metadata(String),
 
+    #[error("Decode error: {0}")]
+    Decode(String),
+
     #[error("Sync cancelled")]
     SyncCancelled,
 
@@ -32,6 +35,11 @@ pub enum AppError {
     #[error("Path not accessible: {0}")]
     PathNotAccessible(String),
 
+    #[error(
+        "Database schema version {found} is newer than this build supports ({supported}); update Orchestra to open it"
+    )]
+    SchemaTooNew { found: i64, supported: i64 },
+
     #[error("{0}")]
     General(String),
 }
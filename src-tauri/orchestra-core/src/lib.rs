@@ -1,6 +1,11 @@
+pub mod backup;
 pub mod cover;
 pub mod db;
+pub mod dedupe;
 pub mod error;
 pub mod media_session;
 pub mod models;
+pub mod rating;
 pub mod scanner;
+pub mod scrobble;
+pub mod trash;
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::device::{AlbumSelection, Device};
+use crate::models::playlist::Playlist;
+use crate::models::sync_profile::SyncProfile;
+
+/// Version of the `UserDataArchive` layout. Bump it when a change would make
+/// older builds misread an archive.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Everything the user curated, without the `tracks` cache a rescan rebuilds.
+/// Tracks are referenced by path and hash rather than by row id, so the
+/// archive can be imported into a database with a freshly scanned library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDataArchive {
+    pub format_version: u32,
+    pub exported_at: i64,
+    pub playlists: Vec<ArchivedPlaylist>,
+    pub favorites: Vec<ArchivedFavorite>,
+    pub play_history: Vec<ArchivedPlay>,
+    #[serde(default)]
+    pub track_ratings: Vec<ArchivedTrackRating>,
+    #[serde(default)]
+    pub album_ratings: Vec<ArchivedAlbumRating>,
+    pub sync_profiles: Vec<SyncProfile>,
+    pub devices: Vec<ArchivedDevice>,
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Identifies a track across databases. Matched by relative path first, then
+/// by file hash, then by audio hash (which survives re-tagging).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackRef {
+    pub relative_path: String,
+    pub hash: Option<String>,
+    pub audio_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedPlaylist {
+    #[serde(flatten)]
+    pub playlist: Playlist,
+    /// In playlist order.
+    pub tracks: Vec<TrackRef>,
+}
+
+/// A favorite. Track favorites carry a `track` reference (their `entity_id`
+/// is the old row id and is ignored on import); albums and artists are
+/// keyed by name in `entity_id`, album names as `artist\0album`, and
+/// matched to the library's rows on import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFavorite {
+    pub entity_type: String,
+    pub entity_id: String,
+    #[serde(default)]
+    pub track: Option<TrackRef>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedPlay {
+    pub track: TrackRef,
+    pub played_at: i64,
+    #[serde(default)]
+    pub listened_secs: Option<f64>,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// A track rating. Ratings also live in tags, but those set in the app may
+/// not have been written back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTrackRating {
+    pub track: TrackRef,
+    pub rating: u8,
+}
+
+/// An album rating, keyed by name so it can be matched to another library's
+/// albums on import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedAlbumRating {
+    pub artist_name: String,
+    pub album_name: String,
+    pub rating: u8,
+    pub rated_at: i64,
+}
+
+/// A device with its sync selections by name. Selections whose artist or
+/// album is not in the library are dropped on import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedDevice {
+    #[serde(flatten)]
+    pub device: Device,
+    pub selected_artists: Vec<String>,
+    pub selected_albums: Vec<AlbumSelection>,
+}
+
+/// What an import added. Existing playlists, profiles and devices with the
+/// same id are left alone, as are existing ratings; settings are overwritten.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub playlists: usize,
+    pub playlist_tracks: usize,
+    pub favorites: usize,
+    pub plays: usize,
+    /// Track and album ratings.
+    #[serde(default)]
+    pub ratings: usize,
+    pub sync_profiles: usize,
+    pub devices: usize,
+    pub settings: usize,
+    /// Track references that matched no track in the library and were
+    /// dropped.
+    pub unmatched_tracks: usize,
+}
+
+/// A database snapshot made by the online backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub path: String,
+    pub created_at: i64,
+    pub size: u64,
+}
@@ -26,6 +26,7 @@ pub enum Resolution {
     KeepSource,
     KeepTarget,
     KeepBoth,
+    MergeTags,
     Skip,
 }
 
@@ -34,3 +35,33 @@ pub struct ConflictResolution {
     pub relative_path: String,
     pub resolution: Resolution,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    NewestWins,
+    LargestWins,
+    SourceWins,
+    TargetWins,
+    KeepBoth,
+}
+
+/// A default resolution for unattended two-way syncs. Rules are checked in
+/// order; `conflict_type` and `pattern` narrow which conflicts a rule covers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConflictPolicyRule {
+    pub policy: ConflictPolicy,
+    #[serde(default)]
+    pub conflict_type: Option<ConflictType>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedResolution {
+    pub relative_path: String,
+    pub resolution: Resolution,
+    /// The profile policy that picked the resolution, or `None` if it was
+    /// chosen manually (or left unresolved).
+    pub policy: Option<ConflictPolicy>,
+}
@@ -13,6 +13,8 @@ pub struct Device {
     pub last_synced_at: Option<i64>,
 }
 
+/// An album by name, as kept in user data archives, which outlive the
+/// database's album ids.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlbumSelection {
     pub artist_name: String,
@@ -21,19 +23,30 @@ pub struct AlbumSelection {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlbumSummary {
+    pub id: i64,
+    pub artist_id: i64,
     pub artist_name: String,
     pub album_name: String,
+    pub sort_name: String,
     pub track_count: usize,
     pub total_size: u64,
+    /// The latest year of its tracks; `year_min` is the earliest.
     pub year: Option<i32>,
+    pub year_min: Option<i32>,
+    pub duration_secs: f64,
+    /// The first track with embedded art, to load the cover from.
+    pub cover_track_id: Option<i64>,
+    #[serde(default)]
+    pub rating: Option<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceWithStatus {
     pub device: Device,
     pub connected: bool,
-    pub selected_artists: Vec<String>,
-    pub selected_albums: Vec<AlbumSelection>,
+    /// Ids of the selected artists and albums.
+    pub selected_artists: Vec<i64>,
+    pub selected_albums: Vec<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,7 +72,9 @@ pub struct RegisterDeviceRequest {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArtistSummary {
+    pub id: i64,
     pub name: String,
+    pub sort_name: String,
     pub album_count: usize,
     pub track_count: usize,
     pub total_size: u64,
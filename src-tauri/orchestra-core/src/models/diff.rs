@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::conflict::{AppliedResolution, ConflictType};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum DiffAction {
@@ -29,6 +31,8 @@ pub struct DiffEntry {
     pub target_hash: Option<String>,
     pub source_modified: Option<i64>,
     pub target_modified: Option<i64>,
+    #[serde(default)]
+    pub conflict_type: Option<ConflictType>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,3 +46,9 @@ pub struct DiffResult {
     pub total_unchanged: usize,
     pub bytes_to_transfer: u64,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncResult {
+    pub files_synced: usize,
+    pub resolutions: Vec<AppliedResolution>,
+}
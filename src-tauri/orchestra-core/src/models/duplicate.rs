@@ -7,6 +7,8 @@ use crate::models::track::Track;
 #[serde(rename_all = "snake_case")]
 pub enum DuplicateMatchType {
     ContentHash,
+    AudioHash,
+    AcousticFingerprint,
     MetadataSimilarity,
 }
 
@@ -14,9 +16,13 @@ pub enum DuplicateMatchType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DuplicateGroup {
     pub match_type: DuplicateMatchType,
-    /// The shared key (hash for content, "title|artist|duration" for metadata)
+    /// The shared key (hash for content/audio, "title|artist|duration" for metadata,
+    /// first track's relative path for acoustic fingerprint)
     pub match_key: String,
     pub tracks: Vec<Track>,
+    /// The track the keep rules picked to survive; the rest are candidates for removal
+    #[serde(default)]
+    pub keeper_id: Option<i64>,
 }
 
 /// Full result returned to the frontend
@@ -26,3 +32,27 @@ pub struct DuplicateResult {
     pub total_duplicate_tracks: usize,
     pub total_wasted_bytes: u64,
 }
+
+/// A criterion for picking which track of a duplicate group to keep. Rules are
+/// applied in order; the first one that tells two tracks apart decides.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum KeepRule {
+    PreferLossless,
+    HigherBitrate,
+    HasAlbumArt,
+    MoreCompleteTags,
+    /// Unrated tracks rank below rated ones.
+    HigherRating,
+    PreferPathPrefix {
+        prefix: String,
+    },
+    Newest,
+}
+
+/// A keeper and the duplicates of it to move to the trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeeperSelection {
+    pub keeper_id: i64,
+    pub duplicate_ids: Vec<i64>,
+}
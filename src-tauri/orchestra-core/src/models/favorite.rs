@@ -2,7 +2,9 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Favorite {
+    /// "track", "album" or "artist".
     pub entity_type: String,
+    /// The row id in `tracks`, `albums` or `artists`.
     pub entity_id: String,
     pub created_at: i64,
 }
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether a track's lyrics carry timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LyricsKind {
+    Plain,
+    Synced,
+}
+
+impl LyricsKind {
+    /// The value stored in `tracks.lyrics`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LyricsKind::Plain => "plain",
+            LyricsKind::Synced => "synced",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "plain" => Some(LyricsKind::Plain),
+            "synced" => Some(LyricsKind::Synced),
+            _ => None,
+        }
+    }
+}
+
+/// Where a track's lyrics were found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LyricsSource {
+    /// A USLT or SYLT frame, or a `LYRICS` tag.
+    Embedded,
+    /// An `.lrc` file next to the track with the same name.
+    Sidecar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricLine {
+    /// Milliseconds from the start of the track; `None` in plain lyrics.
+    pub time_ms: Option<u32>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lyrics {
+    pub source: LyricsSource,
+    /// In order of appearance; synced lyrics are sorted by time and every
+    /// line has one.
+    pub lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+    pub fn kind(&self) -> LyricsKind {
+        if self.lines.iter().any(|line| line.time_ms.is_some()) {
+            LyricsKind::Synced
+        } else {
+            LyricsKind::Plain
+        }
+    }
+
+    /// The line being sung `position_ms` into the track: the last one that
+    /// has started. `None` before the first line and for plain lyrics.
+    pub fn line_at(&self, position_ms: u32) -> Option<usize> {
+        self.lines
+            .partition_point(|line| line.time_ms.is_some_and(|t| t <= position_ms))
+            .checked_sub(1)
+    }
+}
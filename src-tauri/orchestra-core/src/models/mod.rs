@@ -1,9 +1,13 @@
+pub mod backup;
 pub mod conflict;
 pub mod device;
 pub mod diff;
 pub mod duplicate;
 pub mod favorite;
+pub mod lyrics;
 pub mod playlist;
 pub mod progress;
+pub mod scrobble;
+pub mod stats;
 pub mod sync_profile;
 pub mod track;
@@ -65,4 +65,18 @@ pub enum ProgressEvent {
         total_files: usize,
         current_file: String,
     },
+    #[serde(rename = "loudness_started")]
+    LoudnessStarted { total: usize },
+    #[serde(rename = "loudness_progress")]
+    LoudnessProgress {
+        files_analyzed: usize,
+        total_files: usize,
+        current_file: String,
+    },
+    #[serde(rename = "scrobbles_imported")]
+    ScrobblesImported {
+        imported: usize,
+        duplicates: usize,
+        unmatched: usize,
+    },
 }
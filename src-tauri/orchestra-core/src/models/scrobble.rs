@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// One line of an AudioScrobbler `.scrobbler.log`, as written by Rockbox
+/// and other portable players.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrobbleEntry {
+    pub artist: String,
+    pub album: Option<String>,
+    pub title: String,
+    pub track_number: Option<u32>,
+    pub duration_secs: Option<u32>,
+    /// `S` in the log: the track was skipped before half of it was heard.
+    pub skipped: bool,
+    /// Unix time in UTC; logs written with `#TZ/UNKNOWN` are converted from
+    /// local time when parsed.
+    pub played_at: i64,
+    pub mbid: Option<String>,
+}
+
+/// Outcome of importing a scrobble log.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScrobbleImportSummary {
+    pub imported: usize,
+    /// Entries already in the play history, e.g. from an earlier sync.
+    pub duplicates: usize,
+    /// Entries that matched no track in the library.
+    pub unmatched: usize,
+}
+
+/// A listen in the format of ListenBrainz's JSON export and import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListenBrainzListen {
+    pub listened_at: i64,
+    pub track_metadata: ListenBrainzTrackMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListenBrainzTrackMetadata {
+    pub artist_name: String,
+    pub track_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_name: Option<String>,
+    pub additional_info: ListenBrainzAdditionalInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListenBrainzAdditionalInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracknumber: Option<u32>,
+    pub submission_client: String,
+    pub submission_client_version: String,
+}
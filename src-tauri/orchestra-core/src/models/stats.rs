@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::track::Track;
+
+/// A track and what was heard of it within a stats window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackPlayStats {
+    pub track: Track,
+    pub plays: usize,
+    pub skips: usize,
+    pub listened_secs: f64,
+    pub last_played: i64,
+}
+
+/// An artist, album or genre ranked by plays. `artist` is only set for
+/// albums.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayStat {
+    pub name: String,
+    pub artist: Option<String>,
+    pub plays: usize,
+    pub listened_secs: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsPeriod {
+    Day,
+    /// Weeks start on Monday.
+    Week,
+}
+
+/// Listening in one day or week, in local time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListeningTime {
+    /// First day of the period, `YYYY-MM-DD`.
+    pub period: String,
+    pub plays: usize,
+    pub listened_secs: f64,
+}
+
+/// Runs of consecutive days with at least one play. The current streak
+/// still counts if nothing has been played yet today.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListeningStreaks {
+    pub current_days: u32,
+    pub longest_days: u32,
+    /// First and last day of the longest streak, `YYYY-MM-DD`.
+    pub longest_start: Option<String>,
+    pub longest_end: Option<String>,
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::conflict::ConflictPolicyRule;
+use crate::models::track::HashKind;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum SyncMode {
@@ -7,6 +10,16 @@ pub enum SyncMode {
     TwoWay,
 }
 
+/// Suffix appended to the renamed copy when a conflict is resolved with
+/// `Resolution::KeepBoth`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepBothNaming {
+    #[default]
+    Timestamp,
+    Hostname,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncProfile {
     pub id: String,
@@ -15,6 +28,12 @@ pub struct SyncProfile {
     pub target_path: String,
     pub sync_mode: SyncMode,
     pub exclude_patterns: Vec<String>,
+    #[serde(default)]
+    pub conflict_policies: Vec<ConflictPolicyRule>,
+    #[serde(default)]
+    pub keep_both_naming: KeepBothNaming,
+    #[serde(default)]
+    pub hash_kind: HashKind,
     pub created_at: i64,
     pub last_synced_at: Option<i64>,
 }
@@ -26,6 +45,12 @@ pub struct CreateProfileRequest {
     pub target_path: String,
     pub sync_mode: SyncMode,
     pub exclude_patterns: Vec<String>,
+    #[serde(default)]
+    pub conflict_policies: Vec<ConflictPolicyRule>,
+    #[serde(default)]
+    pub keep_both_naming: KeepBothNaming,
+    #[serde(default)]
+    pub hash_kind: HashKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,4 +61,7 @@ pub struct UpdateProfileRequest {
     pub target_path: Option<String>,
     pub sync_mode: Option<SyncMode>,
     pub exclude_patterns: Option<Vec<String>>,
+    pub conflict_policies: Option<Vec<ConflictPolicyRule>>,
+    pub keep_both_naming: Option<KeepBothNaming>,
+    pub hash_kind: Option<HashKind>,
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::models::lyrics::LyricsKind;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Track {
     pub id: Option<i64>,
     pub file_path: String,
@@ -22,16 +24,57 @@ pub struct Track {
     pub has_album_art: bool,
     pub bitrate: Option<u32>,
     pub scanned_at: i64,
+    #[serde(default)]
+    pub audio_hash: Option<String>,
+    /// ReplayGain adjustments in dB, relative to -18 LUFS, and sample peaks
+    /// (1.0 = full scale). Read from tags or filled in by loudness analysis.
+    #[serde(default)]
+    pub replaygain_track_gain: Option<f64>,
+    #[serde(default)]
+    pub replaygain_track_peak: Option<f64>,
+    #[serde(default)]
+    pub replaygain_album_gain: Option<f64>,
+    #[serde(default)]
+    pub replaygain_album_peak: Option<f64>,
+    /// 1–100, one star being 20 (see `rating`); `None` when unrated.
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// The `albums` row the track is filed under, set by
+    /// `album_repo::refresh`; `None` until then.
+    #[serde(default)]
+    pub album_id: Option<i64>,
+    /// Whether the last scan found lyrics, embedded or in an `.lrc` sidecar.
+    /// A sidecar added later shows up on the next scan or once the lyrics
+    /// are read.
+    #[serde(default)]
+    pub lyrics: Option<LyricsKind>,
+}
+
+/// Which content hash to compare files by. `Audio` covers only the audio
+/// stream, so a re-tagged copy still matches the original.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HashKind {
+    #[default]
+    File,
+    Audio,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArtistNode {
+    /// The `artists` row; `None` for tracks not yet filed by
+    /// `album_repo::refresh`.
+    #[serde(default)]
+    pub id: Option<i64>,
     pub name: String,
     pub albums: Vec<AlbumNode>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlbumNode {
+    /// The `albums` row, as in `ArtistNode::id`.
+    #[serde(default)]
+    pub id: Option<i64>,
     pub name: String,
     pub year: Option<i32>,
     pub tracks: Vec<Track>,
@@ -48,7 +91,7 @@ pub const AUDIO_EXTENSIONS: &[&str] = &[
     "flac", "mp3", "m4a", "aac", "wav", "alac", "ogg", "opus", "wma",
 ];
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TrackMetadataUpdate {
     pub file_path: String,
     pub title: Option<String>,
@@ -59,6 +102,9 @@ pub struct TrackMetadataUpdate {
     pub disc_number: Option<u32>,
     pub year: Option<i32>,
     pub genre: Option<String>,
+    /// 1–100; `Some(0)` clears the rating.
+    #[serde(default)]
+    pub rating: Option<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +126,14 @@ pub struct GenreStat {
     pub count: usize,
 }
 
+/// Outcome of `library_repo::upsert_tracks`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpsertSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LibraryStats {
     pub total_tracks: usize,
@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+
+/// Settings key: `false` keeps ratings in the database only instead of also
+/// writing them to the file's tags. Written ratings travel with the file, so
+/// devices and other players see them.
+pub const WRITE_RATINGS_SETTING: &str = "write_ratings_to_tags";
+
+/// Ratings are stored as 1–100, one star being 20. Unrated is `None`.
+pub const MAX_RATING: u8 = 100;
+
+/// POPM values Windows Media Player writes for one to five stars, which most
+/// other players read the same way.
+const POPM_STARS: [u8; 5] = [1, 64, 128, 196, 255];
+
+/// Whole stars (0–5) for a rating, rounding half stars up.
+pub fn stars(rating: Option<u8>) -> u8 {
+    rating.map_or(0, |r| ((r.min(MAX_RATING) + 10) / 20).min(5))
+}
+
+/// Rating from an ID3v2 POPM byte (1–255). 0 means unrated.
+pub fn from_popm(value: u8) -> Option<u8> {
+    let stars = match value {
+        0 => return None,
+        1..=31 => 1,
+        32..=95 => 2,
+        96..=159 => 3,
+        160..=223 => 4,
+        _ => 5,
+    };
+    Some(stars * 20)
+}
+
+/// POPM byte for a rating.
+pub fn to_popm(rating: u8) -> u8 {
+    match stars(Some(rating)) {
+        0 => 0,
+        n => POPM_STARS[n as usize - 1],
+    }
+}
+
+/// Rating from the text of a Vorbis `RATING` comment or an MP4 `rate` atom.
+/// Writers disagree on the scale: values up to 5 are read as stars, a
+/// fraction up to 1.0 as a share of the maximum, anything else as 0–100.
+pub fn from_text(value: &str) -> Option<u8> {
+    let value: f64 = value.trim().parse().ok().filter(|v: &f64| v.is_finite())?;
+    let rating = if value > 0.0 && value <= 1.0 && value.fract() != 0.0 {
+        value * f64::from(MAX_RATING)
+    } else if value <= 5.0 {
+        value * 20.0
+    } else {
+        value
+    };
+    let rating = rating.round().clamp(0.0, f64::from(MAX_RATING)) as u8;
+    (rating > 0).then_some(rating)
+}
+
+/// Payload of an ID3v2 POPM frame (without the frame header): an empty
+/// email, the rating byte and a zero play counter.
+pub fn popm_frame(rating: u8) -> Vec<u8> {
+    vec![0, to_popm(rating), 0, 0, 0, 0]
+}
+
+/// The rating byte of a POPM frame payload: the byte after the
+/// NUL-terminated email.
+pub fn from_popm_frame(data: &[u8]) -> Option<u8> {
+    let email_end = data.iter().position(|b| *b == 0)?;
+    data.get(email_end + 1).copied().and_then(from_popm)
+}
+
+/// A `rating` term in a search query, such as `rating>=4` or `rating:5`.
+/// Compares whole stars; `rating:0` finds unrated tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RatingFilter {
+    pub op: RatingOp,
+    pub stars: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatingOp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl RatingOp {
+    pub fn sql(self) -> &'static str {
+        match self {
+            RatingOp::Eq => "=",
+            RatingOp::Lt => "<",
+            RatingOp::Le => "<=",
+            RatingOp::Gt => ">",
+            RatingOp::Ge => ">=",
+        }
+    }
+}
+
+impl RatingFilter {
+    /// Parses one query term, case-insensitively.
+    pub fn parse(term: &str) -> Option<Self> {
+        let rest = term
+            .get(..6)?
+            .eq_ignore_ascii_case("rating")
+            .then(|| &term[6..])?;
+        let (op, value) = [
+            (">=", RatingOp::Ge),
+            ("<=", RatingOp::Le),
+            (">", RatingOp::Gt),
+            ("<", RatingOp::Lt),
+            ("=", RatingOp::Eq),
+            (":", RatingOp::Eq),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| rest.strip_prefix(prefix).map(|v| (op, v)))?;
+        let stars: u8 = value.parse().ok().filter(|s| *s <= 5)?;
+        Some(RatingFilter { op, stars })
+    }
+
+    pub fn matches(&self, rating: Option<u8>) -> bool {
+        let ord = stars(rating).cmp(&self.stars);
+        match self.op {
+            RatingOp::Eq => ord == Ordering::Equal,
+            RatingOp::Lt => ord == Ordering::Less,
+            RatingOp::Le => ord != Ordering::Greater,
+            RatingOp::Gt => ord == Ordering::Greater,
+            RatingOp::Ge => ord != Ordering::Less,
+        }
+    }
+}
+
+/// Splits the rating filters out of a search query, returning them and the
+/// remaining text.
+pub fn split_query(query: &str) -> (Vec<RatingFilter>, String) {
+    let mut filters = Vec::new();
+    let mut text = Vec::new();
+    for term in query.split_whitespace() {
+        match RatingFilter::parse(term) {
+            Some(filter) => filters.push(filter),
+            None => text.push(term),
+        }
+    }
+    (filters, text.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_popm_round_trip() {
+        assert_eq!(from_popm(0), None);
+        assert_eq!(from_popm(1), Some(20));
+        assert_eq!(from_popm(196), Some(80));
+        assert_eq!(from_popm(255), Some(100));
+        for stars in 1..=5u8 {
+            assert_eq!(from_popm(to_popm(stars * 20)), Some(stars * 20));
+        }
+        assert_eq!(from_popm_frame(&popm_frame(60)), Some(60));
+        assert_eq!(
+            from_popm_frame(b"user@example.com\0\xc4\0\0\0\x07"),
+            Some(80)
+        );
+        assert_eq!(from_popm_frame(b"no terminator"), None);
+    }
+
+    #[test]
+    fn test_from_text_scales() {
+        assert_eq!(from_text("4"), Some(80));
+        assert_eq!(from_text("80"), Some(80));
+        assert_eq!(from_text("0.6"), Some(60));
+        assert_eq!(from_text("3.5"), Some(70));
+        assert_eq!(from_text("250"), Some(100));
+        assert_eq!(from_text("0"), None);
+        assert_eq!(from_text("great"), None);
+    }
+
+    #[test]
+    fn test_stars_round_half_up() {
+        assert_eq!(stars(None), 0);
+        assert_eq!(stars(Some(9)), 0);
+        assert_eq!(stars(Some(10)), 1);
+        assert_eq!(stars(Some(70)), 4);
+        assert_eq!(stars(Some(100)), 5);
+    }
+
+    #[test]
+    fn test_split_query() {
+        let (filters, text) = split_query("blue RATING>=4 monday rating:0 ratings");
+        assert_eq!(text, "blue monday ratings");
+        assert_eq!(
+            filters,
+            vec![
+                RatingFilter {
+                    op: RatingOp::Ge,
+                    stars: 4
+                },
+                RatingFilter {
+                    op: RatingOp::Eq,
+                    stars: 0
+                },
+            ]
+        );
+        assert!(filters[0].matches(Some(80)));
+        assert!(!filters[0].matches(Some(60)));
+        assert!(filters[1].matches(None));
+        assert_eq!(RatingFilter::parse("rating>9"), None);
+    }
+}
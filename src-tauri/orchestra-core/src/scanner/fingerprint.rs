@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::error::AppError;
+
+/// Audio is downmixed and resampled to this rate before analysis.
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const HOP_SIZE: usize = FRAME_SIZE / 3;
+/// Only the start of each track is fingerprinted, like `fpcalc`.
+const MAX_DURATION_SECS: u32 = 120;
+const MIN_FREQ: f32 = 28.0;
+const MAX_FREQ: f32 = 3520.0;
+/// How far (in sub-fingerprints) two fingerprints may be shifted against each
+/// other when matching, to absorb encoder delay and padding.
+const MAX_OFFSET: usize = 16;
+/// How many sub-fingerprints (~32 s) from the start of each track are
+/// indexed by `candidate_pairs`.
+const INDEX_LEN: usize = 256;
+/// Sub-fingerprint values shared by more tracks than this, mostly silence,
+/// are too common to suggest a match.
+const MAX_BUCKET: usize = 50;
+
+/// Default minimum `similarity` for two tracks to count as the same recording.
+pub const DEFAULT_THRESHOLD: f64 = 0.85;
+
+/// Computes a Chromaprint-style fingerprint: one 32-bit sub-fingerprint per
+/// analysis frame (~124 ms), derived from how the 12 chroma bands compare to
+/// each other and change over time. Because it only looks at the decoded
+/// audio, the same recording in different formats or bitrates produces a
+/// near-identical fingerprint.
+pub fn fingerprint_file(path: &Path) -> Result<Vec<u32>, AppError> {
+    let (samples, rate) = decode_mono(path, MAX_DURATION_SECS)?;
+    let samples = resample(&samples, rate, SAMPLE_RATE);
+    Ok(sub_fingerprints(&chroma_frames(&samples)))
+}
+
+/// Fraction of matching bits between two fingerprints at their best alignment,
+/// from 0.0 (unrelated audio scores around 0.5) to 1.0.
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let min_overlap = (a.len().min(b.len()) / 2).max(1);
+    let mut best = 0.0;
+    for shift in -(MAX_OFFSET as isize)..=MAX_OFFSET as isize {
+        let (a_start, b_start) = if shift >= 0 {
+            (shift as usize, 0)
+        } else {
+            (0, shift.unsigned_abs())
+        };
+        if a_start >= a.len() || b_start >= b.len() {
+            continue;
+        }
+        let overlap = (a.len() - a_start).min(b.len() - b_start);
+        if overlap < min_overlap {
+            continue;
+        }
+        let differing: u32 = a[a_start..a_start + overlap]
+            .iter()
+            .zip(&b[b_start..b_start + overlap])
+            .map(|(x, y)| (x ^ y).count_ones())
+            .sum();
+        let score = 1.0 - f64::from(differing) / (overlap as f64 * 32.0);
+        if score > best {
+            best = score;
+        }
+    }
+    best
+}
+
+/// Pairs of fingerprints, by index and lower first, that share at least one
+/// sub-fingerprint value near their start. Recordings similar enough to
+/// match nearly always do, so only these pairs need a full `similarity`.
+pub fn candidate_pairs(fingerprints: &[&[u32]]) -> Vec<(usize, usize)> {
+    let mut index: Vec<(u32, usize)> = Vec::new();
+    for (i, fp) in fingerprints.iter().enumerate() {
+        let mut values = fp[..fp.len().min(INDEX_LEN)].to_vec();
+        values.sort_unstable();
+        values.dedup();
+        index.extend(values.into_iter().map(|v| (v, i)));
+    }
+    index.sort_unstable();
+
+    let mut pairs = HashSet::new();
+    for bucket in index.chunk_by(|a, b| a.0 == b.0) {
+        if bucket.len() < 2 || bucket.len() > MAX_BUCKET {
+            continue;
+        }
+        for (k, &(_, a)) in bucket.iter().enumerate() {
+            pairs.extend(bucket[k + 1..].iter().map(|&(_, b)| (a, b)));
+        }
+    }
+    let mut pairs: Vec<_> = pairs.into_iter().collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Serialises a fingerprint for storage as a BLOB.
+pub fn encode(fingerprint: &[u32]) -> Vec<u8> {
+    fingerprint.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn decode(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Decodes up to `max_secs` of the default track, downmixed to mono.
+fn decode_mono(path: &Path, max_secs: u32) -> Result<(Vec<f32>, u32), AppError> {
+    // Read failures stay IO errors so callers can tell them from bad audio
+    let decode_err = |e: SymphoniaError| match e {
+        SymphoniaError::IoError(e) => AppError::Io(e),
+        e => AppError::Decode(format!("{}: {}", path.display(), e)),
+    };
+
+    let file = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(decode_err)?;
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .ok_or_else(|| AppError::Decode(format!("{}: no audio track", path.display())))?;
+    let track_id = track.id;
+    let rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| AppError::Decode(format!("{}: unknown sample rate", path.display())))?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(decode_err)?;
+
+    let max_samples = (rate * max_secs) as usize;
+    let mut mono = Vec::new();
+    while mono.len() < max_samples {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(decode_err(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet is skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(decode_err(e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buf.copy_interleaved_ref(decoded);
+        mono.extend(
+            buf.samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+    mono.truncate(max_samples);
+    Ok((mono, rate))
+}
+
+/// Box-filter resampler; averaging each output window doubles as the
+/// low-pass filter when downsampling.
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = f64::from(from) / f64::from(to);
+    let out_len = (samples.len() as f64 / ratio) as usize;
+    (0..out_len)
+        .map(|i| {
+            let start = (i as f64 * ratio) as usize;
+            let end = (((i + 1) as f64 * ratio) as usize)
+                .max(start + 1)
+                .min(samples.len());
+            samples[start..end].iter().sum::<f32>() / (end - start) as f32
+        })
+        .collect()
+}
+
+/// Splits the signal into overlapping Hann-windowed frames and folds each
+/// frame's spectrum into 12 pitch classes, normalised to unit length.
+fn chroma_frames(samples: &[f32]) -> Vec<[f32; 12]> {
+    if samples.len() < FRAME_SIZE {
+        return Vec::new();
+    }
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_SIZE as f32).cos())
+        .collect();
+    let bin_note: Vec<Option<usize>> = (0..FRAME_SIZE / 2)
+        .map(|k| {
+            let freq = k as f32 * SAMPLE_RATE as f32 / FRAME_SIZE as f32;
+            if !(MIN_FREQ..=MAX_FREQ).contains(&freq) {
+                return None;
+            }
+            let note = 12.0 * (freq / 440.0).log2() + 69.0;
+            Some((note.round() as i64).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut frames = Vec::new();
+    let mut re = vec![0f32; FRAME_SIZE];
+    let mut im = vec![0f32; FRAME_SIZE];
+    for start in (0..=samples.len() - FRAME_SIZE).step_by(HOP_SIZE) {
+        for (i, (r, s)) in re.iter_mut().zip(&samples[start..]).enumerate() {
+            *r = s * window[i];
+        }
+        im.fill(0.0);
+        fft(&mut re, &mut im);
+
+        let mut chroma = [0f32; 12];
+        for (k, note) in bin_note.iter().enumerate() {
+            if let Some(note) = note {
+                chroma[*note] += re[k] * re[k] + im[k] * im[k];
+            }
+        }
+        let norm = chroma.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if norm > 1e-6 {
+            chroma.iter_mut().for_each(|c| *c /= norm);
+        }
+        frames.push(chroma);
+    }
+    frames
+}
+
+/// Derives one 32-bit value per frame from a four-frame window of chroma:
+/// 12 bits for neighbouring pitch classes, 12 bits for rising/falling energy
+/// per class, and 8 bits comparing classes a fifth apart.
+fn sub_fingerprints(frames: &[[f32; 12]]) -> Vec<u32> {
+    frames
+        .windows(4)
+        .map(|w| {
+            let mut early = [0f32; 12];
+            let mut late = [0f32; 12];
+            for band in 0..12 {
+                early[band] = w[0][band] + w[1][band];
+                late[band] = w[2][band] + w[3][band];
+            }
+            let mean: Vec<f32> = (0..12).map(|b| early[b] + late[b]).collect();
+
+            let mut bits = 0u32;
+            for band in 0..12 {
+                if mean[band] > mean[(band + 1) % 12] {
+                    bits |= 1 << band;
+                }
+                if late[band] > early[band] {
+                    bits |= 1 << (12 + band);
+                }
+            }
+            for band in 0..8 {
+                if mean[band] > mean[(band + 7) % 12] {
+                    bits |= 1 << (24 + band);
+                }
+            }
+            bits
+        })
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT; `re.len()` must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::{Builder, NamedTempFile};
+
+    /// Renders a melody (MIDI note numbers, 0.4 s each) as 16-bit PCM WAV.
+    fn melody_wav(notes: &[u8], rate: u32, channels: u16, gain: f32) -> NamedTempFile {
+        let note_len = (rate as f32 * 0.4) as usize;
+        let mut samples = Vec::new();
+        for (n, note) in notes.iter().enumerate() {
+            let freq = 440.0 * 2f32.powf((f32::from(*note) - 69.0) / 12.0);
+            for i in 0..note_len {
+                let t = (n * note_len + i) as f32 / rate as f32;
+                let tone = (2.0 * PI * freq * t).sin() + 0.5 * (4.0 * PI * freq * t).sin();
+                let value = (tone * gain * 0.3 * i16::MAX as f32) as i16;
+                for _ in 0..channels {
+                    samples.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+
+        let block_align = channels * 2;
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * u32::from(block_align)).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&samples);
+
+        let mut file = Builder::new().suffix(".wav").tempfile().unwrap();
+        file.write_all(&wav).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    const MELODY: [u8; 16] = [
+        60, 64, 67, 72, 71, 67, 64, 62, 60, 65, 69, 72, 70, 67, 65, 64,
+    ];
+    const OTHER: [u8; 16] = [
+        57, 59, 61, 62, 64, 66, 68, 69, 68, 66, 64, 62, 61, 59, 57, 56,
+    ];
+
+    #[test]
+    fn test_same_audio_matches_across_rate_and_channels() {
+        let original = fingerprint_file(melody_wav(&MELODY, 44100, 2, 1.0).path()).unwrap();
+        let transcoded = fingerprint_file(melody_wav(&MELODY, 22050, 1, 0.7).path()).unwrap();
+
+        assert!(!original.is_empty());
+        assert!(similarity(&original, &transcoded) >= DEFAULT_THRESHOLD);
+        assert_eq!(candidate_pairs(&[&original, &transcoded]), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_different_audio_does_not_match() {
+        let a = fingerprint_file(melody_wav(&MELODY, 44100, 1, 1.0).path()).unwrap();
+        let b = fingerprint_file(melody_wav(&OTHER, 44100, 1, 1.0).path()).unwrap();
+        assert!(similarity(&a, &b) < DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn test_similarity_tolerates_offset() {
+        let a: Vec<u32> = (0..100u32).map(|i| i.wrapping_mul(2654435761)).collect();
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert_eq!(similarity(&a[3..], &a), 1.0);
+        assert_eq!(similarity(&[], &a), 0.0);
+    }
+
+    #[test]
+    fn test_candidate_pairs_share_a_value() {
+        let a: Vec<u32> = (0..100u32).map(|i| i.wrapping_mul(2654435761)).collect();
+        let shifted = a[5..].to_vec();
+        let unrelated: Vec<u32> = a.iter().map(|v| !v).collect();
+        assert_eq!(candidate_pairs(&[&a, &unrelated, &shifted]), vec![(0, 2)]);
+
+        // Silence shared by every track does not pair them up
+        let silent = vec![0u32; 10];
+        let many: Vec<&[u32]> = (0..=MAX_BUCKET).map(|_| silent.as_slice()).collect();
+        assert!(candidate_pairs(&many).is_empty());
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let fp = vec![0, 1, u32::MAX, 0xDEAD_BEEF];
+        assert_eq!(decode(&encode(&fp)), fp);
+    }
+
+    #[test]
+    fn test_non_audio_file_returns_error() {
+        let mut file = Builder::new().suffix(".mp3").tempfile().unwrap();
+        file.write_all(b"this is not audio data").unwrap();
+        file.flush().unwrap();
+        assert!(fingerprint_file(file.path()).is_err());
+    }
+}
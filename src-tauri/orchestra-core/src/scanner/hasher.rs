@@ -1,6 +1,9 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
 use crate::error::AppError;
+use crate::models::track::HashKind;
 
 pub fn hash_file(path: &Path) -> Result<String, AppError> {
     let mut hasher = blake3::Hasher::new();
@@ -10,12 +13,343 @@ pub fn hash_file(path: &Path) -> Result<String, AppError> {
     Ok(hasher.finalize().to_hex().to_string())
 }
 
+/// Hashes with `kind`. Files too damaged to find the audio in are hashed
+/// whole, so they still match only themselves.
+pub fn hash_with(path: &Path, kind: &HashKind) -> Result<String, AppError> {
+    match kind {
+        HashKind::File => hash_file(path),
+        HashKind::Audio => match hash_audio(path) {
+            Err(AppError::Decode(_)) => hash_file(path),
+            result => result,
+        },
+    }
+}
+
+/// Hashes only the audio payload of a file, skipping tag data (ID3/APE, FLAC
+/// metadata blocks, Vorbis/Opus comment packets, MP4 atoms outside `mdat`,
+/// RIFF chunks other than `fmt `/`data`, ASF objects other than Data), so that
+/// retagging a track leaves the hash unchanged. Containers it does not
+/// recognise are hashed whole.
+pub fn hash_audio(path: &Path) -> Result<String, AppError> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::with_capacity(1024 * 1024, file);
+    let mut hasher = blake3::Hasher::new();
+
+    let start = skip_id3v2(&mut reader, len)?;
+    let mut magic = [0u8; 16];
+    reader.seek(SeekFrom::Start(start))?;
+    let read = read_up_to(&mut reader, &mut magic)?;
+    let magic = &magic[..read];
+
+    if magic.starts_with(b"fLaC") {
+        hash_flac(&mut reader, start, len, &mut hasher)?;
+    } else if magic.starts_with(b"OggS") {
+        hash_ogg(&mut reader, start, &mut hasher)?;
+    } else if magic.starts_with(b"RIFF") && magic.get(8..12) == Some(b"WAVE") {
+        hash_riff(&mut reader, start + 12, len, &mut hasher)?;
+    } else if magic.get(4..8) == Some(b"ftyp") {
+        hash_mp4(&mut reader, start, len, &mut hasher)?;
+    } else if magic.starts_with(&ASF_HEADER_GUID) {
+        hash_asf(&mut reader, start, len, &mut hasher)?;
+    } else {
+        // MPEG/ADTS frames (or unknown): everything between leading and trailing tags
+        let end = trailing_tags_start(&mut reader, start, len)?;
+        hash_range(&mut reader, start, end, &mut hasher)?;
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+const ASF_HEADER_GUID: [u8; 16] = [
+    0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C,
+];
+const ASF_DATA_GUID: [u8; 16] = [
+    0x36, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C,
+];
+
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn hash_range<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    hasher: &mut blake3::Hasher,
+) -> Result<(), AppError> {
+    if end > start {
+        reader.seek(SeekFrom::Start(start))?;
+        std::io::copy(&mut reader.by_ref().take(end - start), hasher)?;
+    }
+    Ok(())
+}
+
+/// Returns the offset just past any ID3v2 tags at the start of the file.
+fn skip_id3v2<R: Read + Seek>(reader: &mut R, len: u64) -> Result<u64, AppError> {
+    let mut offset = 0u64;
+    loop {
+        let mut header = [0u8; 10];
+        reader.seek(SeekFrom::Start(offset))?;
+        if read_up_to(reader, &mut header)? < 10 || &header[..3] != b"ID3" {
+            return Ok(offset);
+        }
+        let size = header[6..10]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 7) | u64::from(b & 0x7F));
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        offset = (offset + 10 + size + footer).min(len);
+    }
+}
+
+/// Returns the offset where trailing ID3v1 / APEv2 tags begin.
+fn trailing_tags_start<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    len: u64,
+) -> Result<u64, AppError> {
+    let mut end = len;
+    if end >= start + 128 {
+        let mut tag = [0u8; 3];
+        reader.seek(SeekFrom::Start(end - 128))?;
+        reader.read_exact(&mut tag)?;
+        if &tag == b"TAG" {
+            end -= 128;
+        }
+    }
+    if end >= start + 32 {
+        let mut footer = [0u8; 32];
+        reader.seek(SeekFrom::Start(end - 32))?;
+        reader.read_exact(&mut footer)?;
+        if &footer[..8] == b"APETAGEX" {
+            let size = u64::from(u32::from_le_bytes(footer[12..16].try_into().unwrap()));
+            let has_header = footer[23] & 0x80 != 0;
+            let total = size + if has_header { 32 } else { 0 };
+            end = end.saturating_sub(total).max(start);
+        }
+    }
+    Ok(end)
+}
+
+fn hash_flac<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    len: u64,
+    hasher: &mut blake3::Hasher,
+) -> Result<(), AppError> {
+    let mut offset = start + 4;
+    loop {
+        let mut header = [0u8; 4];
+        reader.seek(SeekFrom::Start(offset))?;
+        if read_up_to(reader, &mut header)? < 4 {
+            return Err(AppError::Decode(
+                "FLAC metadata ends before the audio".to_string(),
+            ));
+        }
+        let block_len = u64::from(u32::from_be_bytes([0, header[1], header[2], header[3]]));
+        offset += 4 + block_len;
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    // Otherwise every truncated file would hash the same
+    if offset >= len {
+        return Err(AppError::Decode(
+            "FLAC file has no audio after its metadata".to_string(),
+        ));
+    }
+    let end = trailing_tags_start(reader, offset.min(len), len)?;
+    hash_range(reader, offset, end, hasher)
+}
+
+fn hash_riff<R: Read + Seek>(
+    reader: &mut R,
+    mut offset: u64,
+    len: u64,
+    hasher: &mut blake3::Hasher,
+) -> Result<(), AppError> {
+    while offset + 8 <= len {
+        let mut header = [0u8; 8];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut header)?;
+        let size = u64::from(u32::from_le_bytes(header[4..8].try_into().unwrap()));
+        let body = offset + 8;
+        if &header[..4] == b"fmt " || &header[..4] == b"data" {
+            hash_range(reader, body, (body + size).min(len), hasher)?;
+        }
+        // Chunks are padded to an even length
+        offset = body + size + (size & 1);
+    }
+    Ok(())
+}
+
+fn hash_mp4<R: Read + Seek>(
+    reader: &mut R,
+    mut offset: u64,
+    len: u64,
+    hasher: &mut blake3::Hasher,
+) -> Result<(), AppError> {
+    while offset + 8 <= len {
+        let mut header = [0u8; 8];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut header)?;
+        let mut size = u64::from(u32::from_be_bytes(header[..4].try_into().unwrap()));
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = len - offset;
+        }
+        if size < header_len {
+            break;
+        }
+        if &header[4..8] == b"mdat" {
+            hash_range(
+                reader,
+                offset + header_len,
+                (offset + size).min(len),
+                hasher,
+            )?;
+        }
+        offset += size;
+    }
+    Ok(())
+}
+
+fn hash_asf<R: Read + Seek>(
+    reader: &mut R,
+    mut offset: u64,
+    len: u64,
+    hasher: &mut blake3::Hasher,
+) -> Result<(), AppError> {
+    while offset + 24 <= len {
+        let mut header = [0u8; 24];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut header)?;
+        let size = u64::from_le_bytes(header[16..24].try_into().unwrap());
+        if size < 24 {
+            break;
+        }
+        if header[..16] == ASF_DATA_GUID {
+            hash_range(reader, offset + 24, (offset + size).min(len), hasher)?;
+        }
+        offset += size;
+    }
+    Ok(())
+}
+
+/// Hashes every Ogg packet except Vorbis and Opus comment headers. Packets are
+/// reassembled first, so a longer comment that shifts page boundaries does
+/// not change the hash.
+fn hash_ogg<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    hasher: &mut blake3::Hasher,
+) -> Result<(), AppError> {
+    reader.seek(SeekFrom::Start(start))?;
+    let mut packet: Vec<u8> = Vec::new();
+    loop {
+        let mut header = [0u8; 27];
+        if read_up_to(reader, &mut header)? < 27 || &header[..4] != b"OggS" {
+            break;
+        }
+        let mut segments = vec![0u8; usize::from(header[26])];
+        reader.read_exact(&mut segments)?;
+        for lacing in segments {
+            let mut data = vec![0u8; usize::from(lacing)];
+            reader.read_exact(&mut data)?;
+            packet.extend_from_slice(&data);
+            // A lacing value below 255 terminates the packet
+            if lacing < 255 {
+                if !packet.starts_with(b"\x03vorbis") && !packet.starts_with(b"OpusTags") {
+                    hasher.update(&packet);
+                }
+                packet.clear();
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
     use std::path::PathBuf;
-    use tempfile::Builder;
+    use tempfile::{Builder, NamedTempFile};
+
+    fn audio_hash_of(bytes: &[u8]) -> String {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(bytes).unwrap();
+        file.flush().unwrap();
+        hash_audio(file.path()).unwrap()
+    }
+
+    fn id3v2(payload: &[u8]) -> Vec<u8> {
+        let size = payload.len() as u32;
+        let mut tag = b"ID3\x04\x00\x00".to_vec();
+        tag.extend((0..4).rev().map(|i| ((size >> (7 * i)) & 0x7F) as u8));
+        tag.extend_from_slice(payload);
+        tag
+    }
+
+    fn id3v1(title: &[u8]) -> Vec<u8> {
+        let mut tag = b"TAG".to_vec();
+        tag.extend_from_slice(title);
+        tag.resize(128, 0);
+        tag
+    }
+
+    fn flac(comment: &[u8], frames: &[u8]) -> Vec<u8> {
+        let mut data = b"fLaC".to_vec();
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x04]);
+        data.extend_from_slice(b"info");
+        data.push(0x84);
+        data.extend_from_slice(&(comment.len() as u32).to_be_bytes()[1..]);
+        data.extend_from_slice(comment);
+        data.extend_from_slice(frames);
+        data
+    }
+
+    fn chunk(id: &[u8], body: &[u8], le: bool) -> Vec<u8> {
+        let size = body.len() as u32;
+        let mut out = Vec::new();
+        if le {
+            out.extend_from_slice(id);
+            out.extend_from_slice(&size.to_le_bytes());
+        } else {
+            out.extend_from_slice(&(size + 8).to_be_bytes());
+            out.extend_from_slice(id);
+        }
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn ogg_page(packets: &[&[u8]]) -> Vec<u8> {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let mut page = b"OggS".to_vec();
+        page.resize(26, 0);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+        page
+    }
 
     #[test]
     fn test_hash_produces_64_char_hex() {
@@ -66,4 +400,123 @@ mod tests {
         let result = hash_file(&path);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_audio_hash_ignores_mp3_tags() {
+        let frames = b"\xFF\xFBframe-one\xFF\xFBframe-two";
+        let tagged = |v2: &[u8], v1: &[u8]| [id3v2(v2), frames.to_vec(), id3v1(v1)].concat();
+
+        let original = audio_hash_of(&tagged(b"TIT2 Old Title", b"Old"));
+        assert_eq!(
+            original,
+            audio_hash_of(&tagged(b"TIT2 A Much Longer Title", b"New"))
+        );
+        assert_eq!(original, audio_hash_of(frames));
+        assert_ne!(
+            original,
+            audio_hash_of(&[id3v2(b"TIT2 Old Title"), b"\xFF\xFBother".to_vec()].concat())
+        );
+    }
+
+    #[test]
+    fn test_audio_hash_ignores_flac_metadata_blocks() {
+        let original = audio_hash_of(&flac(b"TITLE=Old", b"\xFF\xF8frames"));
+        assert_eq!(
+            original,
+            audio_hash_of(&flac(b"TITLE=New;ARTIST=Someone", b"\xFF\xF8frames"))
+        );
+        assert_ne!(
+            original,
+            audio_hash_of(&flac(b"TITLE=Old", b"\xFF\xF8other!"))
+        );
+    }
+
+    #[test]
+    fn test_audio_hash_rejects_truncated_flac() {
+        let hash = |bytes: &[u8]| {
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(bytes).unwrap();
+            file.flush().unwrap();
+            hash_audio(file.path())
+        };
+        let whole = flac(b"TITLE=Old", b"\xFF\xF8frames");
+        assert!(hash(&whole).is_ok());
+        // Cut inside a block header, inside the last block and right after it
+        assert!(hash(&whole[..10]).is_err());
+        assert!(hash(&whole[..20]).is_err());
+        assert!(hash(&flac(b"TITLE=Old", b"")).is_err());
+
+        let mut truncated = NamedTempFile::new().unwrap();
+        truncated.write_all(&whole[..20]).unwrap();
+        truncated.flush().unwrap();
+        assert_eq!(
+            hash_with(truncated.path(), &HashKind::Audio).unwrap(),
+            hash_file(truncated.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_audio_hash_ignores_riff_and_mp4_tag_chunks() {
+        let wav = |info: &[u8]| {
+            let body = [
+                b"WAVE".to_vec(),
+                chunk(b"fmt ", b"format16", true),
+                chunk(b"LIST", info, true),
+                chunk(b"data", b"samples", true),
+            ]
+            .concat();
+            chunk(b"RIFF", &body, true)
+        };
+        assert_eq!(
+            audio_hash_of(&wav(b"INAMold")),
+            audio_hash_of(&wav(b"INAMnew title"))
+        );
+
+        let mp4 = |udta: &[u8]| {
+            [
+                chunk(b"ftyp", b"M4A \0\0\0\0", false),
+                chunk(b"moov", &chunk(b"udta", udta, false), false),
+                chunk(b"mdat", b"aac-frames", false),
+            ]
+            .concat()
+        };
+        assert_eq!(
+            audio_hash_of(&mp4(b"old")),
+            audio_hash_of(&mp4(b"a new title"))
+        );
+        assert_ne!(audio_hash_of(&mp4(b"old")), audio_hash_of(&wav(b"old")));
+    }
+
+    #[test]
+    fn test_audio_hash_ignores_ogg_comment_packet() {
+        let ogg = |comment: &[u8]| {
+            let comment = [b"\x03vorbis".as_slice(), comment].concat();
+            [
+                ogg_page(&[b"\x01vorbis-ident"]),
+                ogg_page(&[&comment, b"\x05vorbis-setup"]),
+                ogg_page(&[b"audio-packet"]),
+            ]
+            .concat()
+        };
+        let original = audio_hash_of(&ogg(b"TITLE=Old"));
+        // A comment longer than one lacing segment must not change the hash
+        assert_eq!(original, audio_hash_of(&ogg(&[b'x'; 600])));
+    }
+
+    #[test]
+    fn test_hash_with_selects_kind() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&[id3v2(b"TIT2 Title"), b"\xFF\xFBframes".to_vec()].concat())
+            .unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(
+            hash_with(file.path(), &HashKind::File).unwrap(),
+            hash_file(file.path()).unwrap()
+        );
+        assert_eq!(
+            hash_with(file.path(), &HashKind::Audio).unwrap(),
+            hash_audio(file.path()).unwrap()
+        );
+    }
 }
@@ -0,0 +1,325 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::error::AppError;
+
+/// ReplayGain 2.0 reference level: gains bring tracks to this loudness.
+pub const REFERENCE_LUFS: f64 = -18.0;
+
+/// Blocks quieter than this never count towards the integrated loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks more than this far below the ungated loudness are dropped too.
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// Gating blocks are 400 ms long and overlap by 75%, so they are built from
+/// 100 ms steps.
+const STEPS_PER_BLOCK: usize = 4;
+const STEPS_PER_SECOND: u32 = 10;
+
+/// A ReplayGain adjustment and the sample peak it applies to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gain {
+    /// dB to apply to reach `REFERENCE_LUFS`.
+    pub gain: f64,
+    /// Highest absolute sample value, 1.0 being full scale.
+    pub peak: f64,
+}
+
+/// Loudness measurement of one file, per EBU R128 / ITU-R BS.1770.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// Mean square of the K-weighted signal for each 400 ms block, summed
+    /// over channels.
+    blocks: Vec<f64>,
+    pub peak: f64,
+}
+
+impl Analysis {
+    /// Integrated loudness in LUFS, or `None` for silence and files shorter
+    /// than one block.
+    pub fn loudness(&self) -> Option<f64> {
+        gated_loudness(self.blocks.iter().copied())
+    }
+
+    pub fn track_gain(&self) -> Option<Gain> {
+        self.loudness().map(|loudness| Gain {
+            gain: REFERENCE_LUFS - loudness,
+            peak: self.peak,
+        })
+    }
+}
+
+/// Gain for a whole album: the gating runs over the blocks of all tracks at
+/// once, so quiet interludes do not pull the album level down.
+pub fn album_gain(tracks: &[Analysis]) -> Option<Gain> {
+    let loudness = gated_loudness(tracks.iter().flat_map(|t| t.blocks.iter().copied()))?;
+    Some(Gain {
+        gain: REFERENCE_LUFS - loudness,
+        peak: tracks.iter().map(|t| t.peak).fold(0.0, f64::max),
+    })
+}
+
+fn block_loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn gated_loudness(blocks: impl Iterator<Item = f64> + Clone) -> Option<f64> {
+    let mean = |threshold: f64| {
+        let (sum, count) = blocks
+            .clone()
+            .filter(|&z| z > 0.0 && block_loudness(z) > threshold)
+            .fold((0.0, 0usize), |(sum, count), z| (sum + z, count + 1));
+        (count > 0).then(|| sum / count as f64)
+    };
+    let relative_gate = block_loudness(mean(ABSOLUTE_GATE_LUFS)?) - RELATIVE_GATE_LU;
+    mean(relative_gate.max(ABSOLUTE_GATE_LUFS)).map(block_loudness)
+}
+
+/// Decodes `path` and measures its loudness and sample peak.
+pub fn analyze_file(path: &Path) -> Result<Analysis, AppError> {
+    let decode_err = |e: SymphoniaError| AppError::Decode(format!("{}: {}", path.display(), e));
+
+    let file = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(decode_err)?;
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .ok_or_else(|| AppError::Decode(format!("{}: no audio track", path.display())))?;
+    let track_id = track.id;
+    let rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| AppError::Decode(format!("{}: unknown sample rate", path.display())))?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(decode_err)?;
+
+    let mut meter: Option<Meter> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(decode_err(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet is skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(decode_err(e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buf.copy_interleaved_ref(decoded);
+        meter
+            .get_or_insert_with(|| Meter::new(rate, channels))
+            .process(buf.samples());
+    }
+    Ok(meter.map(Meter::finish).unwrap_or_default())
+}
+
+/// Second-order IIR filter (direct form II transposed).
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two stages of the BS.1770 K-weighting filter, derived for any sample
+/// rate (the standard only lists coefficients for 48 kHz).
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(rate);
+
+    // High shelf modelling the acoustic effect of the head
+    let f0 = 1_681.974_450_955_533;
+    let gain_db = 3.999_843_853_973_347;
+    let q = 0.707_175_236_955_419_6;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    // Revised low-frequency B-curve high-pass
+    let f0 = 38.135_470_876_024_44;
+    let q = 0.500_327_037_323_877_3;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Channel weights for the usual L, R, C, LFE, Ls, Rs order: the LFE is
+/// ignored and surrounds count slightly more.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (6, 4) | (6, 5) | (5, 3) | (5, 4) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Streaming loudness meter: filters each channel and sums the energy of
+/// every 100 ms step.
+struct Meter {
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    step_len: usize,
+    /// Frames in the current (incomplete) step and their weighted energy.
+    frames: usize,
+    energy: f64,
+    steps: Vec<f64>,
+    peak: f64,
+}
+
+impl Meter {
+    fn new(rate: u32, channels: usize) -> Self {
+        Meter {
+            filters: vec![k_weighting(rate); channels],
+            weights: (0..channels).map(|c| channel_weight(c, channels)).collect(),
+            step_len: (rate / STEPS_PER_SECOND).max(1) as usize,
+            frames: 0,
+            energy: 0.0,
+            steps: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    fn process(&mut self, interleaved: &[f32]) {
+        for frame in interleaved.chunks_exact(self.filters.len()) {
+            for ((sample, filters), weight) in
+                frame.iter().zip(&mut self.filters).zip(&self.weights)
+            {
+                let x = f64::from(*sample);
+                self.peak = self.peak.max(x.abs());
+                let [shelf, high_pass] = filters;
+                let y = high_pass.process(shelf.process(x));
+                self.energy += weight * y * y;
+            }
+            self.frames += 1;
+            if self.frames == self.step_len {
+                self.steps.push(self.energy);
+                self.frames = 0;
+                self.energy = 0.0;
+            }
+        }
+    }
+
+    /// The partial step at the end is dropped, as it cannot complete a block.
+    fn finish(self) -> Analysis {
+        let block_len = (self.step_len * STEPS_PER_BLOCK) as f64;
+        let blocks = self
+            .steps
+            .windows(STEPS_PER_BLOCK)
+            .map(|w| w.iter().sum::<f64>() / block_len)
+            .collect();
+        Analysis {
+            blocks,
+            peak: self.peak,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `secs` of a sine at `amplitude` through a stereo meter.
+    fn sine(freq: f64, amplitude: f64, secs: f64) -> Analysis {
+        let rate = 48000;
+        let mut meter = Meter::new(rate, 2);
+        let samples: Vec<f32> = (0..(f64::from(rate) * secs) as usize)
+            .flat_map(|i| {
+                let v = (amplitude * (2.0 * PI * freq * i as f64 / f64::from(rate)).sin()) as f32;
+                [v, v]
+            })
+            .collect();
+        meter.process(&samples);
+        meter.finish()
+    }
+
+    #[test]
+    fn test_sine_loudness() {
+        // A full-scale 997 Hz sine in one channel reads -3.01 LUFS, so in two
+        // channels it reads 0 LUFS; halving the amplitude takes off 6.02 dB.
+        let analysis = sine(997.0, 0.5, 5.0);
+        let loudness = analysis.loudness().unwrap();
+        assert!((loudness + 6.02).abs() < 0.05, "{loudness}");
+        assert!((analysis.peak - 0.5).abs() < 0.001);
+
+        let gain = analysis.track_gain().unwrap();
+        assert!((gain.gain - (REFERENCE_LUFS + 6.02)).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_silence_is_gated() {
+        assert_eq!(sine(997.0, 0.0, 2.0).loudness(), None);
+        assert_eq!(sine(997.0, 0.5, 0.3).loudness(), None);
+
+        let mut with_silence = sine(997.0, 0.5, 5.0);
+        with_silence.blocks.extend(sine(997.0, 0.0, 5.0).blocks);
+        let loudness = with_silence.loudness().unwrap();
+        assert!((loudness + 6.02).abs() < 0.05, "{loudness}");
+    }
+
+    #[test]
+    fn test_album_gain() {
+        let loud = sine(997.0, 0.5, 5.0);
+        let quiet = sine(997.0, 0.25, 5.0);
+        let album = album_gain(&[loud.clone(), quiet.clone()]).unwrap();
+        assert!(album.gain > loud.track_gain().unwrap().gain);
+        assert!(album.gain < quiet.track_gain().unwrap().gain);
+        assert_eq!(album.peak, loud.peak);
+        assert_eq!(album_gain(&[]), None);
+    }
+}
@@ -0,0 +1,424 @@
+use lofty::config::ParseOptions;
+use lofty::file::{AudioFile, TaggedFile, TaggedFileExt};
+use lofty::id3::v2::{
+    Frame, Id3v2Tag, SyncTextContentType, SynchronizedTextFrame, TimestampFormat,
+};
+use lofty::mpeg::MpegFile;
+use lofty::tag::{ItemKey, Tag};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::models::lyrics::{LyricLine, Lyrics, LyricsSource};
+
+/// The `.lrc` file holding `path`'s lyrics: the same name with an `lrc`
+/// extension.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    path.with_extension("lrc")
+}
+
+/// Reads `path`'s lyrics, looking in order at an `.lrc` sidecar, SYLT frames
+/// (MP3 only) and a lyrics tag (USLT, `LYRICS` or `©lyr`). Lyrics tags with
+/// LRC timestamps count as synced.
+pub fn read_lyrics(path: &Path) -> Result<Option<Lyrics>, AppError> {
+    let (tagged_file, sylt) = read_tagged_file(path)?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag());
+    Ok(find_lyrics(path, sylt, tag))
+}
+
+/// Reads `path`'s tags and properties, along with the SYLT lyrics of MP3s,
+/// which lofty leaves out of the generic tag.
+pub(crate) fn read_tagged_file(path: &Path) -> Result<(TaggedFile, Option<Lyrics>), AppError> {
+    let is_mp3 = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("mp3"));
+    if is_mp3 {
+        let mpeg = std::fs::File::open(path).ok().and_then(|file| {
+            let mut reader = std::io::BufReader::new(file);
+            MpegFile::read_from(&mut reader, ParseOptions::new()).ok()
+        });
+        // Anything lofty cannot read as MPEG is probed like other files
+        if let Some(mpeg) = mpeg {
+            let sylt = mpeg.id3v2().and_then(read_sylt);
+            return Ok((mpeg.into(), sylt));
+        }
+    }
+    let tagged_file = lofty::read_from_path(path)
+        .map_err(|e| AppError::Metadata(format!("{}: {}", path.display(), e)))?;
+    Ok((tagged_file, None))
+}
+
+/// `read_lyrics` for a file read by `read_tagged_file`.
+pub(crate) fn find_lyrics(path: &Path, sylt: Option<Lyrics>, tag: Option<&Tag>) -> Option<Lyrics> {
+    read_sidecar(path)
+        .or(sylt)
+        .or_else(|| tag.and_then(read_tag))
+}
+
+fn read_sidecar(path: &Path) -> Option<Lyrics> {
+    let data = std::fs::read(sidecar_path(path)).ok()?;
+    lyrics(
+        LyricsSource::Sidecar,
+        parse_lrc(&String::from_utf8_lossy(&data)),
+    )
+}
+
+/// The first SYLT frame of lyrics timed in milliseconds. Frames timed in
+/// MPEG frames are skipped.
+fn read_sylt(tag: &Id3v2Tag) -> Option<Lyrics> {
+    let sylt = tag.into_iter().find_map(|frame| match frame {
+        Frame::Binary(binary) if binary.id().as_str() == "SYLT" => {
+            SynchronizedTextFrame::parse(&binary.data, binary.flags())
+                .ok()
+                .filter(|sylt| {
+                    sylt.timestamp_format == TimestampFormat::MS
+                        && sylt.content_type == SyncTextContentType::Lyrics
+                })
+        }
+        _ => None,
+    })?;
+    let mut lines: Vec<LyricLine> = sylt
+        .content
+        .into_iter()
+        .map(|(time_ms, text)| LyricLine {
+            time_ms: Some(time_ms),
+            // Taggers often start each entry with the line break before it
+            text: text.trim().to_string(),
+        })
+        .collect();
+    lines.sort_by_key(|line| line.time_ms);
+    lyrics(LyricsSource::Embedded, lines)
+}
+
+fn read_tag(tag: &Tag) -> Option<Lyrics> {
+    let text = tag.get_string(&ItemKey::Lyrics)?;
+    lyrics(LyricsSource::Embedded, parse_lrc(text))
+}
+
+fn lyrics(source: LyricsSource, lines: Vec<LyricLine>) -> Option<Lyrics> {
+    (!lines.is_empty()).then_some(Lyrics { source, lines })
+}
+
+/// Parses LRC lyrics, or plain text, which comes back as untimed lines.
+///
+/// A line may carry several timestamps (`[00:12.00][01:40.00]Chorus`) and
+/// is repeated for each. ID tags such as `[ar:Artist]` are dropped, apart
+/// from `[offset:±ms]`, which shifts every line. Per-word timestamps
+/// (`<00:12.50>`) are removed from the text. Once a file has timestamps,
+/// its lines without one are dropped.
+pub fn parse_lrc(text: &str) -> Vec<LyricLine> {
+    let mut offset_ms = 0i64;
+    let mut timed: Vec<(i64, String)> = Vec::new();
+    let mut plain = Vec::new();
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        let mut id_tag = false;
+        while let Some(end) = rest.strip_prefix('[').and_then(|r| r.find(']')) {
+            let content = &rest[1..=end];
+            if let Some(ms) = parse_timestamp(content) {
+                times.push(ms as i64);
+            } else if let Some((key, value)) = content.split_once(':') {
+                if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+                    break;
+                }
+                if key.eq_ignore_ascii_case("offset") {
+                    offset_ms = value.trim().parse().unwrap_or(0);
+                }
+                id_tag = true;
+            } else {
+                // Plain text such as `[Chorus]`
+                break;
+            }
+            rest = rest[end + 2..].trim_start();
+        }
+
+        let text = strip_word_timestamps(rest);
+        if !times.is_empty() {
+            timed.extend(times.into_iter().map(|ms| (ms, text.clone())));
+        } else if !id_tag {
+            plain.push(text);
+        }
+    }
+
+    if timed.is_empty() {
+        let start = plain
+            .iter()
+            .position(|l| !l.is_empty())
+            .unwrap_or(plain.len());
+        let end = plain
+            .iter()
+            .rposition(|l| !l.is_empty())
+            .map_or(start, |i| i + 1);
+        return plain[start..end]
+            .iter()
+            .map(|text| LyricLine {
+                time_ms: None,
+                text: text.clone(),
+            })
+            .collect();
+    }
+
+    // A positive offset shows the lyrics earlier
+    timed.sort_by_key(|(ms, _)| *ms);
+    timed
+        .into_iter()
+        .map(|(ms, text)| LyricLine {
+            time_ms: Some((ms - offset_ms).clamp(0, u32::MAX as i64) as u32),
+            text,
+        })
+        .collect()
+}
+
+/// `mm:ss`, `mm:ss.xx`, `mm:ss.xxx` or `mm:ss:xx` in milliseconds.
+fn parse_timestamp(value: &str) -> Option<u32> {
+    let (minutes, rest) = value.split_once(':')?;
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (rest, ""),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(minutes) || !digits(seconds) || !(fraction.is_empty() || digits(fraction)) {
+        return None;
+    }
+    let fraction_ms = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u32>().ok()? * 100,
+        2 => fraction.parse::<u32>().ok()? * 10,
+        _ => fraction[..3].parse::<u32>().ok()?,
+    };
+    let seconds: u32 = seconds.parse().ok()?;
+    if seconds >= 60 {
+        return None;
+    }
+    minutes
+        .parse::<u32>()
+        .ok()?
+        .checked_mul(60_000)?
+        .checked_add(seconds * 1000 + fraction_ms)
+}
+
+fn strip_word_timestamps(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(len) if parse_timestamp(&rest[start + 1..start + len]).is_some() => {
+                out.push_str(&rest[..start]);
+                rest = &rest[start + len + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+/// Formats lines as LRC, timed lines with `[mm:ss.xx]` and untimed ones as
+/// plain text, so plain lyrics come out unchanged.
+pub fn format_lrc(lines: &[LyricLine]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        if let Some(ms) = line.time_ms {
+            let _ = write!(
+                out,
+                "[{:02}:{:02}.{:02}]",
+                ms / 60_000,
+                ms / 1000 % 60,
+                ms % 1000 / 10
+            );
+        }
+        out.push_str(&line.text);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::lyrics::LyricsKind;
+    use crate::scanner::writer;
+    use tempfile::TempDir;
+
+    fn timed(ms: u32, text: &str) -> LyricLine {
+        LyricLine {
+            time_ms: Some(ms),
+            text: text.to_string(),
+        }
+    }
+
+    fn plain(text: &str) -> LyricLine {
+        LyricLine {
+            time_ms: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("01:02.5"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02.50"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02.503"), Some(62_503));
+        assert_eq!(parse_timestamp("01:02:50"), Some(62_500));
+        assert_eq!(parse_timestamp("100:00"), Some(6_000_000));
+        assert_eq!(parse_timestamp("01:75.00"), None);
+        assert_eq!(parse_timestamp("ar:Someone"), None);
+        assert_eq!(parse_timestamp("1:-2"), None);
+    }
+
+    #[test]
+    fn test_parse_lrc() {
+        let lrc = "\u{feff}[ar:Someone]\n[ti:Song]\n[offset:+200]\n\
+                   [00:12.00]First <00:12.50>line\n\
+                   [00:20.00][01:00.00]Chorus\n\
+                   stray text\n\
+                   [00:30.00]\n";
+        assert_eq!(
+            parse_lrc(lrc),
+            [
+                timed(11_800, "First line"),
+                timed(19_800, "Chorus"),
+                timed(29_800, ""),
+                timed(59_800, "Chorus"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_plain_lyrics() {
+        let text = "\n[Chorus]\nLa la <la>\n\n[Verse 2: Someone]\nMore\n\n";
+        assert_eq!(
+            parse_lrc(text),
+            [
+                plain("[Chorus]"),
+                plain("La la <la>"),
+                plain(""),
+                plain("[Verse 2: Someone]"),
+                plain("More"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_lrc_round_trip() {
+        let lines = vec![timed(0, "Intro"), timed(83_456, "Later")];
+        assert_eq!(format_lrc(&lines), "[00:00.00]Intro\n[01:23.45]Later\n");
+        assert_eq!(parse_lrc(&format_lrc(&lines))[1], timed(83_450, "Later"));
+
+        let lines = vec![plain("One"), plain(""), plain("Two")];
+        assert_eq!(parse_lrc(&format_lrc(&lines)), lines);
+    }
+
+    #[test]
+    fn test_line_at() {
+        let lyrics = Lyrics {
+            source: LyricsSource::Sidecar,
+            lines: vec![timed(1_000, "a"), timed(2_000, "b"), timed(2_000, "c")],
+        };
+        assert_eq!(lyrics.kind(), LyricsKind::Synced);
+        assert_eq!(lyrics.line_at(500), None);
+        assert_eq!(lyrics.line_at(1_000), Some(0));
+        assert_eq!(lyrics.line_at(2_500), Some(2));
+
+        let lyrics = Lyrics {
+            source: LyricsSource::Embedded,
+            lines: vec![plain("a")],
+        };
+        assert_eq!(lyrics.kind(), LyricsKind::Plain);
+        assert_eq!(lyrics.line_at(5_000), None);
+    }
+
+    /// A second of silence as a 16-bit mono WAV.
+    fn silent_wav(dir: &Path) -> PathBuf {
+        let samples = vec![0u8; 8_000 * 2];
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8_000u32.to_le_bytes());
+        wav.extend_from_slice(&16_000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&samples);
+        let path = dir.join("song.wav");
+        std::fs::write(&path, wav).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_write_and_read_embedded_and_sidecar() {
+        let tmp = TempDir::new().unwrap();
+        let path = silent_wav(tmp.path());
+        assert_eq!(read_lyrics(&path).unwrap(), None);
+
+        let lines = vec![timed(1_000, "Hello"), timed(2_500, "World")];
+        writer::write_lyrics(&path, &lines).unwrap();
+        let embedded = read_lyrics(&path).unwrap().unwrap();
+        assert_eq!(embedded.source, LyricsSource::Embedded);
+        assert_eq!(embedded.lines, lines);
+
+        // A sidecar wins over the tag, and edits go to it from then on
+        std::fs::write(sidecar_path(&path), "Just words\n").unwrap();
+        let sidecar = read_lyrics(&path).unwrap().unwrap();
+        assert_eq!(sidecar.source, LyricsSource::Sidecar);
+        assert_eq!(sidecar.lines, [plain("Just words")]);
+
+        writer::write_lyrics(&path, &[plain("New words")]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(sidecar_path(&path)).unwrap(),
+            "New words\n"
+        );
+
+        writer::write_lyrics(&path, &[]).unwrap();
+        assert!(!sidecar_path(&path).exists());
+        assert_eq!(read_lyrics(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_sylt_from_mp3() {
+        use lofty::config::WriteOptions;
+        use lofty::id3::v2::{BinaryFrame, FrameId};
+        use lofty::tag::TagExt;
+        use lofty::TextEncoding;
+
+        // Silent 128 kb/s MPEG-1 Layer III frames
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("song.mp3");
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.resize(417, 0);
+        std::fs::write(&path, frame.repeat(20)).unwrap();
+
+        let sylt = SynchronizedTextFrame::new(
+            TextEncoding::UTF8,
+            *b"eng",
+            TimestampFormat::MS,
+            SyncTextContentType::Lyrics,
+            None,
+            vec![(2_500, "\nWorld".to_string()), (1_000, "Hello".to_string())],
+        );
+        let mut tag = Id3v2Tag::new();
+        tag.insert(Frame::Binary(BinaryFrame::new(
+            FrameId::new("SYLT").unwrap(),
+            sylt.as_bytes().unwrap(),
+        )));
+        tag.save_to_path(&path, WriteOptions::default()).unwrap();
+
+        let found = read_lyrics(&path).unwrap().unwrap();
+        assert_eq!(found.source, LyricsSource::Embedded);
+        assert_eq!(found.lines, [timed(1_000, "Hello"), timed(2_500, "World")]);
+        let track = crate::scanner::metadata::extract_metadata(&path, tmp.path()).unwrap();
+        assert_eq!(track.lyrics, Some(LyricsKind::Synced));
+        assert!(track.duration_secs.unwrap() > 0.0);
+    }
+}
@@ -1,9 +1,11 @@
 use lofty::file::{AudioFile, TaggedFileExt};
-use lofty::tag::Accessor;
+use lofty::tag::{Accessor, ItemKey, ItemValue, Tag};
 use std::path::Path;
 
 use crate::error::AppError;
 use crate::models::track::Track;
+use crate::rating;
+use crate::scanner::lyrics;
 
 pub fn extract_metadata(path: &Path, library_root: &Path) -> Result<Track, AppError> {
     let relative_path = path
@@ -25,8 +27,7 @@ pub fn extract_metadata(path: &Path, library_root: &Path) -> Result<Track, AppEr
         .unwrap_or("unknown")
         .to_lowercase();
 
-    let tagged_file = lofty::read_from_path(path)
-        .map_err(|e| AppError::Metadata(format!("{}: {}", path.display(), e)))?;
+    let (tagged_file, sylt) = lyrics::read_tagged_file(path)?;
 
     let tag = tagged_file
         .primary_tag()
@@ -51,6 +52,8 @@ pub fn extract_metadata(path: &Path, library_root: &Path) -> Result<Track, AppEr
             (None, None, None, None, None, None, None, None, false)
         };
 
+    let replaygain = |key: ItemKey| tag.and_then(|tag| replaygain_value(tag, key));
+
     let duration_secs = properties.duration().as_secs_f64();
     let bitrate = properties.overall_bitrate();
 
@@ -75,15 +78,59 @@ pub fn extract_metadata(path: &Path, library_root: &Path) -> Result<Track, AppEr
         has_album_art,
         bitrate,
         scanned_at: 0, // set by upsert_track to the current timestamp
+        audio_hash: None,
+        replaygain_track_gain: replaygain(ItemKey::ReplayGainTrackGain),
+        replaygain_track_peak: replaygain(ItemKey::ReplayGainTrackPeak),
+        replaygain_album_gain: replaygain(ItemKey::ReplayGainAlbumGain),
+        replaygain_album_peak: replaygain(ItemKey::ReplayGainAlbumPeak),
+        rating: tag.and_then(read_rating),
+        album_id: None,
+        lyrics: lyrics::find_lyrics(path, sylt, tag).map(|l| l.kind()),
     })
 }
 
+/// The first readable rating: a POPM frame in ID3v2, a `RATING` comment in
+/// Vorbis and APE, the `rate` atom in MP4.
+fn read_rating(tag: &Tag) -> Option<u8> {
+    tag.get_items(&ItemKey::Popularimeter)
+        .find_map(|item| match item.value() {
+            ItemValue::Binary(data) => rating::from_popm_frame(data),
+            ItemValue::Text(text) => rating::from_text(text),
+            ItemValue::Locator(_) => None,
+        })
+}
+
+fn replaygain_value(tag: &Tag, key: ItemKey) -> Option<f64> {
+    tag.get_string(&key).and_then(parse_replaygain)
+}
+
+/// Parses a ReplayGain tag value such as `-6.54 dB` or `0.988831`.
+fn parse_replaygain(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let number = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .or_else(|| value.strip_suffix("DB"))
+        .unwrap_or(value);
+    number.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
     use tempfile::Builder;
 
+    #[test]
+    fn test_parse_replaygain() {
+        assert_eq!(parse_replaygain("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_replaygain("+2.10 dB"), Some(2.1));
+        assert_eq!(parse_replaygain("3.5dB"), Some(3.5));
+        assert_eq!(parse_replaygain(" 0.988831 "), Some(0.988831));
+        assert_eq!(parse_replaygain("loud"), None);
+        assert_eq!(parse_replaygain(""), None);
+    }
+
     #[test]
     fn test_extract_nonexistent_file_returns_error() {
         let path = std::path::PathBuf::from("/nonexistent/path/track.flac");
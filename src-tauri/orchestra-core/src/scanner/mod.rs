@@ -1,4 +1,7 @@
+pub mod fingerprint;
 pub mod hasher;
+pub mod loudness;
+pub mod lyrics;
 pub mod metadata;
 pub mod walker;
 pub mod writer;
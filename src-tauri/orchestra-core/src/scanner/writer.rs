@@ -1,11 +1,14 @@
 use base64::Engine;
 use lofty::file::TaggedFileExt;
 use lofty::picture::PictureType;
-use lofty::tag::{Accessor, TagExt};
+use lofty::tag::{Accessor, ItemKey, ItemValue, TagExt, TagItem, TagType};
 use std::path::Path;
 
 use crate::error::AppError;
+use crate::models::lyrics::LyricLine;
 use crate::models::track::{AlbumArt, TrackMetadataUpdate};
+use crate::rating;
+use crate::scanner::lyrics;
 
 pub fn write_metadata(path: &Path, update: &TrackMetadataUpdate) -> Result<(), AppError> {
     let mut tagged_file = lofty::read_from_path(path)
@@ -74,12 +77,107 @@ pub fn write_metadata(path: &Path, update: &TrackMetadataUpdate) -> Result<(), A
         None => {}
     }
 
+    // Replaces every rating, including other players' POPM frames
+    match update.rating {
+        Some(r) if r > 0 => {
+            tag.remove_key(&ItemKey::Popularimeter);
+            let value = if tag.tag_type() == TagType::Id3v2 {
+                ItemValue::Binary(rating::popm_frame(r))
+            } else {
+                ItemValue::Text(r.min(rating::MAX_RATING).to_string())
+            };
+            tag.insert(TagItem::new(ItemKey::Popularimeter, value));
+        }
+        Some(_) => tag.remove_key(&ItemKey::Popularimeter),
+        None => {}
+    }
+
     tag.save_to_path(path, lofty::config::WriteOptions::default())
         .map_err(|e| AppError::Metadata(format!("Failed to write {}: {}", path.display(), e)))?;
 
     Ok(())
 }
 
+/// Saves edited lyrics where they are read from first: the `.lrc` sidecar
+/// if there is one, else the lyrics tag (USLT in ID3v2). Synced lines are
+/// stored as LRC. No lines clears the lyrics from both.
+pub fn write_lyrics(path: &Path, lines: &[LyricLine]) -> Result<(), AppError> {
+    let sidecar = lyrics::sidecar_path(path);
+    if sidecar.exists() {
+        if !lines.is_empty() {
+            std::fs::write(&sidecar, lyrics::format_lrc(lines))?;
+            return Ok(());
+        }
+        std::fs::remove_file(&sidecar)?;
+    }
+
+    let mut tagged_file = lofty::read_from_path(path)
+        .map_err(|e| AppError::Metadata(format!("{}: {}", path.display(), e)))?;
+    let tag = match tagged_file.primary_tag_mut() {
+        Some(t) => t,
+        None if lines.is_empty() => return Ok(()),
+        None => {
+            let tag_type = tagged_file.primary_tag_type();
+            tagged_file.insert_tag(lofty::tag::Tag::new(tag_type));
+            tagged_file.primary_tag_mut().unwrap()
+        }
+    };
+
+    if lines.is_empty() {
+        tag.remove_key(&ItemKey::Lyrics);
+    } else {
+        let text = lyrics::format_lrc(lines);
+        tag.insert_text(ItemKey::Lyrics, text.trim_end().to_string());
+    }
+
+    tag.save_to_path(path, lofty::config::WriteOptions::default())
+        .map_err(|e| AppError::Metadata(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    Ok(())
+}
+
+/// Fills every tag field missing from `preferred` with the value from `other`,
+/// including artwork. Fields present on both sides keep `preferred`'s value.
+pub fn merge_tags(preferred: &Path, other: &Path) -> Result<(), AppError> {
+    let other_file = lofty::read_from_path(other)
+        .map_err(|e| AppError::Metadata(format!("{}: {}", other.display(), e)))?;
+    let other_tag = match other_file.primary_tag().or_else(|| other_file.first_tag()) {
+        Some(t) => t,
+        None => return Ok(()),
+    };
+
+    let mut tagged_file = lofty::read_from_path(preferred)
+        .map_err(|e| AppError::Metadata(format!("{}: {}", preferred.display(), e)))?;
+
+    let tag = match tagged_file.primary_tag_mut() {
+        Some(t) => t,
+        None => {
+            let tag_type = tagged_file.primary_tag_type();
+            tagged_file.insert_tag(lofty::tag::Tag::new(tag_type));
+            tagged_file.primary_tag_mut().unwrap()
+        }
+    };
+
+    for item in other_tag.items() {
+        if tag.get(item.key()).is_none() {
+            tag.insert(item.clone());
+        }
+    }
+
+    if tag.pictures().is_empty() {
+        for picture in other_tag.pictures() {
+            tag.push_picture(picture.clone());
+        }
+    }
+
+    tag.save_to_path(preferred, lofty::config::WriteOptions::default())
+        .map_err(|e| {
+            AppError::Metadata(format!("Failed to write {}: {}", preferred.display(), e))
+        })?;
+
+    Ok(())
+}
+
 pub fn extract_artwork(path: &Path) -> Result<Option<AlbumArt>, AppError> {
     let tagged_file = lofty::read_from_path(path)
         .map_err(|e| AppError::Metadata(format!("{}: {}", path.display(), e)))?;
@@ -129,6 +227,7 @@ mod tests {
             disc_number: None,
             year: None,
             genre: None,
+            rating: None,
         }
     }
 
@@ -139,6 +238,13 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_merge_tags_nonexistent_file_returns_error() {
+        let path = PathBuf::from("/nonexistent/path/track.flac");
+        let other = PathBuf::from("/nonexistent/path/other.flac");
+        assert!(merge_tags(&path, &other).is_err());
+    }
+
     #[test]
     fn test_extract_artwork_nonexistent_file_returns_error() {
         let path = PathBuf::from("/nonexistent/path/track.flac");
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use chrono::{DateTime, Local, TimeZone};
+use rusqlite::{params, Connection};
+
+use crate::error::AppError;
+use crate::models::scrobble::{
+    ListenBrainzAdditionalInfo, ListenBrainzListen, ListenBrainzTrackMetadata, ScrobbleEntry,
+    ScrobbleImportSummary,
+};
+
+/// Where Rockbox and compatible players write their log: the root of the
+/// device.
+pub const SCROBBLER_LOG_FILE: &str = ".scrobbler.log";
+
+const CLIENT_NAME: &str = "Orchestra";
+const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A play with the track details both export formats need.
+struct ExportedPlay {
+    played_at: i64,
+    artist: String,
+    album: Option<String>,
+    title: String,
+    track_number: Option<u32>,
+    duration_secs: Option<f64>,
+    skipped: bool,
+}
+
+/// Every play of a track with an artist and title, oldest first. Those are
+/// the fields scrobbling services require; plays of untagged tracks are left
+/// out.
+fn exported_plays(conn: &Connection) -> Result<Vec<ExportedPlay>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT ph.played_at, COALESCE(t.artist, t.album_artist), t.album, t.title,
+                t.track_number, t.duration_secs, ph.skipped
+         FROM play_history ph
+         JOIN tracks t ON t.id = ph.track_id
+         WHERE COALESCE(t.artist, t.album_artist) IS NOT NULL AND t.title IS NOT NULL
+         ORDER BY ph.played_at, ph.id",
+    )?;
+    let plays = stmt
+        .query_map([], |row| {
+            Ok(ExportedPlay {
+                played_at: row.get(0)?,
+                artist: row.get(1)?,
+                album: row.get(2)?,
+                title: row.get(3)?,
+                track_number: row.get(4)?,
+                duration_secs: row.get(5)?,
+                skipped: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(plays)
+}
+
+// --- Export ---
+
+/// The play history as ListenBrainz listens. Skips are not listens and are
+/// left out.
+pub fn export_listenbrainz(conn: &Connection) -> Result<Vec<ListenBrainzListen>, AppError> {
+    let listens = exported_plays(conn)?
+        .into_iter()
+        .filter(|play| !play.skipped)
+        .map(|play| ListenBrainzListen {
+            listened_at: play.played_at,
+            track_metadata: ListenBrainzTrackMetadata {
+                artist_name: play.artist,
+                track_name: play.title,
+                release_name: play.album,
+                additional_info: ListenBrainzAdditionalInfo {
+                    duration_ms: play
+                        .duration_secs
+                        .map(|secs| (secs * 1000.0).round() as u64),
+                    tracknumber: play.track_number,
+                    submission_client: CLIENT_NAME.to_string(),
+                    submission_client_version: CLIENT_VERSION.to_string(),
+                },
+            },
+        })
+        .collect();
+    Ok(listens)
+}
+
+/// Writes `export_listenbrainz` to `path` as a JSON array. Returns the number
+/// of listens written.
+pub fn export_listenbrainz_to_file(conn: &Connection, path: &Path) -> Result<usize, AppError> {
+    let listens = export_listenbrainz(conn)?;
+    let json =
+        serde_json::to_string_pretty(&listens).map_err(|e| AppError::General(e.to_string()))?;
+    std::fs::write(path, json)?;
+    Ok(listens.len())
+}
+
+/// The play history as an AudioScrobbler 1.1 log with UTC timestamps.
+/// Skipped plays are kept with the `S` rating.
+pub fn export_scrobbler_log(conn: &Connection) -> Result<(String, usize), AppError> {
+    let plays = exported_plays(conn)?;
+    let mut log = format!("#AUDIOSCROBBLER/1.1\n#TZ/UTC\n#CLIENT/{CLIENT_NAME} {CLIENT_VERSION}\n");
+    for play in &plays {
+        let _ = writeln!(
+            log,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t",
+            log_field(&play.artist),
+            log_field(play.album.as_deref().unwrap_or("")),
+            log_field(&play.title),
+            play.track_number.map(|n| n.to_string()).unwrap_or_default(),
+            play.duration_secs
+                .map(|secs| (secs.round() as u64).to_string())
+                .unwrap_or_default(),
+            if play.skipped { "S" } else { "L" },
+            play.played_at,
+        );
+    }
+    Ok((log, plays.len()))
+}
+
+/// Writes `export_scrobbler_log` to `path`. Returns the number of entries
+/// written.
+pub fn export_scrobbler_log_to_file(conn: &Connection, path: &Path) -> Result<usize, AppError> {
+    let (log, count) = export_scrobbler_log(conn)?;
+    std::fs::write(path, log)?;
+    Ok(count)
+}
+
+/// Tabs and line breaks would split a log line, so they become spaces.
+fn log_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+// --- Import ---
+
+/// Parses an AudioScrobbler log. Malformed lines, such as one cut short when
+/// the device was unplugged mid-write, are skipped.
+pub fn parse_scrobbler_log(text: &str) -> Vec<ScrobbleEntry> {
+    let mut local_time = false;
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some(header) = line.strip_prefix('#') {
+            if let Some(tz) = header.strip_prefix("TZ/") {
+                local_time = tz.trim() != "UTC";
+            }
+            continue;
+        }
+        if let Some(entry) = parse_log_line(line, local_time) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn parse_log_line(line: &str, local_time: bool) -> Option<ScrobbleEntry> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 7 {
+        return None;
+    }
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    let artist = non_empty(fields[0])?;
+    let title = non_empty(fields[2])?;
+    let timestamp: i64 = fields[6].trim().parse().ok()?;
+    let played_at = if local_time {
+        let naive = DateTime::from_timestamp(timestamp, 0)?.naive_utc();
+        Local.from_local_datetime(&naive).earliest()?.timestamp()
+    } else {
+        timestamp
+    };
+    Some(ScrobbleEntry {
+        artist,
+        album: non_empty(fields[1]),
+        title,
+        track_number: fields[3].trim().parse().ok(),
+        duration_secs: fields[4].trim().parse().ok(),
+        skipped: fields[5].trim() == "S",
+        played_at,
+        mbid: fields.get(7).and_then(|s| non_empty(s.trim())),
+    })
+}
+
+/// Adds scrobbles to the play history in one transaction, matching each to a
+/// library track by artist and title, and by album where several tracks
+/// share both. Entries already recorded for the same track and time are
+/// counted as duplicates, so importing a log twice is harmless.
+pub fn import_scrobbles(
+    conn: &Connection,
+    entries: &[ScrobbleEntry],
+) -> Result<ScrobbleImportSummary, AppError> {
+    let tx = conn.unchecked_transaction()?;
+    let index = ScrobbleIndex::load(&tx)?;
+    let mut summary = ScrobbleImportSummary::default();
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO play_history (track_id, played_at, skipped)
+             SELECT ?1, ?2, ?3 WHERE NOT EXISTS
+               (SELECT 1 FROM play_history WHERE track_id = ?1 AND played_at = ?2)",
+        )?;
+        for entry in entries {
+            let Some(track_id) = index.resolve(entry) else {
+                summary.unmatched += 1;
+                continue;
+            };
+            if insert.execute(params![track_id, entry.played_at, entry.skipped])? == 0 {
+                summary.duplicates += 1;
+            } else {
+                summary.imported += 1;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// Parses the log at `path` and imports it with `import_scrobbles`.
+pub fn import_scrobbler_log_file(
+    conn: &Connection,
+    path: &Path,
+) -> Result<ScrobbleImportSummary, AppError> {
+    let bytes = std::fs::read(path)?;
+    let entries = parse_scrobbler_log(&String::from_utf8_lossy(&bytes));
+    import_scrobbles(conn, &entries)
+}
+
+/// A track id and its lowercased album.
+type Candidate = (i64, Option<String>);
+
+/// Library tracks by lowercased artist and title. A track is listed under
+/// both its artist and album artist.
+struct ScrobbleIndex {
+    by_artist_title: HashMap<(String, String), Vec<Candidate>>,
+}
+
+impl ScrobbleIndex {
+    fn load(conn: &Connection) -> Result<Self, AppError> {
+        let mut stmt = conn.prepare(
+            "SELECT id, artist, album_artist, album, title FROM tracks
+             WHERE title IS NOT NULL ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut by_artist_title: HashMap<_, Vec<_>> = HashMap::new();
+        for (id, artist, album_artist, album, title) in rows {
+            let title = title.to_lowercase();
+            let album = album.map(|a| a.to_lowercase());
+            let mut artists: Vec<String> = [artist, album_artist]
+                .into_iter()
+                .flatten()
+                .map(|a| a.to_lowercase())
+                .collect();
+            artists.dedup();
+            for artist in artists {
+                by_artist_title
+                    .entry((artist, title.clone()))
+                    .or_default()
+                    .push((id, album.clone()));
+            }
+        }
+        Ok(ScrobbleIndex { by_artist_title })
+    }
+
+    fn resolve(&self, entry: &ScrobbleEntry) -> Option<i64> {
+        let key = (entry.artist.to_lowercase(), entry.title.to_lowercase());
+        let candidates = self.by_artist_title.get(&key)?;
+        let album = entry.album.as_ref().map(|a| a.to_lowercase());
+        candidates
+            .iter()
+            .find(|(_, candidate)| album.is_some() && *candidate == album)
+            .or_else(|| candidates.first())
+            .map(|(id, _)| *id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{library_repo, schema};
+    use crate::models::track::Track;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn insert_track(conn: &Connection, name: &str, artist: &str, album: &str) -> i64 {
+        let track = Track {
+            id: None,
+            file_path: format!("/music/{artist}/{album}/{name}.flac"),
+            relative_path: format!("{artist}/{album}/{name}.flac"),
+            library_root: "/music".to_string(),
+            title: Some(name.to_string()),
+            artist: Some(artist.to_string()),
+            album_artist: None,
+            album: Some(album.to_string()),
+            track_number: Some(3),
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: Some(201.4),
+            format: "flac".to_string(),
+            file_size: 1000,
+            modified_at: 0,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        };
+        library_repo::upsert_track(conn, &track).unwrap();
+        conn.query_row(
+            "SELECT id FROM tracks WHERE file_path = ?1",
+            params![track.file_path],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn add_play(conn: &Connection, track_id: i64, played_at: i64, skipped: bool) {
+        conn.execute(
+            "INSERT INTO play_history (track_id, played_at, skipped) VALUES (?1, ?2, ?3)",
+            params![track_id, played_at, skipped],
+        )
+        .unwrap();
+    }
+
+    fn play_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM play_history", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_export_scrobbler_log() {
+        let conn = setup_db();
+        let id = insert_track(&conn, "Tab\tTitle", "Artist", "Album");
+        add_play(&conn, id, 1_700_000_000, false);
+        add_play(&conn, id, 1_700_000_500, true);
+
+        let (log, count) = export_scrobbler_log(&conn).unwrap();
+        assert_eq!(count, 2);
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines[0], "#AUDIOSCROBBLER/1.1");
+        assert_eq!(lines[1], "#TZ/UTC");
+        assert_eq!(
+            lines[3],
+            "Artist\tAlbum\tTab Title\t3\t201\tL\t1700000000\t"
+        );
+        assert_eq!(
+            lines[4],
+            "Artist\tAlbum\tTab Title\t3\t201\tS\t1700000500\t"
+        );
+    }
+
+    #[test]
+    fn test_export_listenbrainz_leaves_out_skips() {
+        let conn = setup_db();
+        let id = insert_track(&conn, "Song", "Artist", "Album");
+        add_play(&conn, id, 1_700_000_000, false);
+        add_play(&conn, id, 1_700_000_500, true);
+
+        let listens = export_listenbrainz(&conn).unwrap();
+        assert_eq!(listens.len(), 1);
+        let json = serde_json::to_value(&listens[0]).unwrap();
+        assert_eq!(json["listened_at"], 1_700_000_000);
+        assert_eq!(json["track_metadata"]["artist_name"], "Artist");
+        assert_eq!(json["track_metadata"]["release_name"], "Album");
+        assert_eq!(
+            json["track_metadata"]["additional_info"]["duration_ms"],
+            201_400
+        );
+    }
+
+    #[test]
+    fn test_parse_scrobbler_log() {
+        let log = "#AUDIOSCROBBLER/1.1\r\n#TZ/UTC\r\n#CLIENT/Rockbox sansaclipplus $Revision$\r\n\
+                   Artist\tAlbum\tSong\t2\t180\tL\t1700000000\tabc-123\r\n\
+                   Artist\t\tOther\t\t\tS\t1700000300\t\r\n\
+                   Artist\tAlbum\tCut sh";
+        let entries = parse_scrobbler_log(log);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0],
+            ScrobbleEntry {
+                artist: "Artist".to_string(),
+                album: Some("Album".to_string()),
+                title: "Song".to_string(),
+                track_number: Some(2),
+                duration_secs: Some(180),
+                skipped: false,
+                played_at: 1_700_000_000,
+                mbid: Some("abc-123".to_string()),
+            }
+        );
+        assert_eq!(entries[1].album, None);
+        assert!(entries[1].skipped);
+    }
+
+    #[test]
+    fn test_import_scrobbles_matches_and_dedupes() {
+        let conn = setup_db();
+        let single = insert_track(&conn, "Song", "Artist", "Single");
+        let album = insert_track(&conn, "Song", "Artist", "Album");
+        let entry = |title: &str, album: Option<&str>, played_at| ScrobbleEntry {
+            artist: "ARTIST".to_string(),
+            album: album.map(str::to_string),
+            title: title.to_string(),
+            track_number: None,
+            duration_secs: None,
+            skipped: false,
+            played_at,
+            mbid: None,
+        };
+        let entries = vec![
+            entry("song", Some("album"), 100),
+            entry("Song", None, 200),
+            entry("Missing", None, 300),
+        ];
+
+        let summary = import_scrobbles(&conn, &entries).unwrap();
+        assert_eq!(
+            summary,
+            ScrobbleImportSummary {
+                imported: 2,
+                duplicates: 0,
+                unmatched: 1,
+            }
+        );
+        let track_at = |played_at: i64| -> i64 {
+            conn.query_row(
+                "SELECT track_id FROM play_history WHERE played_at = ?1",
+                params![played_at],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(track_at(100), album);
+        assert_eq!(track_at(200), single);
+
+        let again = import_scrobbles(&conn, &entries).unwrap();
+        assert_eq!(again.imported, 0);
+        assert_eq!(again.duplicates, 2);
+        assert_eq!(play_count(&conn), 2);
+    }
+}
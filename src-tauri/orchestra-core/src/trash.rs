@@ -0,0 +1,176 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// Moves `path` to the user's trash so it can still be restored. Uses
+/// `~/.Trash` on macOS and the freedesktop.org trash
+/// (`$XDG_DATA_HOME/Trash`) elsewhere. Returns the file's new location.
+pub fn move_to_trash(path: &Path) -> Result<PathBuf, AppError> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::General("Cannot locate trash: HOME is not set".to_string()))?;
+
+    if cfg!(target_os = "macos") {
+        let trash = home.join(".Trash");
+        std::fs::create_dir_all(&trash)?;
+        let mut dest = trash.join(path.file_name().unwrap_or_default());
+        let mut n = 1;
+        while dest.exists() {
+            dest = trash.join(numbered_name(path, n));
+            n += 1;
+        }
+        move_file(path, &dest)?;
+        return Ok(dest);
+    }
+
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+    move_to_trash_in(path, &data_home.join("Trash"))
+}
+
+/// Moves `path` into a freedesktop.org trash directory, writing the
+/// `.trashinfo` record that lets file managers restore it.
+pub fn move_to_trash_in(path: &Path, trash: &Path) -> Result<PathBuf, AppError> {
+    let path = std::fs::canonicalize(path)?;
+    let files = trash.join("files");
+    let info = trash.join("info");
+    std::fs::create_dir_all(&files)?;
+    std::fs::create_dir_all(&info)?;
+
+    // Creating the info file exclusively reserves the name in files/
+    for n in 0.. {
+        let name = if n == 0 {
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        } else {
+            numbered_name(&path, n)
+        };
+        let info_path = info.join(format!("{}.trashinfo", name));
+        let mut info_file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+        write!(
+            info_file,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&path.to_string_lossy()),
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+        )?;
+
+        let dest = files.join(&name);
+        if let Err(e) = move_file(&path, &dest) {
+            let _ = std::fs::remove_file(&info_path);
+            return Err(e.into());
+        }
+        return Ok(dest);
+    }
+    unreachable!()
+}
+
+/// Moves a file trashed by `move_to_trash` back to `original`, removing its
+/// `.trashinfo` record if it has one.
+pub fn restore(trashed: &Path, original: &Path) -> Result<(), AppError> {
+    move_file(trashed, original)?;
+    let files = trashed.parent().filter(|dir| dir.ends_with("files"));
+    if let (Some(trash), Some(name)) = (files.and_then(Path::parent), trashed.file_name()) {
+        let mut info = name.to_os_string();
+        info.push(".trashinfo");
+        let _ = std::fs::remove_file(trash.join("info").join(info));
+    }
+    Ok(())
+}
+
+/// `song.flac` -> `song.2.flac`
+fn numbered_name(path: &Path, n: usize) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, n + 1, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, n + 1),
+    }
+}
+
+/// Renames, falling back to copy + delete when the trash is on another device.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    if let Err(e) = std::fs::remove_file(from) {
+        let _ = std::fs::remove_file(to);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_move_to_trash_in_writes_info_and_keeps_names_unique() {
+        let music = TempDir::new().unwrap();
+        let trash = TempDir::new().unwrap();
+
+        let first = music.path().join("my song.flac");
+        std::fs::write(&first, b"one").unwrap();
+        let dest = move_to_trash_in(&first, trash.path()).unwrap();
+        assert!(!first.exists());
+        assert_eq!(dest, trash.path().join("files/my song.flac"));
+
+        let info =
+            std::fs::read_to_string(trash.path().join("info/my song.flac.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("my%20song.flac\n"));
+        assert!(info.contains("DeletionDate="));
+
+        std::fs::write(&first, b"two").unwrap();
+        let dest = move_to_trash_in(&first, trash.path()).unwrap();
+        assert_eq!(dest, trash.path().join("files/my song.2.flac"));
+        assert_eq!(std::fs::read(dest).unwrap(), b"two");
+    }
+
+    #[test]
+    fn test_restore_moves_back_and_drops_info() {
+        let music = TempDir::new().unwrap();
+        let trash = TempDir::new().unwrap();
+        let path = music.path().join("song.flac");
+        std::fs::write(&path, b"one").unwrap();
+
+        let dest = move_to_trash_in(&path, trash.path()).unwrap();
+        restore(&dest, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"one");
+        assert!(!dest.exists());
+        assert!(!trash.path().join("info/song.flac.trashinfo").exists());
+    }
+
+    #[test]
+    fn test_move_missing_file_fails_without_info() {
+        let trash = TempDir::new().unwrap();
+        let result = move_to_trash_in(Path::new("/nonexistent/song.flac"), trash.path());
+        assert!(result.is_err());
+        assert!(!trash.path().join("info/song.flac.trashinfo").exists());
+    }
+}
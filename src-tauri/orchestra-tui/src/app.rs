@@ -1,42 +1,98 @@
-use std::time::Duration;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use orchestra_core::cover;
-use orchestra_core::models::track::{AlbumNode, ArtistNode, LibraryTree, Track};
+use orchestra_core::db::{favorite_repo, playlist_repo, recent_repo};
+use orchestra_core::models::lyrics::Lyrics;
+use orchestra_core::models::playlist::Playlist;
+use orchestra_core::models::track::{AlbumNode, LibraryTree, Track};
+use orchestra_core::scanner::lyrics;
+use ratatui_image::picker::Picker;
+use rusqlite::Connection;
 
+use crate::art::AlbumArt;
+use crate::config::Config;
+use crate::db;
+use crate::keymap::{Action, KeyMap, KeyPress, Resolved};
+use crate::library::{LibraryEvent, LibraryWatcher};
+use crate::listen::ListenThreshold;
 use crate::media_session::MediaSessionHandle;
-use crate::player::PlayerHandle;
+use crate::mouse::{HitMap, Target};
+use crate::player::{PlayItem, PlayerEvent, PlayerHandle};
+use crate::queue::Queue;
+use crate::replaygain::ReplayGainMode;
+use crate::search::{self, Search, SearchHit};
+use crate::theme::Theme;
+use crate::view::{self, TrackGroup, View};
+
+/// How far ←/→ seek.
+const SEEK_STEP_SECS: f64 = 5.0;
+/// Position changes larger than this between two polls are not counted as
+/// listening time.
+const MAX_LISTEN_STEP: Duration = Duration::from_secs(2);
+/// Two clicks on the same row within this long make a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+/// Rows moved per scroll wheel step.
+const SCROLL_LINES: usize = 3;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Pane {
     Artists,
     Albums,
+    /// Playlists or genres, in views that group their tracks.
+    Groups,
     Tracks,
+    Queue,
+    /// Search results, shown instead of the view's panes while searching.
+    Search,
 }
 
 impl Pane {
-    pub fn next(self) -> Self {
-        match self {
-            Pane::Artists => Pane::Albums,
-            Pane::Albums => Pane::Tracks,
-            Pane::Tracks => Pane::Artists,
-        }
+    /// The pane after this one in `panes`.
+    pub fn next(self, panes: &[Pane]) -> Self {
+        let i = panes.iter().position(|p| *p == self).unwrap_or(0);
+        panes[(i + 1) % panes.len()]
     }
 
-    pub fn prev(self) -> Self {
-        match self {
-            Pane::Artists => Pane::Tracks,
-            Pane::Albums => Pane::Artists,
-            Pane::Tracks => Pane::Albums,
-        }
+    /// The pane before this one in `panes`.
+    pub fn prev(self, panes: &[Pane]) -> Self {
+        let i = panes.iter().position(|p| *p == self).unwrap_or(0);
+        panes[(i + panes.len() - 1) % panes.len()]
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct NowPlaying {
+    pub track_id: Option<i64>,
     pub title: String,
     pub artist: String,
+    pub duration: Option<Duration>,
     pub is_paused: bool,
+    /// Whether this play has been written to the play history.
+    pub play_recorded: bool,
+    /// The play history row, once written, so its outcome can be filled in.
+    pub play_id: Option<i64>,
+    /// Time actually played, not counting seeks.
+    pub listened: Duration,
+    pub last_position: Duration,
+}
+
+/// Startup options from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Allow writes (favorites, playlists, play history) to the database.
+    pub read_write: bool,
+    pub listen_threshold: ListenThreshold,
+    pub replaygain: ReplayGainMode,
+}
+
+/// Popup for adding the current selection to a playlist.
+pub struct PlaylistPicker {
+    pub playlists: Vec<Playlist>,
+    pub selected: usize,
+    pub track_ids: Vec<i64>,
 }
 
 pub struct App {
@@ -44,42 +100,116 @@ pub struct App {
     pub selected_artist: usize,
     pub selected_album: usize,
     pub selected_track: usize,
+    pub selected_queue: usize,
+    pub selected_group: usize,
     pub focused_pane: Pane,
+    pub view: View,
+    /// Track lists for the current non-library view.
+    pub groups: Vec<TrackGroup>,
     pub player: PlayerHandle,
+    pub queue: Queue,
     pub now_playing: Option<NowPlaying>,
     pub volume: f32,
     pub status_msg: Option<String>,
     pub should_quit: bool,
-    pub filter_mode: bool,
-    pub filter_text: String,
+    pub search: Option<Search>,
     pub media_session: Option<MediaSessionHandle>,
+    pub conn: Connection,
+    pub options: Options,
+    /// Ids of favorited tracks.
+    pub favorites: HashSet<i64>,
+    pub picker: Option<PlaylistPicker>,
+    /// Non-error feedback, e.g. after adding to a playlist. Cleared on the next key.
+    pub notice: Option<String>,
+    pub keymap: KeyMap,
+    pub theme: Theme,
+    /// Keys typed so far of a multi-key binding such as `gg`.
+    pending_keys: Vec<KeyPress>,
+    /// Whether the key binding overlay is open.
+    pub show_help: bool,
+    pub art: AlbumArt,
+    /// Lyrics of the playing track, if it has any.
+    pub lyrics: Option<Lyrics>,
+    /// Whether the lyrics pane is open.
+    pub show_lyrics: bool,
+    /// Where the last frame drew each pane, for mouse clicks.
+    pub hit_map: HitMap,
+    /// Time and target of the last click, for detecting double-clicks.
+    last_click: Option<(Instant, Target)>,
+    library_watcher: LibraryWatcher,
 }
 
 impl App {
-    pub fn new(tree: LibraryTree) -> Self {
+    pub fn new(
+        tree: LibraryTree,
+        conn: Connection,
+        options: Options,
+        config: Config,
+        picker: Picker,
+        library_watcher: LibraryWatcher,
+    ) -> Self {
+        let favorites = favorite_repo::list_favorites(&conn, "track")
+            .map(|favs| {
+                favs.iter()
+                    .filter_map(|f| f.entity_id.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
         App {
             tree,
             selected_artist: 0,
             selected_album: 0,
             selected_track: 0,
+            selected_queue: 0,
+            selected_group: 0,
             focused_pane: Pane::Artists,
+            view: View::Library,
+            groups: Vec::new(),
             player: PlayerHandle::spawn(),
+            queue: Queue::default(),
             now_playing: None,
             volume: 0.7,
             status_msg: None,
             should_quit: false,
-            filter_mode: false,
-            filter_text: String::new(),
+            search: None,
             media_session: Some(MediaSessionHandle::spawn()),
+            conn,
+            options,
+            favorites,
+            picker: None,
+            notice: None,
+            keymap: config.keymap,
+            theme: config.theme,
+            pending_keys: Vec::new(),
+            show_help: false,
+            art: AlbumArt::new(picker),
+            lyrics: None,
+            show_lyrics: false,
+            hit_map: HitMap::default(),
+            last_click: None,
+            library_watcher,
         }
     }
 
-    /// Drain audio thread errors and media remote events. Call once per frame.
+    /// Drain audio thread events and media remote events. Call once per frame.
     pub fn tick(&mut self) {
-        while let Ok(err) = self.player.errors.try_recv() {
-            self.status_msg = Some(err);
+        while let Ok(event) = self.player.events.try_recv() {
+            match event {
+                PlayerEvent::Error(err) => self.status_msg = Some(err),
+                PlayerEvent::TrackEnded { next_started } => self.on_track_ended(next_started),
+            }
         }
 
+        while let Ok(event) = self.library_watcher.events.try_recv() {
+            match event {
+                LibraryEvent::Reloaded(tree) => self.replace_tree(tree),
+                LibraryEvent::Error(err) => self.status_msg = Some(err),
+            }
+        }
+
+        self.record_play_if_listened();
+
         // Collect remote commands first to avoid borrow conflicts with self.play_selected()
         use crate::media_session::RemoteMediaEvent;
         let remote_events: Vec<RemoteMediaEvent> = if let Some(ref session) = self.media_session {
@@ -96,7 +226,7 @@ impl App {
                             self.player.resume();
                             np.is_paused = false;
                             if let Some(ref session) = self.media_session {
-                                session.update_playback(true, Duration::ZERO);
+                                session.update_playback(true, self.player.position());
                             }
                         }
                     }
@@ -107,321 +237,858 @@ impl App {
                             self.player.pause();
                             np.is_paused = true;
                             if let Some(ref session) = self.media_session {
-                                session.update_playback(false, Duration::ZERO);
-                            }
-                        }
-                    }
-                }
-                RemoteMediaEvent::Next => {
-                    let max = self.current_tracks().len().saturating_sub(1);
-                    if self.selected_track < max {
-                        self.selected_track += 1;
-                        self.play_selected();
-                    }
-                }
-                RemoteMediaEvent::Previous => {
-                    if self.selected_track > 0 {
-                        self.selected_track -= 1;
-                        self.play_selected();
-                    }
-                }
-                RemoteMediaEvent::Toggle => {
-                    if let Some(ref mut np) = self.now_playing {
-                        if np.is_paused {
-                            self.player.resume();
-                            np.is_paused = false;
-                            if let Some(ref session) = self.media_session {
-                                session.update_playback(true, Duration::ZERO);
-                            }
-                        } else {
-                            self.player.pause();
-                            np.is_paused = true;
-                            if let Some(ref session) = self.media_session {
-                                session.update_playback(false, Duration::ZERO);
+                                session.update_playback(false, self.player.position());
                             }
                         }
                     }
                 }
-                RemoteMediaEvent::Seek(_) => {
-                    // rodio 0.20 does not support seek — ignore silently
-                }
+                RemoteMediaEvent::Next => self.next_track(),
+                RemoteMediaEvent::Previous => self.prev_track(),
+                RemoteMediaEvent::Toggle => self.toggle_pause(),
+                RemoteMediaEvent::Seek(pos) => self.seek_to(pos),
             }
         }
     }
 
-    /// Returns artists matching the current filter, paired with their original index.
-    /// When filter_text is empty, returns all artists with their original indices.
-    pub fn filtered_artists(&self) -> Vec<(usize, &ArtistNode)> {
-        if self.filter_text.is_empty() {
-            self.tree.artists.iter().enumerate().collect()
-        } else {
-            let needle = self.filter_text.to_lowercase();
-            self.tree
-                .artists
-                .iter()
-                .enumerate()
-                .filter(|(_, a)| a.name.to_lowercase().contains(&needle))
-                .collect()
-        }
-    }
-
-    /// Maps `selected_artist` (index into filtered list) back to the real tree index.
-    /// Returns 0 if the filtered list is empty.
-    pub fn real_artist_index(&self) -> usize {
-        let filtered = self.filtered_artists();
-        filtered
-            .get(self.selected_artist)
-            .map(|(real_idx, _)| *real_idx)
-            .unwrap_or(0)
-    }
-
     /// Returns the albums for the currently selected artist (if any).
     pub fn current_albums(&self) -> &[AlbumNode] {
-        let real_idx = self.real_artist_index();
         self.tree
             .artists
-            .get(real_idx)
+            .get(self.selected_artist)
             .map(|a| a.albums.as_slice())
             .unwrap_or(&[])
     }
 
-    /// Returns the tracks for the currently selected album (if any).
+    /// Returns the tracks for the currently selected album (if any), or for
+    /// the selected group outside the library view.
     pub fn current_tracks(&self) -> &[Track] {
+        if self.view != View::Library {
+            return self
+                .groups
+                .get(self.selected_group)
+                .map(|g| g.tracks.as_slice())
+                .unwrap_or(&[]);
+        }
         self.current_albums()
             .get(self.selected_album)
             .map(|a| a.tracks.as_slice())
             .unwrap_or(&[])
     }
 
+    /// The panes on screen, in Tab order.
+    pub fn panes(&self) -> &'static [Pane] {
+        if self.search.is_some() {
+            &[Pane::Search, Pane::Queue]
+        } else {
+            self.view.panes()
+        }
+    }
+
+    /// Swaps in a reloaded library, keeping the selection on the same track,
+    /// group, album and artist where they still exist. Playback and the queue
+    /// are unaffected: the queue holds its own copies of the tracks.
+    fn replace_tree(&mut self, tree: LibraryTree) {
+        let artist = self
+            .tree
+            .artists
+            .get(self.selected_artist)
+            .map(|a| a.name.clone());
+        let album = self
+            .current_albums()
+            .get(self.selected_album)
+            .map(|a| a.name.clone());
+        let group = self.groups.get(self.selected_group).map(|g| g.name.clone());
+        let track_id = self
+            .current_tracks()
+            .get(self.selected_track)
+            .and_then(|t| t.id);
+        let search_id = self.search_track().and_then(|t| t.id);
+
+        self.tree = tree;
+
+        let found = track_id.and_then(|id| search::find_track(&self.tree, id));
+        if let (View::Library, Some(hit)) = (self.view, found) {
+            self.selected_artist = hit.artist;
+            self.selected_album = hit.album;
+            self.selected_track = hit.track;
+        } else {
+            self.selected_artist = artist
+                .and_then(|name| self.tree.artists.iter().position(|a| a.name == name))
+                .unwrap_or(0);
+            self.selected_album = album
+                .and_then(|name| self.current_albums().iter().position(|a| a.name == name))
+                .unwrap_or(0);
+            self.selected_track = 0;
+        }
+
+        if self.view != View::Library {
+            self.reload_view();
+            if let Some(i) = group.and_then(|name| self.groups.iter().position(|g| g.name == name))
+            {
+                self.selected_group = i;
+            }
+            self.selected_track = track_id
+                .and_then(|id| self.current_tracks().iter().position(|t| t.id == Some(id)))
+                .unwrap_or(0);
+        }
+
+        if let Some(ref mut search) = self.search {
+            search.update(&self.tree);
+            let hit = search_id.and_then(|id| search::find_track(&self.tree, id));
+            if let Some(i) = hit.and_then(|hit| search.results.iter().position(|h| *h == hit)) {
+                search.selected = i;
+            }
+        }
+    }
+
+    /// Switches the top-level view and loads its tracks. Closes any search.
+    fn switch_view(&mut self, view: View) {
+        self.search = None;
+        self.view = view;
+        self.selected_group = 0;
+        self.selected_track = 0;
+        self.focused_pane = view.panes()[0];
+        self.reload_view();
+    }
+
+    /// Re-reads the current view's tracks, keeping the selection in range.
+    fn reload_view(&mut self) {
+        self.groups = match view::load(self.view, &self.conn, &self.tree) {
+            Ok(groups) => groups,
+            Err(e) => {
+                self.status_msg = Some(format!("Cannot load {}: {e}", self.view.label()));
+                Vec::new()
+            }
+        };
+        self.selected_group = self.selected_group.min(self.groups.len().saturating_sub(1));
+        self.selected_track = self
+            .selected_track
+            .min(self.current_tracks().len().saturating_sub(1));
+    }
+
+    /// Replaces the queue with the current album, starting at the selected track.
     fn play_selected(&mut self) {
-        if let Some(track) = self.current_tracks().get(self.selected_track).cloned() {
-            let title = track
-                .title
-                .clone()
-                .unwrap_or_else(|| "Unknown Title".to_string());
-            let artist = track
-                .album_artist
-                .clone()
-                .or_else(|| track.artist.clone())
-                .unwrap_or_else(|| "Unknown Artist".to_string());
-
-            self.player.play(track.file_path.clone());
-            self.player.set_volume(self.volume);
-            self.now_playing = Some(NowPlaying {
-                title: title.clone(),
-                artist: artist.clone(),
-                is_paused: false,
-            });
-            self.status_msg = None;
-
-            // Update Now Playing metadata
-            if let Some(ref session) = self.media_session {
-                let cover_url = cover::extract_cover(&track.file_path, "orchestra-tui-art.jpg");
-                session.update_metadata(
-                    Some(title),
-                    Some(artist),
-                    track.album.clone(),
-                    track.duration_secs.map(|d| d as f64),
-                    cover_url,
-                );
-                session.update_playback(true, Duration::ZERO);
-            }
-        }
-    }
-
-    pub fn handle_key(&mut self, key: KeyCode) {
-        // Filter mode captures most keys
-        if self.filter_mode {
-            match key {
-                KeyCode::Esc => {
-                    // Exit filter mode, clear filter
-                    self.filter_mode = false;
-                    self.filter_text.clear();
-                    self.selected_artist = 0;
-                    self.selected_album = 0;
-                    self.selected_track = 0;
-                }
-                KeyCode::Enter => {
-                    // Lock the filter and exit filter mode
-                    self.filter_mode = false;
-                    self.selected_artist = 0;
-                    self.selected_album = 0;
-                    self.selected_track = 0;
-                }
-                KeyCode::Backspace => {
-                    self.filter_text.pop();
-                    self.selected_artist = 0;
-                    self.selected_album = 0;
-                    self.selected_track = 0;
-                }
-                KeyCode::Char(c) => {
-                    self.filter_text.push(c);
-                    self.selected_artist = 0;
-                    self.selected_album = 0;
-                    self.selected_track = 0;
+        let tracks = self.current_tracks().to_vec();
+        self.queue.replace(tracks, self.selected_track);
+        self.selected_queue = self.queue.current_index().unwrap_or(0);
+        self.play_current();
+    }
+
+    /// Starts the queue's current track from scratch, with the next one
+    /// queued behind it.
+    fn play_current(&mut self) {
+        let Some(track) = self.queue.current().cloned() else {
+            return;
+        };
+        self.finish_play(false);
+        let next = self.queue.peek_next().map(|t| self.play_item(t));
+        self.player.play(self.play_item(&track), next);
+        self.player.set_volume(self.volume);
+        self.status_msg = None;
+        self.announce(&track);
+    }
+
+    /// Updates the now-playing bar and the media session for `track`.
+    fn announce(&mut self, track: &Track) {
+        let title = track
+            .title
+            .clone()
+            .unwrap_or_else(|| "Unknown Title".to_string());
+        let artist = track
+            .album_artist
+            .clone()
+            .or_else(|| track.artist.clone())
+            .unwrap_or_else(|| "Unknown Artist".to_string());
+
+        self.now_playing = Some(NowPlaying {
+            track_id: track.id,
+            title: title.clone(),
+            artist: artist.clone(),
+            duration: track
+                .duration_secs
+                .filter(|d| d.is_finite() && *d > 0.0)
+                .map(Duration::from_secs_f64),
+            is_paused: false,
+            play_recorded: false,
+            play_id: None,
+            listened: Duration::ZERO,
+            last_position: Duration::ZERO,
+        });
+        self.art.load(track);
+        // Only read files the last scan found lyrics in
+        self.lyrics = track
+            .lyrics
+            .and_then(|_| lyrics::read_lyrics(Path::new(&track.file_path)).ok())
+            .flatten();
+
+        // Update Now Playing metadata
+        if let Some(ref session) = self.media_session {
+            let cover_url = cover::extract_cover(&track.file_path, "orchestra-tui-art.jpg");
+            session.update_metadata(
+                Some(title),
+                Some(artist),
+                track.album.clone(),
+                track.duration_secs,
+                cover_url,
+            );
+            session.update_playback(true, Duration::ZERO);
+        }
+    }
+
+    fn play_item(&self, track: &Track) -> PlayItem {
+        PlayItem {
+            path: track.file_path.clone(),
+            gain: self.options.replaygain.factor(track),
+        }
+    }
+
+    /// Tells the player which track to queue behind the current one. Call
+    /// after anything that may change `queue.peek_next()`.
+    fn sync_next(&self) {
+        if self.now_playing.is_some() {
+            self.player
+                .set_next(self.queue.peek_next().map(|t| self.play_item(t)));
+        }
+    }
+
+    /// Switches ReplayGain off / track / album, re-applying the gain of the
+    /// playing and the queued track.
+    fn cycle_replaygain(&mut self) {
+        self.options.replaygain = self.options.replaygain.cycle();
+        if self.now_playing.is_some() {
+            if let Some(track) = self.queue.current() {
+                self.player.set_gain(self.options.replaygain.factor(track));
+            }
+            self.sync_next();
+        }
+        self.notice = Some(match self.options.replaygain.label() {
+            "" => "ReplayGain off".to_string(),
+            label => format!("Normalizing by {label}"),
+        });
+    }
+
+    fn on_track_ended(&mut self, next_started: bool) {
+        self.finish_play(true);
+        match self.queue.advance().cloned() {
+            Some(track) if next_started => {
+                self.announce(&track);
+                self.sync_next();
+            }
+            // The next track was not queued in time (or failed to decode).
+            Some(_) => self.play_current(),
+            None => {
+                self.now_playing = None;
+                self.art.clear();
+                self.lyrics = None;
+                if let Some(ref session) = self.media_session {
+                    session.update_playback(false, Duration::ZERO);
                 }
-                // All other keys (j/k/Tab/etc.) are ignored while in filter mode
-                _ => {}
             }
+        }
+    }
+
+    /// Writes the current track to the play history once enough of it has
+    /// been heard. Only in read-write mode.
+    fn record_play_if_listened(&mut self) {
+        let position = self.player.position();
+        let Some(ref mut np) = self.now_playing else {
+            return;
+        };
+        // Small forward steps are playback; anything else is a seek or the
+        // next track starting
+        let step = position.saturating_sub(np.last_position);
+        if position > np.last_position && step < MAX_LISTEN_STEP {
+            np.listened += step;
+        }
+        np.last_position = position;
+
+        if !self.options.read_write
+            || np.play_recorded
+            || !self
+                .options
+                .listen_threshold
+                .reached(np.listened, np.duration)
+        {
+            return;
+        }
+        np.play_recorded = true;
+        if let Some(id) = np.track_id {
+            match recent_repo::record_play(&self.conn, id) {
+                Ok(play_id) => np.play_id = Some(play_id),
+                Err(e) => self.status_msg = Some(format!("Cannot record play: {e}")),
+            }
+        }
+    }
+
+    /// Writes how the current play ended: its listened time and whether it
+    /// completed, or a skip if it never counted as played. Only in
+    /// read-write mode, and once per play.
+    fn finish_play(&mut self, completed: bool) {
+        let Some(ref mut np) = self.now_playing else {
             return;
+        };
+        let Some(track_id) = np.track_id.take() else {
+            return;
+        };
+        if !self.options.read_write {
+            return;
+        }
+        let listened = match (completed, np.duration) {
+            (true, Some(duration)) => duration,
+            _ => np.listened,
+        }
+        .as_secs_f64();
+        let result = match np.play_id {
+            Some(play_id) => recent_repo::finish_play(&self.conn, play_id, listened, completed),
+            None if np.listened > Duration::ZERO => {
+                recent_repo::record_skip(&self.conn, track_id, np.listened.as_secs_f64())
+            }
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            self.status_msg = Some(format!("Cannot record play: {e}"));
+        }
+    }
+
+    /// The track that `f` acts on: the highlighted track in the Tracks or
+    /// Queue pane, otherwise the playing track.
+    fn target_track(&self) -> Option<&Track> {
+        match self.focused_pane {
+            Pane::Tracks => self.current_tracks().get(self.selected_track),
+            Pane::Queue => self.queue.tracks().get(self.selected_queue),
+            Pane::Search => self.search_track(),
+            Pane::Artists | Pane::Albums | Pane::Groups => self.queue.current(),
+        }
+    }
+
+    /// Track ids for "add to playlist": every track of the highlighted artist
+    /// or album, or the highlighted track.
+    fn selection_track_ids(&self) -> Vec<i64> {
+        match self.focused_pane {
+            Pane::Artists => self
+                .current_albums()
+                .iter()
+                .flat_map(|a| a.tracks.iter())
+                .filter_map(|t| t.id)
+                .collect(),
+            Pane::Albums | Pane::Groups => {
+                self.current_tracks().iter().filter_map(|t| t.id).collect()
+            }
+            Pane::Tracks | Pane::Queue | Pane::Search => {
+                self.target_track().and_then(|t| t.id).into_iter().collect()
+            }
+        }
+    }
+
+    /// The track behind the highlighted search result.
+    fn search_track(&self) -> Option<&Track> {
+        let hit = self.search.as_ref()?.selected_hit()?;
+        self.tree
+            .artists
+            .get(hit.artist)?
+            .albums
+            .get(hit.album)?
+            .tracks
+            .get(hit.track)
+    }
+
+    fn open_search(&mut self) {
+        let search = self.search.get_or_insert_with(Search::default);
+        search.editing = true;
+        self.focused_pane = Pane::Search;
+    }
+
+    fn close_search(&mut self) {
+        self.search = None;
+        self.focused_pane = self.view.panes()[0];
+    }
+
+    /// Selects the hit's track in the library view and closes the search.
+    fn jump_to_hit(&mut self, hit: SearchHit) {
+        if self.view != View::Library {
+            self.switch_view(View::Library);
         }
+        self.search = None;
+        self.selected_artist = hit.artist;
+        self.selected_album = hit.album;
+        self.selected_track = hit.track;
+        self.focused_pane = Pane::Tracks;
+    }
 
-        // Not in filter mode
+    /// Keys while the search query is being typed. Up/Down move through the
+    /// results, Enter moves focus to them, Esc closes the search.
+    fn handle_search_input(&mut self, key: KeyCode) {
+        if key == KeyCode::Esc {
+            self.close_search();
+            return;
+        }
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
         match key {
-            // Quit
-            KeyCode::Char('q') => {
-                self.should_quit = true;
+            KeyCode::Enter => search.editing = false,
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.update(&self.tree);
+            }
+            KeyCode::Char(c) => {
+                search.query.push(c);
+                search.update(&self.tree);
+            }
+            KeyCode::Down => {
+                let max = search.results.len().saturating_sub(1);
+                search.selected = (search.selected + 1).min(max);
+            }
+            KeyCode::Up => search.selected = search.selected.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    /// Sets an error and returns false when the database is read-only.
+    fn require_write(&mut self) -> bool {
+        if !self.options.read_write {
+            self.status_msg =
+                Some("Read-only mode: restart with --read-write to make changes".to_string());
+        }
+        self.options.read_write
+    }
+
+    fn toggle_favorite(&mut self) {
+        if !self.require_write() {
+            return;
+        }
+        let Some(id) = self.target_track().and_then(|t| t.id) else {
+            return;
+        };
+        match favorite_repo::toggle_favorite(&self.conn, "track", &id.to_string()) {
+            Ok(true) => {
+                self.favorites.insert(id);
             }
+            Ok(false) => {
+                self.favorites.remove(&id);
+            }
+            Err(e) => self.status_msg = Some(format!("Cannot update favorite: {e}")),
+        }
+        if self.view == View::Favorites {
+            self.reload_view();
+        }
+    }
 
-            // Enter filter mode
-            KeyCode::Char('/') => {
-                self.filter_mode = true;
-                self.filter_text.clear();
-                self.selected_artist = 0;
-                self.selected_album = 0;
-                self.selected_track = 0;
+    fn open_playlist_picker(&mut self) {
+        if !self.require_write() {
+            return;
+        }
+        let track_ids = self.selection_track_ids();
+        if track_ids.is_empty() {
+            return;
+        }
+        match playlist_repo::list_playlists(&self.conn) {
+            Ok(playlists) if playlists.is_empty() => {
+                self.notice = Some("No playlists yet — create one in the desktop app".to_string());
+            }
+            Ok(playlists) => {
+                self.picker = Some(PlaylistPicker {
+                    playlists,
+                    selected: 0,
+                    track_ids,
+                });
             }
+            Err(e) => self.status_msg = Some(format!("Cannot load playlists: {e}")),
+        }
+    }
 
-            // Esc when filter is active (but not in filter mode) → clear filter
-            KeyCode::Esc => {
-                if !self.filter_text.is_empty() {
-                    self.filter_text.clear();
-                    self.selected_artist = 0;
-                    self.selected_album = 0;
-                    self.selected_track = 0;
+    /// Keys while the playlist picker is open: Up/Down, Select and Close
+    /// as bound in the keymap.
+    fn handle_picker_key(&mut self, key: KeyPress) {
+        let Some(ref mut picker) = self.picker else {
+            return;
+        };
+        let Resolved::Action(action) = self.keymap.resolve(&[key]) else {
+            return;
+        };
+        match action {
+            Action::Close => self.picker = None,
+            Action::Down => {
+                let max = picker.playlists.len().saturating_sub(1);
+                picker.selected = (picker.selected + 1).min(max);
+            }
+            Action::Up => {
+                picker.selected = picker.selected.saturating_sub(1);
+            }
+            Action::Select => {
+                let Some(picker) = self.picker.take() else {
+                    return;
+                };
+                let Some(playlist) = picker.playlists.get(picker.selected) else {
+                    return;
+                };
+                match db::add_to_playlist(&self.conn, &playlist.id, &picker.track_ids) {
+                    Ok(()) => {
+                        let n = picker.track_ids.len();
+                        let noun = if n == 1 { "track" } else { "tracks" };
+                        self.notice = Some(format!("Added {n} {noun} to \"{}\"", playlist.name));
+                        if self.view == View::Playlists {
+                            self.reload_view();
+                        }
+                    }
+                    Err(e) => self.status_msg = Some(format!("Cannot add to playlist: {e}")),
                 }
             }
+            _ => {}
+        }
+    }
 
-            // Navigation: down
-            KeyCode::Char('j') | KeyCode::Down => match self.focused_pane {
-                Pane::Artists => {
-                    let max = self.filtered_artists().len().saturating_sub(1);
-                    if self.selected_artist < max {
-                        self.selected_artist += 1;
-                        self.selected_album = 0;
-                        self.selected_track = 0;
-                    }
+    /// Seeks the current track, clamped to its known duration.
+    fn seek_to(&mut self, pos: Duration) {
+        let Some(ref np) = self.now_playing else {
+            return;
+        };
+        let pos = np.duration.map_or(pos, |d| pos.min(d));
+        self.player.seek(pos);
+        if let Some(ref session) = self.media_session {
+            session.update_playback(!np.is_paused, pos);
+        }
+    }
+
+    fn seek_by(&mut self, secs: f64) {
+        let target = (self.player.position().as_secs_f64() + secs).max(0.0);
+        self.seek_to(Duration::from_secs_f64(target));
+    }
+
+    /// Jumps to `tenths` × 10% of the current track.
+    fn seek_to_fraction(&mut self, tenths: u32) {
+        if let Some(duration) = self.now_playing.as_ref().and_then(|np| np.duration) {
+            self.seek_to(duration * tenths / 10);
+        }
+    }
+
+    fn next_track(&mut self) {
+        if self.queue.skip_next().is_some() {
+            self.play_current();
+        }
+    }
+
+    fn prev_track(&mut self) {
+        if self.queue.skip_prev().is_some() {
+            self.play_current();
+        }
+    }
+
+    /// Queue pane editing: move the selected track down/up or remove it.
+    fn edit_queue(&mut self, action: Action) {
+        let changed = match action {
+            Action::MoveDown => {
+                let moved = self.queue.move_down(self.selected_queue);
+                if moved {
+                    self.selected_queue += 1;
                 }
-                Pane::Albums => {
-                    let max = self.current_albums().len().saturating_sub(1);
-                    if self.selected_album < max {
-                        self.selected_album += 1;
-                        self.selected_track = 0;
-                    }
+                moved
+            }
+            Action::MoveUp => {
+                let moved = self.queue.move_up(self.selected_queue);
+                if moved {
+                    self.selected_queue -= 1;
                 }
-                Pane::Tracks => {
-                    let max = self.current_tracks().len().saturating_sub(1);
-                    if self.selected_track < max {
-                        self.selected_track += 1;
-                    }
+                moved
+            }
+            Action::Remove => {
+                let removed = self.queue.remove(self.selected_queue);
+                if removed {
+                    let max = self.queue.tracks().len().saturating_sub(1);
+                    self.selected_queue = self.selected_queue.min(max);
                 }
-            },
+                removed
+            }
+            _ => false,
+        };
+        if changed {
+            self.sync_next();
+        }
+    }
 
-            // Navigation: up
-            KeyCode::Char('k') | KeyCode::Up => match self.focused_pane {
-                Pane::Artists => {
-                    if self.selected_artist > 0 {
-                        self.selected_artist -= 1;
-                        self.selected_album = 0;
-                        self.selected_track = 0;
-                    }
+    /// Moves the selection in the focused pane to `index`, clamped to the
+    /// pane's length. Changing artist, album or group resets the panes to its
+    /// right.
+    fn select(&mut self, index: usize) {
+        let clamp = |len: usize| index.min(len.saturating_sub(1));
+        match self.focused_pane {
+            Pane::Artists => {
+                let index = clamp(self.tree.artists.len());
+                if index != self.selected_artist {
+                    self.selected_artist = index;
+                    self.selected_album = 0;
+                    self.selected_track = 0;
                 }
-                Pane::Albums => {
-                    if self.selected_album > 0 {
-                        self.selected_album -= 1;
-                        self.selected_track = 0;
-                    }
+            }
+            Pane::Albums => {
+                let index = clamp(self.current_albums().len());
+                if index != self.selected_album {
+                    self.selected_album = index;
+                    self.selected_track = 0;
                 }
-                Pane::Tracks => {
-                    if self.selected_track > 0 {
-                        self.selected_track -= 1;
-                    }
+            }
+            Pane::Groups => {
+                let index = clamp(self.groups.len());
+                if index != self.selected_group {
+                    self.selected_group = index;
+                    self.selected_track = 0;
                 }
-            },
-
-            // Tab: advance pane
-            KeyCode::Tab => {
-                self.focused_pane = self.focused_pane.next();
-                match self.focused_pane {
-                    Pane::Albums => self.selected_album = 0,
-                    Pane::Tracks => self.selected_track = 0,
-                    Pane::Artists => {}
+            }
+            Pane::Tracks => self.selected_track = clamp(self.current_tracks().len()),
+            Pane::Queue => self.selected_queue = clamp(self.queue.tracks().len()),
+            Pane::Search => {
+                if let Some(ref mut search) = self.search {
+                    search.selected = index.min(search.results.len().saturating_sub(1));
                 }
             }
+        }
+    }
+
+    /// The selected index in the focused pane.
+    fn selected(&self) -> usize {
+        match self.focused_pane {
+            Pane::Artists => self.selected_artist,
+            Pane::Albums => self.selected_album,
+            Pane::Groups => self.selected_group,
+            Pane::Tracks => self.selected_track,
+            Pane::Queue => self.selected_queue,
+            Pane::Search => self.search.as_ref().map_or(0, |s| s.selected),
+        }
+    }
+
+    fn focus(&mut self, pane: Pane) {
+        self.focused_pane = pane;
+        match pane {
+            Pane::Albums => self.selected_album = 0,
+            Pane::Tracks => self.selected_track = 0,
+            Pane::Artists | Pane::Groups | Pane::Queue | Pane::Search => {}
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        let Some(ref mut np) = self.now_playing else {
+            return;
+        };
+        if np.is_paused {
+            self.player.resume();
+        } else {
+            self.player.pause();
+        }
+        np.is_paused = !np.is_paused;
+        if let Some(ref session) = self.media_session {
+            session.update_playback(!np.is_paused, self.player.position());
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.player.set_volume(self.volume);
+    }
+
+    pub fn handle_key(&mut self, event: KeyEvent) {
+        let key = KeyPress::from(event);
+        self.notice = None;
+
+        if self.show_help {
+            self.show_help = false;
+            return;
+        }
+
+        if self.picker.is_some() {
+            self.handle_picker_key(key);
+            return;
+        }
 
-            // Shift+Tab: reverse pane
-            KeyCode::BackTab => {
-                self.focused_pane = self.focused_pane.prev();
-                match self.focused_pane {
-                    Pane::Albums => self.selected_album = 0,
-                    Pane::Tracks => self.selected_track = 0,
-                    Pane::Artists => {}
+        if self.search.as_ref().is_some_and(|s| s.editing) {
+            self.pending_keys.clear();
+            self.handle_search_input(key.code);
+            return;
+        }
+
+        self.pending_keys.push(key);
+        let mut resolved = self.keymap.resolve(&self.pending_keys);
+        // A sequence that went nowhere: forget it and try the last key alone.
+        if resolved == Resolved::Unbound && self.pending_keys.len() > 1 {
+            self.pending_keys = vec![key];
+            resolved = self.keymap.resolve(&self.pending_keys);
+        }
+        match resolved {
+            Resolved::Action(action) => {
+                self.pending_keys.clear();
+                self.perform(action);
+            }
+            Resolved::Pending => {}
+            Resolved::Unbound => {
+                self.pending_keys.clear();
+                // 0-9: jump to 0%-90% of the track
+                if let KeyCode::Char(c @ '0'..='9') = key.code {
+                    if key.modifiers.is_empty() {
+                        self.seek_to_fraction(c.to_digit(10).unwrap_or(0));
+                    }
                 }
             }
+        }
+    }
 
-            // Enter: play if in Tracks pane, else advance pane
-            KeyCode::Enter => {
-                if self.focused_pane == Pane::Tracks {
-                    self.play_selected();
-                } else {
-                    self.focused_pane = self.focused_pane.next();
-                    match self.focused_pane {
-                        Pane::Albums => self.selected_album = 0,
-                        Pane::Tracks => self.selected_track = 0,
-                        Pane::Artists => {}
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if self.show_help {
+            if let MouseEventKind::Down(_) = event.kind {
+                self.show_help = false;
+            }
+            return;
+        }
+        if self.picker.is_some() {
+            return;
+        }
+        let Some(target) = self.hit_map.hit(event.column, event.row) else {
+            return;
+        };
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.click(target),
+            MouseEventKind::ScrollDown => self.scroll(target, SCROLL_LINES as isize),
+            MouseEventKind::ScrollUp => self.scroll(target, -(SCROLL_LINES as isize)),
+            _ => {}
+        }
+    }
+
+    /// A click focuses and selects; a double-click on a row then acts like
+    /// Enter. Clicking the gauge seeks to that point.
+    fn click(&mut self, target: Target) {
+        let now = Instant::now();
+        let double = self
+            .last_click
+            .is_some_and(|(at, prev)| prev == target && now.duration_since(at) <= DOUBLE_CLICK);
+        self.last_click = if double { None } else { Some((now, target)) };
+        self.notice = None;
+        self.pending_keys.clear();
+
+        match target {
+            Target::Gauge(ratio) => {
+                if let Some(duration) = self.now_playing.as_ref().and_then(|np| np.duration) {
+                    self.seek_to(duration.mul_f64(ratio));
+                }
+            }
+            Target::Pane(pane) => self.focus_clicked(pane),
+            Target::Row { pane, index } => {
+                self.focus_clicked(pane);
+                if index < self.pane_len(pane) {
+                    self.select(index);
+                    if double {
+                        self.perform(Action::Select);
                     }
                 }
             }
+        }
+    }
 
-            // Space: toggle play/pause
-            KeyCode::Char(' ') => {
-                if let Some(ref mut np) = self.now_playing {
-                    if np.is_paused {
-                        self.player.resume();
-                        np.is_paused = false;
-                        if let Some(ref session) = self.media_session {
-                            session.update_playback(true, Duration::ZERO);
-                        }
-                    } else {
-                        self.player.pause();
-                        np.is_paused = true;
-                        if let Some(ref session) = self.media_session {
-                            session.update_playback(false, Duration::ZERO);
-                        }
+    /// Scrolls the list under the mouse by `lines`, focusing it.
+    fn scroll(&mut self, target: Target, lines: isize) {
+        let (Target::Row { pane, .. } | Target::Pane(pane)) = target else {
+            return;
+        };
+        self.focus_clicked(pane);
+        self.select(self.selected().saturating_add_signed(lines));
+    }
+
+    /// Focuses `pane` without resetting its selection, and stops editing the
+    /// search query.
+    fn focus_clicked(&mut self, pane: Pane) {
+        self.focused_pane = pane;
+        if let Some(ref mut search) = self.search {
+            search.editing = false;
+        }
+    }
+
+    /// Number of rows in `pane`.
+    fn pane_len(&self, pane: Pane) -> usize {
+        match pane {
+            Pane::Artists => self.tree.artists.len(),
+            Pane::Albums => self.current_albums().len(),
+            Pane::Groups => self.groups.len(),
+            Pane::Tracks => self.current_tracks().len(),
+            Pane::Queue => self.queue.tracks().len(),
+            Pane::Search => self.search.as_ref().map_or(0, |s| s.results.len()),
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Quit => {
+                self.finish_play(false);
+                self.should_quit = true;
+            }
+            Action::Help => self.show_help = true,
+
+            Action::Down => self.select(self.selected() + 1),
+            Action::Up => self.select(self.selected().saturating_sub(1)),
+            Action::Top => self.select(0),
+            Action::Bottom => self.select(usize::MAX),
+
+            Action::NextPane => self.focus(self.focused_pane.next(self.panes())),
+            Action::PrevPane => self.focus(self.focused_pane.prev(self.panes())),
+
+            // Play in Tracks or Queue, jump to a search result, else advance pane
+            Action::Select => match self.focused_pane {
+                Pane::Tracks => self.play_selected(),
+                Pane::Search => {
+                    if let Some(hit) = self.search.as_ref().and_then(|s| s.selected_hit()) {
+                        self.jump_to_hit(hit);
                     }
                 }
-            }
+                Pane::Queue => {
+                    if self.queue.jump(self.selected_queue).is_some() {
+                        self.play_current();
+                    }
+                }
+                Pane::Artists | Pane::Albums | Pane::Groups => {
+                    self.focus(self.focused_pane.next(self.panes()))
+                }
+            },
 
-            // n: next track
-            KeyCode::Char('n') => {
-                let max = self.current_tracks().len().saturating_sub(1);
-                if self.selected_track < max {
-                    self.selected_track += 1;
-                    self.play_selected();
+            Action::Close => {
+                if self.search.is_some() {
+                    self.close_search();
                 }
             }
 
-            // p: previous track
-            KeyCode::Char('p') => {
-                if self.selected_track > 0 {
-                    self.selected_track -= 1;
-                    self.play_selected();
+            Action::NextView => self.switch_view(self.view.next()),
+            Action::PrevView => self.switch_view(self.view.prev()),
+            Action::Search => self.open_search(),
+
+            Action::TogglePause => self.toggle_pause(),
+            Action::NextTrack => self.next_track(),
+            Action::PrevTrack => self.prev_track(),
+            Action::SeekBackward => self.seek_by(-SEEK_STEP_SECS),
+            Action::SeekForward => self.seek_by(SEEK_STEP_SECS),
+            Action::VolumeUp => self.set_volume(self.volume + 0.05),
+            Action::VolumeDown => self.set_volume(self.volume - 0.05),
+
+            Action::Enqueue => {
+                if self.focused_pane == Pane::Tracks {
+                    if let Some(track) = self.current_tracks().get(self.selected_track).cloned() {
+                        self.queue.push(track);
+                        self.sync_next();
+                    }
                 }
             }
-
-            // Volume up
-            KeyCode::Char('+') => {
-                self.volume = (self.volume + 0.05).min(1.0);
-                self.player.set_volume(self.volume);
+            Action::ToggleShuffle => {
+                self.queue.toggle_shuffle();
+                self.sync_next();
             }
-
-            // Volume down
-            KeyCode::Char('-') => {
-                self.volume = (self.volume - 0.05).max(0.0);
-                self.player.set_volume(self.volume);
+            Action::CycleRepeat => {
+                self.queue.cycle_repeat();
+                self.sync_next();
             }
+            Action::CycleReplaygain => self.cycle_replaygain(),
+            Action::ToggleFavorite => self.toggle_favorite(),
+            Action::ToggleLyrics => self.show_lyrics = !self.show_lyrics,
+            Action::AddToPlaylist => self.open_playlist_picker(),
 
-            _ => {}
+            Action::MoveDown | Action::MoveUp | Action::Remove => {
+                if self.focused_pane == Pane::Queue {
+                    self.edit_queue(action);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use orchestra_core::cover;
+use orchestra_core::models::track::Track;
+use ratatui_image::picker::Picker;
+use ratatui_image::protocol::StatefulProtocol;
+
+/// How many albums' covers stay decoded.
+const CACHE_SIZE: usize = 32;
+
+/// Font size assumed when the terminal does not report one.
+const FALLBACK_FONT_SIZE: (u16, u16) = (8, 16);
+
+/// Detects the best image protocol the terminal supports (kitty, iTerm2 or
+/// sixel), falling back to Unicode half blocks. Must run after the terminal
+/// enters the alternate screen and before the event loop reads input.
+pub fn detect_picker() -> Picker {
+    Picker::from_query_stdio().unwrap_or_else(|_| Picker::from_fontsize(FALLBACK_FONT_SIZE))
+}
+
+/// Album art of the playing track, with covers decoded once per album.
+pub struct AlbumArt {
+    picker: Picker,
+    /// Decoded covers by album key, least recently used first. `None` marks
+    /// an album without (readable) art so it is not looked up again.
+    cache: VecDeque<(String, Option<StatefulProtocol>)>,
+    current: Option<String>,
+}
+
+impl AlbumArt {
+    pub fn new(picker: Picker) -> Self {
+        AlbumArt {
+            picker,
+            cache: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    /// Shows the cover of `track`'s album, decoding it unless cached.
+    pub fn load(&mut self, track: &Track) {
+        let key = album_key(track);
+        if let Some(i) = self.cache.iter().position(|(k, _)| *k == key) {
+            if let Some(entry) = self.cache.remove(i) {
+                self.cache.push_back(entry);
+            }
+        } else {
+            let protocol = cover::read_cover(&track.file_path)
+                .and_then(|data| image::load_from_memory(&data).ok())
+                .map(|image| self.picker.new_resize_protocol(image));
+            if self.cache.len() >= CACHE_SIZE {
+                self.cache.pop_front();
+            }
+            self.cache.push_back((key.clone(), protocol));
+        }
+        self.current = Some(key);
+    }
+
+    /// Hides the art, e.g. when playback stops.
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    /// The cover to draw, if the playing album has one.
+    pub fn current(&mut self) -> Option<&mut StatefulProtocol> {
+        let key = self.current.as_ref()?;
+        self.cache
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, protocol)| protocol.as_mut())
+    }
+
+    pub fn has_current(&self) -> bool {
+        let Some(ref key) = self.current else {
+            return false;
+        };
+        self.cache
+            .iter()
+            .any(|(k, protocol)| k == key && protocol.is_some())
+    }
+}
+
+/// Identifies a track's album: the album artist and album tags, or the
+/// track's folder for untagged files.
+pub fn album_key(track: &Track) -> String {
+    match track.album {
+        Some(ref album) => {
+            let artist = track
+                .album_artist
+                .as_deref()
+                .or(track.artist.as_deref())
+                .unwrap_or("");
+            format!("{artist}\u{1f}{album}")
+        }
+        None => Path::new(&track.file_path)
+            .parent()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(path: &str, artist: Option<&str>, album: Option<&str>) -> Track {
+        Track {
+            id: None,
+            file_path: path.to_string(),
+            relative_path: path.trim_start_matches("/music/").to_string(),
+            library_root: "/music".to_string(),
+            title: None,
+            artist: artist.map(str::to_string),
+            album_artist: None,
+            album: album.map(str::to_string),
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: None,
+            format: "flac".to_string(),
+            file_size: 0,
+            modified_at: 0,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    #[test]
+    fn test_album_key() {
+        let a = track("/music/A/Blue/1.flac", Some("Joni"), Some("Blue"));
+        let b = track("/music/A/Blue/2.flac", Some("Joni"), Some("Blue"));
+        let c = track("/music/B/Blue/1.flac", Some("Other"), Some("Blue"));
+        assert_eq!(album_key(&a), album_key(&b));
+        assert_ne!(album_key(&a), album_key(&c));
+
+        let untagged = track("/music/Loose/1.flac", None, None);
+        assert_eq!(album_key(&untagged), "/music/Loose");
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::keymap::{Action, KeyMap};
+use crate::theme::{Theme, ThemeConfig};
+
+/// User settings from the config file.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub keymap: KeyMap,
+    pub theme: Theme,
+}
+
+/// An action's binding in the file: one key sequence or a list of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Binding {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    keys: HashMap<Action, Binding>,
+    theme: ThemeConfig,
+}
+
+/// Returns the path of the config file, if one exists.
+///
+/// Checks:
+/// 1. `$XDG_CONFIG_HOME/orchestra-tui/config.toml`
+/// 2. `dirs::config_dir() / "orchestra-tui" / "config.toml"`
+pub fn find_config_path() -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
+        candidates.push(PathBuf::from(xdg).join("orchestra-tui").join("config.toml"));
+    }
+    if let Some(config_dir) = dirs::config_dir() {
+        candidates.push(config_dir.join("orchestra-tui").join("config.toml"));
+    }
+    candidates.into_iter().find(|path| path.exists())
+}
+
+/// Reads and parses the config file at `path`.
+pub fn load(path: &Path) -> Result<Config> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    parse(&text).with_context(|| format!("invalid config {}", path.display()))
+}
+
+/// Parses config file contents. Anything not set keeps its default.
+pub fn parse(text: &str) -> Result<Config> {
+    let file: ConfigFile = toml::from_str(text)?;
+    let overrides: HashMap<Action, Vec<String>> = file
+        .keys
+        .into_iter()
+        .map(|(action, binding)| {
+            let keys = match binding {
+                Binding::One(key) => vec![key],
+                Binding::Many(keys) => keys,
+            };
+            (action, keys)
+        })
+        .collect();
+    Ok(Config {
+        keymap: KeyMap::with_overrides(&overrides).map_err(|e| anyhow!("keys.{e}"))?,
+        theme: Theme::from_config(&file.theme).map_err(|e| anyhow!(e))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::parse_binding;
+    use ratatui::style::Color;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse(
+            r##"
+            [keys]
+            down = ["j", "Ctrl+n"]
+            quit = "Ctrl+q"
+            help = []
+
+            [theme]
+            border_focused = "cyan"
+            highlight_bg = "#336699"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.keymap.keys_for(Action::Down), vec!["j", "Ctrl+n"]);
+        assert_eq!(config.keymap.keys_for(Action::Quit), vec!["Ctrl+q"]);
+        assert!(config.keymap.keys_for(Action::Help).is_empty());
+        assert_eq!(config.keymap.keys_for(Action::Top), vec!["gg", "Home"]);
+        assert_eq!(config.theme.border_focused, Color::Cyan);
+        assert_eq!(config.theme.highlight_bg, Color::Rgb(0x33, 0x66, 0x99));
+        assert_eq!(config.theme.border, Color::DarkGray);
+
+        let q = parse_binding("q").unwrap();
+        assert_eq!(config.keymap.resolve(&q), crate::keymap::Resolved::Unbound);
+
+        assert!(parse("").is_ok());
+        assert!(parse("[keys]\nfly = \"x\"").is_err());
+        assert!(parse("[keys]\nquit = \"Hyper+q\"").is_err());
+        assert!(parse("[theme]\naccent = \"not-a-color\"").is_err());
+        assert!(parse("[colors]").is_err());
+    }
+}
@@ -1,7 +1,12 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use rusqlite::{Connection, OptionalExtension};
+use orchestra_core::db::{playlist_repo, schema};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Transaction, TransactionBehavior};
+
+/// How long a write waits for the desktop app to release its lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Returns the expected path of the Orchestra SQLite database.
 ///
@@ -49,3 +54,120 @@ pub fn read_library_root(conn: &Connection) -> Result<Option<String>> {
 
     Ok(result)
 }
+
+/// Opens the database read-only, or read-write when `read_write` is set.
+/// Fails if a newer desktop app has upgraded the schema past what this
+/// build understands.
+///
+/// Read-write connections switch the database to WAL (a no-op if the desktop
+/// app already did) and wait on locks instead of failing, so the TUI and the
+/// desktop app can write at the same time.
+pub fn open(path: &Path, read_write: bool) -> Result<Connection> {
+    let flags = if read_write {
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    };
+    let conn = Connection::open_with_flags(path, flags)?;
+    schema::check_not_newer(&conn)?;
+    if read_write {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
+    }
+    Ok(conn)
+}
+
+/// SQLite's `data_version` for `conn`: changes whenever another connection
+/// commits to the database.
+pub fn data_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA data_version", [], |row| row.get(0))
+        .context("Failed to read data_version")
+}
+
+/// Track count and latest `scanned_at` under `library_root`. Changes when a
+/// scan adds, removes or re-reads tracks, but not on play history or
+/// playlist writes.
+pub fn library_fingerprint(conn: &Connection, library_root: &str) -> Result<(i64, i64)> {
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(MAX(scanned_at), 0) FROM tracks WHERE library_root = ?1",
+        [library_root],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .context("Failed to query tracks table")
+}
+
+/// Appends tracks to a playlist and bumps its `updated_at`, in one
+/// transaction.
+pub fn add_to_playlist(conn: &Connection, playlist_id: &str, track_ids: &[i64]) -> Result<()> {
+    // IMMEDIATE takes the write lock up front; a deferred transaction that
+    // upgrades later can fail with SQLITE_BUSY regardless of the timeout.
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    playlist_repo::add_tracks(&tx, playlist_id, track_ids)?;
+    let mut playlist = playlist_repo::get_playlist(&tx, playlist_id)?;
+    playlist.updated_at = now_secs();
+    playlist_repo::update_playlist(&tx, &playlist)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orchestra_core::models::playlist::Playlist;
+
+    #[test]
+    fn test_add_to_playlist_appends_and_touches_playlist() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO tracks (id, file_path, relative_path, library_root, format, file_size, modified_at, scanned_at)
+             VALUES (1, '/m/a.flac', 'a.flac', '/m', 'flac', 1, 0, 0)",
+            [],
+        )
+        .unwrap();
+        playlist_repo::create_playlist(
+            &conn,
+            &Playlist {
+                id: "p1".to_string(),
+                name: "Mix".to_string(),
+                created_at: 0,
+                updated_at: 0,
+            },
+        )
+        .unwrap();
+
+        add_to_playlist(&conn, "p1", &[1]).unwrap();
+
+        let playlist = playlist_repo::get_playlist_with_tracks(&conn, "p1").unwrap();
+        assert_eq!(playlist.tracks.len(), 1);
+        assert!(playlist.playlist.updated_at > 0);
+        assert!(add_to_playlist(&conn, "missing", &[1]).is_err());
+    }
+
+    #[test]
+    fn test_library_fingerprint_tracks_scans() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        assert_eq!(library_fingerprint(&conn, "/m").unwrap(), (0, 0));
+
+        conn.execute(
+            "INSERT INTO tracks (id, file_path, relative_path, library_root, format, file_size, modified_at, scanned_at)
+             VALUES (1, '/m/a.flac', 'a.flac', '/m', 'flac', 1, 0, 100),
+                    (2, '/x/b.flac', 'b.flac', '/x', 'flac', 1, 0, 200)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(library_fingerprint(&conn, "/m").unwrap(), (1, 100));
+
+        conn.execute("UPDATE tracks SET scanned_at = 150 WHERE id = 1", [])
+            .unwrap();
+        assert_eq!(library_fingerprint(&conn, "/m").unwrap(), (1, 150));
+    }
+}
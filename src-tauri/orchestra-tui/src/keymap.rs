@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Everything a key can be bound to. Names are the `[keys]` entries of the
+/// config file, in snake_case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Help,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    NextPane,
+    PrevPane,
+    Select,
+    Close,
+    NextView,
+    PrevView,
+    Search,
+    TogglePause,
+    NextTrack,
+    PrevTrack,
+    SeekBackward,
+    SeekForward,
+    VolumeUp,
+    VolumeDown,
+    Enqueue,
+    ToggleShuffle,
+    CycleRepeat,
+    CycleReplaygain,
+    ToggleFavorite,
+    AddToPlaylist,
+    MoveDown,
+    MoveUp,
+    Remove,
+    ToggleLyrics,
+}
+
+impl Action {
+    /// All actions, in the order the help overlay lists them.
+    pub const ALL: [Action; 30] = [
+        Action::Quit,
+        Action::Help,
+        Action::Down,
+        Action::Up,
+        Action::Top,
+        Action::Bottom,
+        Action::NextPane,
+        Action::PrevPane,
+        Action::Select,
+        Action::Close,
+        Action::NextView,
+        Action::PrevView,
+        Action::Search,
+        Action::TogglePause,
+        Action::NextTrack,
+        Action::PrevTrack,
+        Action::SeekBackward,
+        Action::SeekForward,
+        Action::VolumeUp,
+        Action::VolumeDown,
+        Action::Enqueue,
+        Action::ToggleShuffle,
+        Action::CycleRepeat,
+        Action::CycleReplaygain,
+        Action::ToggleFavorite,
+        Action::AddToPlaylist,
+        Action::MoveDown,
+        Action::MoveUp,
+        Action::Remove,
+        Action::ToggleLyrics,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Show this help",
+            Action::Down => "Move down",
+            Action::Up => "Move up",
+            Action::Top => "Jump to the first item",
+            Action::Bottom => "Jump to the last item",
+            Action::NextPane => "Next pane",
+            Action::PrevPane => "Previous pane",
+            Action::Select => "Play / open the highlighted item",
+            Action::Close => "Close search or popup",
+            Action::NextView => "Next view",
+            Action::PrevView => "Previous view",
+            Action::Search => "Search the library",
+            Action::TogglePause => "Play / pause",
+            Action::NextTrack => "Next track",
+            Action::PrevTrack => "Previous track",
+            Action::SeekBackward => "Seek backward",
+            Action::SeekForward => "Seek forward",
+            Action::VolumeUp => "Volume up",
+            Action::VolumeDown => "Volume down",
+            Action::Enqueue => "Append track to the queue (Tracks pane)",
+            Action::ToggleShuffle => "Toggle shuffle",
+            Action::CycleRepeat => "Cycle repeat off / all / one",
+            Action::CycleReplaygain => "Cycle ReplayGain off / track / album",
+            Action::ToggleFavorite => "Toggle favorite",
+            Action::AddToPlaylist => "Add to playlist",
+            Action::MoveDown => "Move track down (Queue pane)",
+            Action::MoveUp => "Move track up (Queue pane)",
+            Action::Remove => "Remove track (Queue pane)",
+            Action::ToggleLyrics => "Show / hide lyrics",
+        }
+    }
+
+    /// The bindings used when the config file does not override them.
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::Help => &["?"],
+            Action::Down => &["j", "Down"],
+            Action::Up => &["k", "Up"],
+            Action::Top => &["gg", "Home"],
+            Action::Bottom => &["G", "End"],
+            Action::NextPane => &["Tab"],
+            Action::PrevPane => &["Shift+Tab"],
+            Action::Select => &["Enter"],
+            Action::Close => &["Esc"],
+            Action::NextView => &["v"],
+            Action::PrevView => &["V"],
+            Action::Search => &["/"],
+            Action::TogglePause => &["Space"],
+            Action::NextTrack => &["n"],
+            Action::PrevTrack => &["p"],
+            Action::SeekBackward => &["Left"],
+            Action::SeekForward => &["Right"],
+            Action::VolumeUp => &["+"],
+            Action::VolumeDown => &["-"],
+            Action::Enqueue => &["a"],
+            Action::ToggleShuffle => &["s"],
+            Action::CycleRepeat => &["r"],
+            Action::CycleReplaygain => &["R"],
+            Action::ToggleFavorite => &["f"],
+            Action::AddToPlaylist => &["P"],
+            Action::MoveDown => &["J"],
+            Action::MoveUp => &["K"],
+            Action::Remove => &["d", "Delete"],
+            Action::ToggleLyrics => &["l"],
+        }
+    }
+}
+
+/// A single key press. Shift is folded into the character for printable
+/// keys, so `G` and `Shift+g` are the same press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let (code, modifiers) = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => (
+                KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers - KeyModifiers::SHIFT,
+            ),
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => {
+                (KeyCode::BackTab, modifiers - KeyModifiers::SHIFT)
+            }
+            KeyCode::BackTab => (code, modifiers - KeyModifiers::SHIFT),
+            _ => (code, modifiers),
+        };
+        KeyPress {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        KeyPress::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => f.write_str("Shift+Tab"),
+            KeyCode::Esc => f.write_str("Esc"),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Parses one binding into the key presses that make it up.
+///
+/// A binding is one or more whitespace-separated tokens. A token is either a
+/// named key with optional modifiers (`Enter`, `Ctrl+d`, `Shift+Tab`) or
+/// literal characters, each its own press: `gg` is `g` followed by `g`.
+pub fn parse_binding(binding: &str) -> Result<Vec<KeyPress>, String> {
+    let mut keys = Vec::new();
+    for token in binding.split_whitespace() {
+        if let Some(key) = parse_named(token)? {
+            keys.push(key);
+        } else {
+            keys.extend(
+                token
+                    .chars()
+                    .map(|c| KeyPress::new(KeyCode::Char(c), KeyModifiers::NONE)),
+            );
+        }
+    }
+    if keys.is_empty() {
+        return Err("empty key binding".to_string());
+    }
+    Ok(keys)
+}
+
+/// Parses a named key or a `Modifier+key` token. Returns `None` for a token
+/// of plain characters.
+fn parse_named(token: &str) -> Result<Option<KeyPress>, String> {
+    // `+` on its own, or as the key after a modifier (`Ctrl++`).
+    let (mods, key) = match token.strip_suffix("++") {
+        Some(mods) => (Some(mods), "+"),
+        None => match token.rsplit_once('+') {
+            Some((mods, key)) if !mods.is_empty() && !key.is_empty() => (Some(mods), key),
+            _ => (None, token),
+        },
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for m in mods.into_iter().flat_map(|m| m.split('+')) {
+        modifiers |= match m.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" | "meta" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier \"{m}\" in \"{token}\"")),
+        };
+    }
+
+    let code = match key.to_ascii_lowercase().as_str() {
+        _ if key.chars().count() == 1 => KeyCode::Char(key.chars().next().unwrap_or(' ')),
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ if mods.is_some() => return Err(format!("unknown key \"{key}\" in \"{token}\"")),
+            _ => return Ok(None),
+        },
+    };
+    Ok(Some(KeyPress::new(code, modifiers)))
+}
+
+/// Formats a key sequence the way the help overlay shows it: `gg`, `Ctrl+w l`.
+pub fn format_sequence(keys: &[KeyPress]) -> String {
+    let plain = keys
+        .iter()
+        .all(|k| k.modifiers.is_empty() && matches!(k.code, KeyCode::Char(c) if c != ' '));
+    let parts: Vec<String> = keys.iter().map(KeyPress::to_string).collect();
+    parts.join(if plain { "" } else { " " })
+}
+
+/// What the keys pressed so far amount to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolved {
+    Action(Action),
+    /// A prefix of a longer binding; wait for the next key.
+    Pending,
+    Unbound,
+}
+
+/// Action → key sequence bindings.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<(Action, Vec<KeyPress>)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .flat_map(|&action| {
+                action.default_keys().iter().map(move |k| {
+                    // The defaults are known to parse.
+                    (action, parse_binding(k).unwrap_or_default())
+                })
+            })
+            .collect();
+        KeyMap { bindings }
+    }
+}
+
+impl KeyMap {
+    /// The default bindings with each action in `overrides` rebound to the
+    /// given keys. An empty list unbinds the action.
+    pub fn with_overrides(overrides: &HashMap<Action, Vec<String>>) -> Result<Self, String> {
+        let mut map = KeyMap::default();
+        map.bindings
+            .retain(|(action, _)| !overrides.contains_key(action));
+        for action in Action::ALL {
+            for binding in overrides.get(&action).into_iter().flatten() {
+                let keys = parse_binding(binding).map_err(|e| format!("{action:?}: {e}"))?;
+                map.bindings.push((action, keys));
+            }
+        }
+        Ok(map)
+    }
+
+    /// Looks up the keys pressed so far. An exact match wins over a longer
+    /// binding that starts with the same keys.
+    pub fn resolve(&self, pressed: &[KeyPress]) -> Resolved {
+        if let Some((action, _)) = self.bindings.iter().find(|(_, keys)| keys == pressed) {
+            return Resolved::Action(*action);
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|(_, keys)| keys.len() > pressed.len() && keys.starts_with(pressed))
+        {
+            Resolved::Pending
+        } else {
+            Resolved::Unbound
+        }
+    }
+
+    /// The bindings of `action`, formatted for display.
+    pub fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(a, _)| *a == action)
+            .map(|(_, keys)| format_sequence(keys))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyPress {
+        KeyPress::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_parse_binding() {
+        let g = key(KeyCode::Char('g'));
+        assert_eq!(parse_binding("gg"), Ok(vec![g, g]));
+        assert_eq!(parse_binding("Down"), Ok(vec![key(KeyCode::Down)]));
+        assert_eq!(parse_binding("space"), Ok(vec![key(KeyCode::Char(' '))]));
+        assert_eq!(parse_binding("Shift+Tab"), Ok(vec![key(KeyCode::BackTab)]));
+        assert_eq!(parse_binding("Shift+g"), Ok(vec![key(KeyCode::Char('G'))]));
+        assert_eq!(parse_binding("+"), Ok(vec![key(KeyCode::Char('+'))]));
+        assert_eq!(
+            parse_binding("Ctrl+w l"),
+            Ok(vec![
+                KeyPress::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                key(KeyCode::Char('l')),
+            ])
+        );
+        assert_eq!(
+            parse_binding("Ctrl++"),
+            Ok(vec![KeyPress::new(
+                KeyCode::Char('+'),
+                KeyModifiers::CONTROL
+            )])
+        );
+        assert!(parse_binding("Hyper+x").is_err());
+        assert!(parse_binding("Ctrl+Foo").is_err());
+        assert!(parse_binding("  ").is_err());
+
+        assert_eq!(format_sequence(&[g, g]), "gg");
+        assert_eq!(
+            format_sequence(&parse_binding("Ctrl+w l").unwrap()),
+            "Ctrl+w l"
+        );
+    }
+
+    #[test]
+    fn test_resolve_sequences_and_overrides() {
+        let g = key(KeyCode::Char('g'));
+        let map = KeyMap::default();
+        assert_eq!(map.resolve(&[g]), Resolved::Pending);
+        assert_eq!(map.resolve(&[g, g]), Resolved::Action(Action::Top));
+        assert_eq!(
+            map.resolve(&[KeyPress::new(KeyCode::BackTab, KeyModifiers::SHIFT)]),
+            Resolved::Action(Action::PrevPane)
+        );
+        assert_eq!(map.resolve(&[key(KeyCode::Char('z'))]), Resolved::Unbound);
+
+        let overrides = HashMap::from([
+            (Action::Down, vec!["Ctrl+n".to_string()]),
+            (Action::Top, vec![]),
+        ]);
+        let map = KeyMap::with_overrides(&overrides).unwrap();
+        assert_eq!(map.resolve(&[key(KeyCode::Char('j'))]), Resolved::Unbound);
+        assert_eq!(map.resolve(&[g]), Resolved::Unbound);
+        assert_eq!(
+            map.resolve(&[KeyPress::new(KeyCode::Char('n'), KeyModifiers::CONTROL)]),
+            Resolved::Action(Action::Down)
+        );
+        assert_eq!(map.keys_for(Action::Down), vec!["Ctrl+n"]);
+        assert_eq!(map.keys_for(Action::Up), vec!["k", "Up"]);
+    }
+}
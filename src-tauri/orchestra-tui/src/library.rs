@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::db;
+use orchestra_core::db::library_repo;
+use orchestra_core::models::track::LibraryTree;
+
+/// How often the watcher checks the database for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub enum LibraryEvent {
+    /// The library changed on disk; this is the freshly loaded tree.
+    Reloaded(LibraryTree),
+    Error(String),
+}
+
+/// Watches the shared database for rescans by the desktop app and reloads the
+/// library tree on a background thread.
+pub struct LibraryWatcher {
+    pub events: mpsc::Receiver<LibraryEvent>,
+}
+
+impl LibraryWatcher {
+    /// Starts watching the library under `library_root` in the database at
+    /// `db_path`, using a read-only connection of its own.
+    pub fn spawn(db_path: PathBuf, library_root: String) -> Self {
+        let (event_tx, event_rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let conn = match db::open(&db_path, false) {
+                Ok(c) => c,
+                Err(e) => {
+                    let _ = event_tx.send(LibraryEvent::Error(format!(
+                        "Cannot watch library for changes: {e}"
+                    )));
+                    return;
+                }
+            };
+
+            let mut version = db::data_version(&conn).ok();
+            let mut fingerprint = db::library_fingerprint(&conn, &library_root).ok();
+            // Set when the database changed; the reload waits until a poll
+            // sees no further commits, so a running scan is not reloaded
+            // every interval.
+            let mut settling = false;
+
+            loop {
+                thread::sleep(POLL_INTERVAL);
+
+                let current = db::data_version(&conn).ok();
+                if current != version {
+                    version = current;
+                    settling = true;
+                    continue;
+                }
+                if !settling {
+                    continue;
+                }
+                settling = false;
+
+                let current = db::library_fingerprint(&conn, &library_root).ok();
+                if current == fingerprint {
+                    continue;
+                }
+                fingerprint = current;
+
+                let event = match library_repo::get_library_tree(&conn, &library_root) {
+                    Ok(tree) => LibraryEvent::Reloaded(tree),
+                    Err(e) => LibraryEvent::Error(format!("Cannot reload library: {e}")),
+                };
+                if event_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        LibraryWatcher { events: event_rx }
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+/// Used for percentage thresholds when the track length is unknown.
+const FALLBACK_SECS: u64 = 30;
+
+/// How much of a track must be heard before it counts as played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListenThreshold {
+    Seconds(u64),
+    Percent(f64),
+}
+
+impl Default for ListenThreshold {
+    fn default() -> Self {
+        ListenThreshold::Percent(50.0)
+    }
+}
+
+impl ListenThreshold {
+    /// Parses `30` (seconds) or `50%` (of the track length).
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(pct) = value.strip_suffix('%') {
+            let pct: f64 = pct.trim().parse().ok()?;
+            (0.0..=100.0)
+                .contains(&pct)
+                .then_some(ListenThreshold::Percent(pct))
+        } else {
+            value.parse().ok().map(ListenThreshold::Seconds)
+        }
+    }
+
+    pub fn reached(self, position: Duration, duration: Option<Duration>) -> bool {
+        let needed = match (self, duration) {
+            (ListenThreshold::Seconds(secs), _) => Duration::from_secs(secs),
+            (ListenThreshold::Percent(pct), Some(d)) => d.mul_f64(pct / 100.0),
+            (ListenThreshold::Percent(_), None) => Duration::from_secs(FALLBACK_SECS),
+        };
+        position >= needed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ListenThreshold::parse("45"),
+            Some(ListenThreshold::Seconds(45))
+        );
+        assert_eq!(
+            ListenThreshold::parse(" 50% "),
+            Some(ListenThreshold::Percent(50.0))
+        );
+        assert_eq!(ListenThreshold::parse("150%"), None);
+        assert_eq!(ListenThreshold::parse("-3"), None);
+        assert_eq!(ListenThreshold::parse("soon"), None);
+    }
+
+    #[test]
+    fn test_reached() {
+        let four_min = Some(Duration::from_secs(240));
+        let half = ListenThreshold::Percent(50.0);
+        assert!(!half.reached(Duration::from_secs(119), four_min));
+        assert!(half.reached(Duration::from_secs(120), four_min));
+        assert!(!half.reached(Duration::from_secs(29), None));
+        assert!(half.reached(Duration::from_secs(30), None));
+
+        let secs = ListenThreshold::Seconds(10);
+        assert!(secs.reached(Duration::from_secs(10), None));
+        assert!(!secs.reached(Duration::from_secs(9), four_min));
+    }
+}
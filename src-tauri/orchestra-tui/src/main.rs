@@ -8,26 +8,78 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
-use rusqlite::{Connection, OpenFlags};
 
 mod app;
+mod art;
+mod config;
 mod db;
+mod keymap;
+mod library;
+mod listen;
 mod media_session;
+mod mouse;
 mod player;
+mod queue;
+mod replaygain;
+mod search;
+mod theme;
 mod ui;
+mod view;
 
-use app::App;
+use app::{App, Options};
+use config::Config;
+use library::LibraryWatcher;
+use listen::ListenThreshold;
+use replaygain::ReplayGainMode;
 
 fn main() {
-    // Parse optional --db <path> CLI flag.
+    // Parse CLI flags: --db <path>, --config <path>, --read-write,
+    // --listen-threshold <secs|N%>, --replaygain <off|track|album>.
+    let mut options = Options::default();
+    let mut config_path: Option<PathBuf> = None;
     let db_path: PathBuf = {
         let mut args = std::env::args().skip(1);
         let mut override_path: Option<PathBuf> = None;
         while let Some(arg) = args.next() {
-            if arg == "--db" {
-                if let Some(p) = args.next() {
-                    override_path = Some(PathBuf::from(p));
+            match arg.as_str() {
+                "--db" => {
+                    if let Some(p) = args.next() {
+                        override_path = Some(PathBuf::from(p));
+                    }
                 }
+                "--config" => {
+                    if let Some(p) = args.next() {
+                        config_path = Some(PathBuf::from(p));
+                    }
+                }
+                "--read-write" | "-w" => options.read_write = true,
+                "--listen-threshold" => {
+                    let value = args.next().unwrap_or_default();
+                    match ListenThreshold::parse(&value) {
+                        Some(t) => options.listen_threshold = t,
+                        None => {
+                            eprintln!(
+                                "Error: Invalid --listen-threshold \"{value}\" \
+                                 (expected seconds, e.g. 30, or a percentage, e.g. 50%)."
+                            );
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--replaygain" => {
+                    let value = args.next().unwrap_or_default();
+                    match ReplayGainMode::parse(&value) {
+                        Some(mode) => options.replaygain = mode,
+                        None => {
+                            eprintln!(
+                                "Error: Invalid --replaygain \"{value}\" \
+                                 (expected off, track or album)."
+                            );
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                _ => {}
             }
         }
         if let Some(p) = override_path {
@@ -48,11 +100,20 @@ fn main() {
         }
     };
 
-    // Open the database in read-only mode.
-    let conn = match Connection::open_with_flags(
-        &db_path,
-        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
-    ) {
+    // Load keybindings and theme. Without a config file the defaults apply.
+    let config = match config_path.or_else(config::find_config_path) {
+        Some(path) => match config::load(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+
+    // Open the database, read-only unless --read-write was given.
+    let conn = match db::open(&db_path, options.read_write) {
         Ok(c) => c,
         Err(e) => {
             eprintln!(
@@ -98,20 +159,28 @@ fn main() {
     }
 
     // Initialize terminal.
-    if let Err(e) = run_tui(tree) {
+    let watcher = LibraryWatcher::spawn(db_path, library_root);
+    if let Err(e) = run_tui(tree, conn, options, config, watcher) {
         eprintln!("TUI error: {e}");
         std::process::exit(1);
     }
 }
 
-fn run_tui(tree: orchestra_core::models::track::LibraryTree) -> anyhow::Result<()> {
+fn run_tui(
+    tree: orchestra_core::models::track::LibraryTree,
+    conn: rusqlite::Connection,
+    options: Options,
+    config: Config,
+    watcher: LibraryWatcher,
+) -> anyhow::Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(tree);
+    let picker = art::detect_picker();
+    let mut app = App::new(tree, conn, options, config, picker, watcher);
 
     let result = event_loop(&mut terminal, &mut app);
 
@@ -140,13 +209,16 @@ fn event_loop(
         // Draw frame.
         terminal.draw(|f| ui::draw(f, app))?;
 
-        // Poll for keyboard events (~60 fps).
+        // Poll for keyboard and mouse events (~60 fps).
         if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(key) = event::read()? {
+            match event::read()? {
                 // Only handle key-press events (not repeat or release on Windows).
-                if key.kind == KeyEventKind::Press {
-                    app.handle_key(key.code);
-                }
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                Event::Mouse(mouse) => app.handle_mouse(mouse),
+                // The next draw reflows the layout to the new size; clearing
+                // drops anything (e.g. album art) drawn for the old one.
+                Event::Resize(..) => terminal.clear()?,
+                _ => {}
             }
         }
 
@@ -0,0 +1,109 @@
+use ratatui::layout::{Position, Rect};
+
+use crate::app::Pane;
+
+/// A list pane as drawn in the last frame.
+#[derive(Debug, Clone, Copy)]
+struct PaneArea {
+    pane: Pane,
+    area: Rect,
+    /// Index of the first visible row.
+    offset: usize,
+}
+
+/// What is under the mouse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// Row `index` of a list pane.
+    Row { pane: Pane, index: usize },
+    /// A pane's border or the empty space below its last row.
+    Pane(Pane),
+    /// The progress gauge, at this fraction of the track.
+    Gauge(f64),
+}
+
+/// Where the panes and the progress gauge were drawn last frame, so mouse
+/// positions can be mapped back to what the user clicked.
+#[derive(Debug, Default)]
+pub struct HitMap {
+    panes: Vec<PaneArea>,
+    gauge: Option<Rect>,
+}
+
+impl HitMap {
+    /// Forgets the previous frame. Call at the start of every draw.
+    pub fn clear(&mut self) {
+        self.panes.clear();
+        self.gauge = None;
+    }
+
+    /// Records a bordered list pane drawn into `area`, scrolled to `offset`.
+    pub fn add_pane(&mut self, pane: Pane, area: Rect, offset: usize) {
+        self.panes.push(PaneArea { pane, area, offset });
+    }
+
+    /// Records the filled/unfilled line of the progress gauge.
+    pub fn set_gauge(&mut self, area: Rect) {
+        self.gauge = Some(area);
+    }
+
+    pub fn hit(&self, column: u16, row: u16) -> Option<Target> {
+        let pos = Position::new(column, row);
+        if let Some(gauge) = self.gauge.filter(|g| g.contains(pos)) {
+            let ratio = f64::from(column - gauge.x) / f64::from(gauge.width.max(1));
+            return Some(Target::Gauge(ratio));
+        }
+
+        let hit = self.panes.iter().find(|p| p.area.contains(pos))?;
+        let inner = Rect {
+            x: hit.area.x + 1,
+            y: hit.area.y + 1,
+            width: hit.area.width.saturating_sub(2),
+            height: hit.area.height.saturating_sub(2),
+        };
+        if inner.contains(pos) {
+            Some(Target::Row {
+                pane: hit.pane,
+                index: hit.offset + usize::from(row - inner.y),
+            })
+        } else {
+            Some(Target::Pane(hit.pane))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let mut map = HitMap::default();
+        map.add_pane(Pane::Artists, Rect::new(0, 1, 20, 10), 0);
+        map.add_pane(Pane::Tracks, Rect::new(20, 1, 40, 10), 5);
+        map.set_gauge(Rect::new(30, 14, 50, 1));
+
+        assert_eq!(
+            map.hit(3, 2),
+            Some(Target::Row {
+                pane: Pane::Artists,
+                index: 0
+            })
+        );
+        assert_eq!(
+            map.hit(25, 4),
+            Some(Target::Row {
+                pane: Pane::Tracks,
+                index: 7
+            })
+        );
+        assert_eq!(map.hit(20, 4), Some(Target::Pane(Pane::Tracks)));
+        assert_eq!(map.hit(25, 1), Some(Target::Pane(Pane::Tracks)));
+        assert_eq!(map.hit(30, 14), Some(Target::Gauge(0.0)));
+        assert_eq!(map.hit(55, 14), Some(Target::Gauge(0.5)));
+        assert_eq!(map.hit(70, 0), None);
+
+        map.clear();
+        assert_eq!(map.hit(3, 2), None);
+    }
+}
@@ -1,27 +1,74 @@
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::Duration;
 
-use rodio::{Decoder, OutputStream, Sink};
+use rodio::{Decoder, OutputStream, Sink, Source};
+
+/// How often the audio thread checks whether the sink moved on to the next track.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A file to play and the amplitude factor it plays at, on top of the
+/// volume (its ReplayGain adjustment).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayItem {
+    pub path: String,
+    pub gain: f32,
+}
 
 pub enum PlayerCmd {
-    Play(String),
+    /// Start `item` now and queue `next` behind it for gapless playback.
+    Play {
+        item: PlayItem,
+        next: Option<PlayItem>,
+    },
+    /// Replace the track queued behind the current one. Only the gain is
+    /// updated if it is already queued.
+    SetNext(Option<PlayItem>),
+    /// Change the gain of the playing track.
+    SetGain(f32),
+    /// Jump to an absolute position in the current track.
+    Seek(Duration),
     Pause,
     Resume,
     SetVolume(f32),
     Stop,
 }
 
+pub enum PlayerEvent {
+    Error(String),
+    /// The current track played to the end. `next_started` is true when the
+    /// queued next track took over without a gap.
+    TrackEnded {
+        next_started: bool,
+    },
+}
+
+/// A source appended to the sink. Cancelling it makes it end at once, which
+/// is how a queued next track is withdrawn without touching the playing one.
+struct Queued {
+    path: String,
+    cancelled: Arc<AtomicBool>,
+    /// Bits of the `f32` gain, applied by the source as it plays.
+    gain: Arc<AtomicU32>,
+}
+
 pub struct PlayerHandle {
     sender: mpsc::Sender<PlayerCmd>,
-    pub errors: mpsc::Receiver<String>,
+    pub events: mpsc::Receiver<PlayerEvent>,
+    /// Elapsed time in the current track, in milliseconds.
+    position_ms: Arc<AtomicU64>,
 }
 
 impl PlayerHandle {
     pub fn spawn() -> Self {
         let (cmd_tx, cmd_rx) = mpsc::channel::<PlayerCmd>();
-        let (err_tx, err_rx) = mpsc::channel::<String>();
+        let (event_tx, event_rx) = mpsc::channel::<PlayerEvent>();
+        let position_ms = Arc::new(AtomicU64::new(0));
+        let thread_position = position_ms.clone();
 
         thread::spawn(move || {
             // Create the output stream on this thread. Keep _stream alive for
@@ -29,55 +76,101 @@ impl PlayerHandle {
             let (_stream, stream_handle) = match OutputStream::try_default() {
                 Ok(pair) => pair,
                 Err(e) => {
-                    let _ = err_tx.send(format!("Audio device error: {e}"));
+                    let _ = event_tx.send(PlayerEvent::Error(format!("Audio device error: {e}")));
                     return;
                 }
             };
 
             let mut current_sink: Option<Sink> = None;
+            // Mirrors the sources in the sink, oldest (playing) first.
+            let mut queued: VecDeque<Queued> = VecDeque::new();
             let mut current_volume: f32 = 1.0;
 
-            while let Ok(cmd) = cmd_rx.recv() {
+            loop {
+                let cmd = cmd_rx.recv_timeout(POLL_INTERVAL);
+                if let Some(ref sink) = current_sink {
+                    report_finished(sink, &mut queued, &event_tx);
+                    thread_position.store(sink.get_pos().as_millis() as u64, Ordering::Relaxed);
+                }
+
+                let cmd = match cmd {
+                    Ok(cmd) => cmd,
+                    Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                };
+
                 match cmd {
-                    PlayerCmd::Play(path) => {
+                    PlayerCmd::Play { item, next } => {
                         // Drop old sink to stop previous playback.
                         current_sink = None;
-
-                        let file = match File::open(&path) {
-                            Ok(f) => f,
-                            Err(e) => {
-                                let _ = err_tx.send(format!("Cannot open file \"{path}\": {e}"));
-                                continue;
-                            }
-                        };
-
-                        let decode_result =
-                            std::panic::catch_unwind(|| Decoder::new(BufReader::new(file)));
-                        let decoder = match decode_result {
-                            Ok(Ok(d)) => d,
-                            Ok(Err(e)) => {
-                                let _ = err_tx.send(format!("Cannot decode \"{path}\": {e}"));
-                                continue;
-                            }
-                            Err(_) => {
-                                let _ = err_tx
-                                    .send(format!("Cannot decode \"{path}\": unsupported format"));
-                                continue;
-                            }
-                        };
+                        queued.clear();
+                        thread_position.store(0, Ordering::Relaxed);
 
                         let sink = match Sink::try_new(&stream_handle) {
                             Ok(s) => s,
                             Err(e) => {
-                                let _ = err_tx.send(format!("Cannot create sink: {e}"));
+                                let _ = event_tx
+                                    .send(PlayerEvent::Error(format!("Cannot create sink: {e}")));
                                 continue;
                             }
                         };
                         sink.set_volume(current_volume);
-                        sink.append(decoder);
+                        if let Err(e) = append(&sink, &item, &mut queued) {
+                            let _ = event_tx.send(PlayerEvent::Error(e));
+                            continue;
+                        }
+                        if let Some(next) = next {
+                            if let Err(e) = append(&sink, &next, &mut queued) {
+                                let _ = event_tx.send(PlayerEvent::Error(e));
+                            }
+                        }
                         sink.play();
                         current_sink = Some(sink);
                     }
+                    PlayerCmd::SetNext(next) => {
+                        let Some(ref sink) = current_sink else {
+                            continue;
+                        };
+                        if queued.is_empty() {
+                            continue;
+                        }
+                        let pending = queued
+                            .iter()
+                            .skip(1)
+                            .find(|q| !q.cancelled.load(Ordering::SeqCst));
+                        if let (Some(pending), Some(next)) = (pending, &next) {
+                            if pending.path == next.path {
+                                pending.gain.store(next.gain.to_bits(), Ordering::SeqCst);
+                                continue;
+                            }
+                        }
+                        if let Some(pending) = pending {
+                            pending.cancelled.store(true, Ordering::SeqCst);
+                        }
+                        if let Some(next) = next {
+                            if let Err(e) = append(sink, &next, &mut queued) {
+                                let _ = event_tx.send(PlayerEvent::Error(e));
+                            }
+                        }
+                    }
+                    PlayerCmd::SetGain(gain) => {
+                        if let Some(current) = queued.front() {
+                            current.gain.store(gain.to_bits(), Ordering::SeqCst);
+                        }
+                    }
+                    PlayerCmd::Seek(pos) => {
+                        if let Some(ref s) = current_sink {
+                            match s.try_seek(pos) {
+                                Ok(()) => {
+                                    thread_position.store(pos.as_millis() as u64, Ordering::Relaxed)
+                                }
+                                Err(e) => {
+                                    let _ = event_tx
+                                        .send(PlayerEvent::Error(format!("Cannot seek: {e}")));
+                                }
+                            }
+                        }
+                    }
                     PlayerCmd::Pause => {
                         if let Some(ref s) = current_sink {
                             s.pause();
@@ -96,6 +189,8 @@ impl PlayerHandle {
                     }
                     PlayerCmd::Stop => {
                         current_sink = None;
+                        queued.clear();
+                        thread_position.store(0, Ordering::Relaxed);
                     }
                 }
             }
@@ -103,12 +198,32 @@ impl PlayerHandle {
 
         PlayerHandle {
             sender: cmd_tx,
-            errors: err_rx,
+            events: event_rx,
+            position_ms,
         }
     }
 
-    pub fn play(&self, path: String) {
-        let _ = self.sender.send(PlayerCmd::Play(path));
+    pub fn play(&self, item: PlayItem, next: Option<PlayItem>) {
+        // Reset now so the old track's position is never read for the new one.
+        self.position_ms.store(0, Ordering::Relaxed);
+        let _ = self.sender.send(PlayerCmd::Play { item, next });
+    }
+
+    pub fn set_next(&self, next: Option<PlayItem>) {
+        let _ = self.sender.send(PlayerCmd::SetNext(next));
+    }
+
+    pub fn set_gain(&self, gain: f32) {
+        let _ = self.sender.send(PlayerCmd::SetGain(gain));
+    }
+
+    pub fn seek(&self, pos: Duration) {
+        let _ = self.sender.send(PlayerCmd::Seek(pos));
+    }
+
+    /// Elapsed time in the current track, refreshed every `POLL_INTERVAL`.
+    pub fn position(&self) -> Duration {
+        Duration::from_millis(self.position_ms.load(Ordering::Relaxed))
     }
 
     pub fn pause(&self) {
@@ -127,3 +242,55 @@ impl PlayerHandle {
         let _ = self.sender.send(PlayerCmd::Stop);
     }
 }
+
+/// Decodes `item` and appends it to the sink behind whatever is queued.
+fn append(sink: &Sink, item: &PlayItem, queued: &mut VecDeque<Queued>) -> Result<(), String> {
+    let path = &item.path;
+    let file = File::open(path).map_err(|e| format!("Cannot open file \"{path}\": {e}"))?;
+
+    let decoder = match std::panic::catch_unwind(|| Decoder::new(BufReader::new(file))) {
+        Ok(Ok(d)) => d,
+        Ok(Err(e)) => return Err(format!("Cannot decode \"{path}\": {e}")),
+        Err(_) => return Err(format!("Cannot decode \"{path}\": unsupported format")),
+    };
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let gain = Arc::new(AtomicU32::new(item.gain.to_bits()));
+    let flag = cancelled.clone();
+    let factor = gain.clone();
+    sink.append(decoder.amplify(item.gain).stoppable().periodic_access(
+        Duration::from_millis(5),
+        move |src| {
+            if flag.load(Ordering::SeqCst) {
+                src.stop();
+            }
+            src.inner_mut()
+                .set_factor(f32::from_bits(factor.load(Ordering::SeqCst)));
+        },
+    ));
+    queued.push_back(Queued {
+        path: path.to_string(),
+        cancelled,
+        gain,
+    });
+    Ok(())
+}
+
+/// Pops the sources the sink has finished and reports each track that ended
+/// on its own. Cancelled sources end silently.
+fn report_finished(
+    sink: &Sink,
+    queued: &mut VecDeque<Queued>,
+    event_tx: &mpsc::Sender<PlayerEvent>,
+) {
+    while queued.len() > sink.len() {
+        let Some(done) = queued.pop_front() else {
+            break;
+        };
+        if done.cancelled.load(Ordering::SeqCst) {
+            continue;
+        }
+        let next_started = queued.iter().any(|q| !q.cancelled.load(Ordering::SeqCst));
+        let _ = event_tx.send(PlayerEvent::TrackEnded { next_started });
+    }
+}
@@ -0,0 +1,293 @@
+use orchestra_core::models::track::Track;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    All,
+    One,
+}
+
+impl RepeatMode {
+    pub fn cycle(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "",
+            RepeatMode::All => "repeat all",
+            RepeatMode::One => "repeat one",
+        }
+    }
+}
+
+/// The play queue. Shuffle reorders the upcoming tracks in place, so the
+/// queue pane always shows the real play order.
+#[derive(Default)]
+pub struct Queue {
+    tracks: Vec<Track>,
+    current: Option<usize>,
+    pub repeat: RepeatMode,
+    pub shuffle: bool,
+}
+
+impl Queue {
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn current_index(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn current(&self) -> Option<&Track> {
+        self.current.and_then(|i| self.tracks.get(i))
+    }
+
+    /// Replaces the queue with `tracks` and makes `start` the current track.
+    /// With shuffle on, `start` moves to the front and the rest is shuffled.
+    pub fn replace(&mut self, tracks: Vec<Track>, start: usize) {
+        self.tracks = tracks;
+        if start >= self.tracks.len() {
+            self.current = None;
+            return;
+        }
+        if self.shuffle {
+            self.tracks.swap(0, start);
+            fastrand::shuffle(&mut self.tracks[1..]);
+            self.current = Some(0);
+        } else {
+            self.current = Some(start);
+        }
+    }
+
+    pub fn push(&mut self, track: Track) {
+        self.tracks.push(track);
+    }
+
+    /// The track that will play once the current one finishes on its own.
+    pub fn peek_next(&self) -> Option<&Track> {
+        self.next_index(true).and_then(|i| self.tracks.get(i))
+    }
+
+    /// Moves to the track that follows a natural end of the current one.
+    pub fn advance(&mut self) -> Option<&Track> {
+        self.current = self.next_index(true);
+        self.current()
+    }
+
+    /// Moves to the next track on user request. Repeat-one does not apply.
+    /// Returns `None` and leaves the queue alone at the end of the queue.
+    pub fn skip_next(&mut self) -> Option<&Track> {
+        let next = self.next_index(false)?;
+        self.current = Some(next);
+        self.current()
+    }
+
+    /// Moves to the previous track, wrapping around with repeat-all.
+    pub fn skip_prev(&mut self) -> Option<&Track> {
+        let current = self.current?;
+        let prev = if current > 0 {
+            current - 1
+        } else if self.repeat == RepeatMode::All {
+            self.tracks.len() - 1
+        } else {
+            return None;
+        };
+        self.current = Some(prev);
+        self.current()
+    }
+
+    pub fn jump(&mut self, index: usize) -> Option<&Track> {
+        if index < self.tracks.len() {
+            self.current = Some(index);
+        }
+        self.current()
+    }
+
+    /// Removes the track at `index`. The playing track cannot be removed.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index >= self.tracks.len() || self.current == Some(index) {
+            return false;
+        }
+        self.tracks.remove(index);
+        if let Some(current) = self.current {
+            if index < current {
+                self.current = Some(current - 1);
+            }
+        }
+        true
+    }
+
+    /// Swaps the track at `index` with the one above it.
+    pub fn move_up(&mut self, index: usize) -> bool {
+        if index == 0 || index >= self.tracks.len() {
+            return false;
+        }
+        self.swap(index, index - 1);
+        true
+    }
+
+    /// Swaps the track at `index` with the one below it.
+    pub fn move_down(&mut self, index: usize) -> bool {
+        if index + 1 >= self.tracks.len() {
+            return false;
+        }
+        self.swap(index, index + 1);
+        true
+    }
+
+    pub fn toggle_shuffle(&mut self) {
+        self.shuffle = !self.shuffle;
+        if self.shuffle {
+            let start = self.current.map_or(0, |i| i + 1);
+            fastrand::shuffle(&mut self.tracks[start..]);
+        }
+    }
+
+    pub fn cycle_repeat(&mut self) {
+        self.repeat = self.repeat.cycle();
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.tracks.swap(a, b);
+        if self.current == Some(a) {
+            self.current = Some(b);
+        } else if self.current == Some(b) {
+            self.current = Some(a);
+        }
+    }
+
+    fn next_index(&self, natural_end: bool) -> Option<usize> {
+        let current = self.current?;
+        if natural_end && self.repeat == RepeatMode::One {
+            Some(current)
+        } else if current + 1 < self.tracks.len() {
+            Some(current + 1)
+        } else if self.repeat == RepeatMode::All {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(n: i64) -> Track {
+        Track {
+            id: Some(n),
+            file_path: format!("/music/{n}.flac"),
+            relative_path: format!("{n}.flac"),
+            library_root: "/music".to_string(),
+            title: None,
+            artist: None,
+            album_artist: None,
+            album: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: None,
+            format: "flac".to_string(),
+            file_size: 0,
+            modified_at: 0,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    fn queue_of(n: i64, start: usize) -> Queue {
+        let mut queue = Queue::default();
+        queue.replace((1..=n).map(track).collect(), start);
+        queue
+    }
+
+    fn current_id(queue: &Queue) -> Option<i64> {
+        queue.current().and_then(|t| t.id)
+    }
+
+    #[test]
+    fn test_advance_respects_repeat_modes() {
+        let mut queue = queue_of(3, 1);
+        assert_eq!(queue.peek_next().and_then(|t| t.id), Some(3));
+        assert_eq!(queue.advance().and_then(|t| t.id), Some(3));
+        assert!(queue.peek_next().is_none());
+
+        queue.repeat = RepeatMode::All;
+        assert_eq!(queue.advance().and_then(|t| t.id), Some(1));
+
+        queue.repeat = RepeatMode::One;
+        assert_eq!(queue.advance().and_then(|t| t.id), Some(1));
+        // Skipping by hand moves on even with repeat-one
+        assert_eq!(queue.skip_next().and_then(|t| t.id), Some(2));
+
+        queue.repeat = RepeatMode::Off;
+        queue.advance();
+        assert!(queue.advance().is_none());
+        assert!(queue.current().is_none());
+    }
+
+    #[test]
+    fn test_skip_at_ends_keeps_position() {
+        let mut queue = queue_of(2, 1);
+        assert!(queue.skip_next().is_none());
+        assert_eq!(current_id(&queue), Some(2));
+        queue.jump(0);
+        assert!(queue.skip_prev().is_none());
+        assert_eq!(current_id(&queue), Some(1));
+    }
+
+    #[test]
+    fn test_reorder_and_remove_track_current() {
+        let mut queue = queue_of(4, 1);
+        assert!(!queue.remove(1));
+        assert!(queue.remove(0));
+        assert_eq!(queue.current_index(), Some(0));
+        assert_eq!(current_id(&queue), Some(2));
+
+        assert!(queue.move_down(0));
+        assert_eq!(queue.current_index(), Some(1));
+        assert_eq!(current_id(&queue), Some(2));
+        assert_eq!(queue.peek_next().and_then(|t| t.id), Some(4));
+
+        assert!(queue.move_up(1));
+        assert_eq!(queue.current_index(), Some(0));
+        assert_eq!(queue.peek_next().and_then(|t| t.id), Some(3));
+        assert!(!queue.move_up(0));
+        assert!(!queue.move_down(2));
+    }
+
+    #[test]
+    fn test_shuffle_keeps_played_tracks_in_place() {
+        let mut queue = queue_of(20, 5);
+        queue.toggle_shuffle();
+        assert_eq!(current_id(&queue), Some(6));
+        let ids: Vec<i64> = queue.tracks().iter().filter_map(|t| t.id).collect();
+        assert_eq!(&ids[..6], &[1, 2, 3, 4, 5, 6]);
+        let mut rest = ids[6..].to_vec();
+        rest.sort();
+        assert_eq!(rest, (7..=20).collect::<Vec<_>>());
+
+        queue.replace((1..=5).map(track).collect(), 3);
+        assert_eq!(queue.current_index(), Some(0));
+        assert_eq!(current_id(&queue), Some(4));
+    }
+}
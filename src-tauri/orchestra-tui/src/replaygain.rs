@@ -0,0 +1,136 @@
+use orchestra_core::models::track::Track;
+
+/// Which ReplayGain value playback volume is normalized by.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    /// Every track at the same loudness.
+    Track,
+    /// Albums at the same loudness, keeping the level differences between
+    /// their tracks.
+    Album,
+}
+
+impl ReplayGainMode {
+    /// Parses `off`, `track` or `album`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(ReplayGainMode::Off),
+            "track" => Some(ReplayGainMode::Track),
+            "album" => Some(ReplayGainMode::Album),
+            _ => None,
+        }
+    }
+
+    pub fn cycle(self) -> Self {
+        match self {
+            ReplayGainMode::Off => ReplayGainMode::Track,
+            ReplayGainMode::Track => ReplayGainMode::Album,
+            ReplayGainMode::Album => ReplayGainMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReplayGainMode::Off => "",
+            ReplayGainMode::Track => "replaygain track",
+            ReplayGainMode::Album => "replaygain album",
+        }
+    }
+
+    /// Amplitude factor to play `track` at. Falls back to the other gain
+    /// when the preferred one is missing, and is capped so the track's peak
+    /// does not clip. Tracks without ReplayGain play unchanged.
+    pub fn factor(self, track: &Track) -> f32 {
+        let by_track = (track.replaygain_track_gain, track.replaygain_track_peak);
+        let by_album = (track.replaygain_album_gain, track.replaygain_album_peak);
+        let (gain, peak) = match self {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track if by_track.0.is_some() => by_track,
+            ReplayGainMode::Track => by_album,
+            ReplayGainMode::Album if by_album.0.is_some() => by_album,
+            ReplayGainMode::Album => by_track,
+        };
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+        let mut factor = 10f64.powf(gain / 20.0);
+        if let Some(peak) = peak.filter(|p| *p > 0.0) {
+            factor = factor.min(1.0 / peak);
+        }
+        factor as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(track: (Option<f64>, Option<f64>), album: (Option<f64>, Option<f64>)) -> Track {
+        Track {
+            id: None,
+            file_path: "/music/a.flac".to_string(),
+            relative_path: "a.flac".to_string(),
+            library_root: "/music".to_string(),
+            title: None,
+            artist: None,
+            album_artist: None,
+            album: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: None,
+            format: "flac".to_string(),
+            file_size: 0,
+            modified_at: 0,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: track.0,
+            replaygain_track_peak: track.1,
+            replaygain_album_gain: album.0,
+            replaygain_album_peak: album.1,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn test_factor() {
+        let t = track((Some(-6.0206), Some(0.5)), (Some(-12.0412), Some(0.9)));
+        assert_eq!(ReplayGainMode::Off.factor(&t), 1.0);
+        assert!(close(ReplayGainMode::Track.factor(&t), 0.5));
+        assert!(close(ReplayGainMode::Album.factor(&t), 0.25));
+
+        // Album mode falls back to the track gain, and vice versa
+        let t = track((Some(-6.0206), None), (None, None));
+        assert!(close(ReplayGainMode::Album.factor(&t), 0.5));
+        let t = track((None, None), (Some(-6.0206), None));
+        assert!(close(ReplayGainMode::Track.factor(&t), 0.5));
+
+        // Boosts are capped at the peak
+        let t = track((Some(6.0206), Some(0.8)), (None, None));
+        assert!(close(ReplayGainMode::Track.factor(&t), 1.25));
+
+        assert_eq!(
+            ReplayGainMode::Track.factor(&track((None, None), (None, None))),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ReplayGainMode::parse("Album"), Some(ReplayGainMode::Album));
+        assert_eq!(ReplayGainMode::parse(" off "), Some(ReplayGainMode::Off));
+        assert_eq!(ReplayGainMode::parse("loud"), None);
+    }
+}
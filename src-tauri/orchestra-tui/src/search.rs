@@ -0,0 +1,264 @@
+use orchestra_core::models::track::{LibraryTree, Track};
+use orchestra_core::rating::{self, RatingFilter};
+
+/// Stop collecting results after this many hits.
+const MAX_RESULTS: usize = 500;
+
+/// A matching track, addressed by its position in the library tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchHit {
+    pub artist: usize,
+    pub album: usize,
+    pub track: usize,
+}
+
+/// State of the `/` search.
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    /// True while the query is being typed.
+    pub editing: bool,
+    pub results: Vec<SearchHit>,
+    pub selected: usize,
+}
+
+impl Search {
+    /// Re-runs the query against `tree` and resets the selection.
+    pub fn update(&mut self, tree: &LibraryTree) {
+        self.results = search(tree, &self.query);
+        self.selected = 0;
+    }
+
+    pub fn selected_hit(&self) -> Option<SearchHit> {
+        self.results.get(self.selected).copied()
+    }
+}
+
+/// Lowercased, whitespace-separated query terms, without rating filters.
+pub fn terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter(|term| RatingFilter::parse(term).is_none())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Finds tracks where every query term appears in the title, album, artist
+/// or album artist, and that pass every rating filter (`rating>=4`), in
+/// library order.
+pub fn search(tree: &LibraryTree, query: &str) -> Vec<SearchHit> {
+    let terms = terms(query);
+    let (filters, _) = rating::split_query(query);
+    if terms.is_empty() && filters.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits = Vec::new();
+    for (ai, artist) in tree.artists.iter().enumerate() {
+        for (bi, album) in artist.albums.iter().enumerate() {
+            for (ti, track) in album.tracks.iter().enumerate() {
+                let haystack = haystack(track, &album.name, &artist.name);
+                if terms.iter().all(|t| haystack.contains(t.as_str()))
+                    && filters.iter().all(|f| f.matches(track.rating))
+                {
+                    hits.push(SearchHit {
+                        artist: ai,
+                        album: bi,
+                        track: ti,
+                    });
+                    if hits.len() >= MAX_RESULTS {
+                        return hits;
+                    }
+                }
+            }
+        }
+    }
+    hits
+}
+
+fn haystack(track: &Track, album: &str, artist: &str) -> String {
+    [
+        track.title.as_deref().unwrap_or(""),
+        track.album.as_deref().unwrap_or(album),
+        track.artist.as_deref().unwrap_or(""),
+        track.album_artist.as_deref().unwrap_or(artist),
+    ]
+    .join("\n")
+    .to_lowercase()
+}
+
+/// Finds the track with `id` in the tree.
+pub fn find_track(tree: &LibraryTree, id: i64) -> Option<SearchHit> {
+    tree.artists.iter().enumerate().find_map(|(ai, artist)| {
+        artist.albums.iter().enumerate().find_map(|(bi, album)| {
+            let ti = album.tracks.iter().position(|t| t.id == Some(id))?;
+            Some(SearchHit {
+                artist: ai,
+                album: bi,
+                track: ti,
+            })
+        })
+    })
+}
+
+/// Splits `text` into `(segment, is_match)` pieces, marking every
+/// case-insensitive occurrence of any term. Text whose lowercase form has a
+/// different byte length is returned unhighlighted.
+pub fn highlight<'a>(text: &'a str, terms: &[String]) -> Vec<(&'a str, bool)> {
+    let lower = text.to_lowercase();
+    if lower.len() != text.len() {
+        return vec![(text, false)];
+    }
+
+    let mut marked = vec![false; text.len()];
+    for term in terms.iter().filter(|t| !t.is_empty()) {
+        for (start, _) in lower.match_indices(term.as_str()) {
+            marked[start..start + term.len()].fill(true);
+        }
+    }
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for i in 1..=text.len() {
+        let boundary = i == text.len() || marked[i] != marked[start];
+        if boundary && text.is_char_boundary(i) {
+            pieces.push((&text[start..i], marked[start]));
+            start = i;
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orchestra_core::models::track::{AlbumNode, ArtistNode};
+
+    fn track(title: &str) -> Track {
+        Track {
+            id: None,
+            file_path: format!("/music/{title}.flac"),
+            relative_path: format!("{title}.flac"),
+            library_root: "/music".to_string(),
+            title: Some(title.to_string()),
+            artist: None,
+            album_artist: None,
+            album: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            duration_secs: None,
+            format: "flac".to_string(),
+            file_size: 0,
+            modified_at: 0,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    fn tree() -> LibraryTree {
+        LibraryTree {
+            root: "/music".to_string(),
+            artists: vec![
+                ArtistNode {
+                    id: None,
+                    name: "Miles Davis".to_string(),
+                    albums: vec![AlbumNode {
+                        id: None,
+                        name: "Kind of Blue".to_string(),
+                        year: None,
+                        tracks: vec![track("So What"), track("Blue in Green")],
+                    }],
+                },
+                ArtistNode {
+                    id: None,
+                    name: "Joni Mitchell".to_string(),
+                    albums: vec![AlbumNode {
+                        id: None,
+                        name: "Blue".to_string(),
+                        year: None,
+                        tracks: vec![track("River")],
+                    }],
+                },
+            ],
+            total_tracks: 3,
+        }
+    }
+
+    #[test]
+    fn test_search_matches_title_album_and_artist() {
+        let tree = tree();
+        let at = |a, b, t| SearchHit {
+            artist: a,
+            album: b,
+            track: t,
+        };
+
+        assert_eq!(search(&tree, "what"), vec![at(0, 0, 0)]);
+        assert_eq!(search(&tree, "BLUE").len(), 3);
+        assert_eq!(search(&tree, "joni"), vec![at(1, 0, 0)]);
+        // All terms must match, in any field
+        assert_eq!(search(&tree, "blue miles green"), vec![at(0, 0, 1)]);
+        assert!(search(&tree, "   ").is_empty());
+        assert!(search(&tree, "coltrane").is_empty());
+    }
+
+    #[test]
+    fn test_search_rating_filters() {
+        let mut tree = tree();
+        tree.artists[0].albums[0].tracks[1].rating = Some(100);
+        let hit = SearchHit {
+            artist: 0,
+            album: 0,
+            track: 1,
+        };
+
+        assert_eq!(search(&tree, "rating:5"), vec![hit]);
+        assert_eq!(search(&tree, "blue rating>=4"), vec![hit]);
+        assert_eq!(search(&tree, "blue rating<4").len(), 2);
+        assert_eq!(terms("Blue rating>=4"), ["blue"]);
+    }
+
+    #[test]
+    fn test_find_track() {
+        let mut tree = tree();
+        tree.artists[1].albums[0].tracks[0].id = Some(7);
+        assert_eq!(
+            find_track(&tree, 7),
+            Some(SearchHit {
+                artist: 1,
+                album: 0,
+                track: 0
+            })
+        );
+        assert_eq!(find_track(&tree, 8), None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let terms = terms("blue GR");
+        assert_eq!(
+            highlight("Blue in Green", &terms),
+            vec![
+                ("Blue", true),
+                (" in ", false),
+                ("Gr", true),
+                ("een", false)
+            ]
+        );
+        assert_eq!(highlight("River", &terms), vec![("River", false)]);
+        assert_eq!(highlight("", &terms), Vec::<(&str, bool)>::new());
+        assert_eq!(highlight("İstanbul", &terms), vec![("İstanbul", false)]);
+    }
+}
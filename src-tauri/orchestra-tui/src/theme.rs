@@ -0,0 +1,106 @@
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// Colors used across the UI.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Border of unfocused panes.
+    pub border: Color,
+    /// Border of the focused pane and popups.
+    pub border_focused: Color,
+    /// Text and background of the highlighted list row.
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    /// Search matches, the active view tab and the progress gauge.
+    pub accent: Color,
+    /// Secondary text: played queue entries, hints, the gauge's empty part.
+    pub dim: Color,
+    /// Error status line.
+    pub error: Color,
+    /// Non-error feedback in the status line.
+    pub notice: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            border: Color::DarkGray,
+            border_focused: Color::Yellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::Yellow,
+            accent: Color::Yellow,
+            dim: Color::DarkGray,
+            error: Color::Red,
+            notice: Color::Green,
+        }
+    }
+}
+
+/// The `[theme]` section of the config file. Every entry is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    border: Option<String>,
+    border_focused: Option<String>,
+    highlight_fg: Option<String>,
+    highlight_bg: Option<String>,
+    accent: Option<String>,
+    dim: Option<String>,
+    error: Option<String>,
+    notice: Option<String>,
+}
+
+impl Theme {
+    /// The default theme with the colors set in `config` applied. Colors are
+    /// names (`yellow`, `dark gray`), `#rrggbb` or a 256-color index.
+    pub fn from_config(config: &ThemeConfig) -> Result<Self, String> {
+        let mut theme = Theme::default();
+        let fields = [
+            ("border", &config.border, &mut theme.border),
+            (
+                "border_focused",
+                &config.border_focused,
+                &mut theme.border_focused,
+            ),
+            (
+                "highlight_fg",
+                &config.highlight_fg,
+                &mut theme.highlight_fg,
+            ),
+            (
+                "highlight_bg",
+                &config.highlight_bg,
+                &mut theme.highlight_bg,
+            ),
+            ("accent", &config.accent, &mut theme.accent),
+            ("dim", &config.dim, &mut theme.dim),
+            ("error", &config.error, &mut theme.error),
+            ("notice", &config.notice, &mut theme.notice),
+        ];
+        for (name, value, color) in fields {
+            if let Some(value) = value {
+                *color = Color::from_str(value)
+                    .map_err(|_| format!("theme.{name}: invalid color \"{value}\""))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    pub fn border(&self, focused: bool) -> Style {
+        Style::default().fg(if focused {
+            self.border_focused
+        } else {
+            self.border
+        })
+    }
+
+    /// Style of the highlighted row in a list.
+    pub fn highlight(&self) -> Style {
+        Style::default()
+            .fg(self.highlight_fg)
+            .bg(self.highlight_bg)
+            .add_modifier(Modifier::BOLD)
+    }
+}
@@ -1,20 +1,23 @@
 use ratatui::{
     layout::Rect,
-    style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem, ListState},
     Frame,
 };
 
 use orchestra_core::models::track::AlbumNode;
 
-/// Renders the Albums pane into `area`.
-pub fn render(f: &mut Frame, area: Rect, albums: &[AlbumNode], selected: usize, focused: bool) {
-    let border_color = if focused {
-        Color::Yellow
-    } else {
-        Color::DarkGray
-    };
+use crate::theme::Theme;
 
+/// Renders the Albums pane into `area`.
+/// Returns the index of the first visible row.
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    albums: &[AlbumNode],
+    selected: usize,
+    focused: bool,
+    theme: &Theme,
+) -> usize {
     let items: Vec<ListItem> = albums
         .iter()
         .map(|a| {
@@ -32,14 +35,9 @@ pub fn render(f: &mut Frame, area: Rect, albums: &[AlbumNode], selected: usize,
             Block::default()
                 .title("Albums")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(border_color)),
-        )
-        .highlight_style(
-            Style::default()
-                .fg(Color::Black)
-                .bg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
+                .border_style(theme.border(focused)),
         )
+        .highlight_style(theme.highlight())
         .highlight_symbol("> ");
 
     let mut state = ListState::default();
@@ -48,4 +46,5 @@ pub fn render(f: &mut Frame, area: Rect, albums: &[AlbumNode], selected: usize,
     }
 
     f.render_stateful_widget(list, area, &mut state);
+    state.offset()
 }
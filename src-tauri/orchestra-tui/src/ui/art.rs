@@ -0,0 +1,22 @@
+use ratatui::{
+    layout::Rect,
+    widgets::{Block, Borders},
+    Frame,
+};
+use ratatui_image::StatefulImage;
+
+use crate::art::AlbumArt;
+use crate::theme::Theme;
+
+/// Renders the playing album's cover into `area`, scaled to fit.
+pub fn render(f: &mut Frame, area: Rect, art: &mut AlbumArt, theme: &Theme) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border(false));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if let Some(protocol) = art.current() {
+        f.render_stateful_widget(StatefulImage::default(), inner, protocol);
+    }
+}
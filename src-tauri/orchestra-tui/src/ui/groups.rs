@@ -0,0 +1,43 @@
+use ratatui::{
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::theme::Theme;
+use crate::view::TrackGroup;
+
+/// Renders the playlist or genre list of a grouped view into `area`.
+/// Returns the index of the first visible row.
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    groups: &[TrackGroup],
+    selected: usize,
+    focused: bool,
+    theme: &Theme,
+) -> usize {
+    let items: Vec<ListItem> = groups
+        .iter()
+        .map(|g| ListItem::new(format!("{} ({})", g.name, g.tracks.len())))
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.border(focused)),
+        )
+        .highlight_style(theme.highlight())
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    if !groups.is_empty() {
+        state.select(Some(selected));
+    }
+
+    f.render_stateful_widget(list, area, &mut state);
+    state.offset()
+}
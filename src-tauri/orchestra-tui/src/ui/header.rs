@@ -0,0 +1,24 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    widgets::Tabs,
+    Frame,
+};
+
+use crate::theme::Theme;
+use crate::view::View;
+
+/// Renders the view switcher row into `area`.
+pub fn render(f: &mut Frame, area: Rect, current: View, theme: &Theme) {
+    let selected = View::ALL.iter().position(|v| *v == current).unwrap_or(0);
+    let tabs = Tabs::new(View::ALL.iter().map(|v| v.label()))
+        .select(selected)
+        .style(Style::default().fg(theme.dim))
+        .highlight_style(
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+        .divider("|");
+    f.render_widget(tabs, area);
+}
@@ -0,0 +1,44 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::layout;
+use crate::keymap::{Action, KeyMap};
+use crate::theme::Theme;
+
+/// Renders the key binding overlay, built from the active keymap.
+pub fn render(f: &mut Frame, keymap: &KeyMap, theme: &Theme) {
+    let area = layout::centered(f.area(), 60, 80);
+
+    let key_style = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let row = |keys: String, description: &'static str| {
+        Line::from(vec![
+            Span::styled(format!("  {keys:<16}"), key_style),
+            Span::raw(description),
+        ])
+    };
+
+    let mut lines: Vec<Line> = Action::ALL
+        .iter()
+        .filter_map(|&action| {
+            let keys = keymap.keys_for(action);
+            (!keys.is_empty()).then(|| row(keys.join(", "), action.description()))
+        })
+        .collect();
+    lines.push(row("0-9".to_string(), "Seek to 0%-90% of the track"));
+
+    let help = Paragraph::new(lines).block(
+        Block::default()
+            .title("Key bindings (any key to close)")
+            .borders(Borders::ALL)
+            .border_style(theme.border(true)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
@@ -3,26 +3,80 @@ use ratatui::{
     Frame,
 };
 
+use crate::app::Pane;
+
+/// Height of the album art inside its border, in rows.
+const ART_ROWS: u16 = 8;
+
 /// Splits the frame into:
-/// - `top`: full-width area for the 3 browser panes
-/// - `bottom`: 3-row bar for now-playing info
-pub fn split_frame(f: &Frame) -> (Rect, Rect) {
+/// - `header`: 1-row view switcher
+/// - `top`: full-width area for the current view's panes and the queue
+/// - `bottom`: bar for now-playing info and the progress gauge, 4 rows
+///   tall, or taller with room for album art when `with_art` is set
+pub fn split_frame(f: &Frame, with_art: bool) -> (Rect, Rect, Rect) {
+    let bottom = if with_art { ART_ROWS + 2 } else { 4 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(bottom),
+        ])
         .split(f.area());
+    (chunks[0], chunks[1], chunks[2])
+}
+
+/// Splits the top area into one column per pane. Every pane gets a quarter
+/// of the width except Tracks and Search, which take whatever is left.
+pub fn split_top(area: Rect, panes: &[Pane]) -> Vec<(Pane, Rect)> {
+    let constraints = panes.iter().map(|p| match p {
+        Pane::Tracks | Pane::Search => Constraint::Min(0),
+        _ => Constraint::Percentage(25),
+    });
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(area);
+    panes.iter().copied().zip(chunks.iter().copied()).collect()
+}
+
+/// Splits the lyrics pane off the right of the top area, a third of its
+/// width.
+pub fn split_lyrics(area: Rect) -> (Rect, Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Percentage(33)])
+        .split(area);
     (chunks[0], chunks[1])
 }
 
-/// Splits the top area into three equal columns.
-pub fn split_top(area: Rect) -> (Rect, Rect, Rect) {
+/// Splits the now-playing bar into the album art on the left and the
+/// status text on the right. Terminal cells are about twice as tall as they
+/// are wide, so the art gets two columns per row to stay square.
+pub fn split_now_playing(area: Rect) -> (Rect, Rect) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(ART_ROWS * 2 + 2), Constraint::Min(0)])
+        .split(area);
+    (chunks[0], chunks[1])
+}
+
+/// A rect of `pct_x` × `pct_y` percent of `area`, centered in it.
+pub fn centered(area: Rect, pct_x: u16, pct_y: u16) -> Rect {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(33),
-            Constraint::Percentage(34),
-            Constraint::Percentage(33),
+            Constraint::Percentage((100 - pct_y) / 2),
+            Constraint::Percentage(pct_y),
+            Constraint::Percentage((100 - pct_y) / 2),
         ])
         .split(area);
-    (chunks[0], chunks[1], chunks[2])
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - pct_x) / 2),
+            Constraint::Percentage(pct_x),
+            Constraint::Percentage((100 - pct_x) / 2),
+        ])
+        .split(rows[1])[1]
 }
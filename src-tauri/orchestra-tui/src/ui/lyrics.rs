@@ -0,0 +1,55 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::Style,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use orchestra_core::models::lyrics::Lyrics;
+
+use crate::theme::Theme;
+
+/// Renders the lyrics of the playing track into `area`. Synced lyrics
+/// highlight the line at `position_ms` and keep it in the middle of the
+/// pane; plain lyrics are shown from the top.
+pub fn render(f: &mut Frame, area: Rect, lyrics: Option<&Lyrics>, position_ms: u32, theme: &Theme) {
+    let block = Block::default()
+        .title("Lyrics")
+        .borders(Borders::ALL)
+        .border_style(theme.border(false));
+
+    let Some(lyrics) = lyrics else {
+        let empty = Paragraph::new("No lyrics")
+            .style(Style::default().fg(theme.dim))
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(empty, area);
+        return;
+    };
+
+    let current = lyrics.line_at(position_ms);
+    let items: Vec<ListItem> = lyrics
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let style = match current {
+                Some(c) if i < c => Style::default().fg(theme.dim),
+                _ => Style::default(),
+            };
+            ListItem::new(line.text.clone()).style(style)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(theme.highlight());
+
+    let mut state = ListState::default();
+    if let Some(current) = current {
+        let rows = area.height.saturating_sub(2) as usize;
+        *state.offset_mut() = current.saturating_sub(rows / 2);
+        state.select(Some(current));
+    }
+    f.render_stateful_widget(list, area, &mut state);
+}
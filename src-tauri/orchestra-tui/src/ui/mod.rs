@@ -1,52 +1,120 @@
 use ratatui::Frame;
 
 use crate::app::{App, Pane};
+use crate::view::View;
 
 pub mod albums;
+pub mod art;
 pub mod artists;
+pub mod groups;
+pub mod header;
+pub mod help;
 pub mod layout;
+pub mod lyrics;
 pub mod now_playing;
+pub mod playlist_picker;
+pub mod queue;
+pub mod search;
 pub mod tracks;
 
 /// Main draw function — called every frame.
-pub fn draw(f: &mut Frame, app: &App) {
-    let (top, bottom) = layout::split_frame(f);
-    let (artist_area, album_area, track_area) = layout::split_top(top);
-
-    let filtered = app.filtered_artists();
-
-    artists::render(
-        f,
-        artist_area,
-        &filtered,
-        app.selected_artist,
-        app.focused_pane == Pane::Artists,
-        app.filter_mode,
-        &app.filter_text,
-    );
-
-    albums::render(
-        f,
-        album_area,
-        app.current_albums(),
-        app.selected_album,
-        app.focused_pane == Pane::Albums,
-    );
-
-    tracks::render(
-        f,
-        track_area,
-        app.current_tracks(),
-        app.selected_track,
-        app.focused_pane == Pane::Tracks,
-    );
-
-    now_playing::render(
-        f,
-        bottom,
-        &app.now_playing,
-        app.volume,
-        &app.status_msg,
-        app.filter_mode,
-    );
+pub fn draw(f: &mut Frame, app: &mut App) {
+    let with_art = app.art.has_current();
+    let (header_area, top, bottom) = layout::split_frame(f, with_art);
+
+    header::render(f, header_area, app.view, &app.theme);
+
+    let top = if app.show_lyrics {
+        let (panes, lyrics_area) = layout::split_lyrics(top);
+        let position = app.player.position().as_millis().min(u32::MAX as u128) as u32;
+        lyrics::render(f, lyrics_area, app.lyrics.as_ref(), position, &app.theme);
+        panes
+    } else {
+        top
+    };
+
+    app.hit_map.clear();
+    for (pane, area) in layout::split_top(top, app.panes()) {
+        let focused = app.focused_pane == pane;
+        let offset = match pane {
+            Pane::Artists => artists::render(
+                f,
+                area,
+                &app.tree.artists,
+                app.selected_artist,
+                focused,
+                &app.theme,
+            ),
+            Pane::Albums => albums::render(
+                f,
+                area,
+                app.current_albums(),
+                app.selected_album,
+                focused,
+                &app.theme,
+            ),
+            Pane::Groups => groups::render(
+                f,
+                area,
+                app.view.label(),
+                &app.groups,
+                app.selected_group,
+                focused,
+                &app.theme,
+            ),
+            Pane::Tracks => {
+                let title = match app.view {
+                    View::Library => "Tracks",
+                    View::Playlists | View::Genres => app
+                        .groups
+                        .get(app.selected_group)
+                        .map_or("Tracks", |g| g.name.as_str()),
+                    _ => app.view.label(),
+                };
+                tracks::render(
+                    f,
+                    area,
+                    title,
+                    app.current_tracks(),
+                    app.selected_track,
+                    focused,
+                    &app.favorites,
+                    &app.theme,
+                )
+            }
+            Pane::Search => match app.search {
+                Some(ref search) => search::render(f, area, search, &app.tree, focused, &app.theme),
+                None => 0,
+            },
+            Pane::Queue => queue::render(
+                f,
+                area,
+                app.queue.tracks(),
+                app.queue.current_index(),
+                app.selected_queue,
+                focused,
+                &app.theme,
+            ),
+        };
+        app.hit_map.add_pane(pane, area, offset);
+    }
+
+    let gauge = if with_art {
+        let (art_area, info_area) = layout::split_now_playing(bottom);
+        art::render(f, art_area, &mut app.art, &app.theme);
+        now_playing::render(f, info_area, app)
+    } else {
+        now_playing::render(f, bottom, app)
+    };
+    if let Some(gauge) = gauge {
+        app.hit_map.set_gauge(gauge);
+    }
+
+    if let Some(ref picker) = app.picker {
+        playlist_picker::render(f, picker, &app.theme);
+    }
+
+    if app.show_help {
+        help::render(f, &app.keymap, &app.theme);
+    }
 }
@@ -1,49 +1,112 @@
+use std::time::Duration;
+
 use ratatui::{
-    layout::Rect,
-    style::{Color, Style},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
     text::Line,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, LineGauge, Paragraph},
     Frame,
 };
 
-use crate::app::NowPlaying;
+use crate::app::App;
+use crate::keymap::Action;
 
-/// Renders the now-playing bottom bar into `area`.
-pub fn render(
-    f: &mut Frame,
-    area: Rect,
-    now_playing: &Option<NowPlaying>,
-    volume: f32,
-    status_msg: &Option<String>,
-    filter_mode: bool,
-) {
-    let vol_pct = (volume * 100.0).round() as u32;
+/// Renders the now-playing bottom bar into `area`: a status line and, while
+/// a track is loaded, a progress gauge below it. Returns the area of the
+/// gauge's line, which can be clicked to seek.
+pub fn render(f: &mut Frame, area: Rect, app: &App) -> Option<Rect> {
+    let now_playing = &app.now_playing;
+    let queue = &app.queue;
+    let position = app.player.position();
+    let vol_pct = (app.volume * 100.0).round() as u32;
+    let theme = &app.theme;
 
-    let content: Line = if filter_mode {
+    let content: Line = if app.search.as_ref().is_some_and(|s| s.editing) {
         Line::styled(
-            "  Type to filter artists, Enter to confirm, Esc to cancel",
-            Style::default().fg(Color::Yellow),
+            "  Type to search titles, albums and artists (rating>=4 filters by stars), Enter to browse results, Esc to cancel",
+            Style::default().fg(theme.accent),
         )
-    } else if let Some(err) = status_msg {
-        Line::styled(format!("  {err}"), Style::default().fg(Color::Red))
+    } else if let Some(ref err) = app.status_msg {
+        Line::styled(format!("  {err}"), Style::default().fg(theme.error))
+    } else if let Some(ref notice) = app.notice {
+        Line::styled(format!("  {notice}"), Style::default().fg(theme.notice))
     } else if let Some(np) = now_playing {
         let icon = if np.is_paused { "\u{23F8}" } else { "\u{25B6}" };
+        let mut modes = Vec::new();
+        if queue.shuffle {
+            modes.push("shuffle");
+        }
+        if !queue.repeat.label().is_empty() {
+            modes.push(queue.repeat.label());
+        }
+        if !app.options.replaygain.label().is_empty() {
+            modes.push(app.options.replaygain.label());
+        }
+        let modes = if modes.is_empty() {
+            String::new()
+        } else {
+            format!("    [{}]", modes.join(", "))
+        };
         Line::raw(format!(
-            "  {} {} \u{2014} {}    Vol {}%",
-            icon, np.title, np.artist, vol_pct
+            "  {} {} \u{2014} {}    Vol {}%{}",
+            icon, np.title, np.artist, vol_pct, modes
         ))
     } else {
+        let help = match app.keymap.keys_for(Action::Help).first() {
+            Some(key) => format!("  ({key} = show key bindings)"),
+            None => String::new(),
+        };
         Line::styled(
-            "  No track playing  (/ = filter artists, Enter = play, Space = pause, n/p = next/prev, +/- = volume, q = quit)",
-            Style::default().fg(Color::DarkGray),
+            format!("  No track playing{help}"),
+            Style::default().fg(theme.dim),
         )
     };
 
-    let paragraph = Paragraph::new(content).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::DarkGray)),
-    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Length(1)])
+        .split(inner);
+
+    f.render_widget(Paragraph::new(content), rows[0]);
+
+    let np = now_playing.as_ref()?;
+    let (ratio, total) = match np.duration {
+        Some(d) => (
+            (position.as_secs_f64() / d.as_secs_f64()).clamp(0.0, 1.0),
+            format_time(d),
+        ),
+        None => (0.0, "--:--".to_string()),
+    };
+    let label = format!("  {} / {}", format_time(position), total);
+    // The gauge draws its line one column after the label.
+    let line_start = (label.chars().count() as u16 + 1).min(rows[1].width);
+    let gauge = LineGauge::default()
+        .filled_style(Style::default().fg(theme.accent))
+        .unfilled_style(Style::default().fg(theme.dim))
+        .label(label)
+        .ratio(ratio);
+    f.render_widget(gauge, rows[1]);
 
-    f.render_widget(paragraph, area);
+    let line = Rect {
+        x: rows[1].x + line_start,
+        width: rows[1].width - line_start,
+        ..rows[1]
+    };
+    np.duration.map(|_| line)
+}
+
+/// `m:ss`, or `h:mm:ss` for long tracks.
+fn format_time(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
 }
@@ -0,0 +1,41 @@
+use ratatui::{
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use super::layout;
+use crate::app::PlaylistPicker;
+use crate::theme::Theme;
+
+/// Renders the "add to playlist" popup centered over the frame.
+pub fn render(f: &mut Frame, picker: &PlaylistPicker, theme: &Theme) {
+    let area = layout::centered(f.area(), 50, 50);
+
+    let items: Vec<ListItem> = picker
+        .playlists
+        .iter()
+        .map(|p| ListItem::new(p.name.as_str()))
+        .collect();
+
+    let n = picker.track_ids.len();
+    let title = format!(
+        "Add {n} {} to playlist (Enter = add, Esc = cancel)",
+        if n == 1 { "track" } else { "tracks" }
+    );
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.border(true)),
+        )
+        .highlight_style(theme.highlight())
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
@@ -0,0 +1,59 @@
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use orchestra_core::models::track::Track;
+
+use crate::theme::Theme;
+
+/// Renders the Queue pane into `area`. The playing track is marked with ▶.
+/// Returns the index of the first visible row.
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    tracks: &[Track],
+    current: Option<usize>,
+    selected: usize,
+    focused: bool,
+    theme: &Theme,
+) -> usize {
+    let items: Vec<ListItem> = tracks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let title = t.title.as_deref().unwrap_or("Unknown Title");
+            let marker = if current == Some(i) {
+                "\u{25B6} "
+            } else {
+                "  "
+            };
+            let style = if current.is_some_and(|c| i < c) {
+                Style::default().fg(theme.dim)
+            } else {
+                Style::default()
+            };
+            ListItem::new(format!("{marker}{title}")).style(style)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!("Queue ({})", tracks.len()))
+                .borders(Borders::ALL)
+                .border_style(theme.border(focused)),
+        )
+        .highlight_style(theme.highlight())
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    if focused && !tracks.is_empty() {
+        state.select(Some(selected));
+    }
+
+    f.render_stateful_widget(list, area, &mut state);
+    state.offset()
+}
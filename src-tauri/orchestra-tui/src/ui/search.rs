@@ -0,0 +1,85 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use orchestra_core::models::track::LibraryTree;
+
+use crate::search::{self, Search};
+use crate::theme::Theme;
+
+/// Renders the search results pane into `area`. Matched text is highlighted;
+/// while the query is being typed the title shows a cursor.
+/// Returns the index of the first visible row.
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    search: &Search,
+    tree: &LibraryTree,
+    focused: bool,
+    theme: &Theme,
+) -> usize {
+    let title = if search.editing {
+        format!("Search: {}\u{2588}", search.query)
+    } else {
+        format!(
+            "Search: {} ({} results)",
+            search.query,
+            search.results.len()
+        )
+    };
+
+    let terms = search::terms(&search.query);
+    let match_style = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(theme.dim);
+
+    let items: Vec<ListItem> = search
+        .results
+        .iter()
+        .filter_map(|hit| {
+            let artist = tree.artists.get(hit.artist)?;
+            let album = artist.albums.get(hit.album)?;
+            let track = album.tracks.get(hit.track)?;
+            let fields = [
+                track.title.as_deref().unwrap_or("Unknown Title"),
+                track.artist.as_deref().unwrap_or(&artist.name),
+                track.album.as_deref().unwrap_or(&album.name),
+            ];
+
+            let mut spans = Vec::new();
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::styled(" \u{2014} ", dim));
+                }
+                let base = if i == 0 { Style::default() } else { dim };
+                for (text, matched) in search::highlight(field, &terms) {
+                    spans.push(Span::styled(text, if matched { match_style } else { base }));
+                }
+            }
+            Some(ListItem::new(Line::from(spans)))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.border(focused)),
+        )
+        .highlight_style(Style::default().bg(theme.dim))
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    if !search.results.is_empty() {
+        state.select(Some(search.selected));
+    }
+
+    f.render_stateful_widget(list, area, &mut state);
+    state.offset()
+}
@@ -1,20 +1,29 @@
+use std::collections::HashSet;
+
 use ratatui::{
     layout::Rect,
-    style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem, ListState},
     Frame,
 };
 
 use orchestra_core::models::track::Track;
+use orchestra_core::rating;
 
-/// Renders the Tracks pane into `area`.
-pub fn render(f: &mut Frame, area: Rect, tracks: &[Track], selected: usize, focused: bool) {
-    let border_color = if focused {
-        Color::Yellow
-    } else {
-        Color::DarkGray
-    };
+use crate::theme::Theme;
 
+/// Renders the Tracks pane into `area` under `title`. Favorited tracks are marked with ★,
+/// rated tracks end with their stars. Returns the index of the first visible row.
+#[allow(clippy::too_many_arguments)]
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    tracks: &[Track],
+    selected: usize,
+    focused: bool,
+    favorites: &HashSet<i64>,
+    theme: &Theme,
+) -> usize {
     let items: Vec<ListItem> = tracks
         .iter()
         .enumerate()
@@ -24,23 +33,30 @@ pub fn render(f: &mut Frame, area: Rect, tracks: &[Track], selected: usize, focu
                 .track_number
                 .map(|n| format!("{n}. "))
                 .unwrap_or_else(|| format!("{}. ", i + 1));
-            ListItem::new(format!("{num}{title}"))
+            let star = if t.id.is_some_and(|id| favorites.contains(&id)) {
+                " \u{2605}"
+            } else {
+                ""
+            };
+            let stars = match rating::stars(t.rating) {
+                0 => String::new(),
+                n => format!(
+                    "  {}",
+                    "\u{2605}".repeat(n as usize) + &"\u{2606}".repeat(5 - n as usize)
+                ),
+            };
+            ListItem::new(format!("{num}{title}{star}{stars}"))
         })
         .collect();
 
     let list = List::new(items)
         .block(
             Block::default()
-                .title("Tracks")
+                .title(title)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(border_color)),
-        )
-        .highlight_style(
-            Style::default()
-                .fg(Color::Black)
-                .bg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
+                .border_style(theme.border(focused)),
         )
+        .highlight_style(theme.highlight())
         .highlight_symbol("> ");
 
     let mut state = ListState::default();
@@ -49,4 +65,5 @@ pub fn render(f: &mut Frame, area: Rect, tracks: &[Track], selected: usize, focu
     }
 
     f.render_stateful_widget(list, area, &mut state);
+    state.offset()
 }
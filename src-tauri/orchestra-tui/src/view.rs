@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use orchestra_core::db::{favorite_repo, playlist_repo, recent_repo};
+use orchestra_core::error::AppError;
+use orchestra_core::models::track::{LibraryTree, Track};
+use rusqlite::Connection;
+
+use crate::app::Pane;
+
+/// How far back the Recently Added view looks.
+const RECENTLY_ADDED_DAYS: u32 = 30;
+/// Cap on the Recently Added / Recently Played lists.
+const RECENT_LIMIT: usize = 200;
+
+/// Top-level views, switched with `v` / `V`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Library,
+    Playlists,
+    Genres,
+    Favorites,
+    RecentlyAdded,
+    RecentlyPlayed,
+}
+
+impl View {
+    pub const ALL: [View; 6] = [
+        View::Library,
+        View::Playlists,
+        View::Genres,
+        View::Favorites,
+        View::RecentlyAdded,
+        View::RecentlyPlayed,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            View::Library => "Library",
+            View::Playlists => "Playlists",
+            View::Genres => "Genres",
+            View::Favorites => "Favorites",
+            View::RecentlyAdded => "Recently Added",
+            View::RecentlyPlayed => "Recently Played",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|v| *v == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn prev(self) -> Self {
+        let i = Self::ALL.iter().position(|v| *v == self).unwrap_or(0);
+        Self::ALL[(i + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// The panes shown in this view, in Tab order.
+    pub fn panes(self) -> &'static [Pane] {
+        match self {
+            View::Library => &[Pane::Artists, Pane::Albums, Pane::Tracks, Pane::Queue],
+            View::Playlists | View::Genres => &[Pane::Groups, Pane::Tracks, Pane::Queue],
+            View::Favorites | View::RecentlyAdded | View::RecentlyPlayed => {
+                &[Pane::Tracks, Pane::Queue]
+            }
+        }
+    }
+}
+
+/// A named list of tracks: a playlist, a genre, or the single list of a flat view.
+#[derive(Debug, Clone)]
+pub struct TrackGroup {
+    pub name: String,
+    pub tracks: Vec<Track>,
+}
+
+/// Loads the groups for a non-library view. The library view reads the tree
+/// directly and has no groups.
+pub fn load(
+    view: View,
+    conn: &Connection,
+    tree: &LibraryTree,
+) -> Result<Vec<TrackGroup>, AppError> {
+    let single = |tracks| {
+        vec![TrackGroup {
+            name: view.label().to_string(),
+            tracks,
+        }]
+    };
+    Ok(match view {
+        View::Library => Vec::new(),
+        View::Playlists => playlist_repo::list_playlists(conn)?
+            .into_iter()
+            .map(|p| {
+                Ok(TrackGroup {
+                    tracks: playlist_repo::get_playlist_tracks(conn, &p.id)?,
+                    name: p.name,
+                })
+            })
+            .collect::<Result<_, AppError>>()?,
+        View::Genres => group_by_genre(tree),
+        View::Favorites => single(favorite_repo::get_favorite_tracks(conn)?),
+        View::RecentlyAdded => single(recent_repo::get_recently_added(
+            conn,
+            RECENTLY_ADDED_DAYS,
+            RECENT_LIMIT,
+        )?),
+        View::RecentlyPlayed => single(recent_repo::get_recently_played(conn, RECENT_LIMIT)?),
+    })
+}
+
+/// Groups the library by genre, alphabetically, keeping tree order within
+/// each genre. Untagged tracks go under "Unknown Genre".
+pub fn group_by_genre(tree: &LibraryTree) -> Vec<TrackGroup> {
+    let mut genres: BTreeMap<String, Vec<Track>> = BTreeMap::new();
+    for track in tree
+        .artists
+        .iter()
+        .flat_map(|a| a.albums.iter())
+        .flat_map(|a| a.tracks.iter())
+    {
+        let genre = track
+            .genre
+            .as_deref()
+            .map(str::trim)
+            .filter(|g| !g.is_empty())
+            .unwrap_or("Unknown Genre");
+        genres
+            .entry(genre.to_string())
+            .or_default()
+            .push(track.clone());
+    }
+    genres
+        .into_iter()
+        .map(|(name, tracks)| TrackGroup { name, tracks })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orchestra_core::models::track::{AlbumNode, ArtistNode};
+
+    fn track(id: i64, genre: Option<&str>) -> Track {
+        Track {
+            id: Some(id),
+            file_path: format!("/music/{id}.flac"),
+            relative_path: format!("{id}.flac"),
+            library_root: "/music".to_string(),
+            title: None,
+            artist: None,
+            album_artist: None,
+            album: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: genre.map(str::to_string),
+            duration_secs: None,
+            format: "flac".to_string(),
+            file_size: 0,
+            modified_at: 0,
+            hash: None,
+            has_album_art: false,
+            bitrate: None,
+            scanned_at: 0,
+            audio_hash: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            rating: None,
+            album_id: None,
+            lyrics: None,
+        }
+    }
+
+    #[test]
+    fn test_group_by_genre() {
+        let tracks = vec![
+            track(1, Some("Rock")),
+            track(2, Some("Jazz")),
+            track(3, None),
+            track(4, Some("Rock")),
+            track(5, Some("  ")),
+        ];
+        let tree = LibraryTree {
+            root: "/music".to_string(),
+            artists: vec![ArtistNode {
+                id: None,
+                name: "A".to_string(),
+                albums: vec![AlbumNode {
+                    id: None,
+                    name: "B".to_string(),
+                    year: None,
+                    tracks,
+                }],
+            }],
+            total_tracks: 5,
+        };
+
+        let groups = group_by_genre(&tree);
+        let summary: Vec<(&str, Vec<i64>)> = groups
+            .iter()
+            .map(|g| {
+                (
+                    g.name.as_str(),
+                    g.tracks.iter().filter_map(|t| t.id).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Jazz", vec![2]),
+                ("Rock", vec![1, 4]),
+                ("Unknown Genre", vec![3, 5]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_view_cycle_and_panes() {
+        assert_eq!(View::Library.prev(), View::RecentlyPlayed);
+        assert_eq!(View::RecentlyPlayed.next(), View::Library);
+        for view in View::ALL {
+            assert_eq!(view.next().prev(), view);
+            assert_eq!(view.panes().last(), Some(&Pane::Queue));
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Manager;
+
+use orchestra_core::backup;
+use orchestra_core::db::pool::DbPool;
+use orchestra_core::db::settings_repo;
+use orchestra_core::error::AppError;
+use orchestra_core::models::backup::{BackupFile, ImportSummary};
+
+/// How often the background thread checks whether a backup is due.
+const AUTO_BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Keep this many backups when one is made by hand.
+const MANUAL_BACKUP_KEEP: usize = 7;
+
+/// Where database backups are kept: `backups/` in the app data directory.
+fn backups_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("backups"))
+        .map_err(|e| AppError::General(e.to_string()))
+}
+
+#[tauri::command]
+pub async fn export_user_data(db: tauri::State<'_, DbPool>, path: String) -> Result<(), AppError> {
+    let conn = db.read()?;
+    backup::export_to_file(&conn, Path::new(&path))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn import_user_data(
+    db: tauri::State<'_, DbPool>,
+    path: String,
+) -> Result<ImportSummary, AppError> {
+    let conn = db.write()?;
+    backup::import_from_file(&conn, Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn create_database_backup(
+    app: tauri::AppHandle,
+    db: tauri::State<'_, DbPool>,
+) -> Result<BackupFile, AppError> {
+    let dir = backups_dir(&app)?;
+    let conn = db.read()?;
+    let keep = settings_repo::get_setting(&conn, backup::AUTO_BACKUP_KEEP_SETTING)?
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(MANUAL_BACKUP_KEEP);
+    backup::create_backup(&conn, &dir, keep)
+}
+
+#[tauri::command]
+pub async fn list_database_backups(app: tauri::AppHandle) -> Result<Vec<BackupFile>, AppError> {
+    backup::list_backups(&backups_dir(&app)?)
+}
+
+/// Replaces the current database with a backup. The frontend should reload
+/// everything afterwards.
+#[tauri::command]
+pub async fn restore_database_backup(
+    db: tauri::State<'_, DbPool>,
+    path: String,
+) -> Result<(), AppError> {
+    let mut conn = db.write()?;
+    backup::restore_backup(&mut conn, Path::new(&path))
+}
+
+/// Starts a thread that makes a backup of `db_path` into `dir` whenever the
+/// configured interval has passed, using its own connection so it never
+/// waits on the UI's.
+pub fn spawn_auto_backup(db_path: PathBuf, dir: PathBuf) {
+    std::thread::spawn(move || loop {
+        let result = Connection::open(&db_path)
+            .map_err(AppError::from)
+            .and_then(|conn| backup::run_scheduled_backup(&conn, &dir));
+        if let Err(e) = result {
+            eprintln!("Automatic backup failed: {e}");
+        }
+        std::thread::sleep(AUTO_BACKUP_CHECK_INTERVAL);
+    });
+}
@@ -1,27 +1,25 @@
-use rusqlite::Connection;
 use std::path::Path;
 use std::sync::Mutex;
 use tauri::ipc::Channel;
 
 use crate::device::{detect, sync as device_sync};
 use crate::sync::progress::CancelToken;
+use orchestra_core::db::pool::DbPool;
 use orchestra_core::db::{device_repo, library_repo};
 use orchestra_core::error::AppError;
 use orchestra_core::models::device::{
-    AlbumSelection, AlbumSummary, ArtistSummary, DetectedVolume, DeviceWithStatus,
-    RegisterDeviceRequest,
+    AlbumSummary, ArtistSummary, DetectedVolume, DeviceWithStatus, RegisterDeviceRequest,
 };
 use orchestra_core::models::diff::DiffResult;
 use orchestra_core::models::progress::ProgressEvent;
+use orchestra_core::scrobble;
 
 #[tauri::command]
-pub async fn detect_volumes(
-    db: tauri::State<'_, Mutex<Connection>>,
-) -> Result<Vec<DetectedVolume>, AppError> {
+pub async fn detect_volumes(db: tauri::State<'_, DbPool>) -> Result<Vec<DetectedVolume>, AppError> {
     let mut volumes = detect::detect_usb_volumes()?;
 
     // Cross-reference with saved devices and update mount paths
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     for vol in &mut volumes {
         if let Ok(Some(device)) = device_repo::get_device_by_uuid(&conn, &vol.volume_uuid) {
             vol.already_registered = true;
@@ -37,10 +35,10 @@ pub async fn detect_volumes(
 
 #[tauri::command]
 pub async fn register_device(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: RegisterDeviceRequest,
 ) -> Result<DeviceWithStatus, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
 
     // Check if already registered by UUID
     let existing = device_repo::get_device_by_uuid(&conn, &request.volume_uuid)?;
@@ -83,10 +81,8 @@ pub async fn register_device(
 }
 
 #[tauri::command]
-pub async fn list_devices(
-    db: tauri::State<'_, Mutex<Connection>>,
-) -> Result<Vec<DeviceWithStatus>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn list_devices(db: tauri::State<'_, DbPool>) -> Result<Vec<DeviceWithStatus>, AppError> {
+    let conn = db.read()?;
     let devices = device_repo::list_devices(&conn)?;
 
     let mut result = Vec::new();
@@ -111,20 +107,20 @@ pub async fn list_devices(
 
 #[tauri::command]
 pub async fn delete_device(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     device_id: String,
 ) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     device_repo::delete_device(&conn, &device_id)
 }
 
 #[tauri::command]
 pub async fn set_device_artists(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     device_id: String,
-    artists: Vec<String>,
+    artists: Vec<i64>,
 ) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     // Verify device exists
     let _ = device_repo::get_device(&conn, &device_id)?;
     device_repo::set_selected_artists(&conn, &device_id, &artists)
@@ -132,12 +128,12 @@ pub async fn set_device_artists(
 
 #[tauri::command]
 pub async fn compute_device_diff(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     device_id: String,
     on_progress: Channel<ProgressEvent>,
 ) -> Result<DiffResult, AppError> {
     let (device, selected_artists, selected_albums, library_root, hash_cache) = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         let device = device_repo::get_device(&conn, &device_id)?;
         let artists = device_repo::get_selected_artists(&conn, &device.id)?;
         let albums = device_repo::get_selected_albums(&conn, &device.id)?;
@@ -169,7 +165,7 @@ pub async fn compute_device_diff(
 
     // Get tracks for selected artists and albums
     let tracks = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         library_repo::get_tracks_for_device(
             &conn,
             &library_root,
@@ -188,7 +184,7 @@ pub async fn compute_device_diff(
 
     // Persist updated cache (includes any new hashes computed during diff)
     {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.write()?;
         device_repo::save_file_cache(&conn, &device_id, &new_cache)?;
     }
 
@@ -197,14 +193,14 @@ pub async fn compute_device_diff(
 
 #[tauri::command]
 pub async fn execute_device_sync(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     cancel_token: tauri::State<'_, Mutex<CancelToken>>,
     device_id: String,
     diff_result: DiffResult,
     on_progress: Channel<ProgressEvent>,
 ) -> Result<usize, AppError> {
     let (device, library_root, pre_cache) = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         let device = device_repo::get_device(&conn, &device_id)?;
         let cache_map = device_repo::get_file_cache(&conn, &device_id)?;
         let cache_vec: Vec<_> = cache_map.into_values().collect();
@@ -251,32 +247,48 @@ pub async fn execute_device_sync(
 
     // Update last_synced_at and save updated cache
     {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.write()?;
         let now = chrono::Utc::now().timestamp();
         device_repo::update_last_synced(&conn, &device_id, now)?;
         device_repo::save_file_cache(&conn, &device_id, &post_cache)?;
     }
 
+    // Plays logged by the device. The log stays in place for other tools;
+    // entries already imported on an earlier sync count as duplicates.
+    let scrobbler_log = Path::new(mount_path).join(scrobble::SCROBBLER_LOG_FILE);
+    if scrobbler_log.is_file() {
+        let imported = scrobble::import_scrobbler_log_file(&*db.write()?, &scrobbler_log);
+        let event = match imported {
+            Ok(summary) => ProgressEvent::ScrobblesImported {
+                imported: summary.imported,
+                duplicates: summary.duplicates,
+                unmatched: summary.unmatched,
+            },
+            Err(e) => ProgressEvent::SyncError {
+                file: scrobbler_log.to_string_lossy().to_string(),
+                error: e.to_string(),
+            },
+        };
+        let _ = on_progress.send(event);
+    }
+
     Ok(count)
 }
 
 #[tauri::command]
 pub async fn set_device_albums(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     device_id: String,
-    albums: Vec<AlbumSelection>,
+    albums: Vec<i64>,
 ) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     let _ = device_repo::get_device(&conn, &device_id)?;
     device_repo::set_selected_albums(&conn, &device_id, &albums)
 }
 
 #[tauri::command]
-pub async fn eject_device(
-    db: tauri::State<'_, Mutex<Connection>>,
-    device_id: String,
-) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn eject_device(db: tauri::State<'_, DbPool>, device_id: String) -> Result<(), AppError> {
+    let conn = db.write()?;
     let device = device_repo::get_device(&conn, &device_id)?;
 
     let mount_path = device
@@ -297,10 +309,8 @@ pub async fn eject_device(
 }
 
 #[tauri::command]
-pub async fn list_artists(
-    db: tauri::State<'_, Mutex<Connection>>,
-) -> Result<Vec<ArtistSummary>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn list_artists(db: tauri::State<'_, DbPool>) -> Result<Vec<ArtistSummary>, AppError> {
+    let conn = db.read()?;
 
     let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = 'library_root'")?;
     let library_root: String = stmt
@@ -311,10 +321,8 @@ pub async fn list_artists(
 }
 
 #[tauri::command]
-pub async fn list_albums(
-    db: tauri::State<'_, Mutex<Connection>>,
-) -> Result<Vec<AlbumSummary>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn list_albums(db: tauri::State<'_, DbPool>) -> Result<Vec<AlbumSummary>, AppError> {
+    let conn = db.read()?;
 
     let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = 'library_root'")?;
     let library_root: String = stmt
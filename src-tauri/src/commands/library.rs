@@ -1,20 +1,25 @@
-use rusqlite::Connection;
 use std::collections::HashSet;
 use std::path::Path;
-use std::sync::Mutex;
 use tauri::ipc::Channel;
 use walkdir::WalkDir;
 
-use orchestra_core::db::library_repo;
+use orchestra_core::db::pool::{self, DbPool};
+use orchestra_core::db::{
+    album_repo, favorite_repo, library_repo, playlist_repo, recent_repo, settings_repo, stats_repo,
+};
 use orchestra_core::error::AppError;
-use orchestra_core::models::duplicate::DuplicateResult;
+use orchestra_core::models::duplicate::{DuplicateResult, KeeperSelection};
 use orchestra_core::models::progress::ProgressEvent;
-use orchestra_core::models::track::{is_audio_file, LibraryStats, LibraryTree, Track};
-use orchestra_core::scanner::{hasher, metadata, walker};
+use orchestra_core::models::stats::{
+    ListeningStreaks, ListeningTime, PlayStat, StatsPeriod, TrackPlayStats,
+};
+use orchestra_core::models::track::{is_audio_file, HashKind, LibraryStats, LibraryTree, Track};
+use orchestra_core::scanner::{fingerprint, hasher, loudness, lyrics, metadata, walker};
+use orchestra_core::{dedupe, trash};
 
 #[tauri::command]
 pub async fn scan_directory(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     path: String,
     on_progress: Channel<ProgressEvent>,
 ) -> Result<usize, AppError> {
@@ -28,7 +33,7 @@ pub async fn scan_directory(
 
     // Load known directories from DB
     let known_dirs = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         library_repo::get_known_directories(&conn, &path)?
     };
 
@@ -55,7 +60,7 @@ pub async fn scan_directory(
 
     // Process new directories — walk only those dirs for audio files and extract metadata
     {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let mut pending: Vec<Track> = Vec::new();
 
         for (i, dir) in new_dirs.iter().enumerate() {
             let dir_path = root.join(dir);
@@ -86,8 +91,11 @@ pub async fn scan_directory(
                 });
                 match metadata::extract_metadata(file_path, root) {
                     Ok(track) => {
-                        library_repo::upsert_track(&conn, &track)?;
+                        pending.push(track);
                         new_track_count += 1;
+                        if pending.len() >= pool::WRITE_BATCH_SIZE {
+                            flush_tracks(&db, &mut pending)?;
+                        }
                     }
                     Err(e) => {
                         eprintln!("Failed to read metadata for {}: {}", file_path.display(), e);
@@ -96,10 +104,14 @@ pub async fn scan_directory(
             }
         }
 
+        flush_tracks(&db, &mut pending)?;
+
         // Remove tracks from deleted directories
+        let conn = db.write()?;
         for dir in &removed_dirs {
             library_repo::remove_tracks_by_directory(&conn, &path, dir)?;
         }
+        album_repo::refresh(&conn)?;
     }
 
     // Signal frontend to reload tree after phase 1
@@ -113,17 +125,21 @@ pub async fn scan_directory(
 
     // ── Phase 2: Per-file incremental scan of existing directories ──
     let fingerprints = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         library_repo::get_track_fingerprints(&conn, &path)?
     };
 
     let mut files_processed: usize = 0;
-    let mut all_file_paths: Vec<String> = Vec::new();
+    let mut seen_paths: Vec<String> = Vec::new();
     let mut phase2_tracks: Vec<Track> = Vec::new();
+    let scan_id = library_repo::begin_seen_paths(&*db.write()?)?;
 
     for file_path in walker::walk_directory_iter(root, &[]) {
         let path_str = file_path.to_string_lossy().to_string();
-        all_file_paths.push(path_str.clone());
+        seen_paths.push(path_str.clone());
+        if seen_paths.len() >= pool::WRITE_BATCH_SIZE {
+            flush_seen_paths(&db, scan_id, &mut seen_paths)?;
+        }
 
         // Skip files in new directories (already processed in phase 1)
         let rel = file_path.strip_prefix(root).unwrap_or(&file_path);
@@ -161,8 +177,15 @@ pub async fn scan_directory(
                 .map(|d| d.as_secs() as i64)
                 .unwrap_or(0);
 
-            if let Some(&(db_size, db_mtime)) = fingerprints.get(&path_str) {
-                if fs_size == db_size && fs_mtime == db_mtime {
+            if let Some(&(db_size, db_mtime, has_lyrics)) = fingerprints.get(&path_str) {
+                // Re-read files scanned before lyrics were recorded, and
+                // files without lyrics that have since gained an `.lrc`
+                let lyrics_current = match has_lyrics {
+                    Some(true) => true,
+                    Some(false) => !lyrics::sidecar_path(&file_path).exists(),
+                    None => false,
+                };
+                if fs_size == db_size && fs_mtime == db_mtime && lyrics_current {
                     continue;
                 }
             }
@@ -172,6 +195,9 @@ pub async fn scan_directory(
         match metadata::extract_metadata(&file_path, root) {
             Ok(track) => {
                 phase2_tracks.push(track);
+                if phase2_tracks.len() >= pool::WRITE_BATCH_SIZE {
+                    flush_tracks(&db, &mut phase2_tracks)?;
+                }
             }
             Err(e) => {
                 eprintln!("Failed to read metadata for {}: {}", file_path.display(), e);
@@ -180,14 +206,12 @@ pub async fn scan_directory(
     }
 
     // Write phase 2 changes to DB
+    flush_tracks(&db, &mut phase2_tracks)?;
+    flush_seen_paths(&db, scan_id, &mut seen_paths)?;
     {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-
-        for track in &phase2_tracks {
-            library_repo::upsert_track(&conn, track)?;
-        }
-
-        library_repo::remove_tracks_not_in(&conn, &path, &all_file_paths)?;
+        let conn = db.write()?;
+        library_repo::remove_unseen_tracks(&conn, scan_id, &path)?;
+        album_repo::refresh(&conn)?;
     }
 
     let total = new_track_count + files_processed;
@@ -200,55 +224,153 @@ pub async fn scan_directory(
     Ok(total)
 }
 
+/// Upserts `pending` in batched transactions and empties it, so scans and
+/// imports hold the writer for one batch at a time.
+fn flush_tracks(db: &DbPool, pending: &mut Vec<Track>) -> Result<(), AppError> {
+    db.write_in_batches(pending, pool::WRITE_BATCH_SIZE, |conn, batch| {
+        library_repo::upsert_tracks(conn, batch).map(|_| ())
+    })?;
+    pending.clear();
+    Ok(())
+}
+
+/// Records `paths` as found by scan `scan_id` and empties it.
+fn flush_seen_paths(db: &DbPool, scan_id: i64, paths: &mut Vec<String>) -> Result<(), AppError> {
+    db.write_in_batches(paths, pool::WRITE_BATCH_SIZE, |conn, batch| {
+        library_repo::mark_paths_seen(conn, scan_id, batch)
+    })?;
+    paths.clear();
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_library_tree(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     root: String,
 ) -> Result<LibraryTree, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     library_repo::get_library_tree(&conn, &root)
 }
 
 #[tauri::command]
 pub async fn search_library(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     query: String,
 ) -> Result<Vec<Track>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     library_repo::search_tracks(&conn, &query)
 }
 
 #[tauri::command]
 pub async fn get_incomplete_tracks(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     root: String,
 ) -> Result<Vec<Track>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     library_repo::get_incomplete_tracks(&conn, &root)
 }
 
 #[tauri::command]
 pub async fn get_library_stats(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     root: String,
 ) -> Result<LibraryStats, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     library_repo::get_library_stats(&conn, &root)
 }
 
+/// Most played tracks over the last `days` days (all time if omitted).
+#[tauri::command]
+pub async fn get_top_tracks(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<TrackPlayStats>, AppError> {
+    let conn = db.read()?;
+    stats_repo::top_tracks(&conn, &root, days, limit)
+}
+
+#[tauri::command]
+pub async fn get_top_artists(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<PlayStat>, AppError> {
+    let conn = db.read()?;
+    stats_repo::top_artists(&conn, &root, days, limit)
+}
+
+#[tauri::command]
+pub async fn get_top_albums(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<PlayStat>, AppError> {
+    let conn = db.read()?;
+    stats_repo::top_albums(&conn, &root, days, limit)
+}
+
+#[tauri::command]
+pub async fn get_top_genres(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    days: Option<u32>,
+    limit: usize,
+) -> Result<Vec<PlayStat>, AppError> {
+    let conn = db.read()?;
+    stats_repo::top_genres(&conn, &root, days, limit)
+}
+
+#[tauri::command]
+pub async fn get_listening_time(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    period: StatsPeriod,
+    days: Option<u32>,
+) -> Result<Vec<ListeningTime>, AppError> {
+    let conn = db.read()?;
+    stats_repo::listening_time(&conn, &root, period, days)
+}
+
+#[tauri::command]
+pub async fn get_listening_streaks(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+) -> Result<ListeningStreaks, AppError> {
+    let conn = db.read()?;
+    stats_repo::listening_streaks(&conn, &root, chrono::Local::now().date_naive())
+}
+
+/// Tracks not played in the last `months` months.
+#[tauri::command]
+pub async fn get_tracks_not_played_since(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    months: u32,
+    include_never_played: bool,
+    limit: usize,
+) -> Result<Vec<Track>, AppError> {
+    let conn = db.read()?;
+    stats_repo::tracks_not_played_since(&conn, &root, months, include_never_played, limit)
+}
+
 /// Hash all un-hashed tracks for the given library root, calling `on_event` for each
 /// progress event. Returns the number of tracks hashed.
 ///
 /// This function is extracted from `find_duplicates` so that the progress-event logic
 /// can be unit-tested without a live Tauri `Channel`.
 fn hash_unhashed_tracks(
-    db: &Mutex<Connection>,
+    db: &DbPool,
     root: &str,
+    kind: &HashKind,
     mut on_event: impl FnMut(ProgressEvent),
 ) -> Result<usize, AppError> {
     let unhashed = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-        library_repo::get_tracks_without_hash(&conn, root)?
+        let conn = db.read()?;
+        library_repo::get_tracks_without_hash(&conn, root, kind)?
     };
 
     let total = unhashed.len();
@@ -267,10 +389,10 @@ fn hash_unhashed_tracks(
                     .to_string(),
             });
         }
-        match hasher::hash_file(Path::new(file_path)) {
+        match hasher::hash_with(Path::new(file_path), kind) {
             Ok(hash) => {
-                let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-                library_repo::update_track_hash(&conn, *id, &hash)?;
+                let conn = db.write()?;
+                library_repo::update_track_hash(&conn, *id, &hash, kind)?;
             }
             Err(e) => {
                 eprintln!("Failed to hash {}: {}", file_path, e);
@@ -280,24 +402,172 @@ fn hash_unhashed_tracks(
     Ok(total)
 }
 
+/// Fingerprint every track that has no up-to-date acoustic fingerprint,
+/// reporting progress through the same hash events. Files that cannot be
+/// decoded get an empty fingerprint so they are skipped until they change;
+/// files that cannot be read are left to be retried on the next run.
+fn fingerprint_tracks(
+    db: &DbPool,
+    root: &str,
+    mut on_event: impl FnMut(ProgressEvent),
+) -> Result<usize, AppError> {
+    let pending = {
+        let conn = db.read()?;
+        library_repo::get_tracks_without_fingerprint(&conn, root)?
+    };
+
+    let total = pending.len();
+    if total > 0 {
+        on_event(ProgressEvent::HashStarted { total });
+    }
+    for (i, (id, file_path)) in pending.iter().enumerate() {
+        if i % 10 == 0 || i == total - 1 {
+            on_event(ProgressEvent::HashProgress {
+                files_hashed: i,
+                total_files: total,
+                current_file: file_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(file_path)
+                    .to_string(),
+            });
+        }
+        let fp = match fingerprint::fingerprint_file(Path::new(file_path)) {
+            Ok(fp) => fp,
+            Err(e @ AppError::Decode(_)) => {
+                eprintln!("Failed to fingerprint {}: {}", file_path, e);
+                Vec::new()
+            }
+            Err(e) => {
+                eprintln!("Failed to read {} for fingerprinting: {}", file_path, e);
+                continue;
+            }
+        };
+        let conn = db.write()?;
+        library_repo::save_fingerprint(&conn, *id, &fp)?;
+    }
+    Ok(total)
+}
+
+/// Measure the loudness of every track without ReplayGain tags and store
+/// track and album gains. An album with any such track is analysed as a
+/// whole, and every track of it gets the new album gain, so the album stays
+/// consistent when a track is added to it; untagged tracks count as their
+/// own album. Files that cannot be decoded are skipped.
+fn analyze_loudness_tracks(
+    db: &DbPool,
+    root: &str,
+    mut on_event: impl FnMut(ProgressEvent),
+) -> Result<usize, AppError> {
+    let pending = {
+        let conn = db.read()?;
+        library_repo::get_tracks_needing_replaygain(&conn, root)?
+    };
+
+    let total = pending.len();
+    if total > 0 {
+        on_event(ProgressEvent::LoudnessStarted { total });
+    }
+    let album_of = |t: &Track| {
+        t.album.as_ref().map(|album| {
+            (
+                t.album_artist.as_ref().or(t.artist.as_ref()).cloned(),
+                album.clone(),
+            )
+        })
+    };
+    let mut done = 0;
+    let mut analysed = 0;
+    for album in pending.chunk_by(|a, b| album_of(a).is_some() && album_of(a) == album_of(b)) {
+        let mut results = Vec::new();
+        for track in album {
+            if done % 10 == 0 || done == total - 1 {
+                on_event(ProgressEvent::LoudnessProgress {
+                    files_analyzed: done,
+                    total_files: total,
+                    current_file: track
+                        .file_path
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or(&track.file_path)
+                        .to_string(),
+                });
+            }
+            done += 1;
+            match loudness::analyze_file(Path::new(&track.file_path)) {
+                Ok(analysis) => results.push((track, analysis)),
+                Err(e) => eprintln!("Failed to analyse {}: {}", track.file_path, e),
+            }
+        }
+
+        let analyses: Vec<_> = results.iter().map(|(_, a)| a.clone()).collect();
+        let Some(album_gain) = loudness::album_gain(&analyses) else {
+            continue;
+        };
+        let conn = db.write()?;
+        for (track, analysis) in &results {
+            let (Some(id), Some(track_gain)) = (track.id, analysis.track_gain()) else {
+                continue;
+            };
+            library_repo::save_replaygain(&conn, id, &track_gain, &album_gain)?;
+            analysed += 1;
+        }
+    }
+    Ok(analysed)
+}
+
+/// Fill in ReplayGain for tracks whose files carry no ReplayGain tags.
+/// Returns how many tracks were analysed.
+#[tauri::command]
+pub async fn analyze_loudness(
+    db: tauri::State<'_, DbPool>,
+    root: String,
+    on_progress: Channel<ProgressEvent>,
+) -> Result<usize, AppError> {
+    analyze_loudness_tracks(&db, &root, |event| {
+        let _ = on_progress.send(event);
+    })
+}
+
 #[tauri::command]
 pub async fn find_duplicates(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     root: String,
+    hash_kind: Option<HashKind>,
+    acoustic_threshold: Option<f64>,
     on_progress: Channel<ProgressEvent>,
 ) -> Result<DuplicateResult, AppError> {
+    let kind = hash_kind.unwrap_or_default();
+
     // Phase 1: Hash all un-hashed tracks
-    let total = hash_unhashed_tracks(&db, &root, |event| {
+    let mut total = hash_unhashed_tracks(&db, &root, &kind, |event| {
         let _ = on_progress.send(event);
     })?;
 
+    // Phase 1b: Fingerprint tracks when acoustic matching was requested
+    if acoustic_threshold.is_some() {
+        total += fingerprint_tracks(&db, &root, |event| {
+            let _ = on_progress.send(event);
+        })?;
+    }
+
     // Phase 2: Query for duplicates
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-    let hash_groups = library_repo::find_hash_duplicates(&conn, &root)?;
-    let meta_groups = library_repo::find_metadata_duplicates(&conn, &root)?;
+    let conn = db.read()?;
+    let hash_groups = library_repo::find_hash_duplicates(&conn, &root, &kind)?;
+    let meta_groups = library_repo::find_metadata_duplicates(&conn, &root, &kind)?;
+
+    let rules = dedupe::parse_keep_rules(
+        settings_repo::get_setting(&conn, dedupe::KEEP_RULES_SETTING)?.as_deref(),
+    );
 
     let mut all_groups = hash_groups;
+    if let Some(threshold) = acoustic_threshold {
+        all_groups.extend(library_repo::find_fingerprint_duplicates(
+            &conn, &root, threshold, &kind,
+        )?);
+    }
     all_groups.extend(meta_groups);
+    dedupe::mark_keepers(&mut all_groups, &rules);
 
     let total_duplicate_tracks: usize = all_groups.iter().map(|g| g.tracks.len() - 1).sum();
     let total_wasted_bytes: u64 = all_groups
@@ -319,7 +589,7 @@ pub async fn find_duplicates(
 
 #[tauri::command]
 pub async fn delete_duplicate_tracks(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     track_ids: Vec<i64>,
     file_paths: Vec<String>,
 ) -> Result<usize, AppError> {
@@ -331,8 +601,82 @@ pub async fn delete_duplicate_tracks(
     }
 
     // Remove from database
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-    library_repo::delete_tracks_by_ids(&conn, &track_ids)
+    let conn = db.write()?;
+    let deleted = library_repo::delete_tracks_by_ids(&conn, &track_ids)?;
+    album_repo::refresh(&conn)?;
+    Ok(deleted)
+}
+
+/// Move every duplicate to the trash after pointing its playlist entries,
+/// favorite and play history at the keeper. Duplicates whose file cannot be
+/// trashed are left in place. Returns the number of tracks removed.
+#[tauri::command]
+pub async fn trash_duplicates(
+    db: tauri::State<'_, DbPool>,
+    selections: Vec<KeeperSelection>,
+) -> Result<usize, AppError> {
+    trash_duplicates_with(&db, &selections, trash::move_to_trash)
+}
+
+fn trash_duplicates_with(
+    db: &DbPool,
+    selections: &[KeeperSelection],
+    mut trash_file: impl FnMut(&Path) -> Result<std::path::PathBuf, AppError>,
+) -> Result<usize, AppError> {
+    let conn = db.write()?;
+    let mut removed = Vec::new();
+    let mut trashed = Vec::new();
+    for selection in selections {
+        for &id in &selection.duplicate_ids {
+            if id == selection.keeper_id {
+                continue;
+            }
+            let Some(file_path) = library_repo::get_track_path(&conn, id)? else {
+                continue;
+            };
+            let path = Path::new(&file_path);
+            if path.exists() {
+                match trash_file(path) {
+                    Ok(dest) => trashed.push((path.to_path_buf(), dest)),
+                    Err(e) => {
+                        eprintln!("Failed to trash {}: {}", file_path, e);
+                        continue;
+                    }
+                }
+            }
+            removed.push((id, selection.keeper_id));
+        }
+    }
+
+    let result = remove_duplicate_tracks(&conn, &removed);
+    if result.is_err() {
+        // The database is unchanged, so put the files back where it expects them
+        for (original, dest) in &trashed {
+            if let Err(e) = trash::restore(dest, original) {
+                eprintln!("Failed to restore {}: {}", original.display(), e);
+            }
+        }
+    }
+    result
+}
+
+/// Points the references of each `(duplicate, keeper)` at the keeper and
+/// deletes the duplicates, all or nothing.
+fn remove_duplicate_tracks(
+    conn: &rusqlite::Connection,
+    removed: &[(i64, i64)],
+) -> Result<usize, AppError> {
+    let tx = conn.unchecked_transaction()?;
+    for &(id, keeper_id) in removed {
+        playlist_repo::reassign_track(&tx, id, keeper_id)?;
+        favorite_repo::reassign_track(&tx, id, keeper_id)?;
+        recent_repo::reassign_track(&tx, id, keeper_id)?;
+    }
+    let ids: Vec<i64> = removed.iter().map(|&(id, _)| id).collect();
+    let deleted = library_repo::delete_tracks_by_ids(&tx, &ids)?;
+    album_repo::refresh(&tx)?;
+    tx.commit()?;
+    Ok(deleted)
 }
 
 /// Replace filesystem-unsafe characters with underscores, trim whitespace, and ensure non-empty.
@@ -384,7 +728,7 @@ fn read_folder_metadata(src: &Path) -> (String, String) {
 ///
 /// Extracted from `import_tracks` so the logic can be unit-tested without a live Tauri `Channel`.
 fn do_import_tracks(
-    db: &Mutex<Connection>,
+    db: &DbPool,
     source_paths: &[String],
     library_root: &str,
     mut on_event: impl FnMut(ProgressEvent),
@@ -396,6 +740,7 @@ fn do_import_tracks(
 
     let total = source_paths.len();
     let mut imported = 0usize;
+    let mut pending: Vec<Track> = Vec::new();
 
     for (i, source_path) in source_paths.iter().enumerate() {
         let src = Path::new(source_path);
@@ -501,9 +846,11 @@ fn do_import_tracks(
         // Extract metadata from the copied file
         match metadata::extract_metadata(&dest_path, root) {
             Ok(track) => {
-                let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-                library_repo::upsert_track(&conn, &track)?;
+                pending.push(track);
                 imported += 1;
+                if pending.len() >= pool::WRITE_BATCH_SIZE {
+                    flush_tracks(db, &mut pending)?;
+                }
             }
             Err(e) => {
                 eprintln!(
@@ -515,6 +862,9 @@ fn do_import_tracks(
         }
     }
 
+    flush_tracks(db, &mut pending)?;
+    album_repo::refresh(&*db.write()?)?;
+
     on_event(ProgressEvent::ScanComplete {
         total_files: imported,
         duration_ms: 0,
@@ -525,7 +875,7 @@ fn do_import_tracks(
 
 #[tauri::command]
 pub async fn import_tracks(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     source_paths: Vec<String>,
     library_root: String,
     on_progress: Channel<ProgressEvent>,
@@ -540,7 +890,6 @@ mod hash_progress_tests {
     use super::*;
     use orchestra_core::db::schema;
     use rusqlite::Connection;
-    use std::sync::Mutex;
     use tempfile::TempDir;
 
     fn setup_db() -> Connection {
@@ -573,9 +922,9 @@ mod hash_progress_tests {
         insert_track_with_file(&conn, &tmp, "track2.flac");
         insert_track_with_file(&conn, &tmp, "track3.flac");
 
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
         let mut collected: Vec<ProgressEvent> = Vec::new();
-        hash_unhashed_tracks(&db, "/music", |evt| collected.push(evt)).unwrap();
+        hash_unhashed_tracks(&db, "/music", &HashKind::File, |evt| collected.push(evt)).unwrap();
 
         // Must have exactly one HashStarted with total = 3
         let started: Vec<_> = collected
@@ -611,9 +960,10 @@ mod hash_progress_tests {
         )
         .unwrap();
 
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
         let mut collected: Vec<ProgressEvent> = Vec::new();
-        let total = hash_unhashed_tracks(&db, "/music", |evt| collected.push(evt)).unwrap();
+        let total = hash_unhashed_tracks(&db, "/music", &HashKind::File, |evt| collected.push(evt))
+            .unwrap();
 
         // total == 0, no HashStarted should be emitted
         assert_eq!(total, 0);
@@ -622,6 +972,54 @@ mod hash_progress_tests {
             "expected no events when all tracks are already hashed"
         );
     }
+
+    #[test]
+    fn test_fingerprint_undecodable_tracks_not_retried() {
+        let tmp = TempDir::new().unwrap();
+        let conn = setup_db();
+        insert_track_with_file(&conn, &tmp, "track1.flac");
+        insert_track_with_file(&conn, &tmp, "track2.flac");
+
+        let db = DbPool::single(conn);
+        let mut collected: Vec<ProgressEvent> = Vec::new();
+        let total = fingerprint_tracks(&db, "/music", |evt| collected.push(evt)).unwrap();
+        assert_eq!(total, 2);
+        assert!(collected
+            .iter()
+            .any(|e| matches!(e, ProgressEvent::HashStarted { total: 2 })));
+
+        // The fake files cannot be decoded, but are recorded so a rerun skips them
+        let total = fingerprint_tracks(&db, "/music", |_| {}).unwrap();
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn test_fingerprint_unreadable_tracks_retried() {
+        let tmp = TempDir::new().unwrap();
+        let conn = setup_db();
+        let path = insert_track_with_file(&conn, &tmp, "track1.flac");
+        std::fs::remove_file(&path).unwrap();
+
+        let db = DbPool::single(conn);
+        assert_eq!(fingerprint_tracks(&db, "/music", |_| {}).unwrap(), 1);
+        // Nothing was recorded, so the next run tries again
+        assert_eq!(fingerprint_tracks(&db, "/music", |_| {}).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_analyze_loudness_skips_undecodable_tracks() {
+        let tmp = TempDir::new().unwrap();
+        let conn = setup_db();
+        insert_track_with_file(&conn, &tmp, "track1.flac");
+
+        let db = DbPool::single(conn);
+        let mut collected: Vec<ProgressEvent> = Vec::new();
+        let analysed = analyze_loudness_tracks(&db, "/music", |evt| collected.push(evt)).unwrap();
+        assert_eq!(analysed, 0);
+        assert!(collected
+            .iter()
+            .any(|e| matches!(e, ProgressEvent::LoudnessStarted { total: 1 })));
+    }
 }
 
 #[cfg(test)]
@@ -629,7 +1027,6 @@ mod import_tracks_tests {
     use super::*;
     use orchestra_core::db::schema;
     use rusqlite::Connection;
-    use std::sync::Mutex;
     use tempfile::TempDir;
 
     fn setup_db() -> Connection {
@@ -661,7 +1058,7 @@ mod import_tracks_tests {
         let source_path = write_fake_audio(source_dir.path(), "track.flac");
 
         let conn = setup_db();
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
 
         let result = do_import_tracks(
             &db,
@@ -703,7 +1100,7 @@ mod import_tracks_tests {
         let source_path = write_fake_audio(source_dir.path(), "track.flac");
 
         let conn = setup_db();
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
 
         let _result = do_import_tracks(
             &db,
@@ -731,7 +1128,7 @@ mod import_tracks_tests {
         let source_path = write_fake_text(source_dir.path(), "readme.txt");
 
         let conn = setup_db();
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
 
         let result = do_import_tracks(
             &db,
@@ -769,7 +1166,7 @@ mod import_tracks_tests {
         let existing_path = write_fake_audio(source_dir.path(), "present.flac");
 
         let conn = setup_db();
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
 
         let result = do_import_tracks(
             &db,
@@ -807,7 +1204,7 @@ mod import_tracks_tests {
             .collect();
 
         let conn = setup_db();
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
 
         let mut events: Vec<ProgressEvent> = Vec::new();
         let _result = do_import_tracks(&db, &paths, library_dir.path().to_str().unwrap(), |evt| {
@@ -862,7 +1259,7 @@ mod import_tracks_tests {
         let p2 = write_fake_audio(source_dir.path(), "song_b.mp3");
 
         let conn = setup_db();
-        let db = Mutex::new(conn);
+        let db = DbPool::single(conn);
 
         let _result =
             do_import_tracks(&db, &[p1, p2], library_dir.path().to_str().unwrap(), |_| {});
@@ -891,3 +1288,111 @@ mod import_tracks_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod trash_duplicates_tests {
+    use super::*;
+    use orchestra_core::db::schema;
+    use orchestra_core::trash::move_to_trash_in;
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+
+    fn insert_track(conn: &Connection, path: &Path) -> i64 {
+        std::fs::write(path, b"fake audio content").unwrap();
+        conn.execute(
+            "INSERT INTO tracks (file_path, relative_path, library_root, format, file_size, modified_at)
+             VALUES (?1, ?1, '/music', 'flac', 1000, 0)",
+            rusqlite::params![path.to_string_lossy()],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn test_trash_duplicates_moves_files_and_references() {
+        let music = TempDir::new().unwrap();
+        let trash_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+
+        let keeper = insert_track(&conn, &music.path().join("keeper.flac"));
+        let dup_path = music.path().join("dup.mp3");
+        let dup = insert_track(&conn, &dup_path);
+        favorite_repo::add_favorite(&conn, "track", &dup.to_string()).unwrap();
+
+        let db = DbPool::single(conn);
+        let selections = vec![KeeperSelection {
+            keeper_id: keeper,
+            duplicate_ids: vec![keeper, dup],
+        }];
+        let removed =
+            trash_duplicates_with(&db, &selections, |p| move_to_trash_in(p, trash_dir.path()))
+                .unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(!dup_path.exists());
+        assert!(trash_dir.path().join("files/dup.mp3").exists());
+
+        let conn = db.read().unwrap();
+        assert_eq!(library_repo::get_track_path(&conn, dup).unwrap(), None);
+        assert!(library_repo::get_track_path(&conn, keeper)
+            .unwrap()
+            .is_some());
+        assert!(favorite_repo::is_favorite(&conn, "track", &keeper.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_trash_failure_keeps_track() {
+        let music = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        let keeper = insert_track(&conn, &music.path().join("keeper.flac"));
+        let dup = insert_track(&conn, &music.path().join("dup.mp3"));
+
+        let db = DbPool::single(conn);
+        let selections = vec![KeeperSelection {
+            keeper_id: keeper,
+            duplicate_ids: vec![dup],
+        }];
+        let removed = trash_duplicates_with(&db, &selections, |_| {
+            Err(AppError::General("no trash".to_string()))
+        })
+        .unwrap();
+
+        assert_eq!(removed, 0);
+        let conn = db.read().unwrap();
+        assert!(library_repo::get_track_path(&conn, dup).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_trash_database_failure_restores_files() {
+        let music = TempDir::new().unwrap();
+        let trash_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        schema::run_migrations(&conn).unwrap();
+        let keeper = insert_track(&conn, &music.path().join("keeper.flac"));
+        let dup_path = music.path().join("dup.mp3");
+        let dup = insert_track(&conn, &dup_path);
+        favorite_repo::add_favorite(&conn, "track", &dup.to_string()).unwrap();
+        conn.execute_batch(
+            "CREATE TRIGGER fail_delete BEFORE DELETE ON tracks
+             BEGIN SELECT RAISE(ABORT, 'delete failed'); END;",
+        )
+        .unwrap();
+
+        let db = DbPool::single(conn);
+        let selections = vec![KeeperSelection {
+            keeper_id: keeper,
+            duplicate_ids: vec![dup],
+        }];
+        let result =
+            trash_duplicates_with(&db, &selections, |p| move_to_trash_in(p, trash_dir.path()));
+
+        assert!(result.is_err());
+        assert!(dup_path.exists());
+        let conn = db.read().unwrap();
+        assert!(library_repo::get_track_path(&conn, dup).unwrap().is_some());
+        assert!(favorite_repo::is_favorite(&conn, "track", &dup.to_string()).unwrap());
+        assert!(!favorite_repo::is_favorite(&conn, "track", &keeper.to_string()).unwrap());
+    }
+}
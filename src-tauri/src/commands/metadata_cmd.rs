@@ -1,11 +1,12 @@
-use rusqlite::Connection;
 use std::path::Path;
-use std::sync::Mutex;
 
-use orchestra_core::db::library_repo;
+use orchestra_core::db::pool::DbPool;
+use orchestra_core::db::{album_repo, library_repo, rating_repo, settings_repo};
 use orchestra_core::error::AppError;
+use orchestra_core::models::lyrics::{LyricLine, Lyrics};
 use orchestra_core::models::track::{AlbumArt, Track, TrackMetadataUpdate};
-use orchestra_core::scanner::{metadata, writer};
+use orchestra_core::rating;
+use orchestra_core::scanner::{lyrics, metadata, writer};
 
 #[tauri::command]
 pub async fn get_track_artwork(file_path: String) -> Result<Option<AlbumArt>, AppError> {
@@ -18,7 +19,7 @@ pub async fn get_track_artwork(file_path: String) -> Result<Option<AlbumArt>, Ap
 
 #[tauri::command]
 pub async fn update_track_metadata(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     updates: Vec<TrackMetadataUpdate>,
 ) -> Result<Vec<Track>, AppError> {
     let mut updated_tracks = Vec::with_capacity(updates.len());
@@ -34,7 +35,7 @@ pub async fn update_track_metadata(
     }
 
     // Re-read metadata and update DB for all tracks
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     for update in &updates {
         let path = Path::new(&update.file_path);
 
@@ -57,9 +58,126 @@ pub async fn update_track_metadata(
         track.hash = None; // Clear stale hash — will be recomputed on next diff
 
         library_repo::upsert_track(&conn, &track)?;
+        if update.rating == Some(0) {
+            // The upsert keeps a stored rating when the tags have none
+            conn.execute(
+                "UPDATE tracks SET rating = NULL WHERE file_path = ?1",
+                rusqlite::params![update.file_path],
+            )?;
+        }
         track.id = Some(conn.last_insert_rowid());
         updated_tracks.push(track);
     }
+    album_repo::refresh(&conn)?;
 
     Ok(updated_tracks)
 }
+
+/// Rates a track 1–100, or clears its rating with `None` or 0. The rating is
+/// also written to the file's tags unless `write_ratings_to_tags` is `false`.
+#[tauri::command]
+pub async fn set_track_rating(
+    db: tauri::State<'_, DbPool>,
+    track_id: i64,
+    rating: Option<u8>,
+) -> Result<Track, AppError> {
+    let rating = rating_repo::validate(rating)?;
+    let (track, write_tags) = {
+        let conn = db.read()?;
+        let track = library_repo::get_track(&conn, track_id)?
+            .ok_or_else(|| AppError::General(format!("Track {track_id} not found")))?;
+        let write_tags = settings_repo::get_setting(&conn, rating::WRITE_RATINGS_SETTING)?
+            .is_none_or(|v| v.trim() != "false");
+        (track, write_tags)
+    };
+
+    let path = Path::new(&track.file_path);
+    let retagged = if write_tags && path.exists() {
+        let update = TrackMetadataUpdate {
+            file_path: track.file_path.clone(),
+            rating: Some(rating.unwrap_or(0)),
+            ..Default::default()
+        };
+        writer::write_metadata(path, &update)?;
+        let mut retagged = metadata::extract_metadata(path, Path::new(&track.library_root))?;
+        retagged.hash = None; // Clear stale hash — will be recomputed on next diff
+        Some(retagged)
+    } else {
+        None
+    };
+
+    let conn = db.write()?;
+    if let Some(retagged) = retagged {
+        library_repo::upsert_track(&conn, &retagged)?;
+    }
+    // Set directly: a cleared tag leaves the stored rating alone on upsert
+    rating_repo::set_track_rating(&conn, track_id, rating)?;
+    library_repo::get_track(&conn, track_id)?
+        .ok_or_else(|| AppError::General(format!("Track {track_id} not found")))
+}
+
+/// Rates an album 1–100, or clears its rating with `None` or 0. Album
+/// ratings are kept in the database only.
+#[tauri::command]
+pub async fn set_album_rating(
+    db: tauri::State<'_, DbPool>,
+    album_id: i64,
+    rating: Option<u8>,
+) -> Result<(), AppError> {
+    let conn = db.write()?;
+    rating_repo::set_album_rating(&conn, album_id, rating)
+}
+
+/// Reads a track's lyrics from its sidecar or tags. The track's stored
+/// lyrics kind is brought up to date with what was found.
+#[tauri::command]
+pub async fn get_track_lyrics(
+    db: tauri::State<'_, DbPool>,
+    track_id: i64,
+) -> Result<Option<Lyrics>, AppError> {
+    let track = {
+        let conn = db.read()?;
+        library_repo::get_track(&conn, track_id)?
+            .ok_or_else(|| AppError::General(format!("Track {track_id} not found")))?
+    };
+    let path = Path::new(&track.file_path);
+    if !path.exists() {
+        return Err(AppError::PathNotAccessible(track.file_path));
+    }
+
+    let found = lyrics::read_lyrics(path)?;
+    let kind = found.as_ref().map(Lyrics::kind);
+    if kind != track.lyrics {
+        let conn = db.write()?;
+        library_repo::set_track_lyrics(&conn, track_id, kind)?;
+    }
+    Ok(found)
+}
+
+/// Replaces a track's lyrics, in its `.lrc` sidecar if it has one and in
+/// its tags otherwise. No lines removes them. Returns the lyrics as read
+/// back from the file.
+#[tauri::command]
+pub async fn set_track_lyrics(
+    db: tauri::State<'_, DbPool>,
+    track_id: i64,
+    lines: Vec<LyricLine>,
+) -> Result<Option<Lyrics>, AppError> {
+    let track = {
+        let conn = db.read()?;
+        library_repo::get_track(&conn, track_id)?
+            .ok_or_else(|| AppError::General(format!("Track {track_id} not found")))?
+    };
+    let path = Path::new(&track.file_path);
+    if !path.exists() {
+        return Err(AppError::PathNotAccessible(track.file_path));
+    }
+
+    writer::write_lyrics(path, &lines)?;
+    let mut retagged = metadata::extract_metadata(path, Path::new(&track.library_root))?;
+    retagged.hash = None; // Clear stale hash — will be recomputed on next diff
+
+    let conn = db.write()?;
+    library_repo::upsert_track(&conn, &retagged)?;
+    lyrics::read_lyrics(path)
+}
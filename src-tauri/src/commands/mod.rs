@@ -1,3 +1,4 @@
+pub mod backup_cmd;
 pub mod device_cmd;
 pub mod favorite_cmd;
 pub mod library;
@@ -5,5 +6,6 @@ pub mod metadata_cmd;
 pub mod playlist_cmd;
 pub mod profile;
 pub mod recent_cmd;
+pub mod scrobble_cmd;
 pub mod settings;
 pub mod sync_cmd;
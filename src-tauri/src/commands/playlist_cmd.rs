@@ -1,7 +1,5 @@
-use rusqlite::Connection;
-use std::sync::Mutex;
-
 use orchestra_core::db::playlist_repo;
+use orchestra_core::db::pool::DbPool;
 use orchestra_core::error::AppError;
 use orchestra_core::models::playlist::{
     AddTracksRequest, CreatePlaylistRequest, Playlist, PlaylistWithTracks, RemoveTracksRequest,
@@ -10,7 +8,7 @@ use orchestra_core::models::playlist::{
 
 #[tauri::command]
 pub async fn create_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: CreatePlaylistRequest,
 ) -> Result<PlaylistWithTracks, AppError> {
     let now = chrono::Utc::now().timestamp();
@@ -21,7 +19,7 @@ pub async fn create_playlist(
         updated_at: now,
     };
 
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     playlist_repo::create_playlist(&conn, &playlist)?;
     Ok(PlaylistWithTracks {
         playlist,
@@ -30,28 +28,26 @@ pub async fn create_playlist(
 }
 
 #[tauri::command]
-pub async fn list_playlists(
-    db: tauri::State<'_, Mutex<Connection>>,
-) -> Result<Vec<Playlist>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn list_playlists(db: tauri::State<'_, DbPool>) -> Result<Vec<Playlist>, AppError> {
+    let conn = db.read()?;
     playlist_repo::list_playlists(&conn)
 }
 
 #[tauri::command]
 pub async fn get_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     id: String,
 ) -> Result<PlaylistWithTracks, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     playlist_repo::get_playlist_with_tracks(&conn, &id)
 }
 
 #[tauri::command]
 pub async fn update_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: UpdatePlaylistRequest,
 ) -> Result<Playlist, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     let mut playlist = playlist_repo::get_playlist(&conn, &request.id)?;
 
     if let Some(name) = request.name {
@@ -64,20 +60,17 @@ pub async fn update_playlist(
 }
 
 #[tauri::command]
-pub async fn delete_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
-    id: String,
-) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn delete_playlist(db: tauri::State<'_, DbPool>, id: String) -> Result<(), AppError> {
+    let conn = db.write()?;
     playlist_repo::delete_playlist(&conn, &id)
 }
 
 #[tauri::command]
 pub async fn add_tracks_to_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: AddTracksRequest,
 ) -> Result<PlaylistWithTracks, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     playlist_repo::add_tracks(&conn, &request.playlist_id, &request.track_ids)?;
 
     // Touch updated_at
@@ -90,10 +83,10 @@ pub async fn add_tracks_to_playlist(
 
 #[tauri::command]
 pub async fn remove_tracks_from_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: RemoveTracksRequest,
 ) -> Result<PlaylistWithTracks, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     playlist_repo::remove_tracks(&conn, &request.playlist_id, &request.track_ids)?;
 
     // Touch updated_at
@@ -106,10 +99,10 @@ pub async fn remove_tracks_from_playlist(
 
 #[tauri::command]
 pub async fn reorder_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: ReorderTracksRequest,
 ) -> Result<PlaylistWithTracks, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     playlist_repo::reorder_tracks(&conn, &request.playlist_id, &request.track_ids)?;
 
     // Touch updated_at
@@ -122,12 +115,12 @@ pub async fn reorder_playlist(
 
 #[tauri::command]
 pub async fn export_playlist(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     id: String,
     format: String,
     path: String,
 ) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     let pwt = playlist_repo::get_playlist_with_tracks(&conn, &id)?;
 
     let content = match format.to_lowercase().as_str() {
@@ -1,6 +1,4 @@
-use rusqlite::Connection;
-use std::sync::Mutex;
-
+use orchestra_core::db::pool::DbPool;
 use orchestra_core::db::profile_repo;
 use orchestra_core::error::AppError;
 use orchestra_core::models::sync_profile::{
@@ -9,9 +7,10 @@ use orchestra_core::models::sync_profile::{
 
 #[tauri::command]
 pub async fn create_profile(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: CreateProfileRequest,
 ) -> Result<SyncProfile, AppError> {
+    profile_repo::validate_policies(&request.conflict_policies)?;
     let profile = SyncProfile {
         id: uuid::Uuid::new_v4().to_string(),
         name: request.name,
@@ -19,38 +18,39 @@ pub async fn create_profile(
         target_path: request.target_path,
         sync_mode: request.sync_mode,
         exclude_patterns: request.exclude_patterns,
+        conflict_policies: request.conflict_policies,
+        keep_both_naming: request.keep_both_naming,
+        hash_kind: request.hash_kind,
         created_at: chrono::Utc::now().timestamp(),
         last_synced_at: None,
     };
 
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     profile_repo::create_profile(&conn, &profile)?;
     Ok(profile)
 }
 
 #[tauri::command]
 pub async fn get_profile(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     id: String,
 ) -> Result<SyncProfile, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     profile_repo::get_profile(&conn, &id)
 }
 
 #[tauri::command]
-pub async fn list_profiles(
-    db: tauri::State<'_, Mutex<Connection>>,
-) -> Result<Vec<SyncProfile>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn list_profiles(db: tauri::State<'_, DbPool>) -> Result<Vec<SyncProfile>, AppError> {
+    let conn = db.read()?;
     profile_repo::list_profiles(&conn)
 }
 
 #[tauri::command]
 pub async fn update_profile(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     request: UpdateProfileRequest,
 ) -> Result<SyncProfile, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     let mut profile = profile_repo::get_profile(&conn, &request.id)?;
 
     if let Some(name) = request.name {
@@ -68,16 +68,23 @@ pub async fn update_profile(
     if let Some(exclude_patterns) = request.exclude_patterns {
         profile.exclude_patterns = exclude_patterns;
     }
+    if let Some(conflict_policies) = request.conflict_policies {
+        profile_repo::validate_policies(&conflict_policies)?;
+        profile.conflict_policies = conflict_policies;
+    }
+    if let Some(keep_both_naming) = request.keep_both_naming {
+        profile.keep_both_naming = keep_both_naming;
+    }
+    if let Some(hash_kind) = request.hash_kind {
+        profile.hash_kind = hash_kind;
+    }
 
     profile_repo::update_profile(&conn, &profile)?;
     Ok(profile)
 }
 
 #[tauri::command]
-pub async fn delete_profile(
-    db: tauri::State<'_, Mutex<Connection>>,
-    id: String,
-) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+pub async fn delete_profile(db: tauri::State<'_, DbPool>, id: String) -> Result<(), AppError> {
+    let conn = db.write()?;
     profile_repo::delete_profile(&conn, &id)
 }
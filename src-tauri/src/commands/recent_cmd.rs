@@ -1,35 +1,51 @@
-use std::sync::Mutex;
-
-use rusqlite::Connection;
-
+use orchestra_core::db::pool::DbPool;
 use orchestra_core::db::recent_repo;
 use orchestra_core::error::AppError;
 use orchestra_core::models::track::Track;
 
+/// Returns the play's id, for `finish_play`.
+#[tauri::command]
+pub async fn record_play(db: tauri::State<'_, DbPool>, track_id: i64) -> Result<i64, AppError> {
+    let conn = db.write()?;
+    recent_repo::record_play(&conn, track_id)
+}
+
 #[tauri::command]
-pub async fn record_play(
-    db: tauri::State<'_, Mutex<Connection>>,
+pub async fn finish_play(
+    db: tauri::State<'_, DbPool>,
+    play_id: i64,
+    listened_secs: f64,
+    completed: bool,
+) -> Result<(), AppError> {
+    let conn = db.write()?;
+    recent_repo::finish_play(&conn, play_id, listened_secs, completed)
+}
+
+#[tauri::command]
+pub async fn record_skip(
+    db: tauri::State<'_, DbPool>,
     track_id: i64,
+    listened_secs: f64,
 ) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
-    recent_repo::record_play(&conn, track_id)
+    let conn = db.write()?;
+    recent_repo::record_skip(&conn, track_id, listened_secs)
 }
 
 #[tauri::command]
 pub async fn get_recently_added(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     days: u32,
     limit: usize,
 ) -> Result<Vec<Track>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     recent_repo::get_recently_added(&conn, days, limit)
 }
 
 #[tauri::command]
 pub async fn get_recently_played(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     limit: usize,
 ) -> Result<Vec<Track>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     recent_repo::get_recently_played(&conn, limit)
 }
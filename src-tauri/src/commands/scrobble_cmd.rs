@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use orchestra_core::db::pool::DbPool;
+use orchestra_core::error::AppError;
+use orchestra_core::models::scrobble::ScrobbleImportSummary;
+use orchestra_core::scrobble;
+
+/// Writes the play history as a ListenBrainz JSON listens file. Returns the
+/// number of listens written.
+#[tauri::command]
+pub async fn export_listenbrainz(
+    db: tauri::State<'_, DbPool>,
+    path: String,
+) -> Result<usize, AppError> {
+    let conn = db.read()?;
+    scrobble::export_listenbrainz_to_file(&conn, Path::new(&path))
+}
+
+/// Writes the play history as an AudioScrobbler `.scrobbler.log`.
+#[tauri::command]
+pub async fn export_scrobbler_log(
+    db: tauri::State<'_, DbPool>,
+    path: String,
+) -> Result<usize, AppError> {
+    let conn = db.read()?;
+    scrobble::export_scrobbler_log_to_file(&conn, Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn import_scrobbler_log(
+    db: tauri::State<'_, DbPool>,
+    path: String,
+) -> Result<ScrobbleImportSummary, AppError> {
+    let conn = db.write()?;
+    scrobble::import_scrobbler_log_file(&conn, Path::new(&path))
+}
@@ -1,33 +1,31 @@
-use rusqlite::Connection;
-use std::sync::Mutex;
-
+use orchestra_core::db::pool::DbPool;
 use orchestra_core::db::settings_repo;
 use orchestra_core::error::AppError;
 
 #[tauri::command]
 pub async fn get_setting(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     key: String,
 ) -> Result<Option<String>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     settings_repo::get_setting(&conn, &key)
 }
 
 #[tauri::command]
 pub async fn set_setting(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     key: String,
     value: String,
 ) -> Result<(), AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.write()?;
     settings_repo::set_setting(&conn, &key, &value)
 }
 
 #[tauri::command]
 pub async fn get_all_settings(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
 ) -> Result<Vec<(String, String)>, AppError> {
-    let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+    let conn = db.read()?;
     settings_repo::get_all_settings(&conn)
 }
 
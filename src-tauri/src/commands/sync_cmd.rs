@@ -1,24 +1,25 @@
-use rusqlite::Connection;
 use std::path::Path;
 use std::sync::Mutex;
 use tauri::ipc::Channel;
 
 use crate::sync::progress::CancelToken;
 use crate::sync::{diff, one_way, two_way};
+use orchestra_core::db::pool::DbPool;
 use orchestra_core::db::{profile_repo, sync_state_repo};
 use orchestra_core::error::AppError;
 use orchestra_core::models::conflict::{Conflict, ConflictResolution};
-use orchestra_core::models::diff::DiffResult;
+use orchestra_core::models::diff::{DiffResult, SyncResult};
 use orchestra_core::models::progress::ProgressEvent;
 use orchestra_core::models::sync_profile::SyncMode;
+use orchestra_core::models::track::HashKind;
 
 #[tauri::command]
 pub async fn compute_diff(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     profile_id: String,
 ) -> Result<(DiffResult, Vec<Conflict>), AppError> {
     let (profile, baselines) = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         let profile = profile_repo::get_profile(&conn, &profile_id)?;
         let baselines = sync_state_repo::get_baselines(&conn, &profile_id)?;
         (profile, baselines)
@@ -45,6 +46,7 @@ pub async fn compute_diff(
             source,
             target,
             &profile.exclude_patterns,
+            &profile.hash_kind,
             &baselines,
         ),
     }
@@ -52,15 +54,15 @@ pub async fn compute_diff(
 
 #[tauri::command]
 pub async fn execute_sync(
-    db: tauri::State<'_, Mutex<Connection>>,
+    db: tauri::State<'_, DbPool>,
     cancel_token: tauri::State<'_, Mutex<CancelToken>>,
     profile_id: String,
     diff_result: DiffResult,
     conflict_resolutions: Vec<ConflictResolution>,
     on_progress: Channel<ProgressEvent>,
-) -> Result<usize, AppError> {
+) -> Result<SyncResult, AppError> {
     let profile = {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.read()?;
         profile_repo::get_profile(&conn, &profile_id)?
     };
 
@@ -75,15 +77,21 @@ pub async fn execute_sync(
         token.flag()
     };
 
-    let count = match profile.sync_mode {
-        SyncMode::OneWay => {
-            one_way::execute_one_way_sync(&diff_result, source, target, flag, &on_progress)?
-        }
+    let result = match profile.sync_mode {
+        SyncMode::OneWay => SyncResult {
+            files_synced: one_way::execute_one_way_sync(
+                &diff_result,
+                source,
+                target,
+                flag,
+                &on_progress,
+            )?,
+            resolutions: vec![],
+        },
         SyncMode::TwoWay => two_way::execute_two_way_sync(
             &diff_result,
             &conflict_resolutions,
-            source,
-            target,
+            &profile,
             flag,
             &on_progress,
         )?,
@@ -91,16 +99,21 @@ pub async fn execute_sync(
 
     // Save baselines and update last_synced
     {
-        let conn = db.lock().map_err(|e| AppError::General(e.to_string()))?;
+        let conn = db.write()?;
         let now = chrono::Utc::now().timestamp();
         profile_repo::update_last_synced(&conn, &profile_id, now)?;
 
         // Build baselines from current state of source and target
-        let baselines = build_post_sync_baselines(source, target, &profile.exclude_patterns)?;
+        let baselines = build_post_sync_baselines(
+            source,
+            target,
+            &profile.exclude_patterns,
+            &profile.hash_kind,
+        )?;
         sync_state_repo::save_baselines(&conn, &profile_id, &baselines)?;
     }
 
-    Ok(count)
+    Ok(result)
 }
 
 #[tauri::command]
@@ -118,6 +131,7 @@ fn build_post_sync_baselines(
     source: &Path,
     target: &Path,
     exclude_patterns: &[String],
+    hash_kind: &HashKind,
 ) -> Result<Vec<sync_state_repo::FileBaseline>, AppError> {
     use orchestra_core::models::track::is_audio_file;
     use orchestra_core::scanner::hasher;
@@ -153,7 +167,7 @@ fn build_post_sync_baselines(
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs() as i64;
-        let hash = hasher::hash_file(entry.path())?;
+        let hash = hasher::hash_with(entry.path(), hash_kind)?;
         all_rels.insert(rel.clone());
         source_info.insert(rel, (hash, modified, meta.len()));
     }
@@ -181,7 +195,7 @@ fn build_post_sync_baselines(
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs() as i64;
-        let hash = hasher::hash_file(entry.path())?;
+        let hash = hasher::hash_with(entry.path(), hash_kind)?;
         all_rels.insert(rel.clone());
         target_info.insert(rel, (hash, modified, meta.len()));
     }
@@ -229,7 +243,8 @@ mod tests {
         write_fake_audio(&src, "artist/album/track02.flac", b"source audio data 2");
         write_fake_audio(&tgt, "artist/album/track01.flac", b"source audio data 1");
 
-        let baselines = build_post_sync_baselines(src.path(), tgt.path(), &[]).unwrap();
+        let baselines =
+            build_post_sync_baselines(src.path(), tgt.path(), &[], &HashKind::File).unwrap();
 
         assert_eq!(baselines.len(), 2);
 
@@ -254,7 +269,8 @@ mod tests {
         let src = TempDir::new().unwrap();
         let tgt = TempDir::new().unwrap();
 
-        let baselines = build_post_sync_baselines(src.path(), tgt.path(), &[]).unwrap();
+        let baselines =
+            build_post_sync_baselines(src.path(), tgt.path(), &[], &HashKind::File).unwrap();
         assert!(baselines.is_empty());
     }
 
@@ -266,8 +282,13 @@ mod tests {
         write_fake_audio(&src, "keep/track.flac", b"keep me");
         write_fake_audio(&src, "skip/track.flac", b"exclude me");
 
-        let baselines =
-            build_post_sync_baselines(src.path(), tgt.path(), &["skip/**".to_string()]).unwrap();
+        let baselines = build_post_sync_baselines(
+            src.path(),
+            tgt.path(),
+            &["skip/**".to_string()],
+            &HashKind::File,
+        )
+        .unwrap();
 
         assert_eq!(baselines.len(), 1);
         assert_eq!(baselines[0].relative_path, "keep/track.flac");
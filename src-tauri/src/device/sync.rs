@@ -157,6 +157,7 @@ pub fn compute_device_diff(
                     target_hash: None,
                     source_modified: Some(track.modified_at),
                     target_modified: None,
+                    conflict_type: None,
                 });
             }
             (None, Some(dev)) => {
@@ -172,6 +173,7 @@ pub fn compute_device_diff(
                     target_hash: None,
                     source_modified: None,
                     target_modified: Some(dev.modified),
+                    conflict_type: None,
                 });
                 // Don't add to new_cache — file will be removed
             }
@@ -197,6 +199,7 @@ pub fn compute_device_diff(
                         target_hash: None,
                         source_modified: Some(track.modified_at),
                         target_modified: Some(dev.modified),
+                        conflict_type: None,
                     });
                 } else {
                     // Size or mtime differ — hash to confirm
@@ -234,6 +237,7 @@ pub fn compute_device_diff(
                             target_hash: Some(tgt_hash),
                             source_modified: Some(track.modified_at),
                             target_modified: Some(dev.modified),
+                            conflict_type: None,
                         });
                     } else {
                         bytes_to_transfer += track.file_size;
@@ -248,6 +252,7 @@ pub fn compute_device_diff(
                             target_hash: Some(tgt_hash),
                             source_modified: Some(track.modified_at),
                             target_modified: Some(dev.modified),
+                            conflict_type: None,
                         });
                     }
                 }
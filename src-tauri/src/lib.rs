@@ -6,10 +6,11 @@ use rusqlite::Connection;
 use std::sync::Mutex;
 use tauri::Manager;
 
+use orchestra_core::db::pool::{self, DbPool};
 use orchestra_core::db::schema;
 use sync::progress::CancelToken;
 
-fn init_database(app: &tauri::App) -> Result<Connection, Box<dyn std::error::Error>> {
+fn init_database(app: &tauri::App) -> Result<DbPool, Box<dyn std::error::Error>> {
     let app_dir = app.path().app_data_dir()?;
     std::fs::create_dir_all(&app_dir)?;
     // Migrate legacy database filename
@@ -18,9 +19,11 @@ fn init_database(app: &tauri::App) -> Result<Connection, Box<dyn std::error::Err
     if legacy_path.exists() && !db_path.exists() {
         std::fs::rename(&legacy_path, &db_path)?;
     }
-    let conn = Connection::open(db_path)?;
-    schema::run_migrations(&conn)?;
-    Ok(conn)
+    let conn = Connection::open(&db_path)?;
+    schema::migrate_with_backup(&conn, &db_path)?;
+    let pool = DbPool::new(conn, &db_path, pool::DEFAULT_READERS)?;
+    commands::backup_cmd::spawn_auto_backup(db_path, app_dir.join("backups"));
+    Ok(pool)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -28,8 +31,8 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
-            let conn = init_database(app)?;
-            app.manage(Mutex::new(conn));
+            let pool = init_database(app)?;
+            app.manage(pool);
             app.manage(Mutex::new(CancelToken::new()));
             Ok(())
         })
@@ -39,8 +42,25 @@ pub fn run() {
             commands::library::search_library,
             commands::library::get_incomplete_tracks,
             commands::library::get_library_stats,
+            commands::library::get_top_tracks,
+            commands::library::get_top_artists,
+            commands::library::get_top_albums,
+            commands::library::get_top_genres,
+            commands::library::get_listening_time,
+            commands::library::get_listening_streaks,
+            commands::library::get_tracks_not_played_since,
             commands::library::find_duplicates,
+            commands::library::analyze_loudness,
+            commands::backup_cmd::export_user_data,
+            commands::backup_cmd::import_user_data,
+            commands::backup_cmd::create_database_backup,
+            commands::backup_cmd::list_database_backups,
+            commands::backup_cmd::restore_database_backup,
+            commands::scrobble_cmd::export_listenbrainz,
+            commands::scrobble_cmd::export_scrobbler_log,
+            commands::scrobble_cmd::import_scrobbler_log,
             commands::library::delete_duplicate_tracks,
+            commands::library::trash_duplicates,
             commands::library::import_tracks,
             commands::profile::create_profile,
             commands::profile::get_profile,
@@ -66,6 +86,10 @@ pub fn run() {
             commands::device_cmd::list_albums,
             commands::metadata_cmd::get_track_artwork,
             commands::metadata_cmd::update_track_metadata,
+            commands::metadata_cmd::set_track_rating,
+            commands::metadata_cmd::set_album_rating,
+            commands::metadata_cmd::get_track_lyrics,
+            commands::metadata_cmd::set_track_lyrics,
             commands::playlist_cmd::create_playlist,
             commands::playlist_cmd::list_playlists,
             commands::playlist_cmd::get_playlist,
@@ -81,6 +105,8 @@ pub fn run() {
             commands::favorite_cmd::list_all_favorites,
             commands::favorite_cmd::get_favorite_tracks,
             commands::recent_cmd::record_play,
+            commands::recent_cmd::finish_play,
+            commands::recent_cmd::record_skip,
             commands::recent_cmd::get_recently_added,
             commands::recent_cmd::get_recently_played,
         ])
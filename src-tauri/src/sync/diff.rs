@@ -104,6 +104,7 @@ pub fn compute_one_way_diff(
                     target_hash: None,
                     source_modified: Some(src.modified),
                     target_modified: None,
+                    conflict_type: None,
                 });
             }
             (None, Some(tgt)) => {
@@ -118,6 +119,7 @@ pub fn compute_one_way_diff(
                     target_hash: None,
                     source_modified: None,
                     target_modified: Some(tgt.modified),
+                    conflict_type: None,
                 });
             }
             (Some(src), Some(tgt)) => {
@@ -133,6 +135,7 @@ pub fn compute_one_way_diff(
                         target_hash: None,
                         source_modified: Some(src.modified),
                         target_modified: Some(tgt.modified),
+                        conflict_type: None,
                     });
                 } else {
                     let src_hash = compute_hash_if_needed(source, &rel, src)?;
@@ -149,6 +152,7 @@ pub fn compute_one_way_diff(
                             target_hash: Some(tgt_hash),
                             source_modified: Some(src.modified),
                             target_modified: Some(tgt.modified),
+                            conflict_type: None,
                         });
                     } else {
                         bytes_to_transfer += src.size;
@@ -163,6 +167,7 @@ pub fn compute_one_way_diff(
                             target_hash: Some(tgt_hash),
                             source_modified: Some(src.modified),
                             target_modified: Some(tgt.modified),
+                            conflict_type: None,
                         });
                     }
                 }
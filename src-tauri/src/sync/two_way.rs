@@ -1,3 +1,4 @@
+use std::cmp::Ordering as CmpOrdering;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -7,11 +8,15 @@ use tauri::ipc::Channel;
 use crate::sync::one_way::copy_file_safe;
 use orchestra_core::db::sync_state_repo::FileBaseline;
 use orchestra_core::error::AppError;
-use orchestra_core::models::conflict::{Conflict, ConflictResolution, ConflictType, Resolution};
-use orchestra_core::models::diff::{DiffAction, DiffDirection, DiffEntry, DiffResult};
+use orchestra_core::models::conflict::{
+    AppliedResolution, Conflict, ConflictPolicy, ConflictPolicyRule, ConflictResolution,
+    ConflictType, Resolution,
+};
+use orchestra_core::models::diff::{DiffAction, DiffDirection, DiffEntry, DiffResult, SyncResult};
 use orchestra_core::models::progress::ProgressEvent;
-use orchestra_core::models::track::is_audio_file;
-use orchestra_core::scanner::hasher;
+use orchestra_core::models::sync_profile::{KeepBothNaming, SyncProfile};
+use orchestra_core::models::track::{is_audio_file, HashKind};
+use orchestra_core::scanner::{hasher, writer};
 
 struct FileState {
     hash: String,
@@ -22,6 +27,7 @@ struct FileState {
 fn collect_file_states(
     root: &Path,
     exclude_patterns: &[String],
+    hash_kind: &HashKind,
 ) -> Result<HashMap<String, FileState>, AppError> {
     let compiled: Vec<glob::Pattern> = exclude_patterns
         .iter()
@@ -52,7 +58,7 @@ fn collect_file_states(
             .unwrap_or_default()
             .as_secs() as i64;
 
-        let hash = hasher::hash_file(entry.path())?;
+        let hash = hasher::hash_with(entry.path(), hash_kind)?;
         map.insert(
             rel,
             FileState {
@@ -70,10 +76,11 @@ pub fn compute_two_way_diff(
     source: &Path,
     target: &Path,
     exclude_patterns: &[String],
+    hash_kind: &HashKind,
     baselines: &HashMap<String, FileBaseline>,
 ) -> Result<(DiffResult, Vec<Conflict>), AppError> {
-    let source_files = collect_file_states(source, exclude_patterns)?;
-    let target_files = collect_file_states(target, exclude_patterns)?;
+    let source_files = collect_file_states(source, exclude_patterns, hash_kind)?;
+    let target_files = collect_file_states(target, exclude_patterns, hash_kind)?;
 
     let all_keys: HashSet<String> = source_files
         .keys()
@@ -222,6 +229,11 @@ pub fn compute_two_way_diff(
         if action == DiffAction::Unchanged {
             total_unchanged += 1;
         }
+        let conflict_type = if action == DiffAction::Conflict {
+            conflicts.last().map(|c| c.conflict_type.clone())
+        } else {
+            None
+        };
 
         entries.push(DiffEntry {
             relative_path: rel.clone(),
@@ -233,6 +245,7 @@ pub fn compute_two_way_diff(
             target_hash: tgt.map(|t| t.hash.clone()),
             source_modified: src.map(|s| s.modified),
             target_modified: tgt.map(|t| t.modified),
+            conflict_type,
         });
     }
 
@@ -252,18 +265,24 @@ pub fn compute_two_way_diff(
     Ok((diff, conflicts))
 }
 
+/// Conflicts without a manual resolution fall back to the profile's policy
+/// rules; anything neither resolves is skipped.
 pub fn execute_two_way_sync(
     diff: &DiffResult,
     resolutions: &[ConflictResolution],
-    source: &Path,
-    target: &Path,
+    profile: &SyncProfile,
     cancel_flag: Arc<AtomicBool>,
     channel: &Channel<ProgressEvent>,
-) -> Result<usize, AppError> {
+) -> Result<SyncResult, AppError> {
+    let source = Path::new(&profile.source_path);
+    let target = Path::new(&profile.target_path);
     let resolution_map: HashMap<&str, &Resolution> = resolutions
         .iter()
         .map(|r| (r.relative_path.as_str(), &r.resolution))
         .collect();
+    let compiled_policies = compile_policies(&profile.conflict_policies);
+    let conflict_suffix = conflict_suffix(&profile.keep_both_naming);
+    let mut applied_resolutions = Vec::new();
 
     let actionable: Vec<_> = diff
         .entries
@@ -301,11 +320,26 @@ pub fn execute_two_way_sync(
 
         let result = match entry.action {
             DiffAction::Conflict => {
-                if let Some(resolution) = resolution_map.get(entry.relative_path.as_str()) {
-                    apply_resolution(source, target, &entry.relative_path, resolution)
-                } else {
-                    Ok(()) // skip unresolved
-                }
+                let (resolution, policy) = match resolution_map.get(entry.relative_path.as_str()) {
+                    Some(resolution) => ((*resolution).clone(), None),
+                    None => match resolve_by_policy(entry, &compiled_policies) {
+                        Some((resolution, policy)) => (resolution, Some(policy)),
+                        None => (Resolution::Skip, None), // skip unresolved
+                    },
+                };
+                let result = apply_resolution(
+                    source,
+                    target,
+                    &entry.relative_path,
+                    &resolution,
+                    &conflict_suffix,
+                );
+                applied_resolutions.push(AppliedResolution {
+                    relative_path: entry.relative_path.clone(),
+                    resolution,
+                    policy,
+                });
+                result
             }
             DiffAction::Add | DiffAction::Update => match entry.direction {
                 DiffDirection::SourceToTarget => copy_file_safe(
@@ -349,7 +383,66 @@ pub fn execute_two_way_sync(
         duration_ms,
     });
 
-    Ok(files_completed)
+    Ok(SyncResult {
+        files_synced: files_completed,
+        resolutions: applied_resolutions,
+    })
+}
+
+fn compile_policies(
+    policies: &[ConflictPolicyRule],
+) -> Vec<(&ConflictPolicyRule, Option<glob::Pattern>)> {
+    policies
+        .iter()
+        .filter_map(|rule| match &rule.pattern {
+            Some(p) => glob::Pattern::new(p)
+                .ok()
+                .map(|pattern| (rule, Some(pattern))),
+            None => Some((rule, None)),
+        })
+        .collect()
+}
+
+/// Returns the resolution chosen by the first matching rule that can decide.
+/// `NewestWins` and `LargestWins` fall through to the next rule on a tie.
+fn resolve_by_policy(
+    entry: &DiffEntry,
+    policies: &[(&ConflictPolicyRule, Option<glob::Pattern>)],
+) -> Option<(Resolution, ConflictPolicy)> {
+    policies
+        .iter()
+        .filter(|(rule, pattern)| {
+            let type_matches = rule
+                .conflict_type
+                .as_ref()
+                .is_none_or(|t| entry.conflict_type.as_ref() == Some(t));
+            let path_matches = pattern
+                .as_ref()
+                .is_none_or(|p| p.matches(&entry.relative_path));
+            type_matches && path_matches
+        })
+        .find_map(|(rule, _)| {
+            policy_resolution(&rule.policy, entry).map(|r| (r, rule.policy.clone()))
+        })
+}
+
+fn policy_resolution(policy: &ConflictPolicy, entry: &DiffEntry) -> Option<Resolution> {
+    match policy {
+        ConflictPolicy::SourceWins => Some(Resolution::KeepSource),
+        ConflictPolicy::TargetWins => Some(Resolution::KeepTarget),
+        ConflictPolicy::KeepBoth => Some(Resolution::KeepBoth),
+        ConflictPolicy::NewestWins => pick_greater(entry.source_modified, entry.target_modified),
+        ConflictPolicy::LargestWins => pick_greater(entry.source_size, entry.target_size),
+    }
+}
+
+// `None` orders below `Some`, so a deleted side always loses to a modified one.
+fn pick_greater<T: Ord>(source: Option<T>, target: Option<T>) -> Option<Resolution> {
+    match source.cmp(&target) {
+        CmpOrdering::Greater => Some(Resolution::KeepSource),
+        CmpOrdering::Less => Some(Resolution::KeepTarget),
+        CmpOrdering::Equal => None,
+    }
 }
 
 fn apply_resolution(
@@ -357,6 +450,7 @@ fn apply_resolution(
     target: &Path,
     relative_path: &str,
     resolution: &Resolution,
+    conflict_suffix: &str,
 ) -> Result<(), AppError> {
     let src = source.join(relative_path);
     let tgt = target.join(relative_path);
@@ -379,16 +473,8 @@ fn apply_resolution(
         Resolution::KeepBoth => {
             // Rename target with suffix, copy source to target
             if src.exists() && tgt.exists() {
-                let stem = tgt
-                    .file_stem()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-                let ext = tgt
-                    .extension()
-                    .map(|e| format!(".{}", e.to_string_lossy()))
-                    .unwrap_or_default();
-                let conflict_name = format!("{}_conflict{}", stem, ext);
+                let conflict_name =
+                    conflict_file_name(source, target, relative_path, conflict_suffix);
                 let conflict_path = tgt.with_file_name(&conflict_name);
                 std::fs::rename(&tgt, &conflict_path)?;
                 copy_file_safe(&src, &tgt)?;
@@ -399,10 +485,79 @@ fn apply_resolution(
             }
             Ok(())
         }
+        Resolution::MergeTags => {
+            if !src.exists() || !tgt.exists() {
+                return Err(AppError::General(format!(
+                    "Cannot merge tags for {}: file is missing on one side",
+                    relative_path
+                )));
+            }
+            if hasher::hash_audio(&src)? != hasher::hash_audio(&tgt)? {
+                return Err(AppError::General(format!(
+                    "Cannot merge tags for {}: audio differs",
+                    relative_path
+                )));
+            }
+            // Where both sides set a field, the more recently modified file wins
+            let (newer, older) =
+                if std::fs::metadata(&src)?.modified()? >= std::fs::metadata(&tgt)?.modified()? {
+                    (&src, &tgt)
+                } else {
+                    (&tgt, &src)
+                };
+            writer::merge_tags(newer, older)?;
+            copy_file_safe(newer, older)
+        }
         Resolution::Skip => Ok(()),
     }
 }
 
+fn conflict_suffix(naming: &KeepBothNaming) -> String {
+    let timestamp = || chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    match naming {
+        KeepBothNaming::Timestamp => timestamp(),
+        KeepBothNaming::Hostname => hostname().unwrap_or_else(timestamp),
+    }
+}
+
+fn hostname() -> Option<String> {
+    let output = std::process::Command::new("hostname").output().ok()?;
+    let name: String = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .split('.')
+        .next()?
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Builds `<stem>_conflict_<suffix>.<ext>`, adding a counter if that name is
+/// already taken on either side so earlier conflict copies are never overwritten.
+fn conflict_file_name(source: &Path, target: &Path, relative_path: &str, suffix: &str) -> String {
+    let rel = Path::new(relative_path);
+    let stem = rel
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = rel
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let base = format!("{}_conflict_{}", stem, suffix);
+    let mut name = format!("{}{}", base, ext);
+    let mut counter = 2;
+    while source.join(rel.with_file_name(&name)).exists()
+        || target.join(rel.with_file_name(&name)).exists()
+    {
+        name = format!("{}_{}{}", base, counter, ext);
+        counter += 1;
+    }
+    name
+}
+
 fn remove_if_exists(path: &Path) -> Result<(), AppError> {
     if path.exists() {
         std::fs::remove_file(path)?;
@@ -442,8 +597,15 @@ mod tests {
         write_file(source.path(), "new.flac", b"new track");
 
         let baselines = HashMap::new();
-        let (diff, conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         assert_eq!(diff.total_add, 1);
         assert_eq!(conflicts.len(), 0);
@@ -463,8 +625,15 @@ mod tests {
         write_file(target.path(), "new.flac", b"new track on target");
 
         let baselines = HashMap::new();
-        let (diff, conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         assert_eq!(diff.total_add, 1);
         assert_eq!(conflicts.len(), 0);
@@ -485,8 +654,15 @@ mod tests {
         write_file(target.path(), "track.flac", b"version B");
 
         let baselines = HashMap::new();
-        let (diff, conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         assert_eq!(diff.total_conflict, 1);
         assert_eq!(conflicts.len(), 1);
@@ -501,8 +677,15 @@ mod tests {
         write_file(target.path(), "track.flac", b"same content");
 
         let baselines = HashMap::new();
-        let (diff, conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         assert_eq!(diff.total_unchanged, 1);
         assert_eq!(conflicts.len(), 0);
@@ -528,8 +711,15 @@ mod tests {
             make_baseline("track.flac", &orig_hash),
         );
 
-        let (diff, conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         assert_eq!(diff.total_update, 1);
         assert_eq!(conflicts.len(), 0);
@@ -560,8 +750,15 @@ mod tests {
             make_baseline("track.flac", &orig_hash),
         );
 
-        let (diff, conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         assert_eq!(diff.total_conflict, 1);
         assert_eq!(conflicts.len(), 1);
@@ -587,8 +784,15 @@ mod tests {
             make_baseline("track.flac", &orig_hash),
         );
 
-        let (diff, _conflicts) =
-            compute_two_way_diff("test", source.path(), target.path(), &[], &baselines).unwrap();
+        let (diff, _conflicts) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &baselines,
+        )
+        .unwrap();
 
         // Deleted from source, target unchanged → propagate delete to target
         assert_eq!(diff.total_remove, 1);
@@ -600,4 +804,235 @@ mod tests {
         assert_eq!(entry.action, DiffAction::Remove);
         assert_eq!(entry.direction, DiffDirection::TargetToSource);
     }
+
+    fn conflict_entry(rel: &str, conflict_type: ConflictType) -> DiffEntry {
+        DiffEntry {
+            relative_path: rel.to_string(),
+            action: DiffAction::Conflict,
+            direction: DiffDirection::Both,
+            source_size: Some(200),
+            target_size: Some(100),
+            source_hash: Some("aaa".to_string()),
+            target_hash: Some("bbb".to_string()),
+            source_modified: Some(1000),
+            target_modified: Some(2000),
+            conflict_type: Some(conflict_type),
+        }
+    }
+
+    fn rule(
+        policy: ConflictPolicy,
+        conflict_type: Option<ConflictType>,
+        pattern: Option<&str>,
+    ) -> ConflictPolicyRule {
+        ConflictPolicyRule {
+            policy,
+            conflict_type,
+            pattern: pattern.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_two_way_conflict_entry_records_type() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        write_file(source.path(), "track.flac", b"version A");
+        write_file(target.path(), "track.flac", b"version B");
+
+        let (diff, _) = compute_two_way_diff(
+            "test",
+            source.path(),
+            target.path(),
+            &[],
+            &HashKind::File,
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            diff.entries[0].conflict_type,
+            Some(ConflictType::FirstSyncDiffers)
+        );
+    }
+
+    #[test]
+    fn test_policy_newest_and_largest_wins() {
+        let entry = conflict_entry("a.flac", ConflictType::BothModified);
+
+        let policies = [rule(ConflictPolicy::NewestWins, None, None)];
+        let resolved = resolve_by_policy(&entry, &compile_policies(&policies));
+        assert_eq!(
+            resolved,
+            Some((Resolution::KeepTarget, ConflictPolicy::NewestWins))
+        );
+
+        let policies = [rule(ConflictPolicy::LargestWins, None, None)];
+        let resolved = resolve_by_policy(&entry, &compile_policies(&policies));
+        assert_eq!(
+            resolved,
+            Some((Resolution::KeepSource, ConflictPolicy::LargestWins))
+        );
+    }
+
+    #[test]
+    fn test_policy_newest_wins_keeps_modified_side_over_delete() {
+        let mut entry = conflict_entry("a.flac", ConflictType::DeletedAndModified);
+        entry.source_modified = None;
+        entry.source_size = None;
+        entry.source_hash = None;
+
+        let policies = [rule(ConflictPolicy::NewestWins, None, None)];
+        let resolved = resolve_by_policy(&entry, &compile_policies(&policies));
+        assert_eq!(
+            resolved,
+            Some((Resolution::KeepTarget, ConflictPolicy::NewestWins))
+        );
+    }
+
+    #[test]
+    fn test_policy_tie_falls_through_to_next_rule() {
+        let mut entry = conflict_entry("a.flac", ConflictType::BothModified);
+        entry.target_modified = entry.source_modified;
+
+        let policies = [
+            rule(ConflictPolicy::NewestWins, None, None),
+            rule(ConflictPolicy::SourceWins, None, None),
+        ];
+        let resolved = resolve_by_policy(&entry, &compile_policies(&policies));
+        assert_eq!(
+            resolved,
+            Some((Resolution::KeepSource, ConflictPolicy::SourceWins))
+        );
+    }
+
+    #[test]
+    fn test_policy_filters_by_type_and_glob() {
+        let policies = [
+            rule(ConflictPolicy::KeepBoth, None, Some("Live/**")),
+            rule(
+                ConflictPolicy::TargetWins,
+                Some(ConflictType::FirstSyncDiffers),
+                None,
+            ),
+        ];
+        let compiled = compile_policies(&policies);
+
+        let live = conflict_entry("Live/set.flac", ConflictType::BothModified);
+        assert_eq!(
+            resolve_by_policy(&live, &compiled),
+            Some((Resolution::KeepBoth, ConflictPolicy::KeepBoth))
+        );
+
+        let first = conflict_entry("Studio/a.flac", ConflictType::FirstSyncDiffers);
+        assert_eq!(
+            resolve_by_policy(&first, &compiled),
+            Some((Resolution::KeepTarget, ConflictPolicy::TargetWins))
+        );
+
+        let other = conflict_entry("Studio/a.flac", ConflictType::BothModified);
+        assert_eq!(resolve_by_policy(&other, &compiled), None);
+    }
+
+    #[test]
+    fn test_keep_both_twice_keeps_earlier_conflict_copy() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        write_file(source.path(), "a/track.flac", b"source v1");
+        write_file(target.path(), "a/track.flac", b"target v1");
+
+        apply_resolution(
+            source.path(),
+            target.path(),
+            "a/track.flac",
+            &Resolution::KeepBoth,
+            "host",
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read(target.path().join("a/track_conflict_host.flac")).unwrap(),
+            b"target v1"
+        );
+
+        write_file(target.path(), "a/track.flac", b"target v2");
+        apply_resolution(
+            source.path(),
+            target.path(),
+            "a/track.flac",
+            &Resolution::KeepBoth,
+            "host",
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read(target.path().join("a/track_conflict_host.flac")).unwrap(),
+            b"target v1"
+        );
+        assert_eq!(
+            fs::read(target.path().join("a/track_conflict_host_2.flac")).unwrap(),
+            b"target v2"
+        );
+        assert_eq!(
+            fs::read(source.path().join("a/track_conflict_host_2.flac")).unwrap(),
+            b"target v2"
+        );
+        assert_eq!(
+            fs::read(target.path().join("a/track.flac")).unwrap(),
+            b"source v1"
+        );
+    }
+
+    #[test]
+    fn test_merge_tags_rejects_missing_side() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        write_file(source.path(), "track.flac", b"only on source");
+
+        let result = apply_resolution(
+            source.path(),
+            target.path(),
+            "track.flac",
+            &Resolution::MergeTags,
+            "host",
+        );
+        assert!(result.is_err());
+    }
+
+    /// A second of 16-bit mono audio with every byte set to `fill`.
+    fn write_wav(root: &Path, rel: &str, fill: u8) {
+        let samples = vec![fill; 8_000 * 2];
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8_000u32.to_le_bytes());
+        wav.extend_from_slice(&16_000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&samples);
+        write_file(root, rel, &wav);
+    }
+
+    #[test]
+    fn test_merge_tags_rejects_different_audio_with_same_properties() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        // Same format and length, different recording
+        write_wav(source.path(), "track.wav", 0);
+        write_wav(target.path(), "track.wav", 1);
+        let before = fs::read(target.path().join("track.wav")).unwrap();
+
+        let result = apply_resolution(
+            source.path(),
+            target.path(),
+            "track.wav",
+            &Resolution::MergeTags,
+            "host",
+        );
+        assert!(result.is_err());
+        assert_eq!(fs::read(target.path().join("track.wav")).unwrap(), before);
+    }
 }
@@ -2,23 +2,33 @@ import { invoke, Channel } from "@tauri-apps/api/core";
 import type {
   LibraryTree,
   Track,
+  Lyrics,
+  LyricLine,
   SyncProfile,
   CreateProfileRequest,
   UpdateProfileRequest,
   DiffResult,
   Conflict,
   ConflictResolution,
+  SyncResult,
   ProgressEvent,
   DetectedVolume,
   DeviceWithStatus,
   RegisterDeviceRequest,
   ArtistSummary,
-  AlbumSelection,
   AlbumSummary,
   TrackMetadataUpdate,
   AlbumArt,
   LibraryStats,
+  TrackPlayStats,
+  PlayStat,
+  StatsPeriod,
+  ListeningTime,
+  ListeningStreaks,
   DuplicateResult,
+  HashKind,
+  KeepRule,
+  KeeperSelection,
   Playlist,
   PlaylistWithTracks,
   CreatePlaylistRequest,
@@ -27,6 +37,9 @@ import type {
   RemoveTracksRequest,
   ReorderTracksRequest,
   Favorite,
+  ImportSummary,
+  BackupFile,
+  ScrobbleImportSummary,
 } from "./types";
 
 export function scanDirectory(
@@ -77,7 +90,7 @@ export function executeSync(
   diffResult: DiffResult,
   conflictResolutions: ConflictResolution[],
   onProgress: (event: ProgressEvent) => void,
-): Promise<number> {
+): Promise<SyncResult> {
   const channel = new Channel<ProgressEvent>();
   channel.onmessage = onProgress;
   return invoke("execute_sync", {
@@ -104,6 +117,39 @@ export function getAllSettings(): Promise<[string, string][]> {
   return invoke("get_all_settings");
 }
 
+export function exportUserData(path: string): Promise<void> {
+  return invoke("export_user_data", { path });
+}
+
+export function importUserData(path: string): Promise<ImportSummary> {
+  return invoke("import_user_data", { path });
+}
+
+export function createDatabaseBackup(): Promise<BackupFile> {
+  return invoke("create_database_backup");
+}
+
+export function listDatabaseBackups(): Promise<BackupFile[]> {
+  return invoke("list_database_backups");
+}
+
+export function restoreDatabaseBackup(path: string): Promise<void> {
+  return invoke("restore_database_backup", { path });
+}
+
+/** Returns the number of listens written. */
+export function exportListenBrainz(path: string): Promise<number> {
+  return invoke("export_listenbrainz", { path });
+}
+
+export function exportScrobblerLog(path: string): Promise<number> {
+  return invoke("export_scrobbler_log", { path });
+}
+
+export function importScrobblerLog(path: string): Promise<ScrobbleImportSummary> {
+  return invoke("import_scrobbler_log", { path });
+}
+
 export function detectVolumes(): Promise<DetectedVolume[]> {
   return invoke("detect_volumes");
 }
@@ -120,7 +166,7 @@ export function deleteDevice(deviceId: string): Promise<void> {
   return invoke("delete_device", { deviceId });
 }
 
-export function setDeviceArtists(deviceId: string, artists: string[]): Promise<void> {
+export function setDeviceArtists(deviceId: string, artists: number[]): Promise<void> {
   return invoke("set_device_artists", { deviceId, artists });
 }
 
@@ -155,7 +201,7 @@ export function listArtists(): Promise<ArtistSummary[]> {
   return invoke("list_artists");
 }
 
-export function setDeviceAlbums(deviceId: string, albums: AlbumSelection[]): Promise<void> {
+export function setDeviceAlbums(deviceId: string, albums: number[]): Promise<void> {
   return invoke("set_device_albums", { deviceId, albums });
 }
 
@@ -171,6 +217,24 @@ export function updateTrackMetadata(updates: TrackMetadataUpdate[]): Promise<Tra
   return invoke("update_track_metadata", { updates });
 }
 
+/** `null` or 0 clears the rating. */
+export function setTrackRating(trackId: number, rating: number | null): Promise<Track> {
+  return invoke("set_track_rating", { trackId, rating });
+}
+
+export function setAlbumRating(albumId: number, rating: number | null): Promise<void> {
+  return invoke("set_album_rating", { albumId, rating });
+}
+
+export function getTrackLyrics(trackId: number): Promise<Lyrics | null> {
+  return invoke("get_track_lyrics", { trackId });
+}
+
+/** Writes to the track's `.lrc` sidecar if it has one; no lines removes the lyrics. */
+export function setTrackLyrics(trackId: number, lines: LyricLine[]): Promise<Lyrics | null> {
+  return invoke("set_track_lyrics", { trackId, lines });
+}
+
 export function getIncompleteTracks(root: string): Promise<Track[]> {
   return invoke("get_incomplete_tracks", { root });
 }
@@ -179,6 +243,44 @@ export function getLibraryStats(root: string): Promise<LibraryStats> {
   return invoke("get_library_stats", { root });
 }
 
+/** Omit `days` for all-time stats. */
+export function getTopTracks(root: string, limit: number, days?: number): Promise<TrackPlayStats[]> {
+  return invoke("get_top_tracks", { root, days: days ?? null, limit });
+}
+
+export function getTopArtists(root: string, limit: number, days?: number): Promise<PlayStat[]> {
+  return invoke("get_top_artists", { root, days: days ?? null, limit });
+}
+
+export function getTopAlbums(root: string, limit: number, days?: number): Promise<PlayStat[]> {
+  return invoke("get_top_albums", { root, days: days ?? null, limit });
+}
+
+export function getTopGenres(root: string, limit: number, days?: number): Promise<PlayStat[]> {
+  return invoke("get_top_genres", { root, days: days ?? null, limit });
+}
+
+export function getListeningTime(
+  root: string,
+  period: StatsPeriod,
+  days?: number,
+): Promise<ListeningTime[]> {
+  return invoke("get_listening_time", { root, period, days: days ?? null });
+}
+
+export function getListeningStreaks(root: string): Promise<ListeningStreaks> {
+  return invoke("get_listening_streaks", { root });
+}
+
+export function getTracksNotPlayedSince(
+  root: string,
+  months: number,
+  includeNeverPlayed: boolean,
+  limit: number,
+): Promise<Track[]> {
+  return invoke("get_tracks_not_played_since", { root, months, includeNeverPlayed, limit });
+}
+
 export function createPlaylist(request: CreatePlaylistRequest): Promise<PlaylistWithTracks> {
   return invoke("create_playlist", { request });
 }
@@ -218,10 +320,21 @@ export function exportPlaylist(id: string, format: string, path: string): Promis
 export function findDuplicates(
   root: string,
   onProgress: (event: ProgressEvent) => void,
+  hashKind?: HashKind,
+  acousticThreshold?: number,
 ): Promise<DuplicateResult> {
   const channel = new Channel<ProgressEvent>();
   channel.onmessage = onProgress;
-  return invoke("find_duplicates", { root, onProgress: channel });
+  return invoke("find_duplicates", { root, hashKind, acousticThreshold, onProgress: channel });
+}
+
+export function analyzeLoudness(
+  root: string,
+  onProgress: (event: ProgressEvent) => void,
+): Promise<number> {
+  const channel = new Channel<ProgressEvent>();
+  channel.onmessage = onProgress;
+  return invoke("analyze_loudness", { root, onProgress: channel });
 }
 
 export function deleteDuplicateTracks(
@@ -231,6 +344,23 @@ export function deleteDuplicateTracks(
   return invoke("delete_duplicate_tracks", { trackIds, filePaths });
 }
 
+export function trashDuplicates(selections: KeeperSelection[]): Promise<number> {
+  return invoke("trash_duplicates", { selections });
+}
+
+/** Settings key for the duplicate keep rules (see `dedupe::KEEP_RULES_SETTING`). */
+const KEEP_RULES_SETTING = "duplicate_keep_rules";
+
+/** The configured keep rules, or null when the backend defaults apply. */
+export async function getKeepRules(): Promise<KeepRule[] | null> {
+  const value = await getSetting(KEEP_RULES_SETTING);
+  return value ? (JSON.parse(value) as KeepRule[]) : null;
+}
+
+export function setKeepRules(rules: KeepRule[]): Promise<void> {
+  return setSetting(KEEP_RULES_SETTING, JSON.stringify(rules));
+}
+
 export function toggleFavorite(entityType: string, entityId: string): Promise<boolean> {
   return invoke("toggle_favorite", { entityType, entityId });
 }
@@ -261,10 +391,19 @@ export function importTracks(
   return invoke("import_tracks", { sourcePaths, libraryRoot, onProgress: channel });
 }
 
-export function recordPlay(trackId: number): Promise<void> {
+/** Returns the play's id, for `finishPlay`. */
+export function recordPlay(trackId: number): Promise<number> {
   return invoke("record_play", { trackId });
 }
 
+export function finishPlay(playId: number, listenedSecs: number, completed: boolean): Promise<void> {
+  return invoke("finish_play", { playId, listenedSecs, completed });
+}
+
+export function recordSkip(trackId: number, listenedSecs: number): Promise<void> {
+  return invoke("record_skip", { trackId, listenedSecs });
+}
+
 export function getRecentlyAdded(days: number, limit: number): Promise<Track[]> {
   return invoke("get_recently_added", { days, limit });
 }
@@ -36,10 +36,13 @@ export function createProgressHandler(callbacks: {
         break;
       case "hash_started":
       case "hash_progress":
+      case "loudness_started":
+      case "loudness_progress":
       case "scan_tree_updated":
       case "device_scan_progress":
       case "diff_progress":
       case "diff_complete":
+      case "scrobbles_imported":
         // handled by dedicated stores
         break;
     }
@@ -19,15 +19,42 @@ export interface Track {
   has_album_art: boolean;
   bitrate: number | null;
   scanned_at: number;
+  audio_hash: string | null;
+  replaygain_track_gain: number | null;
+  replaygain_track_peak: number | null;
+  replaygain_album_gain: number | null;
+  replaygain_album_peak: number | null;
+  /** 1–100, one star per 20; null when unrated. */
+  rating: number | null;
+  album_id: number | null;
+  /** As of the last scan; `getTrackLyrics` has the current lyrics. */
+  lyrics: LyricsKind | null;
 }
 
+export type LyricsKind = "plain" | "synced";
+
+export interface LyricLine {
+  /** Milliseconds from the start of the track; null in plain lyrics. */
+  time_ms: number | null;
+  text: string;
+}
+
+export interface Lyrics {
+  source: "embedded" | "sidecar";
+  lines: LyricLine[];
+}
+
+export type HashKind = "file" | "audio";
+
 export interface AlbumNode {
+  id: number | null;
   name: string;
   year: number | null;
   tracks: Track[];
 }
 
 export interface ArtistNode {
+  id: number | null;
   name: string;
   albums: AlbumNode[];
 }
@@ -41,6 +68,7 @@ export interface LibraryTree {
 export type LibraryViewMode = "artist" | "album" | "genre" | "folder";
 
 export interface AlbumEntry {
+  id: number | null;
   name: string;
   artist: string;
   year: number | null;
@@ -69,6 +97,8 @@ export interface TrackMetadataUpdate {
   disc_number?: number | null;
   year?: number | null;
   genre?: string | null;
+  /** 1–100; 0 clears the rating. */
+  rating?: number | null;
 }
 
 export interface AlbumArt {
@@ -78,6 +108,8 @@ export interface AlbumArt {
 
 export type SyncMode = "one_way" | "two_way";
 
+export type KeepBothNaming = "timestamp" | "hostname";
+
 export interface SyncProfile {
   id: string;
   name: string;
@@ -85,6 +117,9 @@ export interface SyncProfile {
   target_path: string;
   sync_mode: SyncMode;
   exclude_patterns: string[];
+  conflict_policies: ConflictPolicyRule[];
+  keep_both_naming: KeepBothNaming;
+  hash_kind: HashKind;
   created_at: number;
   last_synced_at: number | null;
 }
@@ -95,6 +130,9 @@ export interface CreateProfileRequest {
   target_path: string;
   sync_mode: SyncMode;
   exclude_patterns: string[];
+  conflict_policies?: ConflictPolicyRule[];
+  keep_both_naming?: KeepBothNaming;
+  hash_kind?: HashKind;
 }
 
 export interface UpdateProfileRequest {
@@ -104,6 +142,9 @@ export interface UpdateProfileRequest {
   target_path?: string;
   sync_mode?: SyncMode;
   exclude_patterns?: string[];
+  conflict_policies?: ConflictPolicyRule[];
+  keep_both_naming?: KeepBothNaming;
+  hash_kind?: HashKind;
 }
 
 export type DiffAction = "add" | "remove" | "update" | "unchanged" | "conflict";
@@ -119,6 +160,7 @@ export interface DiffEntry {
   target_hash: string | null;
   source_modified: number | null;
   target_modified: number | null;
+  conflict_type: ConflictType | null;
 }
 
 export interface DiffResult {
@@ -133,7 +175,7 @@ export interface DiffResult {
 }
 
 export type ConflictType = "both_modified" | "deleted_and_modified" | "first_sync_differs";
-export type Resolution = "keep_source" | "keep_target" | "keep_both" | "skip";
+export type Resolution = "keep_source" | "keep_target" | "keep_both" | "merge_tags" | "skip";
 
 export interface Conflict {
   relative_path: string;
@@ -151,6 +193,30 @@ export interface ConflictResolution {
   resolution: Resolution;
 }
 
+export type ConflictPolicy =
+  | "newest_wins"
+  | "largest_wins"
+  | "source_wins"
+  | "target_wins"
+  | "keep_both";
+
+export interface ConflictPolicyRule {
+  policy: ConflictPolicy;
+  conflict_type?: ConflictType | null;
+  pattern?: string | null;
+}
+
+export interface AppliedResolution {
+  relative_path: string;
+  resolution: Resolution;
+  policy: ConflictPolicy | null;
+}
+
+export interface SyncResult {
+  files_synced: number;
+  resolutions: AppliedResolution[];
+}
+
 export interface DetectedVolume {
   volume_uuid: string;
   volume_name: string;
@@ -173,24 +239,28 @@ export interface Device {
   last_synced_at: number | null;
 }
 
-export interface AlbumSelection {
-  artist_name: string;
-  album_name: string;
-}
-
 export interface AlbumSummary {
+  id: number;
+  artist_id: number;
   artist_name: string;
   album_name: string;
+  sort_name: string;
   track_count: number;
   total_size: number;
+  /** Latest year on the album's tracks. */
   year: number | null;
+  year_min: number | null;
+  duration_secs: number;
+  cover_track_id: number | null;
+  rating: number | null;
 }
 
 export interface DeviceWithStatus {
   device: Device;
   connected: boolean;
-  selected_artists: string[];
-  selected_albums: AlbumSelection[];
+  /** Artist and album ids. */
+  selected_artists: number[];
+  selected_albums: number[];
 }
 
 export interface RegisterDeviceRequest {
@@ -203,7 +273,9 @@ export interface RegisterDeviceRequest {
 }
 
 export interface ArtistSummary {
+  id: number;
   name: string;
+  sort_name: string;
   album_count: number;
   track_count: number;
   total_size: number;
@@ -222,7 +294,10 @@ export type ProgressEvent =
   | { type: "sync_complete"; files_synced: number; duration_ms: number }
   | { type: "sync_error"; file: string; error: string }
   | { type: "hash_started"; total: number }
-  | { type: "hash_progress"; files_hashed: number; total_files: number; current_file: string };
+  | { type: "hash_progress"; files_hashed: number; total_files: number; current_file: string }
+  | { type: "loudness_started"; total: number }
+  | { type: "loudness_progress"; files_analyzed: number; total_files: number; current_file: string }
+  | { type: "scrobbles_imported"; imported: number; duplicates: number; unmatched: number };
 
 export interface FormatStat {
   format: string;
@@ -246,6 +321,37 @@ export interface LibraryStats {
   genres: GenreStat[];
 }
 
+export interface TrackPlayStats {
+  track: Track;
+  plays: number;
+  skips: number;
+  listened_secs: number;
+  last_played: number;
+}
+
+/** An artist, album or genre ranked by plays; `artist` is set for albums. */
+export interface PlayStat {
+  name: string;
+  artist: string | null;
+  plays: number;
+  listened_secs: number;
+}
+
+export type StatsPeriod = "day" | "week";
+
+export interface ListeningTime {
+  period: string;
+  plays: number;
+  listened_secs: number;
+}
+
+export interface ListeningStreaks {
+  current_days: number;
+  longest_days: number;
+  longest_start: string | null;
+  longest_end: string | null;
+}
+
 export interface Playlist {
   id: string;
   name: string;
@@ -288,12 +394,55 @@ export interface Favorite {
   created_at: number;
 }
 
-export type DuplicateMatchType = "content_hash" | "metadata_similarity";
+export interface ImportSummary {
+  playlists: number;
+  playlist_tracks: number;
+  favorites: number;
+  plays: number;
+  ratings: number;
+  sync_profiles: number;
+  devices: number;
+  settings: number;
+  unmatched_tracks: number;
+}
+
+export interface ScrobbleImportSummary {
+  imported: number;
+  duplicates: number;
+  unmatched: number;
+}
+
+export interface BackupFile {
+  path: string;
+  created_at: number;
+  size: number;
+}
+
+export type DuplicateMatchType =
+  | "content_hash"
+  | "audio_hash"
+  | "acoustic_fingerprint"
+  | "metadata_similarity";
 
 export interface DuplicateGroup {
   match_type: DuplicateMatchType;
   match_key: string;
   tracks: Track[];
+  keeper_id: number | null;
+}
+
+export type KeepRule =
+  | { rule: "prefer_lossless" }
+  | { rule: "higher_bitrate" }
+  | { rule: "has_album_art" }
+  | { rule: "more_complete_tags" }
+  | { rule: "higher_rating" }
+  | { rule: "prefer_path_prefix"; prefix: string }
+  | { rule: "newest" };
+
+export interface KeeperSelection {
+  keeper_id: number;
+  duplicate_ids: number[];
 }
 
 export interface DuplicateResult {
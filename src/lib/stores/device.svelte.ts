@@ -3,10 +3,10 @@ import type {
   DeviceWithStatus,
   RegisterDeviceRequest,
   ArtistSummary,
-  AlbumSelection,
   AlbumSummary,
   DiffResult,
   ProgressEvent,
+  ScrobbleImportSummary,
 } from "../api/types";
 import * as commands from "../api/commands";
 
@@ -42,6 +42,8 @@ class DeviceStore {
     currentFile: "",
   });
   syncErrors = $state<{ file: string; error: string }[]>([]);
+  /** Plays read from the device's `.scrobbler.log` during the last sync. */
+  scrobbleImport = $state<ScrobbleImportSummary | null>(null);
   startedAt = $state<number | null>(null);
   error = $state<string | null>(null);
   detecting = $state(false);
@@ -145,7 +147,7 @@ class DeviceStore {
     }
   }
 
-  async setAlbums(deviceId: string, albums: AlbumSelection[]) {
+  async setAlbums(deviceId: string, albums: number[]) {
     this.error = null;
     try {
       await commands.setDeviceAlbums(deviceId, albums);
@@ -159,7 +161,7 @@ class DeviceStore {
     }
   }
 
-  async setArtists(deviceId: string, artists: string[]) {
+  async setArtists(deviceId: string, artists: number[]) {
     this.error = null;
     try {
       await commands.setDeviceArtists(deviceId, artists);
@@ -226,6 +228,7 @@ class DeviceStore {
     this.startedAt = Date.now();
     this.error = null;
     this.syncErrors = [];
+    this.scrobbleImport = null;
     this.syncProgress = {
       filesCompleted: 0,
       totalFiles: 0,
@@ -265,6 +268,13 @@ class DeviceStore {
                 { file: event.file, error: event.error },
               ];
               break;
+            case "scrobbles_imported":
+              this.scrobbleImport = {
+                imported: event.imported,
+                duplicates: event.duplicates,
+                unmatched: event.unmatched,
+              };
+              break;
           }
         },
       );
@@ -289,6 +299,7 @@ class DeviceStore {
     this.syncPhase = "idle";
     this.diffResult = null;
     this.syncErrors = [];
+    this.scrobbleImport = null;
     this.startedAt = null;
     this.error = null;
     this.diffProgress = {
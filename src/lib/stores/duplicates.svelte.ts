@@ -1,4 +1,4 @@
-import type { DuplicateResult, ProgressEvent } from "../api/types";
+import type { DuplicateResult, HashKind, ProgressEvent } from "../api/types";
 import * as commands from "../api/commands";
 
 export type DupDetectionPhase = "idle" | "hashing" | "complete" | "error";
@@ -9,6 +9,9 @@ class DuplicatesStore {
   startedAt = $state<number | null>(null);
   result = $state<DuplicateResult | null>(null);
   error = $state<string | null>(null);
+  hashKind = $state<HashKind>("file");
+  /** Minimum fingerprint similarity for acoustic matches; null skips fingerprinting. */
+  acousticThreshold = $state<number | null>(null);
 
   async run(root: string): Promise<void> {
     if (this.phase !== "idle") return;
@@ -36,7 +39,7 @@ class DuplicatesStore {
             };
             break;
         }
-      });
+      }, this.hashKind, this.acousticThreshold ?? undefined);
       this.result = result;
       this.phase = "complete";
     } catch (e) {
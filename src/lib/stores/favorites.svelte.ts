@@ -9,8 +9,9 @@ class FavoritesStore {
     return `${entityType}:${entityId}`;
   }
 
-  isFavorite(entityType: string, entityId: string): boolean {
-    return this.favorites.has(this.makeKey(entityType, entityId));
+  /** Ids are row ids; albums and artists not yet filed (`null`) can't be favorites. */
+  isFavorite(entityType: string, entityId: string | number | null): boolean {
+    return entityId != null && this.favorites.has(this.makeKey(entityType, String(entityId)));
   }
 
   async load() {
@@ -30,11 +31,12 @@ class FavoritesStore {
     }
   }
 
-  async toggle(entityType: string, entityId: string) {
+  async toggle(entityType: string, entityId: string | number | null) {
+    if (entityId == null) return;
     try {
-      const isNowFavorited = await commands.toggleFavorite(entityType, entityId);
+      const isNowFavorited = await commands.toggleFavorite(entityType, String(entityId));
       const next = new Set(this.favorites);
-      const key = this.makeKey(entityType, entityId);
+      const key = this.makeKey(entityType, String(entityId));
       if (isNowFavorited) {
         next.add(key);
       } else {
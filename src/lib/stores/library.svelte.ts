@@ -76,13 +76,13 @@ class LibraryStore {
 
   displayArtists = $derived<ArtistNode[]>(
     this.favoritesOnly
-      ? this.filteredArtists.filter((a) => favoritesStore.isFavorite('artist', a.name))
+      ? this.filteredArtists.filter((a) => favoritesStore.isFavorite('artist', a.id))
       : this.filteredArtists
   );
 
   displayAlbumEntries = $derived<AlbumEntry[]>(
     this.favoritesOnly
-      ? this.filteredAlbumEntries.filter((a) => favoritesStore.isFavorite('album', a.artist + "\0" + a.name))
+      ? this.filteredAlbumEntries.filter((a) => favoritesStore.isFavorite('album', a.id))
       : this.filteredAlbumEntries
   );
 
@@ -91,7 +91,7 @@ class LibraryStore {
       ? this.filteredGenreNodes
           .map((g) => ({
             ...g,
-            albums: g.albums.filter((a) => favoritesStore.isFavorite('album', a.artist + "\0" + a.name)),
+            albums: g.albums.filter((a) => favoritesStore.isFavorite('album', a.id)),
           }))
           .filter((g) => g.albums.length > 0)
       : this.filteredGenreNodes
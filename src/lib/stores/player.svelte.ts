@@ -1,8 +1,15 @@
 import { convertFileSrc } from "@tauri-apps/api/core";
 import type { Track, AlbumArt } from "../api/types";
-import { getTrackArtwork, recordPlay } from "../api/commands";
+import { finishPlay, getTrackArtwork, recordPlay, recordSkip } from "../api/commands";
 import { equalizerStore } from "./equalizer.svelte";
 
+/** Share of a track that must be heard before it counts as played. */
+const LISTEN_THRESHOLD = 0.5;
+/** Used instead when the track length is unknown. */
+const LISTEN_FALLBACK_SECS = 30;
+/** Position jumps larger than this are seeks, not listening. */
+const MAX_LISTEN_STEP_SECS = 2;
+
 class PlayerStore {
   queue = $state<Track[]>([]);
   queueIndex = $state(0);
@@ -27,6 +34,11 @@ class PlayerStore {
   private analyser: AnalyserNode | null = null;
   private sourceNode: MediaElementAudioSourceNode | null = null;
   private lastPositionUpdateTime = 0;
+  // The play being listened to, for the play history
+  private playTrackId: number | null = null;
+  private playId: Promise<number> | null = null;
+  private listenedSecs = 0;
+  private lastPosition = 0;
 
   getAnalyser(): AnalyserNode | null {
     return this.analyser;
@@ -70,6 +82,7 @@ class PlayerStore {
     el.addEventListener("timeupdate", () => {
       this.currentTime = el.currentTime;
       this.duration = el.duration || 0;
+      this.trackListening(el.currentTime);
       // Throttle position state updates to ~1 Hz
       const now = Date.now();
       if (now - this.lastPositionUpdateTime > 1000) {
@@ -172,6 +185,7 @@ class PlayerStore {
   removeFromQueue(index: number) {
     if (index < 0 || index >= this.queue.length) return;
     if (this.queue.length === 1) {
+      this.finishPlay(false);
       this.queue = [];
       this.queueIndex = 0;
       if (this.audio) {
@@ -263,6 +277,7 @@ class PlayerStore {
   }
 
   private handleTrackEnded() {
+    this.finishPlay(true);
     if (this.hasNext) {
       this.queueIndex++;
       this.loadAndPlay();
@@ -277,15 +292,16 @@ class PlayerStore {
       this.pendingPlay = true;
       return;
     }
+    this.finishPlay(false);
     this.error = null;
     const src = convertFileSrc(this.currentTrack.file_path);
     this.audio.src = src;
     this.safePlay();
     this.loadArtwork();
-    // Record play — fire-and-forget; non-critical if it fails
-    if (this.currentTrack.id != null) {
-      recordPlay(this.currentTrack.id).catch(() => {});
-    }
+    this.playTrackId = this.currentTrack.id;
+    this.playId = null;
+    this.listenedSecs = 0;
+    this.lastPosition = 0;
     // Set OS Now Playing metadata (artwork updated async in loadArtwork)
     if ("mediaSession" in navigator) {
       navigator.mediaSession.metadata = new MediaMetadata({
@@ -296,6 +312,39 @@ class PlayerStore {
     }
   }
 
+  /** Adds playback since the last update to the listened time, and records
+   * the play once enough has been heard. History writes are fire-and-forget. */
+  private trackListening(position: number) {
+    const step = position - this.lastPosition;
+    if (step > 0 && step < MAX_LISTEN_STEP_SECS) this.listenedSecs += step;
+    this.lastPosition = position;
+
+    if (this.playTrackId == null || this.playId) return;
+    const needed =
+      this.duration > 0 && isFinite(this.duration)
+        ? this.duration * LISTEN_THRESHOLD
+        : LISTEN_FALLBACK_SECS;
+    if (this.listenedSecs >= needed) {
+      this.playId = recordPlay(this.playTrackId);
+      this.playId.catch(() => {});
+    }
+  }
+
+  /** Writes how the current play ended: listened time and completion, or a
+   * skip if it never counted as played. */
+  private finishPlay(completed: boolean) {
+    const trackId = this.playTrackId;
+    if (trackId == null) return;
+    this.playTrackId = null;
+    const listened =
+      completed && this.duration > 0 && isFinite(this.duration) ? this.duration : this.listenedSecs;
+    if (this.playId) {
+      this.playId.then((id) => finishPlay(id, listened, completed)).catch(() => {});
+    } else if (this.listenedSecs > 0) {
+      recordSkip(trackId, this.listenedSecs).catch(() => {});
+    }
+  }
+
   private async loadArtwork() {
     if (!this.currentTrack) {
       this.artwork = null;
@@ -18,6 +18,9 @@ const mockProfile: SyncProfile = {
   target_path: "/target",
   sync_mode: "one_way",
   exclude_patterns: [],
+  conflict_policies: [],
+  keep_both_naming: "timestamp",
+  hash_kind: "file",
   created_at: 1700000000,
   last_synced_at: null,
 };
@@ -22,7 +22,7 @@ export function groupByAlbum(tracks: Track[]): AlbumEntry[] {
 
     let entry = map.get(key);
     if (!entry) {
-      entry = { name: album, artist, year: track.year, tracks: [] };
+      entry = { id: track.album_id, name: album, artist, year: track.year, tracks: [] };
       map.set(key, entry);
     }
     entry.tracks.push(track);
@@ -65,7 +65,7 @@ export function groupByGenre(tracks: Track[]): GenreNode[] {
 
     let entry = albumMap.get(albumKey);
     if (!entry) {
-      entry = { name: album, artist, year: track.year, tracks: [] };
+      entry = { id: track.album_id, name: album, artist, year: track.year, tracks: [] };
       albumMap.set(albumKey, entry);
     }
     entry.tracks.push(track);